@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+
+fn main() {
+    // Honour SOURCE_DATE_EPOCH so Debian package builds stay reproducible
+    let build_date = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<i64>().ok())
+        .and_then(|epoch| DateTime::<Utc>::from_timestamp(epoch, 0))
+        .unwrap_or_else(Utc::now);
+
+    println!("cargo:rustc-env=INFO_DISPLAY_BUILD_DATE={}", build_date.format("%Y-%m-%d"));
+    println!("cargo:rustc-env=INFO_DISPLAY_BUILD_TARGET={}", std::env::var("TARGET").unwrap_or_default());
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
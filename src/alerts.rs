@@ -0,0 +1,258 @@
+use crate::config::WebhookConfig;
+use crate::system_info::{usage_percent, SystemSnapshot};
+use crate::utils::format_temp;
+
+// vcgencmd get_throttled bits for conditions present right now
+const UNDER_VOLTAGE_BIT: u32 = 0x1;
+const THROTTLED_BIT: u32 = 0x4;
+
+// How close a reading may come to its limit before it counts as a warning
+const CPU_TEMP_WARNING_MARGIN: f32 = 5.0;
+const PERCENT_WARNING_MARGIN: f32 = 5.0;
+// The clock offset limit is small and varies by orders of magnitude, so this is a share of it
+const CLOCK_OFFSET_WARNING_SHARE: f64 = 0.8;
+
+/// A condition worth an alert, built from the `[webhook]` thresholds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertRule {
+    CpuTemp(f32),
+    MemoryPercent(f32),
+    DiskPercent(f32),
+    // Of the root filesystem
+    InodePercent(f32),
+    UnderVoltage,
+    Throttled,
+    // A filesystem that should be writable mounted read-only, usually a failing SD card
+    ReadOnlyMount,
+    // Offset from the NTP source in milliseconds, either way; an unsynchronized clock fires as well
+    ClockOffset(f64),
+    // More zombie processes than this, e.g. from a cron job that forks without waiting
+    ZombieProcesses(usize),
+    // Fewer degrees than this left before the CPU's throttling trip point
+    ThermalMargin(f32),
+}
+
+impl AlertRule {
+    pub fn from_config(config: &WebhookConfig) -> Vec<Self> {
+        let mut rules = Vec::new();
+        if let Some(limit) = config.cpu_temp_celsius {
+            rules.push(AlertRule::CpuTemp(limit));
+        }
+        if let Some(limit) = config.memory_percent {
+            rules.push(AlertRule::MemoryPercent(limit));
+        }
+        if let Some(limit) = config.disk_percent {
+            rules.push(AlertRule::DiskPercent(limit));
+        }
+        if let Some(limit) = config.inode_percent {
+            rules.push(AlertRule::InodePercent(limit));
+        }
+        if config.under_voltage {
+            rules.push(AlertRule::UnderVoltage);
+        }
+        if config.throttled {
+            rules.push(AlertRule::Throttled);
+        }
+        if config.read_only_mount {
+            rules.push(AlertRule::ReadOnlyMount);
+        }
+        if let Some(limit) = config.clock_offset_ms {
+            rules.push(AlertRule::ClockOffset(limit));
+        }
+        if let Some(limit) = config.zombie_processes {
+            rules.push(AlertRule::ZombieProcesses(limit));
+        }
+        if let Some(limit) = config.thermal_margin_celsius {
+            rules.push(AlertRule::ThermalMargin(limit));
+        }
+        rules
+    }
+
+    #[cfg_attr(not(feature = "webhook"), allow(dead_code))]
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertRule::CpuTemp(_) => "cpu_temp",
+            AlertRule::MemoryPercent(_) => "memory",
+            AlertRule::DiskPercent(_) => "disk",
+            AlertRule::InodePercent(_) => "inodes",
+            AlertRule::UnderVoltage => "under_voltage",
+            AlertRule::Throttled => "throttled",
+            AlertRule::ReadOnlyMount => "read_only_mount",
+            AlertRule::ClockOffset(_) => "clock_offset",
+            AlertRule::ZombieProcesses(_) => "zombie_processes",
+            AlertRule::ThermalMargin(_) => "thermal_margin",
+        }
+    }
+
+    // Whether the condition holds and a description of the reading, or None when the
+    // reading is unavailable, which leaves the rule's state as it was
+    pub fn evaluate(&self, snapshot: &SystemSnapshot) -> Option<(bool, String)> {
+        match *self {
+            AlertRule::CpuTemp(limit) => snapshot.cpu_temp_celsius.map(|temp| {
+                (temp > limit, format!("CPU temperature {} (limit {})", format_temp(temp), format_temp(limit)))
+            }),
+            AlertRule::MemoryPercent(limit) => {
+                let percent = usage_percent(snapshot.memory_used_bytes, snapshot.memory_total_bytes)?;
+                Some((percent > limit, format!("Memory {:.0}% used (limit {:.0}%)", percent, limit)))
+            }
+            AlertRule::DiskPercent(limit) => {
+                let percent = snapshot.disk()?.percent_exact()?;
+                Some((percent > limit, format!("Disk {:.0}% used (limit {:.0}%)", percent, limit)))
+            }
+            AlertRule::InodePercent(limit) => {
+                let percent = snapshot.inodes()?.percent_exact()?;
+                Some((percent > limit, format!("Inodes {:.0}% used (limit {:.0}%)", percent, limit)))
+            }
+            AlertRule::UnderVoltage => snapshot.throttled.map(|bits| {
+                let present = bits & UNDER_VOLTAGE_BIT != 0;
+                (present, if present { "Under-voltage detected" } else { "Supply voltage normal" }.to_string())
+            }),
+            AlertRule::Throttled => snapshot.throttled.map(|bits| {
+                let present = bits & THROTTLED_BIT != 0;
+                (present, if present { "CPU is throttled" } else { "CPU no longer throttled" }.to_string())
+            }),
+            AlertRule::ReadOnlyMount => snapshot.readonly_mounts.as_ref().map(|mounts| {
+                let detail = if mounts.is_empty() {
+                    "Filesystems writable again".to_string()
+                } else {
+                    format!("Mounted read-only: {}", mounts.join(", "))
+                };
+                (!mounts.is_empty(), detail)
+            }),
+            AlertRule::ClockOffset(limit) => {
+                if snapshot.clock_synced == Some(false) {
+                    return Some((true, "Clock not synchronized".to_string()));
+                }
+                snapshot.clock_offset_ms.map(|offset| {
+                    (offset.abs() > limit, format!("Clock offset {:+.1}ms (limit {}ms)", offset, limit))
+                })
+            }
+            AlertRule::ZombieProcesses(limit) => snapshot.processes.as_ref().map(|processes| {
+                (processes.zombies > limit, format!("{} zombie processes (limit {})", processes.zombies, limit))
+            }),
+            AlertRule::ThermalMargin(limit) => snapshot.thermal_margin().map(|margin| {
+                (margin < limit, format!("CPU {} below its trip point (limit {})", format_temp(margin), format_temp(limit)))
+            }),
+        }
+    }
+
+    // Whether the reading is over the limit or within the warning margin of it
+    pub fn nearing(&self, snapshot: &SystemSnapshot) -> bool {
+        match *self {
+            AlertRule::CpuTemp(limit) => snapshot.cpu_temp_celsius
+                .is_some_and(|temp| temp > limit - CPU_TEMP_WARNING_MARGIN),
+            AlertRule::MemoryPercent(limit) => usage_percent(snapshot.memory_used_bytes, snapshot.memory_total_bytes)
+                .is_some_and(|percent| percent > limit - PERCENT_WARNING_MARGIN),
+            AlertRule::DiskPercent(limit) => snapshot.disk().and_then(|usage| usage.percent_exact())
+                .is_some_and(|percent| percent > limit - PERCENT_WARNING_MARGIN),
+            AlertRule::InodePercent(limit) => snapshot.inodes().and_then(|usage| usage.percent_exact())
+                .is_some_and(|percent| percent > limit - PERCENT_WARNING_MARGIN),
+            AlertRule::ClockOffset(limit) => snapshot.clock_synced == Some(false)
+                || snapshot.clock_offset_ms.is_some_and(|offset| offset.abs() > limit * CLOCK_OFFSET_WARNING_SHARE),
+            AlertRule::ThermalMargin(limit) => snapshot.thermal_margin()
+                .is_some_and(|margin| margin < limit + CPU_TEMP_WARNING_MARGIN),
+            // The flags are either set or not, and zombies are not expected at all
+            AlertRule::UnderVoltage | AlertRule::Throttled | AlertRule::ReadOnlyMount | AlertRule::ZombieProcesses(_) => {
+                self.evaluate(snapshot).is_some_and(|(firing, _)| firing)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::ProcessSummary;
+
+    #[test]
+    fn test_rules_from_config() {
+        let rules = AlertRule::from_config(&WebhookConfig::default());
+        assert_eq!(rules, vec![AlertRule::CpuTemp(80.0), AlertRule::UnderVoltage, AlertRule::ReadOnlyMount]);
+
+        let config = WebhookConfig {
+            cpu_temp_celsius: None,
+            disk_percent: Some(90.0),
+            under_voltage: false,
+            throttled: true,
+            read_only_mount: false,
+            ..WebhookConfig::default()
+        };
+        assert_eq!(AlertRule::from_config(&config), vec![AlertRule::DiskPercent(90.0), AlertRule::Throttled]);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let snapshot = SystemSnapshot {
+            cpu_temp_celsius: Some(82.5),
+            memory_used_bytes: 300,
+            memory_total_bytes: 1000,
+            inodes_used: Some(92),
+            inodes_total: Some(100),
+            throttled: Some(0x50005),
+            ..SystemSnapshot::default()
+        };
+
+        let (firing, detail) = AlertRule::CpuTemp(80.0).evaluate(&snapshot).unwrap();
+        assert!(firing);
+        assert_eq!(detail, "CPU temperature 82.5°C (limit 80.0°C)");
+        assert!(!AlertRule::MemoryPercent(90.0).evaluate(&snapshot).unwrap().0);
+        assert!(AlertRule::UnderVoltage.evaluate(&snapshot).unwrap().0);
+        assert!(AlertRule::Throttled.evaluate(&snapshot).unwrap().0);
+        let (firing, detail) = AlertRule::InodePercent(90.0).evaluate(&snapshot).unwrap();
+        assert!(firing);
+        assert_eq!(detail, "Inodes 92% used (limit 90%)");
+
+        let read_only = SystemSnapshot { readonly_mounts: Some(vec!["/".to_string()]), ..SystemSnapshot::default() };
+        assert_eq!(AlertRule::ReadOnlyMount.evaluate(&read_only), Some((true, "Mounted read-only: /".to_string())));
+        assert!(!AlertRule::ReadOnlyMount.evaluate(&SystemSnapshot { readonly_mounts: Some(Vec::new()), ..read_only }).unwrap().0);
+
+        // Missing readings leave the state alone
+        assert!(AlertRule::DiskPercent(90.0).evaluate(&snapshot).is_none());
+        assert!(AlertRule::UnderVoltage.evaluate(&SystemSnapshot::default()).is_none());
+        assert!(AlertRule::ReadOnlyMount.evaluate(&SystemSnapshot::default()).is_none());
+    }
+
+    #[test]
+    fn test_clock_offset() {
+        let rule = AlertRule::ClockOffset(100.0);
+        let behind = SystemSnapshot { clock_synced: Some(true), clock_offset_ms: Some(-250.0), ..SystemSnapshot::default() };
+        assert_eq!(rule.evaluate(&behind), Some((true, "Clock offset -250.0ms (limit 100ms)".to_string())));
+        let close = SystemSnapshot { clock_offset_ms: Some(85.0), ..behind };
+        assert!(!rule.evaluate(&close).unwrap().0);
+        assert!(rule.nearing(&close));
+
+        let unsynced = SystemSnapshot { clock_synced: Some(false), clock_offset_ms: Some(0.0), ..SystemSnapshot::default() };
+        assert_eq!(rule.evaluate(&unsynced), Some((true, "Clock not synchronized".to_string())));
+        assert!(rule.evaluate(&SystemSnapshot::default()).is_none());
+    }
+
+    #[test]
+    fn test_zombie_processes() {
+        let processes = ProcessSummary { total: 140, zombies: 3, ..ProcessSummary::default() };
+        let snapshot = SystemSnapshot { processes: Some(processes), ..SystemSnapshot::default() };
+        assert_eq!(AlertRule::ZombieProcesses(0).evaluate(&snapshot), Some((true, "3 zombie processes (limit 0)".to_string())));
+        assert!(!AlertRule::ZombieProcesses(5).evaluate(&snapshot).unwrap().0);
+        assert!(AlertRule::ZombieProcesses(0).evaluate(&SystemSnapshot::default()).is_none());
+    }
+
+    #[test]
+    fn test_thermal_margin() {
+        let snapshot = SystemSnapshot { cpu_temp_celsius: Some(78.0), cpu_trip_celsius: Some(85.0), ..SystemSnapshot::default() };
+        let rule = AlertRule::ThermalMargin(10.0);
+        assert_eq!(rule.evaluate(&snapshot), Some((true, "CPU 7.0°C below its trip point (limit 10.0°C)".to_string())));
+        assert!(!AlertRule::ThermalMargin(5.0).evaluate(&snapshot).unwrap().0);
+        assert!(AlertRule::ThermalMargin(5.0).nearing(&snapshot));
+        assert!(!AlertRule::ThermalMargin(1.0).nearing(&snapshot));
+        // No trip point, nothing to compare against
+        assert!(rule.evaluate(&SystemSnapshot { cpu_trip_celsius: None, ..snapshot }).is_none());
+    }
+
+    #[test]
+    fn test_nearing() {
+        let snapshot = SystemSnapshot { cpu_temp_celsius: Some(76.0), throttled: Some(0), ..SystemSnapshot::default() };
+        assert!(AlertRule::CpuTemp(80.0).nearing(&snapshot));
+        assert!(!AlertRule::CpuTemp(85.0).nearing(&snapshot));
+        assert!(!AlertRule::UnderVoltage.nearing(&snapshot));
+        assert!(!AlertRule::DiskPercent(90.0).nearing(&snapshot));
+    }
+}
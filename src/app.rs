@@ -1,60 +1,201 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::os::fd::AsFd;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use sysinfo::System;
 use daemonize::Daemonize;
+use tracing::{debug, debug_span, error, info, info_span, warn};
 
 use crate::cli::CliParser;
 use crate::config::AppConfig;
 use crate::errors::{AppError, Result};
+use crate::screen_factory::ScreenRegistry;
 use crate::screen_manager::ScreenManager;
-use crate::display::DisplayManager;
+use crate::screens::RefreshRequirements;
+use crate::system_info::events::{init_throttle_event_log, record_throttle_sample};
+use crate::system_info::gpio::start_gpio_watcher;
+use crate::system_info::{get_firmware_sources, DataSource, DemoDataSource, LiveDataSource, SystemSnapshot};
+use crate::display::{self, DisplayManager};
+use crate::fan::FanController;
+use crate::logging;
+use crate::http_status::{StatusApi, StatusServer};
+use crate::prometheus::MetricsServer;
+use crate::remote::{self, RemoteMessage, RemoteServer};
+use crate::stall::StallWatchdog;
+use crate::status::{Sampler, SnapshotSink, Status};
+use crate::systemd::{self, SystemdNotifier};
+use crate::timing;
+use crate::waybar::WaybarOutput;
 
+// Cap on a single backoff step while waiting for the display at startup
+const MAX_STARTUP_BACKOFF: Duration = Duration::from_secs(16);
+
+// Longest stretch the loop sleeps without checking for a shutdown request
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// /healthz reports unhealthy once no frame has been drawn for this many update intervals
+const HEALTHY_INTERVALS: u32 = 3;
+const MIN_HEALTHY_WINDOW: Duration = Duration::from_secs(30);
+
+// Everything the display loop reads but does not own
+struct LoopContext<'a> {
+    config: &'a AppConfig,
+    data_source: &'a dyn DataSource,
+    notifier: &'a SystemdNotifier,
+    shutdown: &'a AtomicBool,
+    status: &'a Status,
+    sampler: Option<&'a Sampler>,
+}
+
+/// The display service: owns the display, rotates the screens and runs the exporters.
 pub struct Application {
     config: AppConfig,
+    registry: ScreenRegistry,
     display_manager: Option<DisplayManager>,
     screen_manager: Option<ScreenManager>,
+    shutdown: Arc<AtomicBool>,
+    status: Arc<Status>,
+    // Kept across frames so only what the screens need is refreshed, and CPU usage has a previous sample
+    system: System,
+    #[cfg(feature = "otel")]
+    telemetry: Option<crate::telemetry::Telemetry>,
 }
 
 impl Application {
+    /// Configured from the command line, the environment and the config file, with the built-in screens.
     pub fn new() -> Result<Self> {
-        let config = CliParser::parse()?;
-        Ok(Self {
+        Self::with_config(CliParser::parse()?, ScreenRegistry::default())
+    }
+
+    /// Run with a ready-made configuration; enabled screens are looked up in `registry`.
+    pub fn with_config(config: AppConfig, registry: ScreenRegistry) -> Result<Self> {
+        config.validate_with(&registry)?;
+        config.apply_process_settings();
+        Ok(Self::from_parts(config, registry))
+    }
+
+    fn from_parts(config: AppConfig, registry: ScreenRegistry) -> Self {
+        Self {
             config,
+            registry,
             display_manager: None,
             screen_manager: None,
-        })
+            shutdown: Arc::new(AtomicBool::new(false)),
+            system: System::new(),
+            status: Arc::new(Status::default()),
+            #[cfg(feature = "otel")]
+            telemetry: None,
+        }
     }
 
     pub fn initialize(&mut self) -> Result<()> {
+        // Plain text on stdout, before any logging or daemonizing
+        if self.config.list_screens {
+            print!("{}", screen_list(&self.registry));
+            return Ok(());
+        }
+
+        // One JSON line for a status bar, read without any display hardware
+        if self.config.waybar {
+            return self.print_waybar();
+        }
+
+        // Which addresses answer behind the multiplexer, for finding the display's channel
+        if self.config.mux_scan {
+            let table = crate::display::scan_multiplexer(&self.config.multiplexer)?;
+            print!("{}", table);
+            return Ok(());
+        }
+
+        // The telemetry exporters' threads would not survive the daemon fork,
+        // so in that case logging is set up once daemonized
+        let init_logging_after_fork = self.config.daemon_mode && self.config.telemetry_enabled;
+        if !init_logging_after_fork {
+            self.init_logging()?;
+        }
+
         // Handle daemon mode
         if self.config.daemon_mode {
             self.start_daemon()?;
         }
 
+        if init_logging_after_fork {
+            self.init_logging()?;
+        }
+
+        // Stop the display loop cleanly on SIGTERM (systemctl stop) and SIGINT (Ctrl-C)
+        for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT] {
+            signal_hook::flag::register(signal, Arc::clone(&self.shutdown))
+                .map_err(|e| AppError::application(&format!("Failed to register signal handler: {}", e)))?;
+        }
+
+        #[cfg(not(feature = "epd"))]
+        if self.config.epd.enabled {
+            return Err(AppError::display_init("e-paper display requested, but this build does not include the epd feature"));
+        }
+
         // Handle clear-only mode
         if self.config.clear_only {
-            DisplayManager::clear_display(
-                self.config.multiplexer.enabled,
-                self.config.multiplexer.channel,
-                self.config.multiplexer.address,
-            ).map_err(|e| AppError::display_init(&format!("Failed to clear display: {}", e)))?;
+            #[cfg(feature = "epd")]
+            if self.config.epd.enabled {
+                return DisplayManager::clear_epd(&self.config.epd);
+            }
+            DisplayManager::clear_display(&self.config.multiplexer)?;
             return Ok(());
         }
 
-        // Initialize display
-        let display_manager = DisplayManager::new(
-            self.config.multiplexer.enabled,
-            self.config.multiplexer.channel,
-            self.config.multiplexer.address,
-        ).map_err(|e| AppError::display_init(&format!("Failed to initialize display: {}", e)))?;
+        if self.config.mqtt_clear_discovery {
+            return self.clear_mqtt_discovery();
+        }
 
-        self.display_manager = Some(display_manager);
+        if self.config.start_delay_secs > 0 {
+            info!("Waiting {}s before initializing display", self.config.start_delay_secs);
+            thread::sleep(Duration::from_secs(self.config.start_delay_secs));
+        }
+
+        // Sending to a remote display does not need a local one
+        if self.config.remote_send.is_none() {
+            let mut display_manager = self.init_display_with_retry()?;
+            display_manager.set_columns(self.config.columns);
+            display_manager.set_wrap(self.config.wrap.then_some(self.config.max_columns));
+            display_manager.set_heartbeat(self.config.heartbeat);
+            display_manager.set_font(self.config.font);
+            self.display_manager = Some(display_manager);
+        }
+
+        // Screens listed under [remote] show the remote host instead of this one
+        #[cfg(feature = "ssh")]
+        if self.config.remote_host.enabled {
+            let source = Arc::new(crate::ssh::SshDataSource::start(&self.config.remote_host)?);
+            for name in &self.config.remote_host.screens {
+                let source = Arc::clone(&source);
+                self.registry.wrap(name, move |screen| Box::new(crate::ssh::SshScreen::new(screen, Arc::clone(&source))));
+            }
+        }
+        #[cfg(not(feature = "ssh"))]
+        if self.config.remote_host.enabled {
+            warn!("Remote host monitoring requested, but this build does not include the ssh feature");
+        }
 
         // Create screen manager with enabled screens
-        let screen_manager = ScreenManager::new(
+        let mut screen_manager = ScreenManager::with_registry(
+            &self.registry,
             self.config.enabled_screens_as_str_refs(),
-            self.config.screen_duration_secs,
+            self.config.first_screen.as_deref(),
+            self.config.screen_duration,
+            self.config.on_screen_error.clone(),
+            &self.config.screen_options(),
         ).map_err(|e| AppError::system_info(&format!("Failed to create screen manager: {}", e)))?;
+        screen_manager.set_screen_durations(self.config.screen_durations.clone());
+        // Demo mode makes up readings for every screen
+        if !self.config.demo_mode {
+            screen_manager.skip_unavailable();
+        }
 
         self.screen_manager = Some(screen_manager);
 
@@ -62,23 +203,252 @@ impl Application {
     }
 
     pub fn run(&mut self) -> Result<()> {
-        if self.config.clear_only {
+        // One-shot modes are done once initialized
+        if self.config.clear_only || self.config.mqtt_clear_discovery || self.config.list_screens || self.config.waybar || self.config.mux_scan {
             return Ok(());
         }
 
-        let display_manager = self.display_manager.as_mut()
-            .ok_or_else(|| AppError::system_info("Display manager not initialized"))?;
         let screen_manager = self.screen_manager.as_mut()
             .ok_or_else(|| AppError::system_info("Screen manager not initialized"))?;
 
-        Application::run_display_loop(&self.config, display_manager, screen_manager)
+        let data_source = data_source(&self.config);
+        // Made-up readings are not worth remembering, nor do they need real pins
+        if !self.config.demo_mode {
+            init_throttle_event_log(&self.config.state_dir, self.config.event_retention_days);
+            // Looked for once here, so the log says where the firmware readings come from
+            get_firmware_sources();
+            start_gpio_watcher(&self.config.gpio_inputs);
+        }
+
+        // Demo mode shows made-up temperatures, which must not drive a real fan
+        let mut fan = if self.config.fan.enabled && !self.config.demo_mode {
+            FanController::start(&self.config.fan)
+        } else {
+            None
+        };
+
+        if let Some(target) = &self.config.remote_send {
+            let notifier = SystemdNotifier::from_env();
+            let context = LoopContext {
+                config: &self.config,
+                data_source: data_source.as_ref(),
+                notifier: &notifier,
+                shutdown: &self.shutdown,
+                status: &self.status,
+                sampler: None,
+            };
+            let result = Application::run_send_loop(&context, target, screen_manager, &mut self.system, fan.as_mut());
+            notifier.stopping();
+            return result;
+        }
+
+        let display_manager = self.display_manager.as_mut()
+            .ok_or_else(|| AppError::system_info("Display manager not initialized"))?;
+
+        let remote_server = match self.config.remote_listen {
+            Some(addr) => {
+                let (overrides, received) = mpsc::channel();
+                screen_manager.set_overrides(received, self.config.remote_only);
+                Some(RemoteServer::start(addr, self.config.remote_token.clone(), overrides)?)
+            }
+            None => None,
+        };
+
+        let metrics_server = match self.config.metrics_listen {
+            Some(addr) => Some(MetricsServer::start(addr, Arc::clone(&self.status))?),
+            None => None,
+        };
+
+        let status_server = match self.config.http_listen {
+            Some(addr) => {
+                let stale_after = self.config.interval.saturating_mul(HEALTHY_INTERVALS)
+                    .max(MIN_HEALTHY_WINDOW);
+                let available = self.registry.names().into_iter().map(String::from).collect();
+                let api = StatusApi::new(
+                    Arc::clone(&self.status), data_source.device_identity(), available, self.config.enabled_screens.clone(), stale_after,
+                );
+                Some(StatusServer::start(addr, api)?)
+            }
+            None => None,
+        };
+
+        #[allow(unused_mut)]
+        let mut sinks: Vec<Box<dyn SnapshotSink>> = Vec::new();
+        #[cfg(feature = "otel")]
+        if self.telemetry.is_some() {
+            sinks.push(Box::new(crate::metrics::MetricsRecorder::from_global(&data_source.device_identity())));
+        }
+
+        #[cfg(feature = "mqtt")]
+        if self.config.mqtt.enabled {
+            let publisher = crate::mqtt::MqttPublisher::start(&self.config.mqtt, data_source.as_ref())?;
+            sinks.push(Box::new(publisher));
+        }
+        #[cfg(not(feature = "mqtt"))]
+        if self.config.mqtt.enabled {
+            warn!("MQTT publishing requested, but this build does not include the mqtt feature");
+        }
+
+        #[cfg(feature = "influxdb")]
+        if self.config.influxdb.enabled {
+            let exporter = crate::influxdb::InfluxExporter::start(&self.config.influxdb, data_source.as_ref())?;
+            sinks.push(Box::new(exporter));
+        }
+        #[cfg(not(feature = "influxdb"))]
+        if self.config.influxdb.enabled {
+            warn!("InfluxDB export requested, but this build does not include the influxdb feature");
+        }
+
+        #[cfg(feature = "webhook")]
+        if self.config.webhook.enabled {
+            let notifier = crate::webhook::WebhookNotifier::start(&self.config.webhook, data_source.as_ref())?;
+            sinks.push(Box::new(notifier));
+        }
+        #[cfg(not(feature = "webhook"))]
+        if self.config.webhook.enabled {
+            warn!("Webhook alerts requested, but this build does not include the webhook feature");
+        }
+
+        // Snapshots are only taken when something reports on them
+        let sampler = if metrics_server.is_some() || status_server.is_some() || !sinks.is_empty() {
+            Some(Sampler::spawn(Arc::clone(&self.status), sinks)?)
+        } else {
+            None
+        };
+
+        // Measured in the intervals the loop actually runs at, which e-paper stretches
+        let stall_watchdog = match self.config.stall_intervals {
+            0 => None,
+            intervals => {
+                let interval = self.config.interval.max(display_manager.min_refresh_interval());
+                Some(StallWatchdog::start(Arc::clone(&self.status), interval.saturating_mul(intervals))?)
+            }
+        };
+
+        let notifier = SystemdNotifier::from_env();
+        let context = LoopContext {
+            config: &self.config,
+            data_source: data_source.as_ref(),
+            notifier: &notifier,
+            shutdown: &self.shutdown,
+            status: &self.status,
+            sampler: sampler.as_ref(),
+        };
+        let result = Application::run_display_loop(&context, display_manager, screen_manager, &mut self.system, fan.as_mut());
+        notifier.stopping();
+        drop(stall_watchdog);
+        drop(sampler);
+        drop(metrics_server);
+        drop(status_server);
+        drop(remote_server);
+        result
+    }
+
+    fn print_waybar(&self) -> Result<()> {
+        let mut screens = self.registry.create_all(&self.config.enabled_screens_as_str_refs(), &self.config.screen_options())
+            .map_err(|e| AppError::system_info(&format!("Failed to create screens: {}", e)))?;
+        // Everything, as the alert thresholds pick the class from readings the screens may not show
+        let mut sys = System::new();
+        let data_source = data_source(&self.config);
+        let snapshot = take_snapshot(&mut sys, data_source.as_ref(), RefreshRequirements::ALL);
+        let output = WaybarOutput::render(&mut screens, &snapshot, data_source.as_ref(), &self.config.webhook);
+        println!("{}", output.to_json());
+        Ok(())
+    }
+
+    #[cfg(feature = "mqtt")]
+    fn clear_mqtt_discovery(&self) -> Result<()> {
+        crate::mqtt::clear_discovery(&self.config.mqtt, &data_source(&self.config).device_identity())
+    }
+
+    #[cfg(not(feature = "mqtt"))]
+    fn clear_mqtt_discovery(&self) -> Result<()> {
+        Err(AppError::application("--mqtt-clear-discovery requires a build with the mqtt feature"))
+    }
+
+    fn open_display(&self) -> Result<DisplayManager> {
+        #[cfg(feature = "epd")]
+        if self.config.epd.enabled {
+            return DisplayManager::epd(&self.config.epd);
+        }
+        DisplayManager::new(&self.config.multiplexer)
+    }
+
+    // The I2C bus and the panel behind the mux may not be ready yet when started at boot,
+    // so keep retrying with exponential backoff until the configured window runs out.
+    fn init_display_with_retry(&self) -> Result<DisplayManager> {
+        let _span = info_span!("display_init").entered();
+        if let Some(speed) = self.config.i2c_speed.filter(|_| !self.config.epd.enabled) {
+            display::check_i2c_speed(speed);
+        }
+        let max_wait = Duration::from_secs(self.config.startup_max_wait_secs);
+        let started = Instant::now();
+        let mut attempt: u32 = 0;
+
+        loop {
+            attempt += 1;
+            let err = match self.open_display() {
+                Ok(display_manager) => return Ok(display_manager),
+                // Retrying will not fix permissions
+                Err(err @ AppError::Permission(_)) => return Err(err),
+                Err(err) => display::display_reason(&err),
+            };
+
+            let remaining = max_wait.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                let bus = display::i2c_bus_path();
+                let reason = if self.config.epd.enabled {
+                    "e-paper display not responding".to_string()
+                } else if Path::new(&bus).exists() {
+                    format!("display not responding on {}", bus)
+                } else {
+                    format!("I2C device {} missing (is I2C enabled?)", bus)
+                };
+                return Err(AppError::display_init(&format!(
+                    "Failed to initialize display after {} attempt(s) over {}s: {}: {}",
+                    attempt, started.elapsed().as_secs(), reason, err
+                )));
+            }
+
+            let delay = startup_backoff(attempt).min(remaining);
+            warn!(
+                "Display initialization attempt {} failed: {}; retrying in {}s",
+                attempt, err, delay.as_secs_f32()
+            );
+            thread::sleep(delay);
+        }
+    }
+
+    fn init_logging(&mut self) -> Result<()> {
+        let log_level = self.config.log_level.as_deref();
+
+        #[cfg(feature = "otel")]
+        if self.config.telemetry_enabled {
+            self.telemetry = Some(crate::telemetry::init(log_level, &data_source(&self.config).device_identity())?);
+            return Ok(());
+        }
+
+        logging::init(log_level)?;
+        if self.config.telemetry_enabled {
+            warn!("OpenTelemetry export requested, but this build does not include the otel feature");
+        }
+        Ok(())
     }
 
     fn start_daemon(&self) -> Result<()> {
-        let daemonize = Daemonize::new()
-            .pid_file("/tmp/info_display.pid")
+        prepare_pid_file(&self.config.pid_file)?;
+
+        let mut daemonize = Daemonize::new()
+            .pid_file(&self.config.pid_file)
             .chown_pid_file(true)
-            .working_directory("/tmp");
+            .working_directory(&self.config.working_dir);
+
+        // Under systemd stderr is connected to the journal; keep it instead of /dev/null
+        if std::env::var_os("JOURNAL_STREAM").is_some() {
+            if let Ok(stderr) = std::io::stderr().as_fd().try_clone_to_owned() {
+                daemonize = daemonize.stderr(fs::File::from(stderr));
+            }
+        }
 
         daemonize.start()
             .map_err(|e| AppError::daemon(&format!("Failed to start daemon: {}", e)))?;
@@ -87,30 +457,171 @@ impl Application {
     }
 
     fn run_display_loop(
-        config: &AppConfig,
+        context: &LoopContext,
         display_manager: &mut DisplayManager,
         screen_manager: &mut ScreenManager,
+        sys: &mut System,
+        mut fan: Option<&mut FanController>,
     ) -> Result<()> {
-        loop {
-            // Initialize system info
-            let mut sys = System::new_all();
-            sys.refresh_all();
+        let LoopContext { config, data_source, notifier, shutdown, status, sampler } = *context;
+        let mut first_frame = true;
+        let max_sleep_chunk = notifier.watchdog_interval()
+            .map_or(SHUTDOWN_POLL_INTERVAL, |interval| interval.min(SHUTDOWN_POLL_INTERVAL));
+        let refresh = loop_requirements(config, screen_manager, sampler.is_some());
+
+        // E-paper is not refreshed more often than it can take
+        let interval = config.interval.max(display_manager.min_refresh_interval());
+        if interval > config.interval {
+            info!("Updating every {}s, the display's shortest refresh interval", interval.as_secs());
+        }
+
+        let mut last_update_start: Option<Instant> = None;
+        while !shutdown.load(Ordering::Relaxed) {
+            let update_start = Instant::now();
+            let snapshot = take_snapshot(sys, data_source, refresh);
+            if let Some(flags) = snapshot.throttle_flags() {
+                record_throttle_sample(flags.current);
+            }
+            if let Some(fan) = fan.as_deref_mut() {
+                fan.update(snapshot.cpu_temp_celsius);
+            }
 
             // Check if we need to switch screens
             if screen_manager.should_switch_screen() {
                 screen_manager.next_screen();
             }
 
-            // Render current screen
-            let (title, content) = screen_manager.render_current_screen(&sys)
-                .map_err(|e| AppError::system_info(&format!("Failed to render screen: {}", e)))?;
-                
-            display_manager.render_content(&title, &content)
-                .map_err(|e| AppError::display_init(&format!("Failed to render to display: {}", e)))?;
+            // Render current screen; if nothing renders, keep the last frame and retry next cycle
+            let frame_span = debug_span!("frame").entered();
+            let render_start = Instant::now();
+            let rendered = screen_manager.render_current_screen(&snapshot, data_source);
+            let render_time = render_start.elapsed();
+            match rendered {
+                Ok(rendered) => {
+                    display_manager.render_content(rendered.title.as_deref(), &rendered.content, rendered.canvas)
+                        .map_err(|e| AppError::display_init(&format!("Failed to render to display: {}", e)))?;
+                    if screen_manager.showing_override() {
+                        status.set_current_screen("remote");
+                    } else if let Some(screen) = screen_manager.current_screen() {
+                        status.set_current_screen(screen.name());
+                        timing::record_render(screen.name(), render_time);
+                    }
+                    status.set_frame(display_manager.frame().clone());
+                    status.record_frame();
+                }
+                Err(e) => {
+                    error!("Failed to render screen: {}", e);
+                    status.record_render_failure();
+                }
+            }
+            drop(frame_span);
+
+            if let Some(sampler) = sampler {
+                sampler.sample(&snapshot);
+            }
+            timing::record_update(update_start.elapsed(), last_update_start.map(|last| update_start - last));
+            last_update_start = Some(update_start);
+
+            if first_frame {
+                notifier.ready();
+                first_frame = false;
+            }
+            notifier.watchdog();
+
+            // Wait for next update, in chunks so the watchdog is fed and shutdown noticed
+            for chunk in systemd::sleep_chunks(interval, max_sleep_chunk) {
+                if shutdown.load(Ordering::Relaxed) {
+                    break;
+                }
+                thread::sleep(chunk);
+                notifier.watchdog_if_due();
+            }
+        }
 
-            // Wait for next update
-            thread::sleep(Duration::from_secs(config.interval_seconds));
+        info!("Shutting down");
+        // Only on a clean stop; after an error the last frame may say what went wrong
+        if config.clear_on_exit {
+            if let Err(e) = display_manager.blank() {
+                warn!("Failed to clear the display on exit: {}", e);
+            }
         }
+        Ok(())
+    }
+
+    // Render the screens as usual but send them to a remote display, one message per
+    // update. Each lasts two updates so a late one does not let the remote's own screens
+    // flash in between; the next message with the same title replaces it there.
+    fn run_send_loop(
+        context: &LoopContext,
+        target: &str,
+        screen_manager: &mut ScreenManager,
+        sys: &mut System,
+        mut fan: Option<&mut FanController>,
+    ) -> Result<()> {
+        let LoopContext { config, data_source, notifier, shutdown, status, .. } = *context;
+        let mut first_frame = true;
+        let max_sleep_chunk = notifier.watchdog_interval()
+            .map_or(SHUTDOWN_POLL_INTERVAL, |interval| interval.min(SHUTDOWN_POLL_INTERVAL));
+        info!("Sending screens to the remote display at {}", target);
+        let refresh = loop_requirements(config, screen_manager, false);
+
+        while !shutdown.load(Ordering::Relaxed) {
+            let snapshot = take_snapshot(sys, data_source, refresh);
+            if let Some(flags) = snapshot.throttle_flags() {
+                record_throttle_sample(flags.current);
+            }
+            if let Some(fan) = fan.as_deref_mut() {
+                fan.update(snapshot.cpu_temp_celsius);
+            }
+
+            if screen_manager.should_switch_screen() {
+                screen_manager.next_screen();
+            }
+
+            match screen_manager.render_current_screen(&snapshot, data_source) {
+                Ok(rendered) => {
+                    let screen_name = screen_manager.current_screen().map_or("", |screen| screen.name());
+                    let message = RemoteMessage {
+                        token: config.remote_token.clone(),
+                        title: rendered.title.unwrap_or_else(|| screen_name.to_string()),
+                        lines: rendered.content.lines().map(String::from).collect(),
+                        duration: config.interval.saturating_mul(2).as_secs().clamp(1, remote::MAX_DURATION_SECS),
+                    };
+                    // The remote display may be down for a while; keep trying every update
+                    match remote::send(target, &message) {
+                        Ok(()) => {
+                            status.set_current_screen(screen_name);
+                            status.record_frame();
+                        }
+                        Err(e) => {
+                            warn!("Failed to send to remote display {}: {}", target, e);
+                            status.record_render_failure();
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to render screen: {}", e);
+                    status.record_render_failure();
+                }
+            }
+
+            if first_frame {
+                notifier.ready();
+                first_frame = false;
+            }
+            notifier.watchdog();
+
+            for chunk in systemd::sleep_chunks(config.interval, max_sleep_chunk) {
+                if shutdown.load(Ordering::Relaxed) {
+                    break;
+                }
+                thread::sleep(chunk);
+                notifier.watchdog_if_due();
+            }
+        }
+
+        info!("Shutting down");
+        Ok(())
     }
 
     #[allow(dead_code)]
@@ -119,15 +630,94 @@ impl Application {
     }
 }
 
+// The readings the screens need for one frame, taken once however many screens show them.
+// Logged so the cost of collecting is visible next to the frame timing
+fn take_snapshot(sys: &mut System, data: &dyn DataSource, needed: RefreshRequirements) -> SystemSnapshot {
+    let started = Instant::now();
+    needed.refresh(sys);
+    let snapshot = data.snapshot(sys, needed);
+    debug!("Collected system info in {:?}", started.elapsed());
+    snapshot
+}
+
+// What the screens need, plus the throttle register for the event log and the CPU
+// temperature for the fan every update. Exporters get the same snapshot as the screens,
+// so while they run it has every reading
+fn loop_requirements(config: &AppConfig, screen_manager: &ScreenManager, exporting: bool) -> RefreshRequirements {
+    let mut needed = screen_manager.refresh_requirements();
+    if exporting {
+        needed |= RefreshRequirements::ALL;
+    }
+    if !config.demo_mode {
+        needed |= RefreshRequirements::THROTTLE;
+        if config.fan.enabled {
+            needed |= RefreshRequirements::CPU_TEMP;
+        }
+    }
+    needed
+}
+
+fn data_source(config: &AppConfig) -> Arc<dyn DataSource> {
+    if config.demo_mode {
+        info!("Demo mode: showing synthetic data");
+        Arc::new(DemoDataSource::new())
+    } else {
+        Arc::new(LiveDataSource::with_temp_smoothing(config.temp_smoothing))
+    }
+}
+
+// One line per screen, name then description, for --list-screens
+fn screen_list(registry: &ScreenRegistry) -> String {
+    let names = registry.names();
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+    names.iter()
+        .map(|name| format!("{:width$}  {}\n", name, registry.description(name).unwrap_or_default(), width = width))
+        .collect()
+}
+
+// Refuse to start when the PID file belongs to a live instance; remove it when it is stale
+fn prepare_pid_file(pid_file: &Path) -> Result<()> {
+    let contents = match fs::read_to_string(pid_file) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(AppError::daemon(&format!("Failed to read PID file {}: {}", pid_file.display(), e)));
+        }
+    };
+
+    if let Ok(pid) = contents.trim().parse::<u32>() {
+        if is_instance_running(pid) {
+            return Err(AppError::daemon(&format!(
+                "Another instance is already running (PID {}, PID file {})",
+                pid, pid_file.display()
+            )));
+        }
+    }
+
+    warn!("Removing stale PID file {}", pid_file.display());
+    fs::remove_file(pid_file)
+        .map_err(|e| AppError::daemon(&format!("Failed to remove stale PID file {}: {}", pid_file.display(), e)))
+}
+
+// A PID only counts as ours if the process still exists and is running this program,
+// so a recycled PID does not block startup
+fn is_instance_running(pid: u32) -> bool {
+    match fs::read_to_string(format!("/proc/{}/comm", pid)) {
+        Ok(comm) => comm.trim().starts_with(env!("CARGO_PKG_NAME")),
+        Err(_) => false,
+    }
+}
+
+// Backoff before the next display init attempt: 1s, 2s, 4s, ... capped at MAX_STARTUP_BACKOFF
+fn startup_backoff(attempt: u32) -> Duration {
+    let secs = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+    Duration::from_secs(secs).min(MAX_STARTUP_BACKOFF)
+}
+
+// Default settings and built-in screens, independent of the process arguments
 impl Default for Application {
     fn default() -> Self {
-        Self::new().unwrap_or_else(|_| {
-            Self {
-                config: AppConfig::default(),
-                display_manager: None,
-                screen_manager: None,
-            }
-        })
+        Self::from_parts(AppConfig::default(), ScreenRegistry::default())
     }
 }
 
@@ -138,14 +728,59 @@ mod tests {
     #[test]
     fn test_application_default() {
         let app = Application::default();
-        assert_eq!(app.config.interval_seconds, 5);
+        assert_eq!(app.config.interval, Duration::from_secs(5));
         assert!(!app.config.daemon_mode);
     }
 
+    #[test]
+    fn test_screen_list() {
+        let list = screen_list(&ScreenRegistry::default());
+        assert_eq!(list.lines().count(), ScreenRegistry::default().names().len());
+        assert!(list.lines().any(|line| line.starts_with("overview     Combined view")));
+    }
+
+    #[test]
+    fn test_startup_backoff() {
+        assert_eq!(startup_backoff(1), Duration::from_secs(1));
+        assert_eq!(startup_backoff(2), Duration::from_secs(2));
+        assert_eq!(startup_backoff(3), Duration::from_secs(4));
+        assert_eq!(startup_backoff(10), MAX_STARTUP_BACKOFF);
+        assert_eq!(startup_backoff(200), MAX_STARTUP_BACKOFF);
+    }
+
+    fn temp_pid_file(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("info_display_test_{}_{}.pid", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_stale_pid_file_is_removed() {
+        // PID 0 never names a running userspace process
+        let path = temp_pid_file("stale", "0\n");
+        assert!(prepare_pid_file(&path).is_ok());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_live_pid_file_is_refused() {
+        // The test binary is named after the crate, so it looks like a running instance
+        let path = temp_pid_file("live", &std::process::id().to_string());
+        assert!(prepare_pid_file(&path).is_err());
+        assert!(path.exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_pid_file_is_ok() {
+        let path = std::env::temp_dir().join("info_display_test_missing.pid");
+        assert!(prepare_pid_file(&path).is_ok());
+    }
+
     #[test]
     fn test_application_config_access() {
         let app = Application::default();
         let config = app.config();
-        assert_eq!(config.interval_seconds, 5);
+        assert_eq!(config.interval, Duration::from_secs(5));
     }
 }
\ No newline at end of file
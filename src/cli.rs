@@ -1,37 +1,162 @@
 use std::env;
-use crate::config::{AppConfig, ConfigError};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::config::{self, AppConfig, ConfigBuilder, ConfigError, ScreenErrorPolicy};
 
 pub struct CliParser;
 
+// The -v/--verbose count and --quiet flag
+#[derive(Debug, Default)]
+struct Verbosity {
+    verbose: u8,
+    quiet: bool,
+    explicit_level: bool,
+}
+
+impl Verbosity {
+    // The level these flags ask for; --log-level takes precedence over them
+    fn level(&self) -> Option<&'static str> {
+        if self.explicit_level {
+            return None;
+        }
+        match (self.quiet, self.verbose) {
+            (true, _) => Some("error"),
+            (false, 0) => None,
+            (false, 1) => Some("debug"),
+            (false, _) => Some("trace"),
+        }
+    }
+}
+
 impl CliParser {
+    /// Parse the process arguments and validate the result against the built-in screens.
     pub fn parse() -> Result<AppConfig, ConfigError> {
-        let args: Vec<String> = env::args().collect();
-        let mut config = AppConfig::from_env(); // Start with environment variables
-        
+        let config = Self::parse_args(env::args())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Build a configuration from `args` (program name first), the environment and the
+    /// config file, without validating it, so callers can validate against their own screens.
+    pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<AppConfig, ConfigError> {
+        ConfigBuilder::new().args(args).build()
+    }
+
+    /// Apply the arguments (program name first) on top of `config`; see ConfigBuilder.
+    pub(crate) fn apply_args(config: &mut AppConfig, args: &[String]) -> Result<(), ConfigError> {
+        // -v/-vv/--quiet are applied after the loop, unless --log-level was given
+        let mut verbosity = Verbosity::default();
         let mut i = 1;
         while i < args.len() {
             match args[i].as_str() {
+                // Already applied above, just skip the path
+                "--config" if Self::get_next_arg(args, i).is_some() => i += 1,
                 "--clear" => config.clear_only = true,
+                "--list-screens" => config.list_screens = true,
+                "--waybar" => config.waybar = true,
+                "--mux-scan" => config.mux_scan = true,
                 "--daemon" | "-d" => config.daemon_mode = true,
+                "--pid-file" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.pid_file = PathBuf::from(value);
+                        i += 1;
+                    }
+                }
+                "--working-dir" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.working_dir = PathBuf::from(value);
+                        i += 1;
+                    }
+                }
                 "--interval" | "-i" => {
-                    if let Some(value) = Self::get_next_arg(&args, i) {
-                        if let Ok(seconds) = value.parse::<u64>() {
-                            config.interval_seconds = seconds;
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if let Some(duration) = config::parse_seconds(value) {
+                            config.interval = duration;
                             i += 1;
                         }
                     }
                 }
+                "--first-screen" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.first_screen = Some(value.trim().to_string());
+                        i += 1;
+                    }
+                }
                 "--screen-duration" | "-s" => {
-                    if let Some(value) = Self::get_next_arg(&args, i) {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if let Some(duration) = config::parse_seconds(value) {
+                            config.screen_duration = duration;
+                            i += 1;
+                        }
+                    }
+                }
+                "--vcgencmd" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if !value.is_empty() {
+                            config.vcgencmd_path = value.to_string();
+                        }
+                        i += 1;
+                    }
+                }
+                "--state-dir" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.state_dir = PathBuf::from(value);
+                        i += 1;
+                    }
+                }
+                "--stall-timeout" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if let Ok(intervals) = value.parse::<u32>() {
+                            config.stall_intervals = intervals;
+                            i += 1;
+                        }
+                    }
+                }
+                "--event-retention-days" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if let Ok(days) = value.parse::<u32>() {
+                            if days > 0 {
+                                config.event_retention_days = days;
+                            }
+                            i += 1;
+                        }
+                    }
+                }
+                "--start-delay" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if let Ok(seconds) = value.parse::<u64>() {
+                            config.start_delay_secs = seconds;
+                            i += 1;
+                        }
+                    }
+                }
+                "--startup-max-wait" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
                         if let Ok(seconds) = value.parse::<u64>() {
-                            config.screen_duration_secs = seconds;
+                            config.startup_max_wait_secs = seconds;
+                            i += 1;
+                        }
+                    }
+                }
+                "--command-timeout" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if let Ok(millis) = value.parse::<u64>() {
+                            if millis > 0 {
+                                config.command_timeout = Duration::from_millis(millis);
+                            }
                             i += 1;
                         }
                     }
                 }
                 "--screens" => {
-                    if let Some(value) = Self::get_next_arg(&args, i) {
-                        config.enabled_screens = value.split(',').map(|s| s.to_string()).collect();
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.enabled_screens = config::parse_screen_list(value);
+                        i += 1;
+                    }
+                }
+                "--screens-file" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.apply_screens_file(Path::new(value))?;
                         i += 1;
                     }
                 }
@@ -42,23 +167,207 @@ impl CliParser {
                 "--temperature" => config.add_screen("temperature"),
                 "--gpio" => config.add_screen("gpio"),
                 "--overview" => config.add_screen("overview"),
+                "--about" => config.add_screen("about"),
+                "--clock" => config.add_screen("clock"),
+                "--thermal" => config.add_screen("thermal"),
+                "--ambient" => config.add_screen("ambient"),
+                "--qr" => config.add_screen("qr"),
+                "--battery" => config.add_screen("battery"),
+                "--connections" => config.add_screen("connections"),
+                "--qr-content" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.qr_content = Some(value.to_string());
+                        i += 1;
+                    }
+                }
+                "--placeholder" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.placeholder = value.to_string();
+                        i += 1;
+                    }
+                }
+                "--temp-smoothing" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.temp_smoothing = config::parse_temp_smoothing(value)?;
+                        i += 1;
+                    }
+                }
+                "--ip-version" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.ip_version = config::parse_ip_version(value)?;
+                        i += 1;
+                    }
+                }
+                "--font" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.font = config::parse_font(value)?;
+                        i += 1;
+                    }
+                }
+                "--uptime-style" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.uptime_style = config::parse_uptime_style(value)?;
+                        i += 1;
+                    }
+                }
+                "--date-format" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.date_format = value.to_string();
+                        i += 1;
+                    }
+                }
+                "--hostname-source" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.hostname_source = config::parse_hostname_source(value)?;
+                        i += 1;
+                    }
+                }
+                "--dht22-gpio" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if let Ok(pin) = value.parse::<u8>() {
+                            config.dht22_gpio = Some(pin);
+                            i += 1;
+                        }
+                    }
+                }
+                "--show-percent" => config.show_percent = true,
+                "--heartbeat" => config.heartbeat = true,
+                "--clear-on-exit" => config.clear_on_exit = true,
+                "--no-clear-on-exit" => config.clear_on_exit = false,
+                "--wrap" => config.wrap = true,
+                "--fan-control" => config.fan.enabled = true,
+                "--epd" => config.epd.enabled = true,
+                "--columns" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if let Ok(columns) = value.parse::<u8>() {
+                            config.columns = columns;
+                            i += 1;
+                        }
+                    }
+                }
+                "--max-columns" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if let Ok(max_columns) = value.parse::<usize>() {
+                            config.max_columns = max_columns;
+                            i += 1;
+                        }
+                    }
+                }
+                "--overview-fields" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.overview_fields = config::parse_list(value);
+                        i += 1;
+                    }
+                }
+                "--demo" => config.demo_mode = true,
                 "--mux" => config.enable_multiplexer(),
                 "--mux-channel" => {
-                    if let Some(value) = Self::get_next_arg(&args, i) {
+                    if let Some(value) = Self::get_next_arg(args, i) {
                         if let Ok(channel) = value.parse::<u8>() {
                             config.set_multiplexer_channel(channel)?;
                             i += 1;
                         }
                     }
                 }
+                "--mux-channels" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.set_multiplexer_channels(value)?;
+                        i += 1;
+                    }
+                }
+                "--mux-target" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.set_multiplexer_target(value)?;
+                        i += 1;
+                    }
+                }
                 "--mux-address" => {
-                    if let Some(value) = Self::get_next_arg(&args, i) {
+                    if let Some(value) = Self::get_next_arg(args, i) {
                         if let Ok(addr) = u8::from_str_radix(value.trim_start_matches("0x"), 16) {
                             config.set_multiplexer_address(addr);
                             i += 1;
                         }
                     }
                 }
+                "--mux-scan-addresses" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.multiplexer.scan_addresses = config::parse_address_list(value)?;
+                        i += 1;
+                    }
+                }
+                "--i2c-probe" => config.i2c_probe = true,
+                "--i2c-buses" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.i2c_buses = config::parse_bus_list(value)?;
+                        i += 1;
+                    }
+                }
+                "--w1-family" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.w1_family = Some(config::parse_w1_family(value)?);
+                        i += 1;
+                    }
+                }
+                "--i2c-speed" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        if let Ok(speed) = value.parse::<u32>() {
+                            config.i2c_speed = Some(speed);
+                            i += 1;
+                        }
+                    }
+                }
+                "--log-level" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.log_level = Some(value.to_string());
+                        verbosity.explicit_level = true;
+                        i += 1;
+                    }
+                }
+                "--verbose" => verbosity.verbose = verbosity.verbose.saturating_add(1),
+                "--quiet" | "-q" => verbosity.quiet = true,
+                arg if arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| c == 'v') => {
+                    verbosity.verbose = verbosity.verbose.saturating_add((arg.len() - 1) as u8);
+                }
+                "--otel" => config.telemetry_enabled = true,
+                "--mqtt" => config.mqtt.enabled = true,
+                "--mqtt-clear-discovery" => config.mqtt_clear_discovery = true,
+                "--metrics-listen" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.metrics_listen = Some(config::parse_listen_address(value)?);
+                        i += 1;
+                    }
+                }
+                "--http-listen" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.http_listen = Some(config::parse_listen_address(value)?);
+                        i += 1;
+                    }
+                }
+                "--listen" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.remote_listen = Some(config::parse_listen_address(value)?);
+                        i += 1;
+                    }
+                }
+                "--send" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.remote_send = Some(value.to_string());
+                        i += 1;
+                    }
+                }
+                "--remote-token" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.remote_token = Some(value.to_string());
+                        i += 1;
+                    }
+                }
+                "--remote-only" => config.remote_only = true,
+                "--on-screen-error" => {
+                    if let Some(value) = Self::get_next_arg(args, i) {
+                        config.on_screen_error = ScreenErrorPolicy::parse(value)?;
+                        i += 1;
+                    }
+                }
                 "--help" | "-h" => {
                     Self::print_help(&args[0]);
                     std::process::exit(0);
@@ -67,23 +376,161 @@ impl CliParser {
                     Self::print_version();
                     std::process::exit(0);
                 }
+                arg if arg.starts_with("--config=") => {}
+                arg if arg.starts_with("--pid-file=") => {
+                    if let Some(value) = arg.strip_prefix("--pid-file=") {
+                        config.pid_file = PathBuf::from(value);
+                    }
+                }
+                arg if arg.starts_with("--working-dir=") => {
+                    if let Some(value) = arg.strip_prefix("--working-dir=") {
+                        config.working_dir = PathBuf::from(value);
+                    }
+                }
                 arg if arg.starts_with("--interval=") => {
                     if let Some(value) = arg.strip_prefix("--interval=") {
-                        if let Ok(seconds) = value.parse::<u64>() {
-                            config.interval_seconds = seconds;
+                        if let Some(duration) = config::parse_seconds(value) {
+                            config.interval = duration;
                         }
                     }
                 }
+                arg if arg.starts_with("--first-screen=") => {
+                    if let Some(value) = arg.strip_prefix("--first-screen=") {
+                        config.first_screen = Some(value.trim().to_string());
+                    }
+                }
                 arg if arg.starts_with("--screen-duration=") => {
                     if let Some(value) = arg.strip_prefix("--screen-duration=") {
+                        if let Some(duration) = config::parse_seconds(value) {
+                            config.screen_duration = duration;
+                        }
+                    }
+                }
+                arg if arg.starts_with("--vcgencmd=") => {
+                    if let Some(value) = arg.strip_prefix("--vcgencmd=") {
+                        if !value.is_empty() {
+                            config.vcgencmd_path = value.to_string();
+                        }
+                    }
+                }
+                arg if arg.starts_with("--state-dir=") => {
+                    if let Some(value) = arg.strip_prefix("--state-dir=") {
+                        config.state_dir = PathBuf::from(value);
+                    }
+                }
+                arg if arg.starts_with("--stall-timeout=") => {
+                    if let Some(value) = arg.strip_prefix("--stall-timeout=") {
+                        if let Ok(intervals) = value.parse::<u32>() {
+                            config.stall_intervals = intervals;
+                        }
+                    }
+                }
+                arg if arg.starts_with("--event-retention-days=") => {
+                    if let Some(value) = arg.strip_prefix("--event-retention-days=") {
+                        if let Ok(days) = value.parse::<u32>() {
+                            if days > 0 {
+                                config.event_retention_days = days;
+                            }
+                        }
+                    }
+                }
+                arg if arg.starts_with("--start-delay=") => {
+                    if let Some(value) = arg.strip_prefix("--start-delay=") {
                         if let Ok(seconds) = value.parse::<u64>() {
-                            config.screen_duration_secs = seconds;
+                            config.start_delay_secs = seconds;
+                        }
+                    }
+                }
+                arg if arg.starts_with("--startup-max-wait=") => {
+                    if let Some(value) = arg.strip_prefix("--startup-max-wait=") {
+                        if let Ok(seconds) = value.parse::<u64>() {
+                            config.startup_max_wait_secs = seconds;
+                        }
+                    }
+                }
+                arg if arg.starts_with("--command-timeout=") => {
+                    if let Some(value) = arg.strip_prefix("--command-timeout=") {
+                        if let Ok(millis) = value.parse::<u64>() {
+                            if millis > 0 {
+                                config.command_timeout = Duration::from_millis(millis);
+                            }
                         }
                     }
                 }
                 arg if arg.starts_with("--screens=") => {
                     if let Some(value) = arg.strip_prefix("--screens=") {
-                        config.enabled_screens = value.split(',').map(|s| s.to_string()).collect();
+                        config.enabled_screens = config::parse_screen_list(value);
+                    }
+                }
+                arg if arg.starts_with("--screens-file=") => {
+                    if let Some(value) = arg.strip_prefix("--screens-file=") {
+                        config.apply_screens_file(Path::new(value))?;
+                    }
+                }
+                arg if arg.starts_with("--overview-fields=") => {
+                    if let Some(value) = arg.strip_prefix("--overview-fields=") {
+                        config.overview_fields = config::parse_list(value);
+                    }
+                }
+                arg if arg.starts_with("--qr-content=") => {
+                    if let Some(value) = arg.strip_prefix("--qr-content=") {
+                        config.qr_content = Some(value.to_string());
+                    }
+                }
+                arg if arg.starts_with("--placeholder=") => {
+                    if let Some(value) = arg.strip_prefix("--placeholder=") {
+                        config.placeholder = value.to_string();
+                    }
+                }
+                arg if arg.starts_with("--temp-smoothing=") => {
+                    if let Some(value) = arg.strip_prefix("--temp-smoothing=") {
+                        config.temp_smoothing = config::parse_temp_smoothing(value)?;
+                    }
+                }
+                arg if arg.starts_with("--ip-version=") => {
+                    if let Some(value) = arg.strip_prefix("--ip-version=") {
+                        config.ip_version = config::parse_ip_version(value)?;
+                    }
+                }
+                arg if arg.starts_with("--font=") => {
+                    if let Some(value) = arg.strip_prefix("--font=") {
+                        config.font = config::parse_font(value)?;
+                    }
+                }
+                arg if arg.starts_with("--uptime-style=") => {
+                    if let Some(value) = arg.strip_prefix("--uptime-style=") {
+                        config.uptime_style = config::parse_uptime_style(value)?;
+                    }
+                }
+                arg if arg.starts_with("--date-format=") => {
+                    if let Some(value) = arg.strip_prefix("--date-format=") {
+                        config.date_format = value.to_string();
+                    }
+                }
+                arg if arg.starts_with("--hostname-source=") => {
+                    if let Some(value) = arg.strip_prefix("--hostname-source=") {
+                        config.hostname_source = config::parse_hostname_source(value)?;
+                    }
+                }
+                arg if arg.starts_with("--dht22-gpio=") => {
+                    if let Some(value) = arg.strip_prefix("--dht22-gpio=") {
+                        if let Ok(pin) = value.parse::<u8>() {
+                            config.dht22_gpio = Some(pin);
+                        }
+                    }
+                }
+                arg if arg.starts_with("--max-columns=") => {
+                    if let Some(value) = arg.strip_prefix("--max-columns=") {
+                        if let Ok(max_columns) = value.parse::<usize>() {
+                            config.max_columns = max_columns;
+                        }
+                    }
+                }
+                arg if arg.starts_with("--columns=") => {
+                    if let Some(value) = arg.strip_prefix("--columns=") {
+                        if let Ok(columns) = value.parse::<u8>() {
+                            config.columns = columns;
+                        }
                     }
                 }
                 arg if arg.starts_with("--mux-channel=") => {
@@ -93,6 +540,16 @@ impl CliParser {
                         }
                     }
                 }
+                arg if arg.starts_with("--mux-channels=") => {
+                    if let Some(value) = arg.strip_prefix("--mux-channels=") {
+                        config.set_multiplexer_channels(value)?;
+                    }
+                }
+                arg if arg.starts_with("--mux-target=") => {
+                    if let Some(value) = arg.strip_prefix("--mux-target=") {
+                        config.set_multiplexer_target(value)?;
+                    }
+                }
                 arg if arg.starts_with("--mux-address=") => {
                     if let Some(value) = arg.strip_prefix("--mux-address=") {
                         if let Ok(addr) = u8::from_str_radix(value.trim_start_matches("0x"), 16) {
@@ -100,16 +557,89 @@ impl CliParser {
                         }
                     }
                 }
+                arg if arg.starts_with("--mux-scan-addresses=") => {
+                    if let Some(value) = arg.strip_prefix("--mux-scan-addresses=") {
+                        config.multiplexer.scan_addresses = config::parse_address_list(value)?;
+                    }
+                }
+                arg if arg.starts_with("--i2c-buses=") => {
+                    if let Some(value) = arg.strip_prefix("--i2c-buses=") {
+                        config.i2c_buses = config::parse_bus_list(value)?;
+                    }
+                }
+                arg if arg.starts_with("--w1-family=") => {
+                    if let Some(value) = arg.strip_prefix("--w1-family=") {
+                        config.w1_family = Some(config::parse_w1_family(value)?);
+                    }
+                }
+                arg if arg.starts_with("--i2c-speed=") => {
+                    if let Some(value) = arg.strip_prefix("--i2c-speed=") {
+                        if let Ok(speed) = value.parse::<u32>() {
+                            config.i2c_speed = Some(speed);
+                        }
+                    }
+                }
+                arg if arg.starts_with("--log-level=") => {
+                    if let Some(value) = arg.strip_prefix("--log-level=") {
+                        config.log_level = Some(value.to_string());
+                        verbosity.explicit_level = true;
+                    }
+                }
+                arg if arg.starts_with("--on-screen-error=") => {
+                    if let Some(value) = arg.strip_prefix("--on-screen-error=") {
+                        config.on_screen_error = ScreenErrorPolicy::parse(value)?;
+                    }
+                }
+                arg if arg.starts_with("--http-listen=") => {
+                    if let Some(value) = arg.strip_prefix("--http-listen=") {
+                        config.http_listen = Some(config::parse_listen_address(value)?);
+                    }
+                }
+                arg if arg.starts_with("--listen=") => {
+                    if let Some(value) = arg.strip_prefix("--listen=") {
+                        config.remote_listen = Some(config::parse_listen_address(value)?);
+                    }
+                }
+                arg if arg.starts_with("--send=") => {
+                    if let Some(value) = arg.strip_prefix("--send=") {
+                        config.remote_send = Some(value.to_string());
+                    }
+                }
+                arg if arg.starts_with("--remote-token=") => {
+                    if let Some(value) = arg.strip_prefix("--remote-token=") {
+                        config.remote_token = Some(value.to_string());
+                    }
+                }
+                arg if arg.starts_with("--metrics-listen=") => {
+                    if let Some(value) = arg.strip_prefix("--metrics-listen=") {
+                        config.metrics_listen = Some(config::parse_listen_address(value)?);
+                    }
+                }
                 _ => {}
             }
             i += 1;
         }
-        
-        // Validate the configuration before returning
-        config.validate()?;
-        Ok(config)
+
+        if let Some(level) = verbosity.level() {
+            config.log_level = Some(level.to_string());
+        }
+
+        Ok(())
     }
-    
+
+    /// The path given with --config, if any.
+    pub(crate) fn config_file_arg(args: &[String]) -> Option<PathBuf> {
+        for (i, arg) in args.iter().enumerate().skip(1) {
+            if arg == "--config" {
+                return Self::get_next_arg(args, i).map(PathBuf::from);
+            }
+            if let Some(value) = arg.strip_prefix("--config=") {
+                return Some(PathBuf::from(value));
+            }
+        }
+        None
+    }
+
     fn get_next_arg(args: &[String], index: usize) -> Option<&String> {
         if index + 1 < args.len() {
             Some(&args[index + 1])
@@ -130,11 +660,25 @@ impl CliParser {
         println!("Usage: {} [OPTIONS]", program_name);
         println!();
         println!("Options:");
+        println!("  --config <path>      Read settings from a TOML config file");
         println!("  --clear              Clear display and exit");
+        println!("  --list-screens       List the available screens and exit");
+        println!("  --waybar             Print the screens as one line of Waybar JSON and exit");
         println!("  --daemon, -d         Run as daemon");
-        println!("  --interval, -i <N>   Update interval in seconds (default: 5)");
-        println!("  --screen-duration, -s <N>  Duration each screen is shown (default: 10)");
-        println!("  --screens <list>     Comma-separated list of screens (network,system,storage,hardware,temperature,gpio,overview)");
+        println!("  --pid-file <path>    Daemon PID file (default: /tmp/info_display.pid)");
+        println!("  --working-dir <path> Daemon working directory (default: /tmp)");
+        println!("  --interval, -i <N>   Update interval in seconds, fractions allowed, e.g. 0.5 (default: 5)");
+        println!("  --screen-duration, -s <N>  Seconds each screen is shown, fractions allowed (default: 10)");
+        println!("  --first-screen <name>  Start with this screen when it is enabled (default: the first listed)");
+        println!("  --start-delay <N>    Wait N seconds before initializing the display (default: 0)");
+        println!("  --startup-max-wait <N>  Keep retrying display init for up to N seconds (default: 60)");
+        println!("  --command-timeout <MS>  Kill vcgencmd and other helpers after MS milliseconds (default: 500)");
+        println!("  --vcgencmd <path>    Where vcgencmd is, e.g. /usr/bin/vcgencmd (default: from the PATH)");
+        println!("  --state-dir <path>   Where throttle events are logged (default: /var/lib/info-display)");
+        println!("  --event-retention-days <N>  Days throttle events are kept (default: 7)");
+        println!("  --stall-timeout <N>  Exit with status 6 when no frame was drawn for N update intervals (default: 0, off)");
+        println!("  --screens <list>     Screens, separated by commas or spaces (network,system,storage,hardware,temperature,gpio,overview,about,clock,thermal,ambient,qr,battery,connections)");
+        println!("  --screens-file <path> Screens from a file, one name[:seconds] per line");
         println!("  --network            Enable network screen");
         println!("  --system             Enable system screen");
         println!("  --storage            Enable storage screen");
@@ -142,20 +686,116 @@ impl CliParser {
         println!("  --temperature        Enable temperature screen");
         println!("  --gpio               Enable GPIO/sensor screen");
         println!("  --overview           Enable overview screen (default)");
+        println!("  --about              Enable about screen (version and build info)");
+        println!("  --clock              Enable full-height clock screen");
+        println!("  --thermal            Enable thermal zones screen");
+        println!("  --ambient            Enable DHT22 temperature and humidity screen");
+        println!("  --dht22-gpio <pin>   BCM GPIO the DHT22 data line is connected to");
+        println!("  --qr                 Enable QR code screen");
+        println!("  --qr-content <text>  What the QR code encodes (default: http://<ip>)");
+        println!("  --battery            Enable UPS/battery HAT screen (HAT type in the [battery] config section)");
+        println!("  --connections        Enable established TCP connections screen");
+        println!("  --show-percent       Show percentage used for memory, swap and disk");
+        println!("  --wrap               Wrap long lines onto the next rows instead of cutting them off");
+        println!("  --font <ascii|latin1>  Content font (default: latin1); ascii has no degree sign, temperatures show as 51.9C");
+        println!("  --heartbeat          Blink a pixel in the top right corner on every update");
+        println!("  --clear-on-exit      Blank the display when stopped; --no-clear-on-exit keeps the last frame (default)");
+        println!("  --fan-control        Set the PWM fan speed from the CPU temperature (curve in the [fan] config section)");
+        println!("  --epd                Draw on a Waveshare 2.13\" e-paper HAT over SPI instead of the OLED");
+        println!("                       (requires the epd feature; settings in the [epd] config section)");
+        println!("  --columns <1-2>      Lay the content out in columns, e.g. 2 for two readings per row");
+        println!("  --max-columns <n>    Characters per content line before screens cut it (default: 21)");
+        println!("  --placeholder <text> Shown where a value is unavailable (default: N/A)");
+        println!("  --ip-version <v4|v6|both>  Addresses the network screen shows (default: v4)");
+        println!("  --uptime-style <compact|since>  Uptime as a duration or as the boot time (default: compact)");
+        println!("  --date-format <format>  strftime format of the boot time (default: %d %b %H:%M)");
+        println!("  --temp-smoothing <mode>  Smooth temperatures: off, ema:<alpha> or window:<N> (default: off)");
+        println!("  --hostname-source <source>  Hostname to show: system (default, the kernel's),");
+        println!("                       file (/etc/hostname) or name:<hostname>");
+        println!("  --overview-fields <list>  Overview lines, in order (default: ip,temp,mem,disk; four fit below the title)");
+        println!("  --demo               Show synthetic data instead of reading the system");
         println!("  --mux                Use TCA9548A I2C multiplexer");
         println!("  --mux-channel <0-7>  Select multiplexer channel (default: 0)");
+        println!("  --mux-channels <list>  Enable several channels, e.g. 2,5; the display is on the first");
         println!("  --mux-address <addr> Set multiplexer I2C address (default: 0x70)");
+        println!("  --mux-target <mux:channel>  Display behind one of several multiplexers, by address");
+        println!("                       or [[multiplexers]] label, e.g. 0x71:3");
+        println!("  --mux-scan           List which addresses answer on each multiplexer channel and exit");
+        println!("  --mux-scan-addresses <list>  Addresses to probe besides 0x3C,0x3D, e.g. 0x48,0x76");
+        println!("  --i2c-probe          List the devices answering on the I2C bus on the GPIO screen");
+        println!("  --i2c-buses <list>   Buses to probe, e.g. 1,3 for /dev/i2c-1 and /dev/i2c-3 (default: the header's bus)");
+        println!("  --w1-family <code>   Only list 1-Wire devices of this family on the GPIO screen, e.g. 28 for DS18B20");
+        println!("  --i2c-speed <hz>     Expected I2C bus speed, e.g. 100000; warns with the fix if it differs");
+        println!("  --on-screen-error <policy>  What to do when a screen fails: skip (default),");
+        println!("                       placeholder, or fallback:<screen>");
+        println!("  --log-level <level>  Log level or filter, e.g. debug (default: RUST_LOG or info)");
+        println!("  --verbose, -v        Log at debug level, -vv for trace (overrides RUST_LOG)");
+        println!("  --quiet, -q          Only log errors (overrides RUST_LOG)");
+        println!("  --otel               Export traces and metrics over OTLP (requires the otel feature)");
+        println!("  --mqtt               Publish stats to the MQTT broker from the config file");
+        println!("                       (requires the mqtt feature)");
+        println!("  --mqtt-clear-discovery  Remove the Home Assistant discovery entries and exit");
+        println!("  --metrics-listen <addr>  Serve Prometheus metrics on http://<addr>/metrics");
+        println!("  --http-listen <addr>     Serve JSON on http://<addr>/status, /screens and /healthz");
+        println!("  --listen <addr>      Show messages sent by other machines (JSON lines over TCP)");
+        println!("  --remote-only        Only show remote messages, not the local screens");
+        println!("  --send <host:port>   Send the enabled screens to a remote display instead");
+        println!("  --remote-token <token>  Shared token for --listen and --send");
         println!("  --version, -V        Show version information");
         println!("  --help, -h           Show this help message");
         println!();
         println!("Environment Variables:");
+        println!("  INFO_DISPLAY_CONFIG=<path>              Config file");
         println!("  INFO_DISPLAY_INTERVAL=<seconds>         Update interval");
         println!("  INFO_DISPLAY_SCREEN_DURATION=<seconds>  Screen duration");
-        println!("  INFO_DISPLAY_SCREENS=<screen1,screen2>  Enabled screens");
+        println!("  INFO_DISPLAY_FIRST_SCREEN=<name>        Screen shown first");
+        println!("  INFO_DISPLAY_SCREENS=<screen1,screen2>  Enabled screens, separated by commas or spaces");
         println!("  INFO_DISPLAY_DAEMON=<true|false>        Daemon mode");
+        println!("  INFO_DISPLAY_PID_FILE=<path>            Daemon PID file");
+        println!("  INFO_DISPLAY_WORKING_DIR=<path>         Daemon working directory");
+        println!("  INFO_DISPLAY_START_DELAY=<seconds>      Delay before display init");
+        println!("  INFO_DISPLAY_STARTUP_MAX_WAIT=<seconds> Display init retry window");
+        println!("  INFO_DISPLAY_COMMAND_TIMEOUT_MS=<ms>    Timeout for external commands");
+        println!("  INFO_DISPLAY_VCGENCMD=<path>            Where vcgencmd is");
+        println!("  INFO_DISPLAY_STATE_DIR=<path>           Throttle event log directory");
+        println!("  INFO_DISPLAY_EVENT_RETENTION_DAYS=<N>   Days throttle events are kept");
+        println!("  INFO_DISPLAY_STALL_TIMEOUT=<N>          Update intervals without a frame before exiting");
         println!("  INFO_DISPLAY_MUX_ENABLED=<true|false>   Enable multiplexer");
         println!("  INFO_DISPLAY_MUX_CHANNEL=<0-7>          Multiplexer channel");
         println!("  INFO_DISPLAY_MUX_ADDRESS=<0xNN>         Multiplexer address");
+        println!("  INFO_DISPLAY_I2C_SPEED=<hz>             Expected I2C bus speed");
+        println!("  INFO_DISPLAY_ON_SCREEN_ERROR=<policy>   Screen error policy");
+        println!("  INFO_DISPLAY_SHOW_PERCENT=<true|false>  Show percentage used");
+        println!("  INFO_DISPLAY_WRAP=<true|false>          Wrap long lines");
+        println!("  INFO_DISPLAY_FONT=<ascii|latin1>        Content font");
+        println!("  INFO_DISPLAY_HEARTBEAT=<true|false>     Blink a heartbeat pixel");
+        println!("  INFO_DISPLAY_CLEAR_ON_EXIT=<true|false> Blank the display when stopped");
+        println!("  INFO_DISPLAY_FAN_CONTROL=<true|false>   Control the PWM fan");
+        println!("  INFO_DISPLAY_EPD=<true|false>           Use the e-paper display");
+        println!("  INFO_DISPLAY_MUX_TARGET=<mux:channel>   Display's multiplexer and channel");
+        println!("  INFO_DISPLAY_I2C_PROBE=<true|false>     Probe the I2C bus for the GPIO screen");
+        println!("  INFO_DISPLAY_I2C_BUSES=<list>           I2C buses to probe");
+        println!("  INFO_DISPLAY_W1_FAMILY=<code>           1-Wire family code listed on the GPIO screen");
+        println!("  INFO_DISPLAY_COLUMNS=<1-2>              Content columns");
+        println!("  INFO_DISPLAY_MAX_COLUMNS=<n>            Characters per content line");
+        println!("  INFO_DISPLAY_DEMO=<true|false>          Show synthetic data");
+        println!("  INFO_DISPLAY_OVERVIEW_FIELDS=<list>     Overview lines");
+        println!("  INFO_DISPLAY_DHT22_GPIO=<pin>           DHT22 data pin");
+        println!("  INFO_DISPLAY_QR_CONTENT=<text>          QR code content");
+        println!("  INFO_DISPLAY_PLACEHOLDER=<text>         Text shown for missing values");
+        println!("  INFO_DISPLAY_HOSTNAME_SOURCE=<source>   Hostname source");
+        println!("  INFO_DISPLAY_IP_VERSION=<v4|v6|both>    Network screen addresses");
+        println!("  INFO_DISPLAY_UPTIME_STYLE=<compact|since>  Uptime as a duration or as the boot time");
+        println!("  INFO_DISPLAY_DATE_FORMAT=<format>       strftime format of the boot time");
+        println!("  INFO_DISPLAY_TEMP_SMOOTHING=<mode>      off, ema:<alpha> or window:<N>");
+        println!("  INFO_DISPLAY_LOG_LEVEL=<level>          Log level (overrides RUST_LOG)");
+        println!("  INFO_DISPLAY_OTEL=<true|false>          Enable OpenTelemetry export");
+        println!("  INFO_DISPLAY_METRICS_LISTEN=<addr>      Prometheus metrics address");
+        println!("  INFO_DISPLAY_HTTP_LISTEN=<addr>         JSON status address");
+        println!("  INFO_DISPLAY_MQTT=<true|false>          Enable MQTT publishing");
+        println!("  INFO_DISPLAY_REMOTE_LISTEN=<addr>       Remote display listen address");
+        println!("  INFO_DISPLAY_REMOTE_SEND=<host:port>    Remote display to send to");
+        println!("  INFO_DISPLAY_REMOTE_TOKEN=<token>       Remote display shared token");
         println!();
         println!("Examples:");
         println!("  {} --network --system                    # Show network and system screens", program_name);
@@ -163,5 +803,38 @@ impl CliParser {
         println!("  {} --screen-duration 15 --overview       # Show overview screen for 15s each", program_name);
         println!("  {} --mux --mux-channel 3                 # Use multiplexer channel 3", program_name);
         println!("  INFO_DISPLAY_SCREENS=network,system {} # Set screens via environment", program_name);
+        println!();
+        println!("Exit Codes:");
+        println!("  0  Success");
+        println!("  1  Other error");
+        println!("  2  Invalid configuration (arguments, environment or config file)");
+        println!("  3  Display or other hardware error");
+        println!("  4  Permission denied, e.g. on /dev/i2c-1 or the GPIO devices");
+        println!("  5  Could not start as a daemon");
+        println!("  6  No frame was drawn within --stall-timeout");
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_level(args: &[&str]) -> Option<String> {
+        let args = std::iter::once("info_display").chain(args.iter().copied()).map(String::from);
+        CliParser::parse_args(args).unwrap().log_level
+    }
+
+    #[test]
+    fn test_verbosity_flags() {
+        assert_eq!(log_level(&["-v"]).as_deref(), Some("debug"));
+        assert_eq!(log_level(&["-vv"]).as_deref(), Some("trace"));
+        assert_eq!(log_level(&["-v", "--verbose"]).as_deref(), Some("trace"));
+        assert_eq!(log_level(&["--quiet"]).as_deref(), Some("error"));
+        assert_eq!(log_level(&["-q", "-v"]).as_deref(), Some("error"));
+    }
+
+    #[test]
+    fn test_log_level_beats_verbosity() {
+        assert_eq!(log_level(&["-v", "--log-level", "warn"]).as_deref(), Some("warn"));
+        assert_eq!(log_level(&["--log-level=info", "-q"]).as_deref(), Some("info"));
     }
-}
\ No newline at end of file
+}
@@ -1,289 +1,2465 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::env;
-use crate::screen_factory::ScreenFactory;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::format::{Item, StrftimeItems};
+use serde::Deserialize;
+use crate::cli::CliParser;
+use crate::display::{ContentFont, LINE_CHARS, MAX_COLUMNS, MAX_I2C_SPEED, MIN_I2C_SPEED, MIN_LINE_CHARS};
+use crate::screen_factory::ScreenRegistry;
+use crate::screens::{
+    IpVersion, OverviewField, ScreenOptions, UptimeFormat, UptimeStyle, DEFAULT_DATE_FORMAT, DEFAULT_INODE_WARNING_PERCENT,
+    DEFAULT_PROCESS_WARNING_COUNT, DEFAULT_THERMAL_MARGIN_WARNING,
+};
+use crate::system_info::dht22::MAX_GPIO_PIN;
+use crate::system_info::gpio::GpioInput;
+use crate::system_info::{BatteryHat, HostnameSource, TempSmoothing};
+use crate::system_info::command::DEFAULT_COMMAND_TIMEOUT;
+use crate::system_info::firmware::DEFAULT_VCGENCMD_PATH;
+use crate::system_info::events::{DEFAULT_EVENT_RETENTION_DAYS, DEFAULT_STATE_DIR};
+use crate::system_info::placeholder::DEFAULT_PLACEHOLDER;
+
+// Shortest update interval and screen duration; anything faster only burns CPU
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
 
 #[derive(Debug, Clone)]
 pub struct AppConfig {
-    pub interval_seconds: u64,
-    pub screen_duration_secs: u64,
+    pub interval: Duration,
+    pub screen_duration: Duration,
     pub enabled_screens: Vec<String>,
+    // Seconds per screen from --screens-file, in place of screen_duration
+    pub screen_durations: HashMap<String, Duration>,
+    // Shown first after startup when enabled, instead of the first in the list
+    pub first_screen: Option<String>,
     pub daemon_mode: bool,
+    pub pid_file: PathBuf,
+    pub working_dir: PathBuf,
     pub clear_only: bool,
+    pub list_screens: bool,
+    pub waybar: bool,
+    pub mux_scan: bool,
     pub multiplexer: MultiplexerConfig,
+    pub i2c_speed: Option<u32>,
+    pub i2c_probe: bool,
+    pub i2c_buses: Vec<u8>,
+    pub w1_family: Option<u8>,
+    pub on_screen_error: ScreenErrorPolicy,
+    pub start_delay_secs: u64,
+    pub startup_max_wait_secs: u64,
+    pub command_timeout: Duration,
+    // Run from the PATH unless configured, e.g. /usr/bin/vcgencmd on Ubuntu
+    pub vcgencmd_path: String,
+    // Throttle events are logged here and kept for this many days
+    pub state_dir: PathBuf,
+    pub event_retention_days: u32,
+    // Applied to the temperatures before screens, exporters and alerts see them
+    pub temp_smoothing: TempSmoothing,
+    pub log_level: Option<String>,
+    pub telemetry_enabled: bool,
+    pub show_percent: bool,
+    pub columns: u8,
+    // Characters per content line; 128px / 6px wide glyphs unless configured
+    pub max_columns: usize,
+    // Wrap long lines onto the next rows instead of cutting them at max_columns
+    pub wrap: bool,
+    // The font without a degree sign gets temperatures as "51.9C"
+    pub font: ContentFont,
+    pub heartbeat: bool,
+    // Blank the display when the loop stops on SIGTERM or SIGINT, rather than leave the last frame
+    pub clear_on_exit: bool,
+    // Exit when no frame was drawn for this many update intervals; 0 leaves it to systemd
+    pub stall_intervals: u32,
+    pub overview_fields: Vec<String>,
+    pub ip_version: IpVersion,
+    pub uptime_style: UptimeStyle,
+    // strftime format of the boot time in the since uptime style
+    pub date_format: String,
+    pub dht22_gpio: Option<u8>,
+    pub qr_content: Option<String>,
+    pub placeholder: String,
+    pub hostname_source: HostnameSource,
+    pub metrics_listen: Option<SocketAddr>,
+    pub http_listen: Option<SocketAddr>,
+    pub remote_listen: Option<SocketAddr>,
+    pub remote_send: Option<String>,
+    pub remote_token: Option<String>,
+    pub remote_only: bool,
+    pub demo_mode: bool,
+    pub mqtt: MqttConfig,
+    pub mqtt_clear_discovery: bool,
+    pub influxdb: InfluxDbConfig,
+    pub webhook: WebhookConfig,
+    pub remote_host: RemoteHostConfig,
+    pub battery: BatteryConfig,
+    pub fan: FanConfig,
+    pub epd: EpdConfig,
+    // Input pins watched for the GPIO screen, from [[gpio_inputs]]
+    pub gpio_inputs: Vec<GpioInput>,
+    // [screens.<name>] sections of the config file, in file order
+    pub screen_sections: Vec<(String, ScreenSection)>,
 }
 
-#[derive(Debug, Clone)]
-pub struct MultiplexerConfig {
+/// What to do when a screen fails to render
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ScreenErrorPolicy {
+    /// Move on to the next screen that renders successfully
+    #[default]
+    Skip,
+    /// Keep the failing screen's title and show a placeholder body
+    Placeholder,
+    /// Show the named screen instead
+    Fallback(String),
+}
+
+impl ScreenErrorPolicy {
+    pub fn parse(value: &str) -> Result<Self, ConfigError> {
+        let value = value.trim();
+        match value.to_lowercase().as_str() {
+            "skip" => Ok(ScreenErrorPolicy::Skip),
+            "placeholder" => Ok(ScreenErrorPolicy::Placeholder),
+            _ => match value.strip_prefix("fallback:") {
+                Some(screen) if !screen.trim().is_empty() => {
+                    Ok(ScreenErrorPolicy::Fallback(screen.trim().to_string()))
+                }
+                _ => Err(ConfigError::InvalidScreenErrorPolicy(value.to_string())),
+            },
+        }
+    }
+}
+
+/// MQTT publishing, configured in the `[mqtt]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
     pub enabled: bool,
-    pub channel: u8,
-    pub address: u8,
+    /// mqtt://host[:port], port defaults to 1883
+    pub broker_url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Values go to <base_topic>/<hostname>/state
+    pub base_topic: String,
+    pub qos: u8,
+    /// Announce the sensors to Home Assistant
+    pub discovery: bool,
+    pub discovery_prefix: String,
 }
 
-impl Default for AppConfig {
+impl Default for MqttConfig {
     fn default() -> Self {
         Self {
-            interval_seconds: 5,
-            screen_duration_secs: 10,
-            enabled_screens: vec!["overview".to_string()],
-            daemon_mode: false,
-            clear_only: false,
-            multiplexer: MultiplexerConfig::default(),
+            enabled: false,
+            broker_url: "mqtt://localhost:1883".to_string(),
+            username: None,
+            password: None,
+            base_topic: "raspi".to_string(),
+            qos: 0,
+            discovery: true,
+            discovery_prefix: "homeassistant".to_string(),
         }
     }
 }
 
-impl Default for MultiplexerConfig {
+impl MqttConfig {
+    /// Host and port of the broker
+    pub fn broker_address(&self) -> Result<(String, u16), ConfigError> {
+        let invalid = || ConfigError::InvalidMqttBroker(self.broker_url.clone());
+        let url = self.broker_url.trim();
+        let address = url.strip_prefix("mqtt://")
+            .or_else(|| url.strip_prefix("tcp://"))
+            .unwrap_or(url);
+        if address.is_empty() || address.contains('/') {
+            return Err(invalid());
+        }
+
+        match address.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() => {
+                Ok((host.to_string(), port.parse().map_err(|_| invalid())?))
+            }
+            Some(_) => Err(invalid()),
+            None => Ok((address.to_string(), 1883)),
+        }
+    }
+}
+
+/// InfluxDB 2.x export, configured in the `[influxdb]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InfluxDbConfig {
+    pub enabled: bool,
+    /// Base URL of the server, e.g. http://influx.lan:8086
+    pub url: String,
+    pub org: String,
+    pub bucket: String,
+    pub token: Option<String>,
+    /// Snapshots collected before each write
+    pub batch_intervals: u32,
+    /// Points kept while the server is unreachable; the oldest are dropped beyond this
+    pub max_queue: usize,
+}
+
+impl Default for InfluxDbConfig {
     fn default() -> Self {
         Self {
             enabled: false,
-            channel: 0,
-            address: 0x70,
+            url: "http://localhost:8086".to_string(),
+            org: String::new(),
+            bucket: String::new(),
+            token: None,
+            batch_intervals: 6,
+            max_queue: 1000,
         }
     }
 }
 
-impl AppConfig {
-    pub fn enabled_screens_as_str_refs(&self) -> Vec<&str> {
-        self.enabled_screens.iter().map(|s| s.as_str()).collect()
+impl InfluxDbConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: &str| Err(ConfigError::InvalidInfluxDb(reason.to_string()));
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return invalid(&format!("url must start with http:// or https://, got {}", self.url));
+        }
+        if self.org.is_empty() || self.bucket.is_empty() {
+            return invalid("org and bucket are required");
+        }
+        if self.batch_intervals == 0 || self.max_queue == 0 {
+            return invalid("batch_intervals and max_queue must be at least 1");
+        }
+        Ok(())
     }
+}
 
-    pub fn from_env() -> Self {
-        let mut config = Self::default();
-        config.apply_env_vars();
-        config
-    }
+/// Alert notifications over HTTP, configured in the `[webhook]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub enabled: bool,
+    /// Receives a JSON POST per notification, e.g. a Slack or Discord webhook URL
+    pub url: String,
+    /// A condition that persists is notified again after this many minutes
+    pub renotify_minutes: u64,
+    /// Thresholds; leave one out (or set the flags to false) to disable that alert
+    pub cpu_temp_celsius: Option<f32>,
+    pub memory_percent: Option<f32>,
+    pub disk_percent: Option<f32>,
+    /// Inodes used on the root filesystem
+    pub inode_percent: Option<f32>,
+    pub under_voltage: bool,
+    pub throttled: bool,
+    /// A filesystem remounted read-only, e.g. after SD card errors
+    pub read_only_mount: bool,
+    /// Milliseconds the clock may be off its NTP source, either way
+    pub clock_offset_ms: Option<f64>,
+    /// Zombie processes allowed before alerting
+    pub zombie_processes: Option<usize>,
+    /// Degrees the CPU must stay below its throttling trip point
+    pub thermal_margin_celsius: Option<f32>,
+}
 
-    pub fn apply_env_vars(&mut self) {
-        // Interval
-        if let Ok(interval_str) = env::var("INFO_DISPLAY_INTERVAL") {
-            if let Ok(interval) = interval_str.parse::<u64>() {
-                if interval > 0 {
-                    self.interval_seconds = interval;
-                }
-            }
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            renotify_minutes: 30,
+            cpu_temp_celsius: Some(80.0),
+            memory_percent: None,
+            disk_percent: None,
+            inode_percent: None,
+            under_voltage: true,
+            throttled: false,
+            read_only_mount: true,
+            clock_offset_ms: None,
+            zombie_processes: None,
+            thermal_margin_celsius: None,
         }
+    }
+}
 
-        // Screen duration
-        if let Ok(duration_str) = env::var("INFO_DISPLAY_SCREEN_DURATION") {
-            if let Ok(duration) = duration_str.parse::<u64>() {
-                if duration > 0 {
-                    self.screen_duration_secs = duration;
-                }
-            }
+impl WebhookConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: &str| Err(ConfigError::InvalidWebhook(reason.to_string()));
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return invalid(&format!("url must start with http:// or https://, got {:?}", self.url));
         }
-
-        // Enabled screens
-        if let Ok(screens_str) = env::var("INFO_DISPLAY_SCREENS") {
-            let screens: Vec<String> = screens_str
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty() && ScreenFactory::validate_screen_type(s))
-                .collect();
-            if !screens.is_empty() {
-                self.enabled_screens = screens;
+        if self.renotify_minutes == 0 {
+            return invalid("renotify_minutes must be at least 1");
+        }
+        for percent in [self.memory_percent, self.disk_percent, self.inode_percent].into_iter().flatten() {
+            if !(0.0..=100.0).contains(&percent) {
+                return invalid(&format!("percentages must be 0-100, got {}", percent));
             }
         }
-
-        // Daemon mode
-        if let Ok(daemon_str) = env::var("INFO_DISPLAY_DAEMON") {
-            self.daemon_mode = daemon_str.to_lowercase() == "true" || daemon_str == "1";
+        if self.clock_offset_ms.is_some_and(|limit| limit <= 0.0) {
+            return invalid("clock_offset_ms must be above 0");
         }
-
-        // Multiplexer config
-        if let Ok(mux_enabled_str) = env::var("INFO_DISPLAY_MUX_ENABLED") {
-            self.multiplexer.enabled = mux_enabled_str.to_lowercase() == "true" || mux_enabled_str == "1";
+        if self.thermal_margin_celsius.is_some_and(|margin| margin < 0.0) {
+            return invalid("thermal_margin_celsius must not be negative");
         }
+        Ok(())
+    }
+}
 
-        if let Ok(mux_channel_str) = env::var("INFO_DISPLAY_MUX_CHANNEL") {
-            if let Ok(channel) = mux_channel_str.parse::<u8>() {
-                if channel <= 7 {
-                    self.multiplexer.channel = channel;
-                }
-            }
-        }
+/// Stats of another machine read over SSH, configured in the `[remote]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoteHostConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    /// Private key; without one the SSH agent is asked
+    pub key: Option<PathBuf>,
+    /// Host keys to trust, ~/.ssh/known_hosts by default
+    pub known_hosts: Option<PathBuf>,
+    /// Screens that show the remote host instead of this one
+    pub screens: Vec<String>,
+    /// Seconds between collections
+    pub poll_secs: u64,
+    /// Limit on connecting and on each SSH operation
+    pub timeout_secs: u64,
+}
 
-        if let Ok(mux_addr_str) = env::var("INFO_DISPLAY_MUX_ADDRESS") {
-            if let Ok(address) = u8::from_str_radix(&mux_addr_str.trim_start_matches("0x"), 16) {
-                self.multiplexer.address = address;
-            } else if let Ok(address) = mux_addr_str.parse::<u8>() {
-                self.multiplexer.address = address;
-            }
+impl Default for RemoteHostConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: 22,
+            user: String::new(),
+            key: None,
+            known_hosts: None,
+            screens: Vec::new(),
+            poll_secs: 10,
+            timeout_secs: 5,
         }
     }
+}
 
-    pub fn validate(&self) -> Result<(), ConfigError> {
-        // Validate interval
-        if self.interval_seconds == 0 {
-            return Err(ConfigError::InvalidInterval);
+impl RemoteHostConfig {
+    fn validate(&self, registry: &ScreenRegistry) -> Result<(), ConfigError> {
+        let invalid = |reason: &str| Err(ConfigError::InvalidRemoteHost(reason.to_string()));
+        if self.host.is_empty() || self.user.is_empty() {
+            return invalid("host and user are required");
         }
-
-        // Validate screen duration
-        if self.screen_duration_secs == 0 {
-            return Err(ConfigError::InvalidScreenDuration);
+        if self.screens.is_empty() {
+            return invalid("screens must list at least one screen");
         }
-
-        // Validate screens
-        if self.enabled_screens.is_empty() {
-            return Err(ConfigError::NoScreensEnabled);
+        if let Some(screen) = self.screens.iter().find(|screen| !registry.contains(screen)) {
+            return invalid(&format!("unknown screen {}", screen));
         }
-
-        for screen in &self.enabled_screens {
-            if !ScreenFactory::validate_screen_type(screen) {
-                return Err(ConfigError::InvalidScreen(screen.clone()));
-            }
+        if self.poll_secs == 0 || self.timeout_secs == 0 {
+            return invalid("poll_secs and timeout_secs must be at least 1");
         }
+        Ok(())
+    }
+}
 
-        // Validate multiplexer config
-        if self.multiplexer.channel > 7 {
-            return Err(ConfigError::InvalidMultiplexerChannel(self.multiplexer.channel));
+/// The UPS HAT read by the battery screen, configured in the `[battery]` section of the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatteryConfig {
+    pub hat: BatteryHat,
+    /// 7-bit I2C address, when the HAT is not at its usual one
+    pub address: Option<u8>,
+}
+
+/// PWM fan control driven by the CPU temperature, configured in the `[fan]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FanConfig {
+    pub enabled: bool,
+    /// hwmon directory with the fan's pwm1, found by name (pwmfan) when not set
+    pub hwmon: Option<PathBuf>,
+    /// [temperature °C, duty] points in rising temperature order; the duty is
+    /// interpolated between them and clamped to 0-255
+    pub curve: Vec<(f32, i32)>,
+}
+
+impl Default for FanConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hwmon: None,
+            curve: vec![(50.0, 0), (60.0, 100), (70.0, 180), (80.0, 255)],
         }
+    }
+}
 
+impl FanConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: &str| Err(ConfigError::InvalidFan(reason.to_string()));
+        if self.curve.is_empty() {
+            return invalid("curve needs at least one point");
+        }
+        if self.curve.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return invalid("curve temperatures must be rising");
+        }
         Ok(())
     }
+}
 
-    pub fn add_screen(&mut self, screen: &str) {
-        // Replace default overview with first specific screen
-        if self.enabled_screens == vec!["overview"] && screen != "overview" {
-            self.enabled_screens = vec![screen.to_string()];
-        } else if !self.enabled_screens.contains(&screen.to_string()) {
-            self.enabled_screens.push(screen.to_string());
+/// A Waveshare 2.13" e-paper HAT in place of the OLED, configured in the `[epd]` section
+/// of the config file. Pins are BCM numbers; the defaults are the HAT's wiring.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EpdConfig {
+    pub enabled: bool,
+    /// Partial refreshes between full ones, which clear the ghosting partial ones leave
+    pub full_refresh_every: u32,
+    /// Shortest time between refreshes; a shorter update interval is raised to this
+    pub min_refresh_secs: u64,
+    pub spi_hz: u32,
+    pub dc_pin: u8,
+    pub reset_pin: u8,
+    pub busy_pin: u8,
+}
+
+impl Default for EpdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            full_refresh_every: 20,
+            min_refresh_secs: 30,
+            spi_hz: 4_000_000,
+            dc_pin: 25,
+            reset_pin: 17,
+            busy_pin: 24,
         }
     }
+}
 
-    pub fn enable_multiplexer(&mut self) {
-        self.multiplexer.enabled = true;
+impl EpdConfig {
+    /// How often the panel may refresh at most.
+    pub fn min_refresh(&self) -> Duration {
+        Duration::from_secs(self.min_refresh_secs)
     }
 
-    pub fn set_multiplexer_channel(&mut self, channel: u8) -> Result<(), ConfigError> {
-        if channel > 7 {
-            return Err(ConfigError::InvalidMultiplexerChannel(channel));
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: &str| Err(ConfigError::InvalidEpd(reason.to_string()));
+        let pins = [self.dc_pin, self.reset_pin, self.busy_pin];
+        if let Some(pin) = pins.iter().find(|&&pin| pin > MAX_GPIO_PIN) {
+            return invalid(&format!("GPIO {} does not exist (must be 0-{})", pin, MAX_GPIO_PIN));
+        }
+        if pins[0] == pins[1] || pins[0] == pins[2] || pins[1] == pins[2] {
+            return invalid("dc_pin, reset_pin and busy_pin must be different pins");
+        }
+        if self.spi_hz == 0 {
+            return invalid("spi_hz must be above 0");
         }
-        self.multiplexer.channel = channel;
-        self.multiplexer.enabled = true;
         Ok(())
     }
+}
 
-    pub fn set_multiplexer_address(&mut self, address: u8) {
-        self.multiplexer.address = address;
-    }
+/// A TCA9548A on the bus, from a `[[multiplexers]]` entry of the config file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MuxDefinition {
+    pub address: u8,
+    /// Name to use instead of the address in --mux-target
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
-#[derive(Debug)]
-pub enum ConfigError {
-    InvalidInterval,
-    InvalidScreenDuration,
-    NoScreensEnabled,
-    InvalidScreen(String),
-    InvalidMultiplexerChannel(u8),
+/// Options from a `[screens.<name>]` section of the config file. A section enables
+/// its screen, and what it sets applies to that screen only.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScreenSection {
+    Network(NetworkSection),
+    Storage(StorageSection),
+    System(SystemSection),
+    Temperature(TemperatureSection),
+    Overview(OverviewSection),
+    Gpio(GpioSection),
+    Ambient(AmbientSection),
+    Qr(QrSection),
+    // Screens without options of their own
+    Plain,
 }
 
-impl fmt::Display for ConfigError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkSection {
+    /// Show this interface instead of the first wired one
+    pub interface: Option<String>,
+    /// v4, v6 or both
+    pub ip_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSection {
+    pub show_percent: Option<bool>,
+    /// Show the root filesystem's inode usage once it is above this percentage
+    pub inode_warning_percent: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SystemSection {
+    /// Show the process count once there are more than this many processes
+    pub process_warning_count: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemperatureSection {
+    /// Mark the margin to the CPU's trip point once it is below this many degrees
+    pub margin_warning_celsius: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverviewSection {
+    pub show_percent: Option<bool>,
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpioSection {
+    pub i2c_probe: Option<bool>,
+    pub i2c_buses: Option<Vec<u8>>,
+    // 1-Wire family code, e.g. 0x28 to list only DS18B20 sensors
+    pub w1_family: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AmbientSection {
+    /// BCM GPIO of the DHT22
+    pub gpio: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QrSection {
+    pub content: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoOptions {}
+
+impl ScreenSection {
+    fn parse(screen: &str, section: toml::Value) -> Result<Self, ConfigError> {
+        let invalid = |e: toml::de::Error| ConfigError::ConfigFile(format!("[screens.{}]: {}", screen, e));
+        let section = match screen {
+            "network" => ScreenSection::Network(section.try_into().map_err(invalid)?),
+            "storage" => ScreenSection::Storage(section.try_into().map_err(invalid)?),
+            "system" => ScreenSection::System(section.try_into().map_err(invalid)?),
+            "temperature" => ScreenSection::Temperature(section.try_into().map_err(invalid)?),
+            "overview" => ScreenSection::Overview(section.try_into().map_err(invalid)?),
+            "gpio" => ScreenSection::Gpio(section.try_into().map_err(invalid)?),
+            "ambient" => ScreenSection::Ambient(section.try_into().map_err(invalid)?),
+            "qr" => ScreenSection::Qr(section.try_into().map_err(invalid)?),
+            _ => {
+                section.try_into::<NoOptions>().map_err(invalid)?;
+                ScreenSection::Plain
+            }
+        };
+        section.validate()?;
+        Ok(section)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
         match self {
-            ConfigError::InvalidInterval => write!(f, "Update interval must be greater than 0"),
-            ConfigError::InvalidScreenDuration => write!(f, "Screen duration must be greater than 0"),
-            ConfigError::NoScreensEnabled => write!(f, "At least one screen must be enabled"),
-            ConfigError::InvalidScreen(screen) => write!(f, "Invalid screen type: {}", screen),
-            ConfigError::InvalidMultiplexerChannel(channel) => write!(f, "Multiplexer channel must be 0-7, got: {}", channel),
+            ScreenSection::Overview(OverviewSection { fields: Some(fields), .. }) => {
+                if fields.is_empty() {
+                    return Err(ConfigError::NoOverviewFields);
+                }
+                if let Some(field) = fields.iter().find(|field| OverviewField::from_name(field).is_none()) {
+                    return Err(ConfigError::InvalidOverviewField(field.clone()));
+                }
+            }
+            ScreenSection::Network(NetworkSection { ip_version: Some(version), .. }) if IpVersion::from_name(version).is_none() => {
+                return Err(ConfigError::InvalidIpVersion(version.clone()));
+            }
+            ScreenSection::Gpio(GpioSection { i2c_buses: Some(buses), .. }) if buses.is_empty() => {
+                return Err(ConfigError::InvalidI2cBuses(String::new()));
+            }
+            ScreenSection::Storage(StorageSection { inode_warning_percent: Some(percent), .. }) if *percent > 100 => {
+                return Err(ConfigError::InvalidInodeWarningPercent(*percent));
+            }
+            ScreenSection::Ambient(AmbientSection { gpio: Some(pin) }) if *pin > MAX_GPIO_PIN => {
+                return Err(ConfigError::InvalidGpioPin(*pin));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn apply(&self, options: &mut ScreenOptions) {
+        match self {
+            ScreenSection::Network(section) => {
+                if let Some(interface) = &section.interface {
+                    options.network_interface = Some(interface.clone());
+                }
+                // Checked in validate()
+                if let Some(version) = section.ip_version.as_deref().and_then(IpVersion::from_name) {
+                    options.ip_version = version;
+                }
+            }
+            ScreenSection::Storage(section) => {
+                if let Some(show_percent) = section.show_percent {
+                    options.show_percent = show_percent;
+                }
+                if let Some(percent) = section.inode_warning_percent {
+                    options.inode_warning_percent = percent;
+                }
+            }
+            ScreenSection::System(section) => {
+                if let Some(count) = section.process_warning_count {
+                    options.process_warning_count = count;
+                }
+            }
+            ScreenSection::Temperature(section) => {
+                if let Some(margin) = section.margin_warning_celsius {
+                    options.thermal_margin_warning = margin;
+                }
+            }
+            ScreenSection::Overview(section) => {
+                if let Some(show_percent) = section.show_percent {
+                    options.show_percent = show_percent;
+                }
+                if let Some(fields) = &section.fields {
+                    // Names are checked in validate()
+                    options.overview_fields = fields.iter().filter_map(|name| OverviewField::from_name(name)).collect();
+                }
+            }
+            ScreenSection::Gpio(section) => {
+                if let Some(probe) = section.i2c_probe {
+                    options.i2c_probe = probe;
+                }
+                if let Some(buses) = &section.i2c_buses {
+                    options.i2c_buses = buses.clone();
+                }
+                if let Some(family) = section.w1_family {
+                    options.w1_family = Some(family);
+                }
+            }
+            ScreenSection::Ambient(section) => {
+                if let Some(pin) = section.gpio {
+                    options.dht22_gpio = Some(pin);
+                }
+            }
+            ScreenSection::Qr(section) => {
+                if let Some(content) = &section.content {
+                    options.qr_content = Some(content.clone());
+                }
+            }
+            ScreenSection::Plain => {}
         }
     }
 }
 
-impl std::error::Error for ConfigError {}
+// Layout of the file passed with --config
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    mqtt: MqttConfig,
+    influxdb: InfluxDbConfig,
+    webhook: WebhookConfig,
+    remote: RemoteHostConfig,
+    battery: BatteryConfig,
+    fan: FanConfig,
+    epd: EpdConfig,
+    multiplexers: Vec<MuxDefinition>,
+    gpio_inputs: Vec<GpioInput>,
+    screens: toml::Table,
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+// Looks up an environment variable by name
+type EnvLookup = Box<dyn Fn(&str) -> Option<String>>;
 
-    #[test]
-    fn test_default_config() {
-        let config = AppConfig::default();
-        assert_eq!(config.interval_seconds, 5);
-        assert_eq!(config.enabled_screens, vec!["overview"]);
-        assert!(!config.multiplexer.enabled);
-    }
+/// Builds the configuration in layers, each overriding the one before: the built-in
+/// defaults, the config file, INFO_DISPLAY_* environment variables and the command line.
+pub struct ConfigBuilder {
+    config_file: Option<PathBuf>,
+    env: EnvLookup,
+    args: Vec<String>,
+}
 
-    #[test]
-    fn test_add_screen_replaces_overview() {
-        let mut config = AppConfig::default();
-        config.add_screen("network");
-        assert_eq!(config.enabled_screens, vec!["network"]);
-        
-        config.add_screen("system");
-        assert_eq!(config.enabled_screens, vec!["network", "system"]);
+impl ConfigBuilder {
+    /// The process environment, no arguments and no config file unless one is named there.
+    pub fn new() -> Self {
+        Self {
+            config_file: None,
+            env: Box::new(|name| env::var(name).ok()),
+            args: Vec::new(),
+        }
     }
 
-    #[test]
-    fn test_validate_valid_config() {
-        let config = AppConfig::default();
-        assert!(config.validate().is_ok());
+    /// Read this config file, whatever --config or INFO_DISPLAY_CONFIG say.
+    pub fn config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_file = Some(path.into());
+        self
     }
 
-    #[test]
-    fn test_validate_invalid_screen() {
-        let mut config = AppConfig::default();
-        config.enabled_screens = vec!["invalid".to_string()];
-        assert!(config.validate().is_err());
+    /// Look environment variables up through `var` instead, e.g. in a map.
+    pub fn env(mut self, var: impl Fn(&str) -> Option<String> + 'static) -> Self {
+        self.env = Box::new(var);
+        self
     }
 
-    #[test]
-    fn test_validate_invalid_channel() {
-        let mut config = AppConfig::default();
-        config.multiplexer.channel = 8;
-        assert!(config.validate().is_err());
+    /// Command line arguments, program name first.
+    pub fn args<I: IntoIterator<Item = String>>(mut self, args: I) -> Self {
+        self.args = args.into_iter().collect();
+        self
     }
 
-    #[test]
-    fn test_env_var_interval() {
-        unsafe {
-            env::set_var("INFO_DISPLAY_INTERVAL", "10");
-        }
-        let config = AppConfig::from_env();
-        assert_eq!(config.interval_seconds, 10);
-        unsafe {
-            env::remove_var("INFO_DISPLAY_INTERVAL");
+    /// The configuration, not validated yet so callers can validate against their own screens.
+    pub fn build(self) -> Result<AppConfig, ConfigError> {
+        let mut config = AppConfig::default();
+        let config_file = self.config_file.clone()
+            .or_else(|| CliParser::config_file_arg(&self.args))
+            .or_else(|| (self.env)("INFO_DISPLAY_CONFIG").map(PathBuf::from));
+        if let Some(path) = config_file {
+            config.apply_file(&path)?;
         }
+        config.apply_env(|name| (self.env)(name));
+        CliParser::apply_args(&mut config, &self.args)?;
+        Ok(config)
     }
+}
 
-    #[test]
-    fn test_env_var_screens() {
-        unsafe {
-            env::set_var("INFO_DISPLAY_SCREENS", "network,system,storage");
-        }
-        let config = AppConfig::from_env();
-        assert_eq!(config.enabled_screens, vec!["network", "system", "storage"]);
-        unsafe {
-            env::remove_var("INFO_DISPLAY_SCREENS");
-        }
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MultiplexerConfig {
+    pub enabled: bool,
+    pub channel: u8,
+    pub address: u8,
+    // Channels enabled together with the display's, one bit per channel
+    pub shared_channels: u8,
+    // Every multiplexer on the bus; empty when the display's is the only one
+    pub muxes: Vec<MuxDefinition>,
+    // Probed by --mux-scan on top of the display addresses
+    pub scan_addresses: Vec<u8>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            screen_duration: Duration::from_secs(10),
+            enabled_screens: vec!["overview".to_string()],
+            screen_durations: HashMap::new(),
+            first_screen: None,
+            daemon_mode: false,
+            pid_file: PathBuf::from("/tmp/info_display.pid"),
+            working_dir: PathBuf::from("/tmp"),
+            clear_only: false,
+            list_screens: false,
+            waybar: false,
+            mux_scan: false,
+            multiplexer: MultiplexerConfig::default(),
+            i2c_speed: None,
+            i2c_probe: false,
+            i2c_buses: Vec::new(),
+            w1_family: None,
+            on_screen_error: ScreenErrorPolicy::default(),
+            start_delay_secs: 0,
+            startup_max_wait_secs: 60,
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
+            vcgencmd_path: DEFAULT_VCGENCMD_PATH.to_string(),
+            state_dir: PathBuf::from(DEFAULT_STATE_DIR),
+            event_retention_days: DEFAULT_EVENT_RETENTION_DAYS,
+            temp_smoothing: TempSmoothing::Off,
+            log_level: None,
+            telemetry_enabled: false,
+            show_percent: false,
+            columns: 1,
+            max_columns: LINE_CHARS,
+            wrap: false,
+            font: ContentFont::default(),
+            heartbeat: false,
+            clear_on_exit: false,
+            stall_intervals: 0,
+            dht22_gpio: None,
+            qr_content: None,
+            placeholder: DEFAULT_PLACEHOLDER.to_string(),
+            hostname_source: HostnameSource::default(),
+            overview_fields: OverviewField::DEFAULT.iter().map(|field| field.name().to_string()).collect(),
+            ip_version: IpVersion::default(),
+            uptime_style: UptimeStyle::default(),
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            metrics_listen: None,
+            http_listen: None,
+            remote_listen: None,
+            remote_send: None,
+            remote_token: None,
+            remote_only: false,
+            demo_mode: false,
+            mqtt: MqttConfig::default(),
+            mqtt_clear_discovery: false,
+            influxdb: InfluxDbConfig::default(),
+            webhook: WebhookConfig::default(),
+            remote_host: RemoteHostConfig::default(),
+            battery: BatteryConfig::default(),
+            fan: FanConfig::default(),
+            epd: EpdConfig::default(),
+            gpio_inputs: Vec::new(),
+            screen_sections: Vec::new(),
+        }
+    }
+}
+
+impl Default for MultiplexerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel: 0,
+            address: 0x70,
+            shared_channels: 0,
+            muxes: Vec::new(),
+            scan_addresses: Vec::new(),
+        }
+    }
+}
+
+impl MultiplexerConfig {
+    /// The multiplexers other than the display's, whose channels are kept disabled.
+    pub fn other_addresses(&self) -> Vec<u8> {
+        self.muxes.iter()
+            .map(|mux| mux.address)
+            .filter(|&address| address != self.address)
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: String| Err(ConfigError::InvalidMultiplexers(reason));
+        for (i, mux) in self.muxes.iter().enumerate() {
+            // The TCA9548A's address pins select 0x70-0x77
+            if !(0x70..=0x77).contains(&mux.address) {
+                return invalid(format!("address 0x{:02X} is not a TCA9548A address (0x70-0x77)", mux.address));
+            }
+            let earlier = &self.muxes[..i];
+            if earlier.iter().any(|other| other.address == mux.address) {
+                return invalid(format!("address 0x{:02X} is listed twice", mux.address));
+            }
+            if mux.label.is_some() && earlier.iter().any(|other| other.label == mux.label) {
+                return invalid(format!("label {} is used twice", mux.label.as_deref().unwrap_or_default()));
+            }
+        }
+        if !self.muxes.is_empty() && !self.muxes.iter().any(|mux| mux.address == self.address) {
+            return invalid(format!("the display's multiplexer 0x{:02X} is not listed", self.address));
+        }
+        Ok(())
+    }
+}
+
+impl AppConfig {
+    pub fn enabled_screens_as_str_refs(&self) -> Vec<&str> {
+        self.enabled_screens.iter().map(|s| s.as_str()).collect()
+    }
+
+    pub fn screen_options(&self) -> ScreenOptions {
+        let mut options = self.shared_screen_options();
+        for (screen, section) in &self.screen_sections {
+            let mut screen_options = options.clone();
+            section.apply(&mut screen_options);
+            options.per_screen.insert(screen.clone(), screen_options);
+        }
+        options
+    }
+
+    fn shared_screen_options(&self) -> ScreenOptions {
+        ScreenOptions {
+            show_percent: self.show_percent,
+            inode_warning_percent: DEFAULT_INODE_WARNING_PERCENT,
+            process_warning_count: DEFAULT_PROCESS_WARNING_COUNT,
+            thermal_margin_warning: DEFAULT_THERMAL_MARGIN_WARNING,
+            // Names are checked in validate()
+            overview_fields: self.overview_fields.iter()
+                .filter_map(|name| OverviewField::from_name(name))
+                .collect(),
+            dht22_gpio: self.dht22_gpio,
+            qr_content: self.qr_content.clone(),
+            battery_hat: self.battery.hat,
+            battery_address: self.battery.address,
+            i2c_probe: self.i2c_probe,
+            i2c_buses: self.i2c_buses.clone(),
+            w1_family: self.w1_family,
+            network_interface: None,
+            ip_version: self.ip_version,
+            // Lines are left whole for the display to wrap at max_columns
+            max_columns: if self.wrap { usize::MAX } else { self.max_columns },
+            uptime: UptimeFormat { style: self.uptime_style, date_format: self.date_format.clone() },
+            per_screen: HashMap::new(),
+        }
+    }
+
+    /// Apply settings from a TOML config file
+    pub fn apply_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::ConfigFile(format!("{}: {}", path.display(), e)))?;
+        self.apply_toml(&contents)
+            .map_err(|reason| ConfigError::ConfigFile(format!("{}: {}", path.display(), reason)))
+    }
+
+    fn apply_toml(&mut self, contents: &str) -> Result<(), String> {
+        let file: FileConfig = toml::from_str(contents).map_err(|e| e.to_string())?;
+
+        self.mqtt = file.mqtt;
+        self.influxdb = file.influxdb;
+        self.webhook = file.webhook;
+        self.remote_host = file.remote;
+        self.battery = file.battery;
+        self.fan = file.fan;
+        self.epd = file.epd;
+        if let Some(first) = file.multiplexers.first() {
+            self.multiplexer.address = first.address;
+        }
+        self.multiplexer.muxes = file.multiplexers;
+        self.gpio_inputs = file.gpio_inputs;
+        for (screen, section) in file.screens {
+            let section = ScreenSection::parse(&screen, section).map_err(|e| e.to_string())?;
+            self.add_screen(&screen);
+            self.screen_sections.push((screen, section));
+        }
+        Ok(())
+    }
+
+    /// Take the screens, and how long each is shown, from a file written for
+    /// parse_screens_file
+    pub fn apply_screens_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::InvalidScreensFile(format!("{}: {}", path.display(), e)))?;
+        let entries = parse_screens_file(&contents)
+            .map_err(|reason| ConfigError::InvalidScreensFile(format!("{}: {}", path.display(), reason)))?;
+
+        self.enabled_screens = entries.iter().map(|(screen, _)| screen.clone()).collect();
+        self.screen_durations = entries.into_iter()
+            .filter_map(|(screen, duration)| Some((screen, duration?)))
+            .collect();
+        Ok(())
+    }
+
+    pub fn apply_env_vars(&mut self) {
+        self.apply_env(|name| env::var(name).ok());
+    }
+
+    /// Apply INFO_DISPLAY_* settings looked up through `var`, e.g. from a map in tests
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) {
+        // Interval
+        if let Some(interval_str) = var("INFO_DISPLAY_INTERVAL") {
+            if let Some(interval) = parse_seconds(&interval_str) {
+                if !interval.is_zero() {
+                    self.interval = interval;
+                }
+            }
+        }
+
+        // Screen duration
+        if let Some(duration_str) = var("INFO_DISPLAY_SCREEN_DURATION") {
+            if let Some(duration) = parse_seconds(&duration_str) {
+                if !duration.is_zero() {
+                    self.screen_duration = duration;
+                }
+            }
+        }
+
+        if let Some(first_str) = var("INFO_DISPLAY_FIRST_SCREEN") {
+            let first = first_str.trim();
+            if !first.is_empty() {
+                self.first_screen = Some(first.to_string());
+            }
+        }
+
+        // Enabled screens
+        if let Some(screens_str) = var("INFO_DISPLAY_SCREENS") {
+            let screens = parse_screen_list(&screens_str);
+            if !screens.is_empty() {
+                self.enabled_screens = screens;
+            }
+        }
+
+        // Daemon mode
+        if let Some(daemon_str) = var("INFO_DISPLAY_DAEMON") {
+            self.daemon_mode = daemon_str.to_lowercase() == "true" || daemon_str == "1";
+        }
+
+        if let Some(pid_file) = var("INFO_DISPLAY_PID_FILE") {
+            if !pid_file.trim().is_empty() {
+                self.pid_file = PathBuf::from(pid_file.trim());
+            }
+        }
+
+        if let Some(working_dir) = var("INFO_DISPLAY_WORKING_DIR") {
+            if !working_dir.trim().is_empty() {
+                self.working_dir = PathBuf::from(working_dir.trim());
+            }
+        }
+
+        // Multiplexer config
+        if let Some(speed_str) = var("INFO_DISPLAY_I2C_SPEED") {
+            if let Ok(speed) = speed_str.trim().parse::<u32>() {
+                self.i2c_speed = Some(speed);
+            }
+        }
+
+        if let Some(probe_str) = var("INFO_DISPLAY_I2C_PROBE") {
+            self.i2c_probe = probe_str.to_lowercase() == "true" || probe_str == "1";
+        }
+
+        if let Some(buses_str) = var("INFO_DISPLAY_I2C_BUSES") {
+            if let Ok(buses) = parse_bus_list(&buses_str) {
+                self.i2c_buses = buses;
+            }
+        }
+
+        if let Some(family_str) = var("INFO_DISPLAY_W1_FAMILY") {
+            if let Ok(family) = parse_w1_family(&family_str) {
+                self.w1_family = Some(family);
+            }
+        }
+
+        if let Some(mux_enabled_str) = var("INFO_DISPLAY_MUX_ENABLED") {
+            self.multiplexer.enabled = mux_enabled_str.to_lowercase() == "true" || mux_enabled_str == "1";
+        }
+
+        if let Some(mux_channel_str) = var("INFO_DISPLAY_MUX_CHANNEL") {
+            if let Ok(channel) = mux_channel_str.parse::<u8>() {
+                if channel <= 7 {
+                    self.multiplexer.channel = channel;
+                }
+            }
+        }
+
+        if let Some(mux_addr_str) = var("INFO_DISPLAY_MUX_ADDRESS") {
+            if let Ok(address) = u8::from_str_radix(mux_addr_str.trim_start_matches("0x"), 16) {
+                self.multiplexer.address = address;
+            } else if let Ok(address) = mux_addr_str.parse::<u8>() {
+                self.multiplexer.address = address;
+            }
+        }
+
+        // A target names both, e.g. 0x71:3; invalid ones are ignored like the other variables
+        if let Some(target) = var("INFO_DISPLAY_MUX_TARGET") {
+            let _ = self.set_multiplexer_target(&target);
+        }
+
+        // Startup timing
+        if let Some(delay_str) = var("INFO_DISPLAY_START_DELAY") {
+            if let Ok(delay) = delay_str.parse::<u64>() {
+                self.start_delay_secs = delay;
+            }
+        }
+
+        if let Some(wait_str) = var("INFO_DISPLAY_STARTUP_MAX_WAIT") {
+            if let Ok(wait) = wait_str.parse::<u64>() {
+                self.startup_max_wait_secs = wait;
+            }
+        }
+
+        // A zero timeout would kill every command straight away, so it is ignored
+        if let Some(timeout_str) = var("INFO_DISPLAY_COMMAND_TIMEOUT_MS") {
+            if let Ok(millis) = timeout_str.trim().parse::<u64>() {
+                if millis > 0 {
+                    self.command_timeout = Duration::from_millis(millis);
+                }
+            }
+        }
+
+        if let Some(path) = var("INFO_DISPLAY_VCGENCMD") {
+            if !path.trim().is_empty() {
+                self.vcgencmd_path = path.trim().to_string();
+            }
+        }
+
+        if let Some(state_dir) = var("INFO_DISPLAY_STATE_DIR") {
+            if !state_dir.trim().is_empty() {
+                self.state_dir = PathBuf::from(state_dir.trim());
+            }
+        }
+
+        if let Some(smoothing) = var("INFO_DISPLAY_TEMP_SMOOTHING").as_deref().and_then(TempSmoothing::parse) {
+            self.temp_smoothing = smoothing;
+        }
+
+        // Keeping events for no days would count none
+        if let Some(days_str) = var("INFO_DISPLAY_EVENT_RETENTION_DAYS") {
+            if let Ok(days) = days_str.trim().parse::<u32>() {
+                if days > 0 {
+                    self.event_retention_days = days;
+                }
+            }
+        }
+
+        if let Some(intervals_str) = var("INFO_DISPLAY_STALL_TIMEOUT") {
+            if let Ok(intervals) = intervals_str.trim().parse::<u32>() {
+                self.stall_intervals = intervals;
+            }
+        }
+
+        // Logging and telemetry
+        if let Some(level) = var("INFO_DISPLAY_LOG_LEVEL") {
+            if !level.trim().is_empty() {
+                self.log_level = Some(level.trim().to_string());
+            }
+        }
+
+        if let Some(otel_str) = var("INFO_DISPLAY_OTEL") {
+            self.telemetry_enabled = otel_str.to_lowercase() == "true" || otel_str == "1";
+        }
+
+        if let Some(percent_str) = var("INFO_DISPLAY_SHOW_PERCENT") {
+            self.show_percent = percent_str.to_lowercase() == "true" || percent_str == "1";
+        }
+
+        if let Some(wrap_str) = var("INFO_DISPLAY_WRAP") {
+            self.wrap = wrap_str.to_lowercase() == "true" || wrap_str == "1";
+        }
+
+        if let Some(font) = var("INFO_DISPLAY_FONT").as_deref().and_then(ContentFont::from_name) {
+            self.font = font;
+        }
+
+        if let Some(heartbeat_str) = var("INFO_DISPLAY_HEARTBEAT") {
+            self.heartbeat = heartbeat_str.to_lowercase() == "true" || heartbeat_str == "1";
+        }
+
+        if let Some(clear_str) = var("INFO_DISPLAY_CLEAR_ON_EXIT") {
+            self.clear_on_exit = clear_str.to_lowercase() == "true" || clear_str == "1";
+        }
+
+        if let Some(fan_str) = var("INFO_DISPLAY_FAN_CONTROL") {
+            self.fan.enabled = fan_str.to_lowercase() == "true" || fan_str == "1";
+        }
+
+        if let Some(epd_str) = var("INFO_DISPLAY_EPD") {
+            self.epd.enabled = epd_str.to_lowercase() == "true" || epd_str == "1";
+        }
+
+        if let Some(columns_str) = var("INFO_DISPLAY_COLUMNS") {
+            if let Ok(columns) = columns_str.trim().parse::<u8>() {
+                self.columns = columns;
+            }
+        }
+
+        if let Some(max_columns_str) = var("INFO_DISPLAY_MAX_COLUMNS") {
+            if let Ok(max_columns) = max_columns_str.trim().parse::<usize>() {
+                self.max_columns = max_columns;
+            }
+        }
+
+        if let Some(fields_str) = var("INFO_DISPLAY_OVERVIEW_FIELDS") {
+            if !fields_str.trim().is_empty() {
+                self.overview_fields = parse_list(&fields_str);
+            }
+        }
+
+        if let Some(version) = var("INFO_DISPLAY_IP_VERSION").as_deref().and_then(IpVersion::from_name) {
+            self.ip_version = version;
+        }
+
+        if let Some(style) = var("INFO_DISPLAY_UPTIME_STYLE").as_deref().and_then(UptimeStyle::from_name) {
+            self.uptime_style = style;
+        }
+
+        if let Some(format) = var("INFO_DISPLAY_DATE_FORMAT") {
+            if !format.trim().is_empty() {
+                self.date_format = format;
+            }
+        }
+
+        if let Some(pin_str) = var("INFO_DISPLAY_DHT22_GPIO") {
+            if let Ok(pin) = pin_str.parse::<u8>() {
+                self.dht22_gpio = Some(pin);
+            }
+        }
+
+        if let Some(content) = var("INFO_DISPLAY_QR_CONTENT") {
+            if !content.is_empty() {
+                self.qr_content = Some(content);
+            }
+        }
+
+        if let Some(placeholder) = var("INFO_DISPLAY_PLACEHOLDER") {
+            if !placeholder.is_empty() {
+                self.placeholder = placeholder;
+            }
+        }
+
+        if let Some(source_str) = var("INFO_DISPLAY_HOSTNAME_SOURCE") {
+            if let Some(source) = HostnameSource::parse(&source_str) {
+                self.hostname_source = source;
+            }
+        }
+
+        if let Some(listen_str) = var("INFO_DISPLAY_METRICS_LISTEN") {
+            if let Ok(addr) = parse_listen_address(&listen_str) {
+                self.metrics_listen = Some(addr);
+            }
+        }
+
+        if let Some(listen_str) = var("INFO_DISPLAY_HTTP_LISTEN") {
+            if let Ok(addr) = parse_listen_address(&listen_str) {
+                self.http_listen = Some(addr);
+            }
+        }
+
+        if let Some(listen_str) = var("INFO_DISPLAY_REMOTE_LISTEN") {
+            if let Ok(addr) = parse_listen_address(&listen_str) {
+                self.remote_listen = Some(addr);
+            }
+        }
+
+        if let Some(target) = var("INFO_DISPLAY_REMOTE_SEND") {
+            self.remote_send = Some(target.trim().to_string());
+        }
+
+        if let Some(token) = var("INFO_DISPLAY_REMOTE_TOKEN") {
+            self.remote_token = Some(token);
+        }
+
+        if let Some(demo_str) = var("INFO_DISPLAY_DEMO") {
+            self.demo_mode = demo_str.to_lowercase() == "true" || demo_str == "1";
+        }
+
+        if let Some(mqtt_str) = var("INFO_DISPLAY_MQTT") {
+            self.mqtt.enabled = mqtt_str.to_lowercase() == "true" || mqtt_str == "1";
+        }
+
+        // Screen error policy
+        if let Some(policy_str) = var("INFO_DISPLAY_ON_SCREEN_ERROR") {
+            if let Ok(policy) = ScreenErrorPolicy::parse(&policy_str) {
+                self.on_screen_error = policy;
+            }
+        }
+    }
+
+    /// Hand the settings read through process-wide state to the modules that read them:
+    /// the placeholder, command timeout, vcgencmd path, hostname source, device ID
+    /// directory and ASCII symbols. `Application::with_config` calls this; a program
+    /// driving `ScreenManager` itself calls it once before rendering.
+    pub fn apply_process_settings(&self) {
+        crate::system_info::placeholder::set_placeholder(&self.placeholder);
+        crate::utils::set_ascii_symbols(!self.font.has_glyph('°'));
+        crate::system_info::command::set_command_timeout(self.command_timeout);
+        crate::system_info::firmware::set_vcgencmd_path(&self.vcgencmd_path);
+        crate::system_info::network::set_hostname_source(self.hostname_source.clone());
+        crate::system_info::hardware::set_device_id_dir(&self.state_dir);
+    }
+
+    /// Validate against the built-in screens
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.validate_with(&ScreenRegistry::default())
+    }
+
+    /// Validate, accepting any screen in `registry`
+    pub fn validate_with(&self, registry: &ScreenRegistry) -> Result<(), ConfigError> {
+        // Validate interval
+        if self.interval < MIN_INTERVAL {
+            return Err(ConfigError::InvalidInterval);
+        }
+
+        // Validate screen duration
+        if self.screen_duration < MIN_INTERVAL || self.screen_durations.values().any(|duration| *duration < MIN_INTERVAL) {
+            return Err(ConfigError::InvalidScreenDuration);
+        }
+
+        // The placeholder replaces part of a line, so it must not break one
+        if self.placeholder.chars().any(char::is_control) {
+            return Err(ConfigError::InvalidPlaceholder(self.placeholder.clone()));
+        }
+
+        // chrono only finds out while formatting, when it panics
+        if StrftimeItems::new(&self.date_format).any(|item| item == Item::Error) {
+            return Err(ConfigError::InvalidDateFormat(self.date_format.clone()));
+        }
+
+        // Validate screens
+        if self.enabled_screens.is_empty() {
+            return Err(ConfigError::NoScreensEnabled);
+        }
+
+        for screen in self.enabled_screens.iter().chain(&self.first_screen) {
+            if !registry.contains(screen) {
+                return Err(ConfigError::InvalidScreen(screen.clone()));
+            }
+        }
+
+        // Validate multiplexer config
+        if self.multiplexer.channel > 7 {
+            return Err(ConfigError::InvalidMultiplexerChannel(self.multiplexer.channel));
+        }
+
+        if !(1..=MAX_COLUMNS).contains(&self.columns) {
+            return Err(ConfigError::InvalidColumns(self.columns));
+        }
+
+        if self.max_columns < MIN_LINE_CHARS {
+            return Err(ConfigError::InvalidMaxColumns(self.max_columns));
+        }
+
+        self.multiplexer.validate()?;
+
+        // Validate I2C bus speed
+        if let Some(speed) = self.i2c_speed {
+            if !(MIN_I2C_SPEED..=MAX_I2C_SPEED).contains(&speed) {
+                return Err(ConfigError::InvalidI2cSpeed(speed));
+            }
+        }
+
+        // Validate log level / filter directives
+        if let Some(level) = &self.log_level {
+            if tracing_subscriber::EnvFilter::try_new(level).is_err() {
+                return Err(ConfigError::InvalidLogLevel(level.clone()));
+            }
+        }
+
+        // Validate fallback screen
+        if let ScreenErrorPolicy::Fallback(screen) = &self.on_screen_error {
+            if !registry.contains(screen) {
+                return Err(ConfigError::InvalidScreen(screen.clone()));
+            }
+        }
+
+        // Validate the DHT22 pin; the ambient screen cannot work without one
+        if let Some(pin) = self.dht22_gpio {
+            if pin > MAX_GPIO_PIN {
+                return Err(ConfigError::InvalidGpioPin(pin));
+            }
+        } else if self.enabled_screens.iter().any(|screen| screen == "ambient")
+            && self.screen_options().for_screen("ambient").dht22_gpio.is_none()
+        {
+            return Err(ConfigError::MissingDht22Gpio);
+        }
+
+        // Validate overview fields
+        if self.overview_fields.is_empty() {
+            return Err(ConfigError::NoOverviewFields);
+        }
+        for field in &self.overview_fields {
+            if OverviewField::from_name(field).is_none() {
+                return Err(ConfigError::InvalidOverviewField(field.clone()));
+            }
+        }
+
+        // Validate MQTT settings
+        if self.mqtt.enabled || self.mqtt_clear_discovery {
+            self.mqtt.broker_address()?;
+            if self.mqtt.qos > 2 {
+                return Err(ConfigError::InvalidMqttQos(self.mqtt.qos));
+            }
+        }
+
+        // Validate remote display settings
+        if self.remote_listen.is_some() && self.remote_send.is_some() {
+            return Err(ConfigError::ConflictingRemoteModes);
+        }
+        if self.remote_only && self.remote_listen.is_none() {
+            return Err(ConfigError::RemoteOnlyWithoutListen);
+        }
+        if let Some(target) = &self.remote_send {
+            let valid = target.rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+            if !valid {
+                return Err(ConfigError::InvalidRemoteAddress(target.clone()));
+            }
+        }
+
+        // Validate InfluxDB settings
+        if self.influxdb.enabled {
+            self.influxdb.validate()?;
+        }
+
+        // Validate webhook settings
+        if self.webhook.enabled {
+            self.webhook.validate()?;
+        }
+
+        // Validate the fan curve
+        if self.fan.enabled {
+            self.fan.validate()?;
+        }
+
+        if self.epd.enabled {
+            self.epd.validate()?;
+        }
+
+        // Validate the SSH remote host
+        if self.remote_host.enabled {
+            self.remote_host.validate(registry)?;
+        }
+
+        // Validate the watched inputs
+        for (index, input) in self.gpio_inputs.iter().enumerate() {
+            let invalid = |reason: String| Err(ConfigError::InvalidGpioInputs(reason));
+            if input.gpio > MAX_GPIO_PIN {
+                return invalid(format!("GPIO {} does not exist (must be 0-{})", input.gpio, MAX_GPIO_PIN));
+            }
+            if input.label.trim().is_empty() {
+                return invalid(format!("GPIO {} needs a label", input.gpio));
+            }
+            if self.gpio_inputs[..index].iter().any(|other| other.gpio == input.gpio) {
+                return invalid(format!("GPIO {} is listed more than once", input.gpio));
+            }
+        }
+
+        // Validate the battery HAT address; 0x00-0x02 and 0x78-0x7F are reserved
+        if let Some(address) = self.battery.address {
+            if !(0x03..=0x77).contains(&address) {
+                return Err(ConfigError::InvalidBatteryAddress(address));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn add_screen(&mut self, screen: &str) {
+        // Replace default overview with first specific screen
+        if self.enabled_screens == vec!["overview"] && screen != "overview" {
+            self.enabled_screens = vec![screen.to_string()];
+        } else if !self.enabled_screens.contains(&screen.to_string()) {
+            self.enabled_screens.push(screen.to_string());
+        }
+    }
+
+    pub fn enable_multiplexer(&mut self) {
+        self.multiplexer.enabled = true;
+    }
+
+    pub fn set_multiplexer_channel(&mut self, channel: u8) -> Result<(), ConfigError> {
+        if channel > 7 {
+            return Err(ConfigError::InvalidMultiplexerChannel(channel));
+        }
+        self.multiplexer.channel = channel;
+        self.multiplexer.enabled = true;
+        Ok(())
+    }
+
+    /// Enable several channels at once, e.g. "2,5"; the display is on the first one.
+    pub fn set_multiplexer_channels(&mut self, value: &str) -> Result<(), ConfigError> {
+        let mut channels = Vec::new();
+        for item in parse_list(value) {
+            let channel = item.parse::<u8>()
+                .map_err(|_| ConfigError::InvalidMultiplexerChannels(value.to_string()))?;
+            if channel > 7 {
+                return Err(ConfigError::InvalidMultiplexerChannel(channel));
+            }
+            channels.push(channel);
+        }
+
+        let (&display, shared) = channels.split_first()
+            .ok_or_else(|| ConfigError::InvalidMultiplexerChannels(value.to_string()))?;
+        self.set_multiplexer_channel(display)?;
+        self.multiplexer.shared_channels = shared.iter()
+            .filter(|&&channel| channel != display)
+            .fold(0, |mask, channel| mask | 1 << channel);
+        Ok(())
+    }
+
+    /// Put the display behind one of several multiplexers, e.g. "0x71:3" or, with a
+    /// labelled `[[multiplexers]]` entry, "right:3".
+    pub fn set_multiplexer_target(&mut self, value: &str) -> Result<(), ConfigError> {
+        let invalid = || ConfigError::InvalidMultiplexerTarget(value.to_string());
+        let (mux, channel) = value.trim().split_once(':').ok_or_else(invalid)?;
+        let channel = channel.trim().parse::<u8>().map_err(|_| invalid())?;
+
+        let address = match self.multiplexer.muxes.iter().find(|def| def.label.as_deref() == Some(mux)) {
+            Some(def) => def.address,
+            None => u8::from_str_radix(mux.trim_start_matches("0x"), 16).map_err(|_| invalid())?,
+        };
+        self.set_multiplexer_channel(channel)?;
+        self.set_multiplexer_address(address);
+        Ok(())
+    }
+
+    pub fn set_multiplexer_address(&mut self, address: u8) {
+        self.multiplexer.address = address;
+    }
+}
+
+/// Seconds as a whole or fractional number, e.g. "5" or "0.5"
+pub fn parse_seconds(value: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(value.trim().parse().ok()?).ok()
+}
+
+/// One screen per line as `name[:seconds]`, e.g. `clock:5`; without seconds the screen
+/// is shown for the screen duration. Blank lines and `#` comments are skipped. Names are
+/// checked in validate(), against the registered screens.
+pub fn parse_screens_file(contents: &str) -> Result<Vec<(String, Option<Duration>)>, String> {
+    let mut entries = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let entry = line.split('#').next().unwrap_or_default().trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let (screen, duration) = match entry.split_once(':') {
+            Some((screen, seconds)) => {
+                let duration = parse_seconds(seconds)
+                    .ok_or_else(|| format!("line {}: invalid duration {:?}", number + 1, seconds.trim()))?;
+                (screen.trim(), Some(duration))
+            }
+            None => (entry, None),
+        };
+        if screen.is_empty() {
+            return Err(format!("line {}: missing screen name", number + 1));
+        }
+        entries.push((screen.to_string(), duration));
+    }
+    if entries.is_empty() {
+        return Err("no screens listed".to_string());
+    }
+    Ok(entries)
+}
+
+/// Split a comma-separated list, dropping empty entries
+pub fn parse_list(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Screen names separated by commas, spaces or both, e.g. "network, system storage",
+/// the same for --screens and INFO_DISPLAY_SCREENS. Names are checked in validate(),
+/// once custom screens are registered.
+pub fn parse_screen_list(value: &str) -> Vec<String> {
+    value.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Comma-separated 7-bit I2C addresses in hex, e.g. "0x48,76"
+pub fn parse_address_list(value: &str) -> Result<Vec<u8>, ConfigError> {
+    parse_list(value).iter()
+        .map(|item| u8::from_str_radix(item.trim_start_matches("0x"), 16)
+            .ok()
+            .filter(|address| (0x03..=0x77).contains(address))
+            .ok_or_else(|| ConfigError::InvalidI2cAddress(item.clone())))
+        .collect()
+}
+
+/// Comma-separated I2C bus numbers, e.g. "1,3" for /dev/i2c-1 and /dev/i2c-3
+pub fn parse_bus_list(value: &str) -> Result<Vec<u8>, ConfigError> {
+    let buses = parse_list(value).iter()
+        .map(|item| item.parse::<u8>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ConfigError::InvalidI2cBuses(value.to_string()))?;
+    if buses.is_empty() {
+        return Err(ConfigError::InvalidI2cBuses(value.to_string()));
+    }
+    Ok(buses)
+}
+
+/// A 1-Wire family code in hex, e.g. "28" or "0x28" as in the device IDs under /sys/bus/w1/devices
+pub fn parse_w1_family(value: &str) -> Result<u8, ConfigError> {
+    let value = value.trim();
+    u8::from_str_radix(value.trim_start_matches("0x"), 16)
+        .map_err(|_| ConfigError::InvalidW1Family(value.to_string()))
+}
+
+pub fn parse_listen_address(value: &str) -> Result<SocketAddr, ConfigError> {
+    value.trim().parse().map_err(|_| ConfigError::InvalidListenAddress(value.to_string()))
+}
+
+pub fn parse_ip_version(value: &str) -> Result<IpVersion, ConfigError> {
+    IpVersion::from_name(value).ok_or_else(|| ConfigError::InvalidIpVersion(value.to_string()))
+}
+
+pub fn parse_font(value: &str) -> Result<ContentFont, ConfigError> {
+    ContentFont::from_name(value).ok_or_else(|| ConfigError::InvalidFont(value.to_string()))
+}
+
+pub fn parse_uptime_style(value: &str) -> Result<UptimeStyle, ConfigError> {
+    UptimeStyle::from_name(value).ok_or_else(|| ConfigError::InvalidUptimeStyle(value.to_string()))
+}
+
+pub fn parse_temp_smoothing(value: &str) -> Result<TempSmoothing, ConfigError> {
+    TempSmoothing::parse(value).ok_or_else(|| ConfigError::InvalidTempSmoothing(value.to_string()))
+}
+
+pub fn parse_hostname_source(value: &str) -> Result<HostnameSource, ConfigError> {
+    HostnameSource::parse(value).ok_or_else(|| ConfigError::InvalidHostnameSource(value.to_string()))
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    InvalidInterval,
+    InvalidScreenDuration,
+    NoScreensEnabled,
+    InvalidScreen(String),
+    InvalidMultiplexerChannel(u8),
+    InvalidMultiplexerChannels(String),
+    InvalidMultiplexerTarget(String),
+    InvalidMultiplexers(String),
+    InvalidI2cSpeed(u32),
+    InvalidColumns(u8),
+    InvalidMaxColumns(usize),
+    InvalidScreenErrorPolicy(String),
+    InvalidLogLevel(String),
+    InvalidListenAddress(String),
+    ConfigFile(String),
+    InvalidGpioPin(u8),
+    InvalidInodeWarningPercent(u8),
+    MissingDht22Gpio,
+    NoOverviewFields,
+    InvalidOverviewField(String),
+    InvalidMqttBroker(String),
+    InvalidMqttQos(u8),
+    InvalidInfluxDb(String),
+    InvalidWebhook(String),
+    InvalidFan(String),
+    InvalidEpd(String),
+    InvalidGpioInputs(String),
+    InvalidRemoteHost(String),
+    InvalidRemoteAddress(String),
+    ConflictingRemoteModes,
+    RemoteOnlyWithoutListen,
+    InvalidBatteryAddress(u8),
+    InvalidI2cAddress(String),
+    InvalidI2cBuses(String),
+    InvalidW1Family(String),
+    InvalidPlaceholder(String),
+    InvalidHostnameSource(String),
+    InvalidIpVersion(String),
+    InvalidTempSmoothing(String),
+    InvalidScreensFile(String),
+    InvalidUptimeStyle(String),
+    InvalidFont(String),
+    InvalidDateFormat(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidInterval => write!(f, "Update interval must be at least {}s", MIN_INTERVAL.as_secs_f64()),
+            ConfigError::InvalidScreenDuration => write!(f, "Screen duration must be at least {}s", MIN_INTERVAL.as_secs_f64()),
+            ConfigError::NoScreensEnabled => write!(f, "At least one screen must be enabled"),
+            ConfigError::InvalidScreen(screen) => write!(f, "Invalid screen type: {}", screen),
+            ConfigError::InvalidMultiplexerChannel(channel) => write!(f, "Multiplexer channel must be 0-7, got: {}", channel),
+            ConfigError::InvalidMultiplexerChannels(list) => write!(f, "Invalid multiplexer channel list: {} (expected e.g. 2,5)", list),
+            ConfigError::InvalidColumns(columns) => write!(f, "Columns must be 1-{}, got: {}", MAX_COLUMNS, columns),
+            ConfigError::InvalidMaxColumns(max_columns) => {
+                write!(f, "Max columns must be at least {}, got: {}", MIN_LINE_CHARS, max_columns)
+            }
+            ConfigError::InvalidMultiplexerTarget(target) => write!(f, "Invalid multiplexer target: {} (expected <label or address>:<channel>, e.g. 0x71:3)", target),
+            ConfigError::InvalidMultiplexers(reason) => write!(f, "Invalid [[multiplexers]] settings: {}", reason),
+            ConfigError::InvalidI2cSpeed(speed) => write!(f, "I2C speed must be {}-{} Hz, got: {}", MIN_I2C_SPEED, MAX_I2C_SPEED, speed),
+            ConfigError::InvalidLogLevel(level) => write!(f, "Invalid log level: {}", level),
+            ConfigError::InvalidListenAddress(addr) => write!(f, "Invalid listen address: {} (expected host:port, e.g. 0.0.0.0:9091)", addr),
+            ConfigError::ConfigFile(msg) => write!(f, "Failed to load config file {}", msg),
+            ConfigError::InvalidScreensFile(msg) => write!(f, "Failed to load screens file {}", msg),
+            ConfigError::InvalidGpioPin(pin) => write!(f, "Invalid GPIO pin: {} (must be 0-{})", pin, MAX_GPIO_PIN),
+            ConfigError::InvalidInodeWarningPercent(percent) => {
+                write!(f, "Invalid inode warning percentage: {} (must be 0-100)", percent)
+            }
+            ConfigError::MissingDht22Gpio => write!(f, "The ambient screen needs the DHT22 pin (--dht22-gpio)"),
+            ConfigError::NoOverviewFields => write!(f, "At least one overview field must be configured"),
+            ConfigError::InvalidOverviewField(field) => write!(f, "Invalid overview field: {} (expected ip, temp, mem, disk or uptime)", field),
+            ConfigError::InvalidInfluxDb(reason) => write!(f, "Invalid [influxdb] settings: {}", reason),
+            ConfigError::InvalidRemoteHost(reason) => write!(f, "Invalid [remote] settings: {}", reason),
+            ConfigError::InvalidWebhook(reason) => write!(f, "Invalid [webhook] settings: {}", reason),
+            ConfigError::InvalidFan(reason) => write!(f, "Invalid [fan] settings: {}", reason),
+            ConfigError::InvalidEpd(reason) => write!(f, "Invalid [epd] settings: {}", reason),
+            ConfigError::InvalidGpioInputs(reason) => write!(f, "Invalid [[gpio_inputs]] settings: {}", reason),
+            ConfigError::InvalidRemoteAddress(addr) => write!(f, "Invalid remote display address: {} (expected host:port)", addr),
+            ConfigError::ConflictingRemoteModes => write!(f, "--listen and --send cannot be used together"),
+            ConfigError::RemoteOnlyWithoutListen => write!(f, "--remote-only needs --listen"),
+            ConfigError::InvalidPlaceholder(text) => write!(f, "Placeholder text must be a single line, got: {:?}", text),
+            ConfigError::InvalidIpVersion(version) => write!(f, "Invalid IP version: {} (expected v4, v6 or both)", version),
+            ConfigError::InvalidUptimeStyle(style) => write!(f, "Invalid uptime style: {} (expected compact or since)", style),
+            ConfigError::InvalidFont(font) => write!(f, "Invalid font: {} (expected ascii or latin1)", font),
+            ConfigError::InvalidDateFormat(format) => write!(f, "Invalid date format: {} (expected strftime, e.g. %d %b %H:%M)", format),
+            ConfigError::InvalidTempSmoothing(smoothing) => {
+                write!(f, "Invalid temperature smoothing: {} (expected off, ema:<0-1> or window:<samples>)", smoothing)
+            }
+            ConfigError::InvalidHostnameSource(source) => write!(f, "Invalid hostname source: {} (expected system, file or name:<hostname>)", source),
+            ConfigError::InvalidBatteryAddress(address) => write!(f, "Battery HAT address must be 0x03-0x77, got: 0x{:02X}", address),
+            ConfigError::InvalidI2cAddress(address) => write!(f, "Invalid I2C address: {} (expected hex 0x03-0x77)", address),
+            ConfigError::InvalidI2cBuses(list) => write!(f, "Invalid I2C bus list: {} (expected bus numbers, e.g. 1,3)", list),
+            ConfigError::InvalidW1Family(family) => write!(f, "Invalid 1-Wire family code: {} (expected hex, e.g. 28)", family),
+            ConfigError::InvalidMqttBroker(url) => write!(f, "Invalid MQTT broker URL: {} (expected mqtt://host[:port])", url),
+            ConfigError::InvalidMqttQos(qos) => write!(f, "MQTT QoS must be 0-2, got: {}", qos),
+            ConfigError::InvalidScreenErrorPolicy(policy) => write!(f, "Invalid screen error policy: {} (expected skip, placeholder or fallback:<screen>)", policy),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::system_info::gpio::GpioBias;
+
+    #[test]
+    fn test_default_config() {
+        let config = AppConfig::default();
+        assert_eq!(config.interval, Duration::from_secs(5));
+        assert_eq!(config.enabled_screens, vec!["overview"]);
+        assert!(!config.multiplexer.enabled);
+    }
+
+    #[test]
+    fn test_add_screen_replaces_overview() {
+        let mut config = AppConfig::default();
+        config.add_screen("network");
+        assert_eq!(config.enabled_screens, vec!["network"]);
+        
+        config.add_screen("system");
+        assert_eq!(config.enabled_screens, vec!["network", "system"]);
+    }
+
+    #[test]
+    fn test_validate_valid_config() {
+        let config = AppConfig::default();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_invalid_screen() {
+        let mut config = AppConfig::default();
+        config.enabled_screens = vec!["invalid".to_string()];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_invalid_channel() {
+        let mut config = AppConfig::default();
+        config.multiplexer.channel = 8;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_screen_error_policy() {
+        assert_eq!(ScreenErrorPolicy::parse("skip").unwrap(), ScreenErrorPolicy::Skip);
+        assert_eq!(ScreenErrorPolicy::parse("Placeholder").unwrap(), ScreenErrorPolicy::Placeholder);
+        assert_eq!(
+            ScreenErrorPolicy::parse("fallback:overview").unwrap(),
+            ScreenErrorPolicy::Fallback("overview".to_string())
+        );
+        assert!(ScreenErrorPolicy::parse("fallback:").is_err());
+        assert!(ScreenErrorPolicy::parse("explode").is_err());
+    }
+
+    #[test]
+    fn test_validate_invalid_fallback_screen() {
+        let mut config = AppConfig::default();
+        config.on_screen_error = ScreenErrorPolicy::Fallback("invalid".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_listen_address() {
+        assert_eq!(parse_listen_address("0.0.0.0:9091").unwrap(), "0.0.0.0:9091".parse().unwrap());
+        assert!(parse_listen_address("9091").is_err());
+        assert!(parse_listen_address("localhost").is_err());
+    }
+
+    #[test]
+    fn test_columns() {
+        let config = config_from_env(&[("INFO_DISPLAY_COLUMNS", "2")]);
+        assert_eq!(config.columns, 2);
+        assert!(config.validate().is_ok());
+
+        let config = config_from_env(&[("INFO_DISPLAY_COLUMNS", "3")]);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidColumns(3))));
+    }
+
+    #[test]
+    fn test_first_screen() {
+        let config = config_from_env(&[("INFO_DISPLAY_FIRST_SCREEN", " network ")]);
+        assert_eq!(config.first_screen.as_deref(), Some("network"));
+        // Need not be enabled, but must exist
+        assert!(config.validate().is_ok());
+
+        let config = config_from_env(&[("INFO_DISPLAY_FIRST_SCREEN", "netwerk")]);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidScreen(screen)) if screen == "netwerk"));
     }
 
     #[test]
-    fn test_env_var_daemon_mode() {
-        unsafe {
-            env::set_var("INFO_DISPLAY_DAEMON", "true");
+    fn test_wrap() {
+        assert!(!AppConfig::default().wrap);
+        let config = config_from_env(&[("INFO_DISPLAY_WRAP", "1"), ("INFO_DISPLAY_MAX_COLUMNS", "18")]);
+        assert!(config.wrap);
+        assert_eq!(config.max_columns, 18);
+        assert_eq!(config.screen_options().max_columns, usize::MAX);
+    }
+
+    #[test]
+    fn test_font() {
+        assert_eq!(AppConfig::default().font, ContentFont::Latin1);
+        assert_eq!(config_from_env(&[("INFO_DISPLAY_FONT", "ASCII")]).font, ContentFont::Ascii);
+        assert_eq!(config_from_env(&[("INFO_DISPLAY_FONT", "comic")]).font, ContentFont::Latin1);
+        let config = layered_config("", &[("INFO_DISPLAY_FONT", "ascii")], &["--font=latin1"]);
+        assert_eq!(config.font, ContentFont::Latin1);
+        assert!(matches!(parse_font("comic"), Err(ConfigError::InvalidFont(_))));
+    }
+
+    #[test]
+    fn test_stall_timeout() {
+        assert_eq!(AppConfig::default().stall_intervals, 0);
+        let vars = [("INFO_DISPLAY_STALL_TIMEOUT", "5")];
+        assert_eq!(layered_config("", &vars, &[]).stall_intervals, 5);
+        assert_eq!(layered_config("", &vars, &["--stall-timeout", "0"]).stall_intervals, 0);
+        assert_eq!(layered_config("", &[], &["--stall-timeout=3"]).stall_intervals, 3);
+        assert_eq!(config_from_env(&[("INFO_DISPLAY_STALL_TIMEOUT", "soon")]).stall_intervals, 0);
+    }
+
+    #[test]
+    fn test_clear_on_exit() {
+        assert!(!AppConfig::default().clear_on_exit);
+        let vars = [("INFO_DISPLAY_CLEAR_ON_EXIT", "true")];
+        assert!(layered_config("", &vars, &[]).clear_on_exit);
+        assert!(!layered_config("", &vars, &["--no-clear-on-exit"]).clear_on_exit);
+        assert!(layered_config("", &[], &["--clear-on-exit"]).clear_on_exit);
+        // The one-shot clear is a mode of its own
+        assert!(!layered_config("", &[], &["--clear"]).clear_on_exit);
+    }
+
+    #[test]
+    fn test_max_columns() {
+        assert_eq!(AppConfig::default().screen_options().max_columns, 21);
+
+        let config = config_from_env(&[("INFO_DISPLAY_MAX_COLUMNS", "16")]);
+        assert_eq!(config.screen_options().max_columns, 16);
+        assert!(config.validate().is_ok());
+
+        let config = config_from_env(&[("INFO_DISPLAY_MAX_COLUMNS", "4")]);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidMaxColumns(4))));
+    }
+
+    #[test]
+    fn test_command_timeout() {
+        assert_eq!(AppConfig::default().command_timeout, Duration::from_millis(500));
+
+        let config = config_from_env(&[("INFO_DISPLAY_COMMAND_TIMEOUT_MS", "1500")]);
+        assert_eq!(config.command_timeout, Duration::from_millis(1500));
+
+        let config = config_from_env(&[("INFO_DISPLAY_COMMAND_TIMEOUT_MS", "0")]);
+        assert_eq!(config.command_timeout, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_vcgencmd_path() {
+        assert_eq!(AppConfig::default().vcgencmd_path, "vcgencmd");
+        let vars = [("INFO_DISPLAY_VCGENCMD", "/usr/bin/vcgencmd")];
+        assert_eq!(layered_config("", &vars, &[]).vcgencmd_path, "/usr/bin/vcgencmd");
+        assert_eq!(layered_config("", &vars, &["--vcgencmd=/opt/vc/bin/vcgencmd"]).vcgencmd_path, "/opt/vc/bin/vcgencmd");
+        assert_eq!(config_from_env(&[("INFO_DISPLAY_VCGENCMD", " ")]).vcgencmd_path, "vcgencmd");
+    }
+
+    #[test]
+    fn test_event_log_settings() {
+        let config = AppConfig::default();
+        assert_eq!(config.state_dir, PathBuf::from("/var/lib/info-display"));
+        assert_eq!(config.event_retention_days, 7);
+
+        let config = config_from_env(&[("INFO_DISPLAY_STATE_DIR", "/run/info-display"), ("INFO_DISPLAY_EVENT_RETENTION_DAYS", "30")]);
+        assert_eq!(config.state_dir, PathBuf::from("/run/info-display"));
+        assert_eq!(config.event_retention_days, 30);
+
+        let config = config_from_env(&[("INFO_DISPLAY_EVENT_RETENTION_DAYS", "0")]);
+        assert_eq!(config.event_retention_days, 7);
+    }
+
+    #[test]
+    fn test_temp_smoothing() {
+        assert_eq!(AppConfig::default().temp_smoothing, TempSmoothing::Off);
+
+        let config = config_from_env(&[("INFO_DISPLAY_TEMP_SMOOTHING", "ema:0.2")]);
+        assert_eq!(config.temp_smoothing, TempSmoothing::Ema(0.2));
+
+        let config = config_from_env(&[("INFO_DISPLAY_TEMP_SMOOTHING", "window:0")]);
+        assert_eq!(config.temp_smoothing, TempSmoothing::Off);
+
+        assert!(matches!(parse_temp_smoothing("ema:2"), Err(ConfigError::InvalidTempSmoothing(_))));
+    }
+
+    #[test]
+    fn test_set_multiplexer_channels() {
+        let mut config = AppConfig::default();
+        config.set_multiplexer_channels("2,5").unwrap();
+        assert!(config.multiplexer.enabled);
+        assert_eq!(config.multiplexer.channel, 2);
+        assert_eq!(config.multiplexer.shared_channels, 0x20);
+
+        assert!(matches!(config.set_multiplexer_channels("2,8"), Err(ConfigError::InvalidMultiplexerChannel(8))));
+        assert!(matches!(config.set_multiplexer_channels("two"), Err(ConfigError::InvalidMultiplexerChannels(_))));
+        assert!(matches!(config.set_multiplexer_channels(""), Err(ConfigError::InvalidMultiplexerChannels(_))));
+    }
+
+    #[test]
+    fn test_i2c_probe_settings() {
+        let config = AppConfig::default();
+        assert!(!config.screen_options().i2c_probe);
+        assert!(config.screen_options().i2c_buses.is_empty());
+
+        let config = config_from_env(&[("INFO_DISPLAY_I2C_PROBE", "true"), ("INFO_DISPLAY_I2C_BUSES", "1, 3")]);
+        assert!(config.screen_options().i2c_probe);
+        assert_eq!(config.screen_options().i2c_buses, vec![1, 3]);
+
+        assert!(matches!(parse_bus_list("i2c-1"), Err(ConfigError::InvalidI2cBuses(_))));
+        assert!(parse_bus_list("").is_err());
+    }
+
+    #[test]
+    fn test_w1_family_settings() {
+        assert_eq!(AppConfig::default().screen_options().w1_family, None);
+
+        let config = config_from_env(&[("INFO_DISPLAY_W1_FAMILY", "28")]);
+        assert_eq!(config.screen_options().w1_family, Some(0x28));
+
+        assert_eq!(parse_w1_family("0x3b").unwrap(), 0x3b);
+        assert!(matches!(parse_w1_family("28-"), Err(ConfigError::InvalidW1Family(_))));
+        assert!(parse_w1_family("").is_err());
+    }
+
+    #[test]
+    fn test_parse_address_list() {
+        assert_eq!(parse_address_list("0x48, 76").unwrap(), vec![0x48, 0x76]);
+        assert!(matches!(parse_address_list("0x48,0x80"), Err(ConfigError::InvalidI2cAddress(a)) if a == "0x80"));
+        assert!(parse_address_list("sensor").is_err());
+    }
+
+    #[test]
+    fn test_validate_with_registered_screen() {
+        let mut config = AppConfig::default();
+        config.enabled_screens = vec!["custom".to_string()];
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidScreen(_))));
+
+        let mut registry = ScreenRegistry::default();
+        registry.register("custom", "", |options| crate::screen_factory::ScreenFactory::create_screen("clock", options));
+        assert!(config.validate_with(&registry).is_ok());
+    }
+
+    #[test]
+    fn test_validate_i2c_speed() {
+        let mut config = config_from_env(&[("INFO_DISPLAY_I2C_SPEED", "100000")]);
+        assert_eq!(config.i2c_speed, Some(100_000));
+        assert!(config.validate().is_ok());
+
+        config.i2c_speed = Some(5_000_000);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidI2cSpeed(5_000_000))));
+    }
+
+    #[test]
+    fn test_validate_remote_display() {
+        let mut config = AppConfig::default();
+        config.remote_only = true;
+        assert!(matches!(config.validate(), Err(ConfigError::RemoteOnlyWithoutListen)));
+
+        config.remote_listen = Some("0.0.0.0:7654".parse().unwrap());
+        assert!(config.validate().is_ok());
+
+        config.remote_send = Some("display.local:7654".to_string());
+        assert!(matches!(config.validate(), Err(ConfigError::ConflictingRemoteModes)));
+
+        config.remote_only = false;
+        config.remote_listen = None;
+        assert!(config.validate().is_ok());
+        config.remote_send = Some("display.local".to_string());
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidRemoteAddress(_))));
+    }
+
+    #[test]
+    fn test_validate_dht22_gpio() {
+        let mut config = AppConfig::default();
+        config.enabled_screens = vec!["ambient".to_string()];
+        assert!(matches!(config.validate(), Err(ConfigError::MissingDht22Gpio)));
+
+        config.dht22_gpio = Some(4);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.screen_options().dht22_gpio, Some(4));
+
+        config.dht22_gpio = Some(40);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidGpioPin(40))));
+    }
+
+    #[test]
+    fn test_overview_fields() {
+        let mut config = AppConfig::default();
+        assert_eq!(config.screen_options().overview_fields, OverviewField::DEFAULT.to_vec());
+
+        config.overview_fields = parse_list("uptime, ip");
+        assert!(config.validate().is_ok());
+        assert_eq!(config.screen_options().overview_fields, vec![OverviewField::Uptime, OverviewField::Ip]);
+    }
+
+    #[test]
+    fn test_validate_invalid_overview_field() {
+        let mut config = AppConfig::default();
+        config.overview_fields = parse_list("ip,cpu");
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidOverviewField(field)) if field == "cpu"));
+
+        config.overview_fields = parse_list(" , ");
+        assert!(matches!(config.validate(), Err(ConfigError::NoOverviewFields)));
+    }
+
+    #[test]
+    fn test_mqtt_broker_address() {
+        let mut mqtt = MqttConfig::default();
+        assert_eq!(mqtt.broker_address().unwrap(), ("localhost".to_string(), 1883));
+
+        mqtt.broker_url = "mqtt://broker.lan:8883".to_string();
+        assert_eq!(mqtt.broker_address().unwrap(), ("broker.lan".to_string(), 8883));
+
+        mqtt.broker_url = "broker.lan".to_string();
+        assert_eq!(mqtt.broker_address().unwrap(), ("broker.lan".to_string(), 1883));
+
+        mqtt.broker_url = "mqtt://broker.lan:port".to_string();
+        assert!(mqtt.broker_address().is_err());
+        mqtt.broker_url = "http://broker.lan/".to_string();
+        assert!(mqtt.broker_address().is_err());
+    }
+
+    #[test]
+    fn test_config_file_mqtt_section() {
+        let config = config_from_toml("[mqtt]\nenabled = true\nbroker_url = \"mqtt://broker.lan\"\nqos = 1\n").unwrap();
+        assert!(config.mqtt.enabled);
+        assert_eq!(config.mqtt.broker_url, "mqtt://broker.lan");
+        assert_eq!(config.mqtt.qos, 1);
+        assert_eq!(config.mqtt.base_topic, "raspi");
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_file_rejects_unknown_keys() {
+        let result = config_from_toml("[mqtt]\nbroker = \"mqtt://broker.lan\"\n");
+        assert!(matches!(result, Err(ConfigError::ConfigFile(_))));
+    }
+
+    #[test]
+    fn test_config_file_influxdb_section() {
+        let config = config_from_toml("[influxdb]\nenabled = true\nurl = \"http://influx.lan:8086\"\norg = \"home\"\nbucket = \"pi\"\ntoken = \"secret\"\n").unwrap();
+        assert!(config.influxdb.enabled);
+        assert_eq!(config.influxdb.bucket, "pi");
+        assert_eq!(config.influxdb.token.as_deref(), Some("secret"));
+        assert_eq!(config.influxdb.batch_intervals, 6);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_invalid_influxdb() {
+        let mut config = AppConfig::default();
+        config.influxdb.enabled = true;
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidInfluxDb(_))));
+
+        config.influxdb.org = "home".to_string();
+        config.influxdb.bucket = "pi".to_string();
+        assert!(config.validate().is_ok());
+
+        config.influxdb.url = "influx.lan:8086".to_string();
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidInfluxDb(_))));
+    }
+
+    #[test]
+    fn test_config_file_webhook_section() {
+        let mut config = config_from_toml("[webhook]\nenabled = true\nurl = \"https://hooks.example.com/pi\"\ndisk_percent = 90\n").unwrap();
+        assert!(config.webhook.enabled);
+        assert_eq!(config.webhook.disk_percent, Some(90.0));
+        assert_eq!(config.webhook.cpu_temp_celsius, Some(80.0));
+        assert!(config.validate().is_ok());
+
+        config.webhook.disk_percent = Some(150.0);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidWebhook(_))));
+        config.webhook.disk_percent = None;
+        config.webhook.clock_offset_ms = Some(0.0);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidWebhook(_))));
+        config.webhook.clock_offset_ms = None;
+        config.webhook.thermal_margin_celsius = Some(-5.0);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidWebhook(_))));
+        config.webhook.thermal_margin_celsius = None;
+        config.webhook.url = String::new();
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidWebhook(_))));
+    }
+
+    #[test]
+    fn test_config_file_remote_section() {
+        let mut config = config_from_toml("[remote]\nenabled = true\nhost = \"nas.lan\"\nuser = \"pi\"\nscreens = [\"system\", \"storage\"]\n").unwrap();
+        assert_eq!(config.remote_host.host, "nas.lan");
+        assert_eq!(config.remote_host.port, 22);
+        assert_eq!(config.remote_host.screens, vec!["system", "storage"]);
+        assert!(config.validate().is_ok());
+
+        config.remote_host.screens.push("bogus".to_string());
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidRemoteHost(_))));
+        config.remote_host.screens.pop();
+        config.remote_host.user = String::new();
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidRemoteHost(_))));
+    }
+
+    #[test]
+    fn test_config_file_battery_section() {
+        let mut config = config_from_toml("[battery]\nhat = \"max17040\"\naddress = 0x37\n").unwrap();
+        assert_eq!(config.screen_options().battery_hat, BatteryHat::Max17040);
+        assert_eq!(config.screen_options().battery_address, Some(0x37));
+        assert!(config.validate().is_ok());
+
+        config.battery.address = Some(0x78);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidBatteryAddress(0x78))));
+    }
+
+    #[test]
+    fn test_config_file_fan_section() {
+        let mut config = config_from_toml("[fan]\nenabled = true\ncurve = [[45, 0], [65.5, 255]]\n").unwrap();
+        assert!(config.fan.enabled);
+        assert_eq!(config.fan.curve, vec![(45.0, 0), (65.5, 255)]);
+        assert!(config.validate().is_ok());
+
+        config.fan.curve = vec![(60.0, 100), (50.0, 200)];
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidFan(_))));
+        assert!(config_from_env(&[("INFO_DISPLAY_FAN_CONTROL", "1")]).fan.enabled);
+    }
+
+    #[test]
+    fn test_config_file_epd_section() {
+        let mut config = config_from_toml("[epd]\nenabled = true\nmin_refresh_secs = 60\nbusy_pin = 5\n").unwrap();
+        assert!(config.epd.enabled);
+        assert_eq!(config.epd.min_refresh(), Duration::from_secs(60));
+        assert_eq!((config.epd.dc_pin, config.epd.busy_pin), (25, 5));
+        assert!(config.validate().is_ok());
+
+        config.epd.busy_pin = 25;
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidEpd(_))));
+        config.epd.busy_pin = 40;
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidEpd(_))));
+        assert!(config_from_env(&[("INFO_DISPLAY_EPD", "true")]).epd.enabled);
+    }
+
+    // Builds from a config file with `contents`, the variables in `vars` and the arguments
+    // after the program name in `args`
+    fn layered_config(contents: &str, vars: &[(&str, &str)], args: &[&str]) -> AppConfig {
+        // Tests run in parallel, each needs its own file
+        static FILES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let file = FILES.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("info_display_layers_{}_{}.toml", std::process::id(), file));
+        fs::write(&path, contents).unwrap();
+        let vars: HashMap<String, String> = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        let config = ConfigBuilder::new()
+            .config_file(&path)
+            .env(move |name| vars.get(name).cloned())
+            .args(std::iter::once("info_display").chain(args.iter().copied()).map(String::from))
+            .build();
+        fs::remove_file(&path).unwrap();
+        config.unwrap()
+    }
+
+    #[test]
+    fn test_config_layers_override_in_order() {
+        // defaults < file
+        assert_eq!(AppConfig::default().enabled_screens, vec!["overview"]);
+        let file = "[epd]\nenabled = true\n\n[screens.clock]\n";
+        let config = layered_config(file, &[], &[]);
+        assert_eq!(config.enabled_screens, vec!["clock"]);
+        assert!(config.epd.enabled);
+
+        // file < env
+        let config = layered_config(file, &[("INFO_DISPLAY_SCREENS", "system"), ("INFO_DISPLAY_EPD", "false")], &[]);
+        assert_eq!(config.enabled_screens, vec!["system"]);
+        assert!(!config.epd.enabled);
+
+        // env < CLI
+        let config = layered_config(file, &[("INFO_DISPLAY_SCREENS", "system"), ("INFO_DISPLAY_EPD", "false")], &["--screens", "gpio", "--epd"]);
+        assert_eq!(config.enabled_screens, vec!["gpio"]);
+        assert!(config.epd.enabled);
+    }
+
+    #[test]
+    fn test_parse_screens_file() {
+        let entries = parse_screens_file("# rotation\n\nclock:5\n  network  # default duration\nstorage : 2.5\n").unwrap();
+        assert_eq!(entries, vec![
+            ("clock".to_string(), Some(Duration::from_secs(5))),
+            ("network".to_string(), None),
+            ("storage".to_string(), Some(Duration::from_millis(2500))),
+        ]);
+        assert_eq!(parse_screens_file("clock\nnetwork:soon\n").unwrap_err(), "line 2: invalid duration \"soon\"");
+        assert_eq!(parse_screens_file(":5").unwrap_err(), "line 1: missing screen name");
+        assert_eq!(parse_screens_file("# nothing yet\n").unwrap_err(), "no screens listed");
+    }
+
+    #[test]
+    fn test_screens_file_layer() {
+        let path = env::temp_dir().join(format!("info_display_screens_{}.txt", std::process::id()));
+        fs::write(&path, "clock:5\nnetwork\n").unwrap();
+        let screens_file = format!("--screens-file={}", path.display());
+
+        let config = layered_config("", &[("INFO_DISPLAY_SCREENS", "system")], &[&screens_file]);
+        assert_eq!(config.enabled_screens, vec!["clock", "network"]);
+        assert_eq!(config.screen_durations, HashMap::from([("clock".to_string(), Duration::from_secs(5))]));
+        assert!(config.validate().is_ok());
+
+        // Later options on the command line still win
+        let config = layered_config("", &[], &[&screens_file, "--screens", "storage"]);
+        assert_eq!(config.enabled_screens, vec!["storage"]);
+
+        fs::write(&path, "clock:0.01\n").unwrap();
+        let config = layered_config("", &[], &[&screens_file]);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidScreenDuration)));
+        fs::write(&path, "clocks\n").unwrap();
+        let config = layered_config("", &[], &[&screens_file]);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidScreen(_))));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_config_file_from_env_layer() {
+        let path = env::temp_dir().join(format!("info_display_env_file_{}.toml", std::process::id()));
+        fs::write(&path, "[screens.about]\n").unwrap();
+        let file = path.to_string_lossy().to_string();
+        let config = ConfigBuilder::new()
+            .env(move |name| (name == "INFO_DISPLAY_CONFIG").then(|| file.clone()))
+            .build()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.enabled_screens, vec!["about"]);
+    }
+
+    #[test]
+    fn test_config_file_screen_sections() {
+        let config = config_from_toml("[screens.storage]\nshow_percent = true\ninode_warning_percent = 80\n\n[screens.network]\ninterface = \"wlan0\"\n\n\
+                          [screens.clock]\n\n[screens.system]\nprocess_warning_count = 600\n\n\
+                          [screens.temperature]\nmargin_warning_celsius = 15.0\n").unwrap();
+        assert_eq!(config.enabled_screens, vec!["storage", "network", "clock", "system", "temperature"]);
+        assert!(config.validate().is_ok());
+
+        // Options apply to their own screen only
+        let options = config.screen_options();
+        assert!(!options.show_percent);
+        assert!(options.for_screen("storage").show_percent);
+        assert_eq!(options.for_screen("storage").inode_warning_percent, 80);
+        assert_eq!(options.inode_warning_percent, 90);
+        assert_eq!(options.process_warning_count, DEFAULT_PROCESS_WARNING_COUNT);
+        assert_eq!(options.for_screen("system").process_warning_count, 600);
+        assert_eq!(options.for_screen("temperature").thermal_margin_warning, 15.0);
+        assert_eq!(options.thermal_margin_warning, DEFAULT_THERMAL_MARGIN_WARNING);
+        assert_eq!(options.for_screen("network").network_interface.as_deref(), Some("wlan0"));
+        assert!(options.for_screen("overview").network_interface.is_none());
+    }
+
+    #[test]
+    fn test_config_file_screen_section_errors() {
+        for (contents, expected) in [
+            ("[screens.network]\niface = \"eth0\"\n", "iface"),
+            ("[screens.clock]\nshow_percent = true\n", "show_percent"),
+            ("[screens.overview]\nfields = [\"cpu\"]\n", "cpu"),
+            ("[screens.storage]\ninode_warning_percent = 101\n", "101"),
+        ] {
+            let err = config_from_toml(contents).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
         }
-        let config = AppConfig::from_env();
+
+        // The ambient screen can get its pin from its own section
+        let config = config_from_toml("[screens.ambient]\ngpio = 4\n").unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.screen_options().for_screen("ambient").dht22_gpio, Some(4));
+    }
+
+    #[test]
+    fn test_config_file_multiplexers() {
+        let mut config = config_from_toml("[[multiplexers]]\naddress = 0x70\nlabel = \"left\"\n\n[[multiplexers]]\naddress = 0x71\nlabel = \"right\"\n").unwrap();
+        assert_eq!(config.multiplexer.muxes.len(), 2);
+        assert_eq!(config.multiplexer.other_addresses(), vec![0x71]);
+
+        config.set_multiplexer_target("right:3").unwrap();
+        assert!(config.multiplexer.enabled);
+        assert_eq!((config.multiplexer.address, config.multiplexer.channel), (0x71, 3));
+        assert_eq!(config.multiplexer.other_addresses(), vec![0x70]);
+        assert!(config.validate().is_ok());
+
+        config.set_multiplexer_target("0x70:1").unwrap();
+        assert_eq!((config.multiplexer.address, config.multiplexer.channel), (0x70, 1));
+        assert!(matches!(config.set_multiplexer_target("middle:1"), Err(ConfigError::InvalidMultiplexerTarget(_))));
+        assert!(matches!(config.set_multiplexer_target("0x71"), Err(ConfigError::InvalidMultiplexerTarget(_))));
+
+        // A target outside the listed multiplexers
+        config.set_multiplexer_target("0x72:0").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidMultiplexers(_))));
+
+        config.set_multiplexer_target("left:0").unwrap();
+        config.multiplexer.muxes[1].address = 0x70;
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidMultiplexers(_))));
+    }
+
+    #[test]
+    fn test_config_file_gpio_inputs() {
+        let mut config = config_from_toml("[[gpio_inputs]]
+gpio = 17
+label = \"Door\"
+active_low = true
+bias = \"pull_up\"
+                          active_text = \"OPEN\"
+inactive_text = \"CLOSED\"
+
+[[gpio_inputs]]
+gpio = 27
+label = \"PIR\"
+").unwrap();
+        assert!(config.validate().is_ok());
+        let door = &config.gpio_inputs[0];
+        assert!(door.active_low);
+        assert_eq!(door.bias, GpioBias::PullUp);
+        assert_eq!((door.active_text.as_str(), door.inactive_text.as_str()), ("OPEN", "CLOSED"));
+        let pir = &config.gpio_inputs[1];
+        assert_eq!((pir.gpio, pir.active_low, pir.bias, pir.active_text.as_str()), (27, false, GpioBias::AsIs, "ON"));
+
+        config.gpio_inputs[1].gpio = 17;
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidGpioInputs(_))));
+        config.gpio_inputs[1].gpio = 28;
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidGpioInputs(_))));
+        config.gpio_inputs[1].gpio = 27;
+        config.gpio_inputs[1].label = " ".to_string();
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidGpioInputs(_))));
+    }
+
+    #[test]
+    fn test_validate_invalid_mqtt_qos() {
+        let mut config = AppConfig::default();
+        config.mqtt.enabled = true;
+        config.mqtt.qos = 3;
+        assert!(config.validate().is_err());
+    }
+
+    // The settings a config file with `contents` gives
+    fn config_from_toml(contents: &str) -> Result<AppConfig, ConfigError> {
+        let mut config = AppConfig::default();
+        config.apply_toml(contents).map_err(ConfigError::ConfigFile)?;
+        Ok(config)
+    }
+
+    fn config_from_env(vars: &[(&str, &str)]) -> AppConfig {
+        let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+        let mut config = AppConfig::default();
+        config.apply_env(|name| vars.get(name).map(|value| value.to_string()));
+        config
+    }
+
+    #[test]
+    fn test_env_var_interval() {
+        let config = config_from_env(&[("INFO_DISPLAY_INTERVAL", "10")]);
+        assert_eq!(config.interval, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_fractional_interval() {
+        let config = config_from_env(&[("INFO_DISPLAY_INTERVAL", "0.5"), ("INFO_DISPLAY_SCREEN_DURATION", "2.5")]);
+        assert_eq!(config.interval, Duration::from_millis(500));
+        assert_eq!(config.screen_duration, Duration::from_millis(2500));
+        assert!(config.validate().is_ok());
+
+        assert_eq!(parse_seconds(" 3 "), Some(Duration::from_secs(3)));
+        assert_eq!(parse_seconds("-1"), None);
+        assert_eq!(parse_seconds("NaN"), None);
+
+        let mut config = AppConfig::default();
+        config.interval = Duration::from_millis(10);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidInterval)));
+    }
+
+    #[test]
+    fn test_placeholder() {
+        let config = config_from_env(&[("INFO_DISPLAY_PLACEHOLDER", "--")]);
+        assert_eq!(config.placeholder, "--");
+        assert!(config.validate().is_ok());
+
+        assert_eq!(config_from_env(&[("INFO_DISPLAY_PLACEHOLDER", "")]).placeholder, DEFAULT_PLACEHOLDER);
+
+        let mut config = AppConfig::default();
+        config.placeholder = "n/\na".to_string();
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidPlaceholder(_))));
+    }
+
+    #[test]
+    fn test_uptime_format() {
+        let config = config_from_env(&[("INFO_DISPLAY_UPTIME_STYLE", "since"), ("INFO_DISPLAY_DATE_FORMAT", "%Y-%m-%d %H:%M")]);
+        assert_eq!(
+            config.screen_options().uptime,
+            UptimeFormat { style: UptimeStyle::Since, date_format: "%Y-%m-%d %H:%M".to_string() }
+        );
+        assert!(config.validate().is_ok());
+        assert_eq!(config_from_env(&[("INFO_DISPLAY_UPTIME_STYLE", "boot")]).uptime_style, UptimeStyle::Compact);
+        assert!(matches!(parse_uptime_style("boot"), Err(ConfigError::InvalidUptimeStyle(_))));
+
+        let config = config_from_env(&[("INFO_DISPLAY_DATE_FORMAT", "%Q")]);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidDateFormat(_))));
+    }
+
+    #[test]
+    fn test_ip_version() {
+        let config = config_from_env(&[("INFO_DISPLAY_IP_VERSION", "both")]);
+        assert_eq!(config.screen_options().ip_version, IpVersion::Both);
+        assert_eq!(config_from_env(&[("INFO_DISPLAY_IP_VERSION", "v5")]).ip_version, IpVersion::V4);
+        assert!(matches!(parse_ip_version("ipv6"), Err(ConfigError::InvalidIpVersion(_))));
+
+        let section = toml::Value::Table(toml::toml! { ip_version = "v6" });
+        let mut options = ScreenOptions::default();
+        ScreenSection::parse("network", section).unwrap().apply(&mut options);
+        assert_eq!(options.ip_version, IpVersion::V6);
+        let section = toml::Value::Table(toml::toml! { ip_version = "v5" });
+        assert!(matches!(ScreenSection::parse("network", section), Err(ConfigError::InvalidIpVersion(_))));
+    }
+
+    #[test]
+    fn test_hostname_source() {
+        assert_eq!(AppConfig::default().hostname_source, HostnameSource::System);
+        let config = config_from_env(&[("INFO_DISPLAY_HOSTNAME_SOURCE", "file")]);
+        assert_eq!(config.hostname_source, HostnameSource::File);
+        let config = config_from_env(&[("INFO_DISPLAY_HOSTNAME_SOURCE", "bogus")]);
+        assert_eq!(config.hostname_source, HostnameSource::System);
+        assert!(matches!(parse_hostname_source("name:"), Err(ConfigError::InvalidHostnameSource(_))));
+    }
+
+    #[test]
+    fn test_env_var_screens() {
+        let config = config_from_env(&[("INFO_DISPLAY_SCREENS", "network, system,,storage")]);
+        assert_eq!(config.enabled_screens, vec!["network", "system", "storage"]);
+        let config = config_from_env(&[("INFO_DISPLAY_SCREENS", " network system\tstorage ")]);
+        assert_eq!(config.enabled_screens, vec!["network", "system", "storage"]);
+        let config = config_from_env(&[("INFO_DISPLAY_SCREENS", " , ")]);
+        assert_eq!(config.enabled_screens, vec!["overview"]);
+    }
+
+    #[test]
+    fn test_parse_screen_list() {
+        assert_eq!(parse_screen_list("network,system"), vec!["network", "system"]);
+        assert_eq!(parse_screen_list("network system  storage"), vec!["network", "system", "storage"]);
+        assert_eq!(parse_screen_list(" network , system,storage clock "), vec!["network", "system", "storage", "clock"]);
+        assert!(parse_screen_list(" ,, ").is_empty());
+    }
+
+    #[test]
+    fn test_screen_list_same_from_cli_and_env() {
+        let from_cli = layered_config("", &[], &["--screens", "network, system storage"]);
+        let from_env = config_from_env(&[("INFO_DISPLAY_SCREENS", "network, system storage")]);
+        assert_eq!(from_cli.enabled_screens, vec!["network", "system", "storage"]);
+        assert_eq!(from_cli.enabled_screens, from_env.enabled_screens);
+        let config = layered_config("", &[], &["--screens=network,,system"]);
+        assert_eq!(config.enabled_screens, vec!["network", "system"]);
+
+        // Names are checked however they were separated
+        let config = layered_config("", &[], &["--screens", "network sytem"]);
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidScreen(name)) if name == "sytem"));
+        let config = layered_config("", &[], &["--screens", " , "]);
+        assert!(matches!(config.validate(), Err(ConfigError::NoScreensEnabled)));
+    }
+
+    #[test]
+    fn test_env_var_daemon_mode() {
+        let config = config_from_env(&[("INFO_DISPLAY_DAEMON", "true")]);
         assert!(config.daemon_mode);
-        unsafe {
-            env::remove_var("INFO_DISPLAY_DAEMON");
-        }
     }
 
     #[test]
     fn test_env_var_multiplexer() {
-        unsafe {
-            env::set_var("INFO_DISPLAY_MUX_ENABLED", "true");
-            env::set_var("INFO_DISPLAY_MUX_CHANNEL", "3");
-            env::set_var("INFO_DISPLAY_MUX_ADDRESS", "0x71");
-        }
-        let config = AppConfig::from_env();
+        let config = config_from_env(&[
+            ("INFO_DISPLAY_MUX_ENABLED", "true"),
+            ("INFO_DISPLAY_MUX_CHANNEL", "3"),
+            ("INFO_DISPLAY_MUX_ADDRESS", "0x71"),
+        ]);
         assert!(config.multiplexer.enabled);
         assert_eq!(config.multiplexer.channel, 3);
         assert_eq!(config.multiplexer.address, 0x71);
-        unsafe {
-            env::remove_var("INFO_DISPLAY_MUX_ENABLED");
-            env::remove_var("INFO_DISPLAY_MUX_CHANNEL");
-            env::remove_var("INFO_DISPLAY_MUX_ADDRESS");
-        }
     }
-}
\ No newline at end of file
+}
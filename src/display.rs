@@ -1,123 +1,683 @@
 use embedded_graphics::{
-    mono_font::{ascii::FONT_6X10, iso_8859_16::FONT_7X13_BOLD, MonoTextStyle},
+    mono_font::{ascii::FONT_6X10, iso_8859_1, iso_8859_16::FONT_7X13_BOLD, MonoFont, MonoTextStyle},
     pixelcolor::BinaryColor,
     prelude::*,
     text::Text,
 };
-use linux_embedded_hal::I2cdev;
+use embedded_hal::i2c::{ErrorType, I2c, Operation};
+use linux_embedded_hal::{I2CError, I2cdev};
 use ssd1306::{prelude::*, I2CDisplayInterface, Ssd1306};
+use std::fs;
 use std::sync::{Arc, Mutex};
-use crate::tca9548a::Tca9548a;
+use std::time::Duration;
+use tracing::{debug_span, info, warn};
+#[cfg(feature = "epd")]
+use crate::config::EpdConfig;
+use crate::config::MultiplexerConfig;
+use crate::errors::AppError;
+use crate::framebuffer::FrameBuffer;
+use crate::system_info::get_header_i2c_bus;
+use crate::tca9548a::{channel_mask, MultiplexedI2c, MultiplexedI2cError, Tca9548a};
 
-pub struct DisplayManager {
-    display: Ssd1306<I2CInterface<I2cdev>, DisplaySize128x64, ssd1306::mode::BufferedGraphicsMode<DisplaySize128x64>>,
-    _mux_handle: Option<Arc<Mutex<Tca9548a>>>,
+/// Device of the I2C bus on the header pins, i2c-1 on most Pis but not on every one.
+pub fn i2c_bus_path() -> String {
+    format!("/dev/i2c-{}", get_header_i2c_bus())
 }
 
-impl DisplayManager {
-    pub fn new(use_multiplexer: bool, mux_channel: u8, mux_address: u8) -> Result<Self, Box<dyn std::error::Error>> {
-        let (display, mux_handle) = if use_multiplexer {
-            println!("Using TCA9548A multiplexer on address 0x{:02X}, channel {}", mux_address, mux_channel);
-            
-            // Create shared I2C bus and multiplexer
-            let i2c_shared = Arc::new(Mutex::new(I2cdev::new("/dev/i2c-1")?));
-            let mut mux = Tca9548a::with_address(Arc::clone(&i2c_shared), mux_address);
-            mux.select_channel(mux_channel)?;
-            
-            // Store mux in Arc<Mutex> to keep it alive
-            let mux_handle = Arc::new(Mutex::new(mux));
-            
-            // Create a new I2C connection for the display
-            // (the channel is already selected on the multiplexer)
-            let i2c = I2cdev::new("/dev/i2c-1")?;
-            let interface = I2CDisplayInterface::new(i2c);
-            
-            let mut display = Ssd1306::new(
-                interface,
-                DisplaySize128x64,
-                DisplayRotation::Rotate0,
-            )
-            .into_buffered_graphics_mode();
-            
-            display.init().map_err(|e| format!("Failed to initialize display on multiplexer channel {}: {:?}", mux_channel, e))?;
-            (display, Some(mux_handle))
-        } else {
-            // Standard I2C connection
-            let i2c = I2cdev::new("/dev/i2c-1")?;
-            let interface = I2CDisplayInterface::new(i2c);
-            
-            let mut display = Ssd1306::new(
-                interface,
-                DisplaySize128x64,
-                DisplayRotation::Rotate0,
-            )
-            .into_buffered_graphics_mode();
-            
-            display.init().map_err(|e| format!("Failed to initialize display on I2C bus: {:?}. Check if display is connected or use --mux flag if using multiplexer.", e))?;
-            (display, None)
+// Bus clock from the device tree, set with dtparam=i2c_arm_baudrate on a Pi
+fn i2c_clock_frequency_path() -> String {
+    format!("/sys/class/i2c-adapter/i2c-{}/of_node/clock-frequency", get_header_i2c_bus())
+}
+
+// Supported by the SSD1306 and the Pi's I2C controller
+pub const MIN_I2C_SPEED: u32 = 10_000;
+pub const MAX_I2C_SPEED: u32 = 1_000_000;
+
+// Baseline of the first content line, below the title bar or at the very top
+const CONTENT_TOP_WITH_TITLE: i32 = 25;
+const CONTENT_TOP_WITHOUT_TITLE: i32 = 8;
+const CONTENT_LINE_HEIGHT: i32 = 12;
+const DISPLAY_HEIGHT: i32 = 64;
+
+const DISPLAY_WIDTH: u32 = 128;
+// Blank space kept at the right of each column so neighbours do not run together
+const COLUMN_GAP: u32 = 4;
+
+/// Most content columns that still fit a readable value in each
+pub const MAX_COLUMNS: u8 = 2;
+
+/// Characters that fit across the display on a content line, and in the title
+pub const LINE_CHARS: usize = (DISPLAY_WIDTH / FONT_6X10.character_size.width) as usize;
+pub const TITLE_CHARS: usize = (DISPLAY_WIDTH / FONT_7X13_BOLD.character_size.width) as usize;
+
+/// Narrowest configurable content line; room for a short value and "..."
+pub const MIN_LINE_CHARS: usize = 8;
+
+/// Font of the content lines. Both are 6x10 with the same ASCII glyphs; characters a
+/// font lacks are drawn as '?'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentFont {
+    Ascii,
+    /// ISO 8859-1, which has the degree sign
+    #[default]
+    Latin1,
+}
+
+impl ContentFont {
+    pub const ALL: [ContentFont; 2] = [ContentFont::Ascii, ContentFont::Latin1];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|font| font.name() == name.trim().to_lowercase())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContentFont::Ascii => "ascii",
+            ContentFont::Latin1 => "latin1",
+        }
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        match self {
+            ContentFont::Ascii => c.is_ascii(),
+            ContentFont::Latin1 => (c as u32) < 0x100,
+        }
+    }
+
+    fn mono_font(&self) -> &'static MonoFont<'static> {
+        match self {
+            ContentFont::Ascii => &FONT_6X10,
+            ContentFont::Latin1 => &iso_8859_1::FONT_6X10,
+        }
+    }
+}
+
+/// The I2C bus clock in Hz, if the kernel exposes it.
+pub fn i2c_bus_speed() -> Option<u32> {
+    fs::read(i2c_clock_frequency_path()).ok().and_then(|bytes| parse_clock_frequency(&bytes))
+}
+
+// Device tree cells are big-endian u32s
+fn parse_clock_frequency(bytes: &[u8]) -> Option<u32> {
+    let cell: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+    Some(u32::from_be_bytes(cell))
+}
+
+/// Check the bus runs at `requested` Hz. i2c-dev has no ioctl to change an adapter's
+/// clock, so a mismatch is reported with the boot setting that fixes it.
+pub fn check_i2c_speed(requested: u32) {
+    match i2c_bus_speed() {
+        Some(current) if current == requested => info!("I2C bus running at {} Hz", current),
+        Some(current) => warn!(
+            "I2C bus runs at {} Hz, not the requested {} Hz; add dtparam=i2c_arm_baudrate={} to config.txt and reboot",
+            current, requested, requested
+        ),
+        None => warn!(
+            "Could not read the I2C bus speed from {}; to run at {} Hz set dtparam=i2c_arm_baudrate={} in config.txt",
+            i2c_clock_frequency_path(), requested, requested
+        ),
+    }
+}
+
+// Opening the bus is where a missing i2c group membership shows up, so explain it there
+fn open_i2c_bus() -> crate::errors::Result<I2cdev> {
+    let path = i2c_bus_path();
+    I2cdev::new(&path).map_err(|e| match std::io::Error::from(e) {
+        e if e.kind() == std::io::ErrorKind::PermissionDenied => AppError::permission_denied(&path, Some("i2c")),
+        e => AppError::display_init(&e.to_string()),
+    })
+}
+
+// Errors from opening the bus are AppErrors already; everything else is the display's
+fn into_app_error(err: Box<dyn std::error::Error>, context: &str) -> AppError {
+    match err.downcast::<AppError>() {
+        Ok(err) if matches!(*err, AppError::Permission(_)) => *err,
+        Ok(err) => AppError::display_init(&format!("{}{}", context, display_reason(&err))),
+        Err(err) => AppError::display_init(&format!("{}{}", context, err)),
+    }
+}
+
+/// What went wrong with the display, without the "Display error" prefix.
+pub fn display_reason(err: &AppError) -> String {
+    match err {
+        AppError::Display(reason) => reason.clone(),
+        err => err.to_string(),
+    }
+}
+
+// Where SSD1306 modules answer, depending on their address jumper
+const DISPLAY_ADDRESSES: [u8; 2] = [0x3C, 0x3D];
+
+/// Probe every channel of the configured multiplexer for displays and the scan addresses,
+/// returning a table of which addresses answered on which channel. Any other multiplexers
+/// are disabled first so their devices do not show up.
+pub fn scan_multiplexer(config: &MultiplexerConfig) -> crate::errors::Result<String> {
+    scan_multiplexer_channels(config).map_err(|e| into_app_error(e, "Multiplexer scan failed: "))
+}
+
+fn scan_multiplexer_channels(config: &MultiplexerConfig) -> Result<String, Box<dyn std::error::Error>> {
+    let mut addresses = DISPLAY_ADDRESSES.to_vec();
+    for &address in &config.scan_addresses {
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+
+    let i2c = Arc::new(Mutex::new(open_i2c_bus()?));
+    for address in config.other_addresses() {
+        Tca9548a::with_address(Arc::clone(&i2c), address).disable_all_channels()
+            .map_err(|e| format!("Multiplexer 0x{:02X}: {}", address, e))?;
+    }
+    let found = Tca9548a::with_address(i2c, config.address).scan(&addresses)?;
+    Ok(format_scan(&addresses, &found))
+}
+
+fn format_scan(addresses: &[u8], found: &[Vec<u8>]) -> String {
+    let mut table = String::from("Channel");
+    for address in addresses {
+        table.push_str(&format!("  0x{:02X}", address));
+    }
+    table.push('\n');
+
+    for (channel, acked) in found.iter().enumerate() {
+        table.push_str(&format!("{:<7}", channel));
+        for address in addresses {
+            let mark = if acked.contains(address) { "ACK" } else { "-" };
+            table.push_str(&format!("  {:<4}", mark));
+        }
+        table.truncate(table.trim_end().len());
+        table.push('\n');
+    }
+    table
+}
+
+/// How a screen's title and content lines are drawn, apart from the panel they end up
+/// on, so frames can be drawn into any `DrawTarget`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextLayout {
+    /// Content lines are laid out left to right in this many columns
+    pub columns: u8,
+    /// Characters per row when long lines are wrapped rather than cut off
+    pub wrap_width: Option<usize>,
+    pub font: ContentFont,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self { columns: 1, wrap_width: None, font: ContentFont::default() }
+    }
+}
+
+impl TextLayout {
+    /// Rows of content that fit below the title, or on the whole display without one.
+    pub fn rows(&self, with_title: bool) -> usize {
+        ((DISPLAY_HEIGHT - 1 - content_top(with_title)) / CONTENT_LINE_HEIGHT + 1) as usize
+    }
+
+    /// Draw the title, when there is one, and the content lines over `target`. Rows
+    /// below the display are left out.
+    pub fn draw<D>(&self, title: Option<&str>, content: &str, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        // Draw title (bold, at the top); without one the content starts at the top
+        if let Some(title) = title {
+            let title_style = MonoTextStyle::new(&FONT_7X13_BOLD, BinaryColor::On);
+            Text::new(title, Point::new(0, 12), title_style).draw(target)?;
+        }
+        let content_top = content_top(title.is_some());
+
+        // Draw content lines
+        let content_style = MonoTextStyle::new(self.font.mono_font(), BinaryColor::On);
+        let wrapped;
+        let content = match self.wrap_width {
+            Some(width) if self.columns == 1 => {
+                wrapped = wrap_content(content, width, self.rows(title.is_some()));
+                wrapped.as_str()
+            }
+            _ => content,
         };
+        for (x_pos, row, line) in layout_columns(content, self.columns, self.font.mono_font().character_size.width) {
+            let y_pos = content_top + (row as i32 * CONTENT_LINE_HEIGHT);
+            if y_pos < DISPLAY_HEIGHT { // Make sure we don't exceed display height
+                Text::new(&line, Point::new(x_pos, y_pos), content_style).draw(target)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn content_top(with_title: bool) -> i32 {
+    if with_title { CONTENT_TOP_WITH_TITLE } else { CONTENT_TOP_WITHOUT_TITLE }
+}
+
+// Where each content line goes as (x, row, text): the lines fill the rows left to right,
+// cut to the width of their column when there is more than one
+fn layout_columns(content: &str, columns: u8, char_width: u32) -> Vec<(i32, usize, String)> {
+    let columns = usize::from(columns.max(1));
+    let column_width = DISPLAY_WIDTH / columns as u32;
+    let max_chars = ((column_width - COLUMN_GAP) / char_width) as usize;
+
+    content.lines().enumerate().map(|(i, line)| {
+        let (row, column) = (i / columns, i % columns);
+        let text = if columns > 1 { line.chars().take(max_chars).collect() } else { line.to_string() };
+        ((column as u32 * column_width) as i32, row, text)
+    }).collect()
+}
+
+// The content with lines longer than `width` characters split at spaces, or inside a
+// word longer than a row, in at most `max_rows` rows; a row that cuts content short
+// ends in "..."
+fn wrap_content(content: &str, width: usize, max_rows: usize) -> String {
+    let width = width.max(1);
+    let mut rows: Vec<String> = content.lines().flat_map(|line| wrap_line(line, width)).collect();
+    if rows.len() > max_rows {
+        rows.truncate(max_rows);
+        if let Some(last) = rows.last_mut() {
+            let kept: String = last.chars().take(width.saturating_sub(3)).collect();
+            *last = format!("{}...", kept);
+        }
+    }
+    rows.join("\n")
+}
+
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    if line.chars().count() <= width {
+        return vec![line.to_string()];
+    }
+    let mut rows = Vec::new();
+    let mut row: Vec<char> = Vec::new();
+    for word in line.split(' ') {
+        let word: Vec<char> = word.chars().collect();
+        let needed = if row.is_empty() { word.len() } else { row.len() + 1 + word.len() };
+        if needed <= width {
+            if !row.is_empty() {
+                row.push(' ');
+            }
+            row.extend(&word);
+            continue;
+        }
+        if !row.is_empty() {
+            rows.push(row.drain(..).collect());
+        }
+        let mut chunks = word.chunks(width).peekable();
+        while let Some(chunk) = chunks.next() {
+            if chunks.peek().is_some() {
+                rows.push(chunk.iter().collect());
+            } else {
+                row = chunk.to_vec();
+            }
+        }
+    }
+    rows.push(row.into_iter().collect());
+    rows
+}
+
+type SharedMux = Arc<Mutex<Tca9548a>>;
+
+// The multiplexers a display sits behind: its own, with the channels to keep enabled
+// (the display's included), and any others on the bus, kept with every channel
+// disabled so a device behind them cannot answer in place of the display
+struct MuxHandles {
+    display: SharedMux,
+    channels: u8,
+    others: Vec<Tca9548a>,
+}
+
+impl MuxHandles {
+    fn open(config: &MultiplexerConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let i2c_shared = Arc::new(Mutex::new(open_i2c_bus()?));
+
+        // Before enabling anything, so two displays at the same address never share the bus
+        let mut others = Vec::new();
+        for address in config.other_addresses() {
+            let mut other = Tca9548a::with_address(Arc::clone(&i2c_shared), address);
+            other.disable_all_channels()
+                .map_err(|e| format!("Multiplexer 0x{:02X}: {}", address, e))?;
+            others.push(other);
+        }
 
-        Ok(DisplayManager {
-            display,
-            _mux_handle: mux_handle,
+        let channels = channel_mask(config.channel)? | config.shared_channels;
+        let mut mux = Tca9548a::with_address(i2c_shared, config.address);
+        mux.select_channels(channels)?;
+        Ok(Self {
+            display: Arc::new(Mutex::new(mux)),
+            channels,
+            others,
         })
     }
 
-    pub fn clear_display(use_multiplexer: bool, mux_channel: u8, mux_address: u8) -> Result<(), Box<dyn std::error::Error>> {
-        if use_multiplexer {
-            // Setup multiplexer and select channel
-            let i2c = Arc::new(Mutex::new(I2cdev::new("/dev/i2c-1")?));
-            let mut mux = Tca9548a::with_address(Arc::clone(&i2c), mux_address);
-            mux.select_channel(mux_channel)?;
-            drop(mux);
-            
-            // Now use regular I2C (the channel is already selected)
-            let i2c = I2cdev::new("/dev/i2c-1")?;
-            let interface = I2CDisplayInterface::new(i2c);
-            let mut display = Ssd1306::new(
-                interface,
-                DisplaySize128x64,
-                DisplayRotation::Rotate0,
-            )
-            .into_buffered_graphics_mode();
-            display.init().map_err(|e| format!("Failed to initialize display on multiplexer channel {} for clearing: {:?}", mux_channel, e))?;
-            display.clear(BinaryColor::Off).unwrap();
-            display.flush().unwrap();
-        } else {
-            let i2c = I2cdev::new("/dev/i2c-1")?;
-            let interface = I2CDisplayInterface::new(i2c);
-            let mut display = Ssd1306::new(
-                interface,
-                DisplaySize128x64,
-                DisplayRotation::Rotate0,
-            )
-            .into_buffered_graphics_mode();
-            display.init().map_err(|e| format!("Failed to initialize display for clearing: {:?}. Check if display is connected or use --mux flag if using multiplexer.", e))?;
-            display.clear(BinaryColor::Off).unwrap();
-            display.flush().unwrap();
+    // Other programs may switch the multiplexers, so they are put back before every flush
+    fn restore(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        for other in &mut self.others {
+            if other.ensure_disabled()? {
+                warn!("Multiplexer 0x{:02X} had channels enabled by something else on the bus; disabled them again", other.address());
+            }
+        }
+
+        let mut mux = self.display.lock().map_err(|_| "Multiplexer lock poisoned")?;
+        if mux.reselect_channels(self.channels)? {
+            warn!("Multiplexer channels 0x{:02X} were switched by something else on the bus; selected them again", self.channels);
         }
         Ok(())
     }
+}
 
-    pub fn render_content(&mut self, title: &str, content: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Clear display
-        self.display.clear(BinaryColor::Off).unwrap();
-        
-        // Draw title (bold, at the top)
-        let title_style = MonoTextStyle::new(&FONT_7X13_BOLD, BinaryColor::On);
-        Text::new(title, Point::new(0, 12), title_style).draw(&mut self.display).unwrap();
-        
-        // Draw content lines
-        let content_style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
-        for (i, line) in content.lines().enumerate() {
-            let y_pos = 25 + (i as i32 * 12);
-            if y_pos < 64 { // Make sure we don't exceed display height
-                Text::new(line, Point::new(0, y_pos), content_style).draw(&mut self.display).unwrap();
+// The bus the display is on: the Pi's I2C bus directly, or one channel of a multiplexer
+enum DisplayBus {
+    Direct(I2cdev),
+    Multiplexed(MultiplexedI2c),
+}
+
+impl DisplayBus {
+    // The bus for a display, with the multiplexers it sits behind when there are any
+    fn open(config: &MultiplexerConfig) -> Result<(Self, Option<MuxHandles>), Box<dyn std::error::Error>> {
+        if !config.enabled {
+            return Ok((DisplayBus::Direct(open_i2c_bus()?), None));
+        }
+
+        let muxes = MuxHandles::open(config)?;
+        let bus = MultiplexedI2c::with_shared_channels(Arc::clone(&muxes.display), config.channel, config.shared_channels);
+        Ok((DisplayBus::Multiplexed(bus), Some(muxes)))
+    }
+}
+
+impl ErrorType for DisplayBus {
+    type Error = MultiplexedI2cError<I2CError>;
+}
+
+impl I2c for DisplayBus {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        match self {
+            DisplayBus::Direct(i2c) => i2c.transaction(address, operations).map_err(MultiplexedI2cError::Bus),
+            DisplayBus::Multiplexed(i2c) => i2c.transaction(address, operations),
+        }
+    }
+}
+
+type Display = Ssd1306<I2CInterface<DisplayBus>, DisplaySize128x64, ssd1306::mode::BufferedGraphicsMode<DisplaySize128x64>>;
+
+fn init_display(bus: DisplayBus, config: &MultiplexerConfig) -> Result<Display, Box<dyn std::error::Error>> {
+    let interface = I2CDisplayInterface::new(bus);
+    let mut display = Ssd1306::new(
+        interface,
+        DisplaySize128x64,
+        DisplayRotation::Rotate0,
+    )
+    .into_buffered_graphics_mode();
+
+    if config.enabled {
+        display.init().map_err(|e| format!("Failed to initialize display on multiplexer channel {}: {:?}", config.channel, e))?;
+    } else {
+        display.init().map_err(|e| format!("Failed to initialize display on I2C bus: {:?}. Check if display is connected or use --mux flag if using multiplexer.", e))?;
+    }
+    Ok(display)
+}
+
+// The panel frames are copied to
+enum Panel {
+    Oled {
+        display: Box<Display>,
+        mux: Option<MuxHandles>,
+    },
+    #[cfg(feature = "epd")]
+    Epd {
+        panel: crate::epd::EpdPanel,
+        min_refresh: Duration,
+    },
+}
+
+pub struct DisplayManager {
+    panel: Panel,
+    // The last frame drawn, kept so it can be served remotely
+    frame: FrameBuffer,
+    layout: TextLayout,
+    // Blink a corner pixel so a hung loop is distinguishable from a static screen
+    heartbeat: bool,
+    frames_rendered: u64,
+}
+
+impl DisplayManager {
+    /// Open the bus and initialize the display, behind the multiplexer when enabled.
+    pub fn new(multiplexer: &MultiplexerConfig) -> crate::errors::Result<Self> {
+        Self::connect(multiplexer).map_err(|e| into_app_error(e, ""))
+    }
+
+    fn connect(multiplexer: &MultiplexerConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        if multiplexer.enabled {
+            info!("Using TCA9548A multiplexer on address 0x{:02X}, channel {}", multiplexer.address, multiplexer.channel);
+        }
+        let (bus, mux) = DisplayBus::open(multiplexer)?;
+        let display = init_display(bus, multiplexer)?;
+
+        Ok(Self::with_panel(Panel::Oled { display: Box::new(display), mux }))
+    }
+
+    /// Initialize the e-paper HAT instead of an OLED.
+    #[cfg(feature = "epd")]
+    pub fn epd(config: &EpdConfig) -> crate::errors::Result<Self> {
+        info!("Using e-paper display on {}", crate::epd::SPI_DEVICE_PATH);
+        let panel = crate::epd::EpdPanel::open(config).map_err(|e| into_app_error(e, ""))?;
+        Ok(Self::with_panel(Panel::Epd { panel, min_refresh: config.min_refresh() }))
+    }
+
+    fn with_panel(panel: Panel) -> Self {
+        DisplayManager {
+            panel,
+            frame: FrameBuffer::default(),
+            layout: TextLayout::default(),
+            heartbeat: false,
+            frames_rendered: 0,
+        }
+    }
+
+    pub fn clear_display(multiplexer: &MultiplexerConfig) -> crate::errors::Result<()> {
+        Self::clear(multiplexer).map_err(|e| into_app_error(e, "Failed to clear display: "))
+    }
+
+    fn clear(multiplexer: &MultiplexerConfig) -> Result<(), Box<dyn std::error::Error>> {
+        let (bus, _mux) = DisplayBus::open(multiplexer)?;
+        let mut display = init_display(bus, multiplexer)?;
+        display.clear(BinaryColor::Off).unwrap();
+        display.flush().map_err(|e| format!("{:?}", e))?;
+        Ok(())
+    }
+
+    /// Blank the e-paper HAT, which otherwise keeps showing the last frame.
+    #[cfg(feature = "epd")]
+    pub fn clear_epd(config: &EpdConfig) -> crate::errors::Result<()> {
+        let mut panel = crate::epd::EpdPanel::open(config).map_err(|e| into_app_error(e, ""))?;
+        panel.clear().map_err(|e| into_app_error(e, "Failed to clear display: "))
+    }
+
+    /// The shortest time the panel needs between frames; e-paper ghosts and wears
+    /// when refreshed too often.
+    pub fn min_refresh_interval(&self) -> Duration {
+        match &self.panel {
+            Panel::Oled { .. } => Duration::ZERO,
+            #[cfg(feature = "epd")]
+            Panel::Epd { min_refresh, .. } => *min_refresh,
+        }
+    }
+
+    /// Pack the content lines into `columns` columns, e.g. two readings per row.
+    pub fn set_columns(&mut self, columns: u8) {
+        self.layout.columns = columns.clamp(1, MAX_COLUMNS);
+    }
+
+    /// Wrap content lines longer than `width` characters onto the next rows instead of
+    /// running off the display; only with a single column.
+    pub fn set_wrap(&mut self, width: Option<usize>) {
+        self.layout.wrap_width = width;
+    }
+
+    /// Toggle a pixel in the top right corner on every frame.
+    pub fn set_heartbeat(&mut self, enabled: bool) {
+        self.heartbeat = enabled;
+    }
+
+    pub fn set_font(&mut self, font: ContentFont) {
+        self.layout.font = font;
+    }
+
+    /// Draw the title and content over `canvas` and show the result.
+    pub fn render_content(&mut self, title: Option<&str>, content: &str, canvas: FrameBuffer) -> Result<(), Box<dyn std::error::Error>> {
+        // Draw into the in-memory frame first, then copy it to the panel
+        let mut frame = canvas;
+        self.layout.draw(title, content, &mut frame)?;
+
+        if self.heartbeat {
+            draw_heartbeat(&mut frame, self.frames_rendered);
+        }
+        self.frames_rendered = self.frames_rendered.wrapping_add(1);
+
+        let _span = debug_span!("flush").entered();
+        match &mut self.panel {
+            Panel::Oled { display, mux } => {
+                display.clear(BinaryColor::Off).unwrap();
+                display.draw_iter(frame.pixels()).unwrap();
+
+                if let Some(mux) = mux {
+                    mux.restore()?;
+                }
+
+                // Flush to display
+                display.flush().map_err(|e| format!("Failed to flush display: {:?}", e))?;
+            }
+            #[cfg(feature = "epd")]
+            Panel::Epd { panel, .. } => {
+                panel.show(&frame).map_err(|e| format!("Failed to refresh e-paper display: {}", e))?;
+            }
+        }
+        self.frame = frame;
+        Ok(())
+    }
+
+    /// Blank the panel, e.g. on exit; the frame kept for remote viewers stays as it was.
+    pub fn blank(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        match &mut self.panel {
+            Panel::Oled { display, mux } => {
+                display.clear(BinaryColor::Off).unwrap();
+                if let Some(mux) = mux {
+                    mux.restore()?;
+                }
+                display.flush().map_err(|e| format!("Failed to flush display: {:?}", e))?;
             }
+            #[cfg(feature = "epd")]
+            Panel::Epd { panel, .. } => panel.clear()?,
         }
-        
-        // Flush to display
-        self.display.flush().unwrap();
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// The frame most recently drawn by render_content.
+    pub fn frame(&self) -> &FrameBuffer {
+        &self.frame
+    }
+}
+
+// Lit on every other frame, in the corner the title and content leave free
+fn draw_heartbeat(frame: &mut FrameBuffer, frames_rendered: u64) {
+    let color = if frames_rendered.is_multiple_of(2) { BinaryColor::On } else { BinaryColor::Off };
+    let _ = Pixel(Point::new(DISPLAY_WIDTH as i32 - 1, 0), color).draw(frame);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_font() {
+        assert_eq!(ContentFont::from_name(" Latin1"), Some(ContentFont::Latin1));
+        assert_eq!(ContentFont::from_name("comic"), None);
+        assert!(!ContentFont::Ascii.has_glyph('°'));
+        assert!(ContentFont::Latin1.has_glyph('°'));
+        assert!(!ContentFont::Latin1.has_glyph('…'));
+        // Lines hold as many characters in either
+        assert_eq!(ContentFont::Ascii.mono_font().character_size, ContentFont::Latin1.mono_font().character_size);
+    }
+
+    #[test]
+    fn test_heartbeat_toggles() {
+        let mut frame = FrameBuffer::default();
+        draw_heartbeat(&mut frame, 0);
+        assert!(frame.pixel(DISPLAY_WIDTH - 1, 0));
+        draw_heartbeat(&mut frame, 1);
+        assert!(!frame.pixel(DISPLAY_WIDTH - 1, 0));
+        draw_heartbeat(&mut frame, 2);
+        assert!(frame.pixel(DISPLAY_WIDTH - 1, 0));
+    }
+
+    #[test]
+    fn test_parse_clock_frequency() {
+        assert_eq!(parse_clock_frequency(&[0x00, 0x01, 0x86, 0xA0]), Some(100_000));
+        assert_eq!(parse_clock_frequency(&[0x00, 0x06, 0x1A, 0x80]), Some(400_000));
+        assert_eq!(parse_clock_frequency(&[0x00, 0x01]), None);
+    }
+
+    #[test]
+    fn test_layout_columns() {
+        let content = "Temp: 42C\nFreq: 1500MHz\nMem: 41%\nDisk: 18%\nUp: 3d";
+        let single = layout_columns(content, 1, 6);
+        assert_eq!(single[1], (0, 1, "Freq: 1500MHz".to_string()));
+
+        let double = layout_columns(content, 2, 6);
+        assert_eq!(double, vec![
+            (0, 0, "Temp: 42C".to_string()),
+            (64, 0, "Freq: 1500".to_string()),
+            (0, 1, "Mem: 41%".to_string()),
+            (64, 1, "Disk: 18%".to_string()),
+            (0, 2, "Up: 3d".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_content_moves_up_without_title() {
+        // The topmost row with a lit pixel
+        let top_row = |title: Option<&str>| {
+            let mut frame = FrameBuffer::default();
+            TextLayout::default().draw(title, "09:05:00", &mut frame).unwrap();
+            (0..DISPLAY_HEIGHT as u32).find(|&y| (0..DISPLAY_WIDTH).any(|x| frame.pixel(x, y))).unwrap()
+        };
+        // An empty title draws nothing but keeps the content below the title rows
+        let with_title = top_row(Some(""));
+        let without_title = top_row(None);
+        assert_eq!(with_title - without_title, (CONTENT_TOP_WITH_TITLE - CONTENT_TOP_WITHOUT_TITLE) as u32);
+        // The content now starts where the title would have been drawn
+        assert!(without_title < 12);
+
+        let layout = TextLayout::default();
+        assert_eq!((layout.rows(true), layout.rows(false)), (4, 5));
+    }
+
+    #[test]
+    fn test_wrap_content() {
+        // Short lines are left alone, long ones break at the last space that fits
+        assert_eq!(wrap_content("CPU: 42C\nModel: Raspberry Pi 4 Model B", 21, 5), "CPU: 42C\nModel: Raspberry Pi 4\nModel B");
+        // Words longer than a row are split
+        assert_eq!(
+            wrap_content("IP: 2001:db8:85a3::8a2e:370:7334", 21, 5),
+            "IP:\n2001:db8:85a3::8a2e:3\n70:7334"
+        );
+        assert_eq!(wrap_content("a b c", 1, 5), "a\nb\nc");
+    }
+
+    #[test]
+    fn test_wrap_content_respects_height() {
+        let content = "one two three four five six seven eight";
+        assert_eq!(wrap_content(content, 10, 2), "one two\nthree f...");
+        assert_eq!(wrap_content(content, 10, 9), "one two\nthree four\nfive six\nseven\neight");
+        assert_eq!(wrap_content("1\n2\n3", 10, 2), "1\n2...");
+    }
+
+    #[test]
+    fn test_format_scan() {
+        let mut found = vec![Vec::new(); 8];
+        found[2] = vec![0x3C, 0x48];
+        let table = format_scan(&[0x3C, 0x3D, 0x48], &found);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[0], "Channel  0x3C  0x3D  0x48");
+        assert_eq!(lines[1], "0        -     -     -");
+        assert_eq!(lines[3], "2        ACK   -     ACK");
+    }
+}
@@ -0,0 +1,323 @@
+use rppal::gpio::{self, Gpio, InputPin, OutputPin};
+use rppal::spi::{self, Bus, Mode, SlaveSelect, Spi};
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::config::EpdConfig;
+use crate::errors::AppError;
+use crate::framebuffer::{FrameBuffer, HEIGHT, WIDTH};
+
+pub const SPI_DEVICE_PATH: &str = "/dev/spidev0.0";
+
+// The panel's short side, across which RAM rows run, and its long side. Frames are
+// shown landscape, centred
+const PANEL_WIDTH: u32 = 122;
+const PANEL_HEIGHT: u32 = 250;
+const ROW_BYTES: usize = PANEL_WIDTH.div_ceil(8) as usize;
+const BUFFER_SIZE: usize = ROW_BYTES * PANEL_HEIGHT as usize;
+const X_OFFSET: u32 = (PANEL_HEIGHT - WIDTH) / 2;
+const Y_OFFSET: u32 = (PANEL_WIDTH - HEIGHT) / 2;
+
+// SSD1680 commands
+const DRIVER_OUTPUT_CONTROL: u8 = 0x01;
+const DEEP_SLEEP: u8 = 0x10;
+const DATA_ENTRY_MODE: u8 = 0x11;
+const SOFT_RESET: u8 = 0x12;
+const TEMPERATURE_SENSOR: u8 = 0x18;
+const MASTER_ACTIVATION: u8 = 0x20;
+const DISPLAY_UPDATE_CONTROL_1: u8 = 0x21;
+const DISPLAY_UPDATE_CONTROL_2: u8 = 0x22;
+const WRITE_RAM: u8 = 0x24;
+// The previous image, which partial refreshes compare against
+const WRITE_PREVIOUS_RAM: u8 = 0x26;
+const BORDER_WAVEFORM: u8 = 0x3C;
+const RAM_X_RANGE: u8 = 0x44;
+const RAM_Y_RANGE: u8 = 0x45;
+const RAM_X_COUNTER: u8 = 0x4E;
+const RAM_Y_COUNTER: u8 = 0x4F;
+
+// Update sequences for DISPLAY_UPDATE_CONTROL_2
+const FULL_UPDATE: u8 = 0xF7;
+const PARTIAL_UPDATE: u8 = 0xFF;
+
+// A full refresh takes about 2s; longer means the panel is not there
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const BUSY_POLL: Duration = Duration::from_millis(10);
+
+// spidev's default buffer size
+const SPI_CHUNK: usize = 4096;
+
+/// How a frame reaches the panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refresh {
+    /// Flashes the whole panel black and white, clearing any ghosting
+    Full,
+    /// Only redraws what changed
+    Partial,
+}
+
+// Decides how each frame is refreshed: not at all when the panel shows it already, and
+// in full every `full_every` refreshes since partial ones leave faint ghosts behind
+#[derive(Debug)]
+struct RefreshPlanner {
+    full_every: u32,
+    partial_since_full: u32,
+    shown: Option<Vec<u8>>,
+}
+
+impl RefreshPlanner {
+    fn new(full_every: u32) -> Self {
+        Self { full_every, partial_since_full: 0, shown: None }
+    }
+
+    fn plan(&mut self, buffer: &[u8]) -> Option<Refresh> {
+        if self.shown.as_deref() == Some(buffer) {
+            return None;
+        }
+        let refresh = if self.shown.is_none() || self.partial_since_full >= self.full_every {
+            self.partial_since_full = 0;
+            Refresh::Full
+        } else {
+            self.partial_since_full += 1;
+            Refresh::Partial
+        };
+        self.shown = Some(buffer.to_vec());
+        Some(refresh)
+    }
+
+    // After a failed refresh nobody knows what the panel shows
+    fn forget(&mut self) {
+        self.shown = None;
+    }
+}
+
+// The frame in the panel's RAM layout: rows along the long side, most significant bit
+// first, and a set bit for white. Lit pixels are drawn in black
+fn panel_buffer(frame: &FrameBuffer) -> Vec<u8> {
+    let mut buffer = vec![0xFF; BUFFER_SIZE];
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            if frame.pixel(x, y) {
+                // Landscape, with the HAT's connector on the left
+                let column = PANEL_WIDTH - 1 - (y + Y_OFFSET);
+                let row = (x + X_OFFSET) as usize;
+                buffer[row * ROW_BYTES + column as usize / 8] &= !(0x80 >> (column % 8));
+            }
+        }
+    }
+    buffer
+}
+
+fn open_error(err: Box<dyn Error>) -> Box<dyn Error> {
+    match err.downcast::<spi::Error>() {
+        Ok(err) => match *err {
+            spi::Error::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                Box::new(AppError::permission_denied(SPI_DEVICE_PATH, Some("spi")))
+            }
+            spi::Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                format!("SPI device {} missing (is SPI enabled?)", SPI_DEVICE_PATH).into()
+            }
+            err => Box::new(err),
+        },
+        Err(err) => match err.downcast::<gpio::Error>() {
+            Ok(err) => match *err {
+                gpio::Error::PermissionDenied(path) => Box::new(AppError::permission_denied(&path, Some("gpio"))),
+                err => Box::new(err),
+            },
+            Err(err) => err,
+        },
+    }
+}
+
+/// A Waveshare 2.13" e-paper HAT (V3 and V4, SSD1680 controller) on SPI0 CE0.
+///
+/// E-paper keeps its image without power and a refresh takes from a third of a second
+/// (partial) to two seconds (full, with the flashing that clears ghosting), so frames
+/// that did not change are not sent at all and partial refreshes are used in between
+/// full ones.
+pub struct EpdPanel {
+    spi: Spi,
+    dc: OutputPin,
+    reset: OutputPin,
+    busy: InputPin,
+    planner: RefreshPlanner,
+}
+
+impl EpdPanel {
+    /// Open SPI and the control pins and initialize the controller.
+    pub fn open(config: &EpdConfig) -> Result<Self, Box<dyn Error>> {
+        Self::connect(config).map_err(open_error)
+    }
+
+    fn connect(config: &EpdConfig) -> Result<Self, Box<dyn Error>> {
+        let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, config.spi_hz, Mode::Mode0)?;
+        let gpio = Gpio::new()?;
+        let mut panel = Self {
+            spi,
+            dc: gpio.get(config.dc_pin)?.into_output(),
+            reset: gpio.get(config.reset_pin)?.into_output_high(),
+            busy: gpio.get(config.busy_pin)?.into_input(),
+            planner: RefreshPlanner::new(config.full_refresh_every),
+        };
+        panel.init()?;
+        Ok(panel)
+    }
+
+    fn init(&mut self) -> Result<(), Box<dyn Error>> {
+        self.hardware_reset(Duration::from_millis(2));
+        self.wait_until_idle()?;
+        self.command(SOFT_RESET)?;
+        self.wait_until_idle()?;
+        self.configure()?;
+        self.command_with(DISPLAY_UPDATE_CONTROL_1, &[0x00, 0x80])?;
+        self.command_with(TEMPERATURE_SENSOR, &[0x80])?;
+        self.wait_until_idle()
+    }
+
+    fn hardware_reset(&mut self, pulse: Duration) {
+        self.reset.set_low();
+        thread::sleep(pulse);
+        self.reset.set_high();
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    // Gate lines, RAM addressing and the full-screen window, which a partial refresh's
+    // reset clears as well
+    fn configure(&mut self) -> Result<(), Box<dyn Error>> {
+        let last_row = (PANEL_HEIGHT - 1) as u16;
+        self.command_with(DRIVER_OUTPUT_CONTROL, &[last_row as u8, (last_row >> 8) as u8, 0x00])?;
+        // X and Y increment
+        self.command_with(DATA_ENTRY_MODE, &[0x03])?;
+        self.command_with(RAM_X_RANGE, &[0x00, (ROW_BYTES - 1) as u8])?;
+        self.command_with(RAM_Y_RANGE, &[0x00, 0x00, last_row as u8, (last_row >> 8) as u8])?;
+        self.command_with(BORDER_WAVEFORM, &[0x05])?;
+        self.home()
+    }
+
+    fn home(&mut self) -> Result<(), Box<dyn Error>> {
+        self.command_with(RAM_X_COUNTER, &[0x00])?;
+        self.command_with(RAM_Y_COUNTER, &[0x00, 0x00])
+    }
+
+    /// Show `frame`, unless the panel shows it already.
+    pub fn show(&mut self, frame: &FrameBuffer) -> Result<(), Box<dyn Error>> {
+        let buffer = panel_buffer(frame);
+        let result = match self.planner.plan(&buffer) {
+            None => return Ok(()),
+            Some(Refresh::Full) => self.full_refresh(&buffer),
+            Some(Refresh::Partial) => self.partial_refresh(&buffer),
+        };
+        if result.is_err() {
+            self.planner.forget();
+        }
+        result
+    }
+
+    /// Blank the panel with a full refresh.
+    pub fn clear(&mut self) -> Result<(), Box<dyn Error>> {
+        self.planner.forget();
+        self.full_refresh(&[0xFF; BUFFER_SIZE])
+    }
+
+    fn full_refresh(&mut self, buffer: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.home()?;
+        self.command_with(WRITE_RAM, buffer)?;
+        // The base the following partial refreshes start from
+        self.home()?;
+        self.command_with(WRITE_PREVIOUS_RAM, buffer)?;
+        self.update(FULL_UPDATE)
+    }
+
+    fn partial_refresh(&mut self, buffer: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.hardware_reset(Duration::from_millis(1));
+        self.configure()?;
+        // Keep the border from flashing
+        self.command_with(BORDER_WAVEFORM, &[0x80])?;
+        self.command_with(WRITE_RAM, buffer)?;
+        self.update(PARTIAL_UPDATE)
+    }
+
+    fn update(&mut self, sequence: u8) -> Result<(), Box<dyn Error>> {
+        self.command_with(DISPLAY_UPDATE_CONTROL_2, &[sequence])?;
+        self.command(MASTER_ACTIVATION)?;
+        self.wait_until_idle()
+    }
+
+    fn command(&mut self, command: u8) -> Result<(), Box<dyn Error>> {
+        self.dc.set_low();
+        self.spi.write(&[command])?;
+        Ok(())
+    }
+
+    fn command_with(&mut self, command: u8, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.command(command)?;
+        self.dc.set_high();
+        for chunk in data.chunks(SPI_CHUNK) {
+            self.spi.write(chunk)?;
+        }
+        Ok(())
+    }
+
+    fn wait_until_idle(&mut self) -> Result<(), Box<dyn Error>> {
+        let started = Instant::now();
+        while self.busy.is_high() {
+            if started.elapsed() > BUSY_TIMEOUT {
+                return Err(format!("e-paper display still busy after {}s", BUSY_TIMEOUT.as_secs()).into());
+            }
+            thread::sleep(BUSY_POLL);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for EpdPanel {
+    // The image stays without power, and leaving the panel driven shortens its life
+    fn drop(&mut self) {
+        let _ = self.command_with(DEEP_SLEEP, &[0x01]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+
+    #[test]
+    fn test_panel_buffer() {
+        let mut frame = FrameBuffer::default();
+        assert!(panel_buffer(&frame).iter().all(|&byte| byte == 0xFF));
+
+        Pixel(Point::new(0, 0), BinaryColor::On).draw(&mut frame).unwrap();
+        Pixel(Point::new(127, 63), BinaryColor::On).draw(&mut frame).unwrap();
+        let buffer = panel_buffer(&frame);
+        assert_eq!(buffer.len(), 4000);
+        // Top left lands at column 121 - 29, row 61
+        assert_eq!(buffer[61 * ROW_BYTES + 11], !0x08);
+        // Bottom right at column 121 - 92, row 188
+        assert_eq!(buffer[188 * ROW_BYTES + 3], !0x04);
+        assert_eq!(buffer.iter().filter(|&&byte| byte != 0xFF).count(), 2);
+    }
+
+    #[test]
+    fn test_refresh_planner() {
+        let mut planner = RefreshPlanner::new(2);
+        let (a, b) = ([0xFF; 4], [0x00; 4]);
+        assert_eq!(planner.plan(&a), Some(Refresh::Full));
+        // Unchanged frames are not refreshed
+        assert_eq!(planner.plan(&a), None);
+        assert_eq!(planner.plan(&b), Some(Refresh::Partial));
+        assert_eq!(planner.plan(&a), Some(Refresh::Partial));
+        assert_eq!(planner.plan(&b), Some(Refresh::Full));
+        assert_eq!(planner.plan(&a), Some(Refresh::Partial));
+
+        planner.forget();
+        assert_eq!(planner.plan(&a), Some(Refresh::Full));
+    }
+
+    #[test]
+    fn test_refresh_planner_always_full() {
+        let mut planner = RefreshPlanner::new(0);
+        assert_eq!(planner.plan(&[1]), Some(Refresh::Full));
+        assert_eq!(planner.plan(&[2]), Some(Refresh::Full));
+    }
+}
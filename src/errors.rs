@@ -75,11 +75,32 @@ impl From<anyhow::Error> for AppError {
     }
 }
 
+/// Exit status when the display loop stalled, see --stall-timeout
+pub const STALL_EXIT_CODE: i32 = 6;
+
 // Convenience type alias
 pub type Result<T> = std::result::Result<T, AppError>;
 
 // Helper functions for creating errors
 impl AppError {
+    /// The process exit status for this error, so scripts can tell failures apart:
+    /// 2 configuration, 3 display or other hardware, 4 permission, 5 daemon, 1 anything else.
+    /// A stalled display loop exits with STALL_EXIT_CODE.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Config(_) => 2,
+            AppError::Display(_) | AppError::Hardware(_) | AppError::Multiplexer(_) => 3,
+            AppError::Permission(_) => 4,
+            AppError::Io(err) if err.kind() == std::io::ErrorKind::PermissionDenied => 4,
+            AppError::Daemon(_) => 5,
+            AppError::Io(_)
+            | AppError::SystemInfo(_)
+            | AppError::ScreenFactory(_)
+            | AppError::ScreenManager(_)
+            | AppError::Application(_) => 1,
+        }
+    }
+
     pub fn display_init(msg: &str) -> Self {
         AppError::Display(msg.to_string())
     }
@@ -117,6 +138,16 @@ impl AppError {
         AppError::Permission(msg.to_string())
     }
 
+    /// `path` could not be opened for lack of permission, with how to fix that: join
+    /// `group`, which owns the device, or run as root.
+    pub fn permission_denied(path: &str, group: Option<&str>) -> Self {
+        let fix = match group {
+            Some(group) => format!("add the user to the {0} group (sudo usermod -aG {0} $USER, then log in again) or run as root", group),
+            None => "run as root".to_string(),
+        };
+        AppError::Permission(format!("cannot access {}; {}", path, fix))
+    }
+
     #[allow(dead_code)]
     pub fn multiplexer<E: std::error::Error + Send + Sync + 'static>(err: E) -> Self {
         AppError::Multiplexer(Box::new(err))
@@ -133,4 +164,33 @@ impl<T, E: fmt::Debug> DisplayErrorExt<T> for std::result::Result<T, E> {
     fn display_err(self, context: &str) -> Result<T> {
         self.map_err(|e| AppError::display_init(&format!("{}: {:?}", context, e)))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigError;
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(AppError::from(ConfigError::NoScreensEnabled).exit_code(), 2);
+        assert_eq!(AppError::display_init("not responding").exit_code(), 3);
+        assert_eq!(AppError::hardware("no sensor").exit_code(), 3);
+        assert_eq!(AppError::permission("/dev/i2c-1").exit_code(), 4);
+        assert_eq!(AppError::from(std::io::Error::from(std::io::ErrorKind::PermissionDenied)).exit_code(), 4);
+        assert_eq!(AppError::from(std::io::Error::from(std::io::ErrorKind::NotFound)).exit_code(), 1);
+        assert_eq!(AppError::daemon("fork failed").exit_code(), 5);
+        assert_eq!(AppError::permission_denied("/dev/i2c-1", Some("i2c")).exit_code(), 4);
+        assert_eq!(AppError::application("failed").exit_code(), 1);
+    }
+
+    #[test]
+    fn test_permission_denied_hint() {
+        let err = AppError::permission_denied("/dev/i2c-1", Some("i2c"));
+        assert_eq!(
+            err.to_string(),
+            "Permission error: cannot access /dev/i2c-1; add the user to the i2c group (sudo usermod -aG i2c $USER, then log in again) or run as root"
+        );
+        assert!(AppError::permission_denied("/sys/bus/w1/devices", None).to_string().ends_with("; run as root"));
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::PathBuf;
+
+use tracing::{debug, info, warn};
+
+use crate::config::FanConfig;
+use crate::system_info::hwmon::{find_hwmon, HWMON_SYSFS_PATH};
+use crate::utils::format_temp;
+
+// hwmon names of PWM fan drivers; the Pi 5 active cooler and the pwm-fan overlay are both "pwmfan"
+const FAN_HWMON_NAMES: [&str; 2] = ["pwmfan", "cooling_fan"];
+
+// pwm1_enable: 1 is manual control, the kernel's own setting is restored on exit
+const PWM_MANUAL: &str = "1";
+
+const MAX_DUTY: i32 = 255;
+
+/// The PWM duty for `celsius`: linear between the curve's points and flat beyond its ends.
+pub fn duty_for(curve: &[(f32, i32)], celsius: f32) -> u8 {
+    let duty = match curve {
+        [] => MAX_DUTY,
+        [(first_temp, first_duty), ..] if celsius <= *first_temp => *first_duty,
+        [.., (_, last_duty)] => curve
+            .windows(2)
+            .find(|pair| celsius <= pair[1].0)
+            .map_or(*last_duty, |pair| {
+                let ((low_temp, low_duty), (high_temp, high_duty)) = (pair[0], pair[1]);
+                let fraction = (celsius - low_temp) / (high_temp - low_temp);
+                low_duty + ((high_duty - low_duty) as f32 * fraction).round() as i32
+            }),
+    };
+    duty.clamp(0, MAX_DUTY) as u8
+}
+
+/// Sets a PWM fan's speed from the (smoothed) CPU temperature on every update.
+pub struct FanController {
+    hwmon: PathBuf,
+    curve: Vec<(f32, i32)>,
+    duty: Option<u8>,
+    // pwm1_enable as found, put back when dropped so the kernel takes over again
+    restore_enable: Option<String>,
+}
+
+impl FanController {
+    /// A controller for the configured (or detected) fan, None where there is no PWM fan.
+    pub fn start(config: &FanConfig) -> Option<Self> {
+        let hwmon = match &config.hwmon {
+            Some(hwmon) => hwmon.clone(),
+            None => match find_hwmon(&FAN_HWMON_NAMES, "pwm1") {
+                Some(hwmon) => hwmon,
+                None => {
+                    warn!("Fan control requested, but no PWM fan was found under {}", HWMON_SYSFS_PATH);
+                    return None;
+                }
+            },
+        };
+        if !hwmon.join("pwm1").exists() {
+            warn!("Fan control requested, but {} has no pwm1", hwmon.display());
+            return None;
+        }
+        info!("Controlling the fan at {}", hwmon.display());
+        Some(Self::with_hwmon(hwmon, config.curve.clone()))
+    }
+
+    fn with_hwmon(hwmon: PathBuf, curve: Vec<(f32, i32)>) -> Self {
+        let enable = hwmon.join("pwm1_enable");
+        let restore_enable = fs::read_to_string(&enable).ok()
+            .map(|mode| mode.trim().to_string())
+            .filter(|mode| mode != PWM_MANUAL);
+        if restore_enable.is_some() {
+            if let Err(e) = fs::write(&enable, PWM_MANUAL) {
+                warn!("Failed to switch {} to manual control: {}", enable.display(), e);
+            }
+        }
+        Self { hwmon, curve, duty: None, restore_enable }
+    }
+
+    /// Set the duty the curve gives for the CPU temperature of this update's snapshot;
+    /// without one the fan keeps its speed.
+    pub fn update(&mut self, celsius: Option<f32>) {
+        match celsius {
+            Some(celsius) => self.set_for(celsius),
+            None => debug!("No CPU temperature for fan control"),
+        }
+    }
+
+    fn set_for(&mut self, celsius: f32) {
+        let duty = duty_for(&self.curve, celsius);
+        if self.duty == Some(duty) {
+            return;
+        }
+        let pwm = self.hwmon.join("pwm1");
+        match fs::write(&pwm, duty.to_string()) {
+            Ok(()) => {
+                info!("Fan duty set to {} at {}", duty, format_temp(celsius));
+                self.duty = Some(duty);
+            }
+            Err(e) => warn!("Failed to set fan duty in {}: {}", pwm.display(), e),
+        }
+    }
+}
+
+impl Drop for FanController {
+    fn drop(&mut self) {
+        if let Some(mode) = &self.restore_enable {
+            let _ = fs::write(self.hwmon.join("pwm1_enable"), mode);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::hwmon::tests::fake_hwmon_tree;
+    use crate::system_info::{SystemSnapshot, TempSmoother, TempSmoothing};
+
+    #[test]
+    fn test_duty_for() {
+        let curve = [(50.0, 0), (60.0, 100), (70.0, 300)];
+        assert_eq!(duty_for(&curve, 20.0), 0);
+        assert_eq!(duty_for(&curve, 55.0), 50);
+        assert_eq!(duty_for(&curve, 60.0), 100);
+        // Clamped to 0-255
+        assert_eq!(duty_for(&curve, 68.0), 255);
+        assert_eq!(duty_for(&curve, 90.0), 255);
+        assert_eq!(duty_for(&[(40.0, -10)], 30.0), 0);
+    }
+
+    #[test]
+    fn test_controller_writes_sysfs() {
+        let base = fake_hwmon_tree("fan", &[(3, "pwmfan", &[("pwm1", "0\n"), ("pwm1_enable", "2\n")])]);
+        let hwmon = base.join("hwmon3");
+
+        let mut fan = FanController::with_hwmon(hwmon.clone(), vec![(50.0, 0), (70.0, 200)]);
+        assert_eq!(fs::read_to_string(hwmon.join("pwm1_enable")).unwrap(), "1");
+        fan.update(Some(60.0));
+        assert_eq!(fs::read_to_string(hwmon.join("pwm1")).unwrap(), "100");
+        // A missing reading leaves the speed as it was
+        fan.update(None);
+        assert_eq!(fs::read_to_string(hwmon.join("pwm1")).unwrap(), "100");
+        drop(fan);
+        assert_eq!(fs::read_to_string(hwmon.join("pwm1_enable")).unwrap(), "2");
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_controller_follows_smoothed_temperature() {
+        let base = fake_hwmon_tree("fan-smoothed", &[(2, "pwmfan", &[("pwm1", "0\n"), ("pwm1_enable", "1\n")])]);
+        let hwmon = base.join("hwmon2");
+        let mut fan = FanController::with_hwmon(hwmon.clone(), vec![(50.0, 0), (70.0, 200)]);
+
+        // One snapshot per update, smoothed once, as the display loop takes them
+        let smoother = TempSmoother::new(TempSmoothing::Ema(0.5));
+        for raw in [50.0, 70.0] {
+            let mut snapshot = SystemSnapshot { cpu_temp_raw_celsius: Some(raw), ..SystemSnapshot::default() };
+            smoother.apply(&mut snapshot);
+            fan.update(snapshot.cpu_temp_celsius);
+        }
+        // 60°C rather than the 70°C spike
+        assert_eq!(fs::read_to_string(hwmon.join("pwm1")).unwrap(), "100");
+        drop(fan);
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+
+pub const WIDTH: u32 = 128;
+pub const HEIGHT: u32 = 64;
+
+// Largest upscaling /frame.png accepts
+pub const MAX_SCALE: u32 = 8;
+
+/// An in-memory copy of the 128x64 panel, one bit per pixel. Frames are drawn here
+/// first and then copied to the display, so the last frame can be served remotely.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameBuffer {
+    // Row-major, most significant bit leftmost, as PNG expects
+    bits: [u8; (WIDTH * HEIGHT / 8) as usize],
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self { bits: [0; (WIDTH * HEIGHT / 8) as usize] }
+    }
+}
+
+impl FrameBuffer {
+    pub fn pixel(&self, x: u32, y: u32) -> bool {
+        if x >= WIDTH || y >= HEIGHT {
+            return false;
+        }
+        let index = (y * WIDTH + x) as usize;
+        self.bits[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, on: bool) {
+        let index = (y * WIDTH + x) as usize;
+        let mask = 0x80 >> (index % 8);
+        if on {
+            self.bits[index / 8] |= mask;
+        } else {
+            self.bits[index / 8] &= !mask;
+        }
+    }
+
+    /// Every pixel, for copying the frame onto a display.
+    pub fn pixels(&self) -> impl Iterator<Item = Pixel<BinaryColor>> + '_ {
+        (0..HEIGHT).flat_map(move |y| (0..WIDTH).map(move |x| {
+            Pixel(Point::new(x as i32, y as i32), BinaryColor::from(self.pixel(x, y)))
+        }))
+    }
+
+    /// Encode as a black and white PNG, each pixel blown up to `scale`x`scale`.
+    pub fn to_png(&self, scale: u32) -> Result<Vec<u8>, png::EncodingError> {
+        let scale = scale.clamp(1, MAX_SCALE);
+        let width = WIDTH * scale;
+        let row_bytes = width.div_ceil(8) as usize;
+
+        let mut data = vec![0u8; row_bytes * (HEIGHT * scale) as usize];
+        for (row, line) in data.chunks_mut(row_bytes).enumerate() {
+            let y = row as u32 / scale;
+            for out_x in 0..width {
+                if self.pixel(out_x / scale, y) {
+                    line[(out_x / 8) as usize] |= 0x80 >> (out_x % 8);
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, HEIGHT * scale);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::One);
+        encoder.write_header()?.write_image_data(&data)?;
+        Ok(out)
+    }
+}
+
+impl OriginDimensions for FrameBuffer {
+    fn size(&self) -> Size {
+        Size::new(WIDTH, HEIGHT)
+    }
+}
+
+impl DrawTarget for FrameBuffer {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            // Drawing outside the panel is clipped, as on the real display
+            if let Ok((x, y)) = <(u32, u32)>::try_from(point) {
+                if x < WIDTH && y < HEIGHT {
+                    self.set_pixel(x, y, color.is_on());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+
+    fn decode(png_data: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+        let mut reader = png::Decoder::new(std::io::Cursor::new(png_data)).read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        buf.truncate(info.buffer_size());
+        (info, buf)
+    }
+
+    #[test]
+    fn test_draw_and_clip() {
+        let mut frame = FrameBuffer::default();
+        Rectangle::new(Point::new(126, 62), Size::new(4, 4))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(&mut frame)
+            .unwrap();
+
+        assert!(frame.pixel(127, 63));
+        assert!(frame.pixel(126, 62));
+        assert!(!frame.pixel(125, 63));
+        assert_eq!(frame.pixels().filter(|Pixel(_, color)| color.is_on()).count(), 4);
+    }
+
+    #[test]
+    fn test_png_dimensions() {
+        let frame = FrameBuffer::default();
+        let (info, _) = decode(&frame.to_png(1).unwrap());
+        assert_eq!((info.width, info.height), (128, 64));
+        assert_eq!(info.bit_depth, png::BitDepth::One);
+
+        let (info, _) = decode(&frame.to_png(4).unwrap());
+        assert_eq!((info.width, info.height), (512, 256));
+
+        // Out of range scales are clamped
+        let (info, _) = decode(&frame.to_png(100).unwrap());
+        assert_eq!(info.width, WIDTH * MAX_SCALE);
+    }
+
+    #[test]
+    fn test_png_pixels_scaled() {
+        let mut frame = FrameBuffer::default();
+        Pixel(Point::new(1, 0), BinaryColor::On).draw(&mut frame).unwrap();
+
+        let (_, data) = decode(&frame.to_png(2).unwrap());
+        // 256 px wide at one bit per pixel; source pixel 1 covers output pixels 2 and 3 of rows 0 and 1
+        assert_eq!(data[0], 0b0011_0000);
+        assert_eq!(data[32], 0b0011_0000);
+        assert_eq!(data[64], 0);
+    }
+}
@@ -0,0 +1,380 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::Serialize;
+use tiny_http::{Header, Response, Server};
+use tracing::{info, warn};
+
+use crate::errors::{AppError, Result};
+use crate::framebuffer::MAX_SCALE;
+use crate::status::Status;
+use crate::system_info::{get_source_failures, DeviceIdentity, SourceFailures, SystemSnapshot, THROTTLE_FLAGS};
+
+// Upscaling of /frame.png unless ?scale= says otherwise
+const DEFAULT_FRAME_SCALE: u32 = 1;
+
+/// Answers `/status`, `/screens` and `/healthz` with JSON and `/frame.png` with
+/// the last frame on the display.
+pub struct StatusApi {
+    status: Arc<Status>,
+    device: DeviceIdentity,
+    available_screens: Vec<String>,
+    enabled_screens: Vec<String>,
+    // /healthz fails once no frame has been drawn for this long
+    stale_after: Duration,
+}
+
+/// A response body with its status code and content type.
+#[derive(Debug)]
+pub struct Reply {
+    pub code: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    fn json<T: Serialize>(code: u16, value: &T) -> Self {
+        Self {
+            code,
+            content_type: "application/json",
+            body: serde_json::to_vec(value).expect("status documents serialize"),
+        }
+    }
+
+    fn error(code: u16, message: &str) -> Self {
+        Self::json(code, &serde_json::json!({ "error": message }))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StatusDocument<'a> {
+    version: &'static str,
+    device: &'a DeviceIdentity,
+    current_screen: Option<String>,
+    #[serde(flatten)]
+    snapshot: Option<&'a SystemSnapshot>,
+    throttle_flags: Vec<&'static str>,
+    frames_rendered: u64,
+    render_failures: u64,
+    // Failed reads since startup, by source
+    source_failures: Vec<SourceFailures>,
+}
+
+#[derive(Debug, Serialize)]
+struct ScreensDocument<'a> {
+    available: &'a [String],
+    enabled: &'a [String],
+}
+
+#[derive(Debug, Serialize)]
+struct HealthDocument {
+    healthy: bool,
+    last_frame_age_secs: Option<f64>,
+    last_render_failed: bool,
+}
+
+impl StatusApi {
+    pub fn new(
+        status: Arc<Status>,
+        device: DeviceIdentity,
+        available_screens: Vec<String>,
+        enabled_screens: Vec<String>,
+        stale_after: Duration,
+    ) -> Self {
+        Self {
+            status,
+            device,
+            available_screens,
+            enabled_screens,
+            stale_after,
+        }
+    }
+
+    /// The reply to a GET of `url` (path and optional query).
+    pub fn respond(&self, url: &str) -> Reply {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        match path {
+            "/status" => self.status(),
+            "/screens" => Reply::json(200, &ScreensDocument {
+                available: &self.available_screens,
+                enabled: &self.enabled_screens,
+            }),
+            "/healthz" => self.health(),
+            "/frame.png" => self.frame(query),
+            _ => Reply::error(404, "not found"),
+        }
+    }
+
+    fn status(&self) -> Reply {
+        let snapshot = self.status.snapshot();
+        let throttle_flags = snapshot.as_ref()
+            .and_then(|snapshot| snapshot.throttled)
+            .map(|throttled| THROTTLE_FLAGS.iter()
+                .filter(|(bit, ..)| throttled & bit != 0)
+                .map(|(_, label, ..)| *label)
+                .collect())
+            .unwrap_or_default();
+
+        Reply::json(200, &StatusDocument {
+            version: env!("CARGO_PKG_VERSION"),
+            device: &self.device,
+            current_screen: self.status.current_screen(),
+            snapshot: snapshot.as_ref(),
+            throttle_flags,
+            frames_rendered: self.status.frames_rendered(),
+            render_failures: self.status.render_failures(),
+            source_failures: get_source_failures(),
+        })
+    }
+
+    fn health(&self) -> Reply {
+        let age = self.status.last_frame_at().map(|at| at.elapsed());
+        let last_render_failed = self.status.last_render_failed();
+        let healthy = !last_render_failed && age.is_some_and(|age| age <= self.stale_after);
+
+        Reply::json(if healthy { 200 } else { 503 }, &HealthDocument {
+            healthy,
+            last_frame_age_secs: age.map(|age| age.as_secs_f64()),
+            last_render_failed,
+        })
+    }
+
+    fn frame(&self, query: &str) -> Reply {
+        let scale = match frame_scale(query) {
+            Some(scale) => scale,
+            None => return Reply::error(400, &format!("scale must be between 1 and {}", MAX_SCALE)),
+        };
+        let Some(frame) = self.status.frame() else {
+            return Reply::error(503, "no frame rendered yet");
+        };
+
+        match frame.to_png(scale) {
+            Ok(body) => Reply { code: 200, content_type: "image/png", body },
+            Err(e) => Reply::error(500, &format!("failed to encode frame: {}", e)),
+        }
+    }
+}
+
+// The `scale` query parameter, None if it is present but out of range
+fn frame_scale(query: &str) -> Option<u32> {
+    let value = query.split('&').find_map(|pair| pair.strip_prefix("scale="));
+    match value {
+        None => Some(DEFAULT_FRAME_SCALE),
+        Some(value) => value.parse().ok().filter(|scale| (1..=MAX_SCALE).contains(scale)),
+    }
+}
+
+/// Serves the StatusApi from its own thread, so slow clients never hold up rendering.
+/// Dropping it stops the server and waits for the thread to finish.
+pub struct StatusServer {
+    server: Arc<Server>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl StatusServer {
+    pub fn start(addr: SocketAddr, api: StatusApi) -> Result<Self> {
+        let server = Server::http(addr)
+            .map_err(|e| AppError::application(&format!("Failed to start status server on {}: {}", addr, e)))?;
+        let server = Arc::new(server);
+        let local_addr = server.server_addr().to_ip().unwrap_or(addr);
+
+        let handle = thread::Builder::new()
+            .name("status-http".to_string())
+            .spawn({
+                let server = Arc::clone(&server);
+                move || serve(&server, &api)
+            })?;
+
+        info!("Serving status on http://{}/status", local_addr);
+        Ok(Self {
+            server,
+            handle: Some(handle),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.server_addr().to_ip().unwrap()
+    }
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn serve(server: &Server, api: &StatusApi) {
+    // Returns once the server is unblocked on shutdown
+    for request in server.incoming_requests() {
+        let reply = api.respond(request.url());
+        let content_type = Header::from_bytes("Content-Type", reply.content_type).expect("valid header");
+        let response = Response::from_data(reply.body)
+            .with_status_code(reply.code)
+            .with_header(content_type);
+
+        if let Err(e) = request.respond(response) {
+            warn!("Failed to answer status request: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::FrameBuffer;
+    use crate::system_info::{DataSource, DemoDataSource};
+    use crate::system_info::failures::record_failure;
+    use serde_json::{json, Value};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn fake_status() -> Arc<Status> {
+        let status = Arc::new(Status::default());
+        status.set_snapshot(SystemSnapshot {
+            hostname: "pi-kitchen".to_string(),
+            ip_addresses: vec!["192.168.1.42".to_string()],
+            cpu_temp_celsius: Some(42.5),
+            memory_used_bytes: 512,
+            memory_total_bytes: 1024,
+            throttled: Some(0x5),
+            ..SystemSnapshot::default()
+        });
+        status
+    }
+
+    fn api(status: Arc<Status>) -> StatusApi {
+        let available = ["overview", "clock", "network"].map(String::from).to_vec();
+        StatusApi::new(status, DemoDataSource::new().device_identity(), available, vec!["overview".to_string(), "clock".to_string()], Duration::from_secs(60))
+    }
+
+    fn json_response(api: &StatusApi, path: &str) -> (u16, Value) {
+        let reply = api.respond(path);
+        assert_eq!(reply.content_type, "application/json");
+        (reply.code, serde_json::from_slice(&reply.body).unwrap())
+    }
+
+    #[test]
+    fn test_status() {
+        let status = fake_status();
+        status.set_current_screen("overview");
+        status.record_frame();
+
+        let (code, body) = json_response(&api(status), "/status");
+        assert_eq!(code, 200);
+        assert_eq!(body["hostname"], "pi-kitchen");
+        assert_eq!(body["ip_addresses"], json!(["192.168.1.42"]));
+        assert_eq!(body["cpu_temp_celsius"], 42.5);
+        assert_eq!(body["memory_total_bytes"], 1024);
+        assert_eq!(body["throttle_flags"], json!(["under_voltage", "currently_throttled"]));
+        assert_eq!(body["current_screen"], "overview");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(body["device"]["id"], "c0ffee42");
+        assert_eq!(body["device"]["serial"], "10000000c0ffee42");
+        assert_eq!(body["frames_rendered"], 1);
+    }
+
+    #[test]
+    fn test_status_source_failures() {
+        record_failure("test-status-source", "timed out");
+        let (_, body) = json_response(&api(fake_status()), "/status");
+        let failures = body["source_failures"].as_array().unwrap().iter()
+            .find(|failures| failures["source"] == "test-status-source")
+            .unwrap();
+        assert_eq!(failures["count"], 1);
+        assert_eq!(failures["last_error"], "timed out");
+    }
+
+    #[test]
+    fn test_status_before_first_snapshot() {
+        let (code, body) = json_response(&api(Arc::new(Status::default())), "/status");
+        assert_eq!(code, 200);
+        assert!(body.get("hostname").is_none());
+        assert!(body["current_screen"].is_null());
+        assert_eq!(body["throttle_flags"], json!([]));
+    }
+
+    #[test]
+    fn test_screens() {
+        let (code, body) = json_response(&api(fake_status()), "/screens");
+        assert_eq!(code, 200);
+        assert_eq!(body["enabled"], json!(["overview", "clock"]));
+        assert_eq!(body["available"], json!(["overview", "clock", "network"]));
+    }
+
+    #[test]
+    fn test_healthz() {
+        let status = fake_status();
+        let api = api(Arc::clone(&status));
+        assert_eq!(api.respond("/healthz").code, 503);
+
+        status.record_frame();
+        let (code, body) = json_response(&api, "/healthz");
+        assert_eq!(code, 200);
+        assert_eq!(body["healthy"], true);
+
+        status.record_render_failure();
+        assert_eq!(api.respond("/healthz").code, 503);
+    }
+
+    #[test]
+    fn test_healthz_stale() {
+        let status = fake_status();
+        status.record_frame();
+        let api = StatusApi::new(status, DemoDataSource::new().device_identity(), Vec::new(), Vec::new(), Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(api.respond("/healthz").code, 503);
+    }
+
+    #[test]
+    fn test_unknown_path() {
+        assert_eq!(api(fake_status()).respond("/metrics").code, 404);
+    }
+
+    #[test]
+    fn test_frame_png() {
+        let status = fake_status();
+        let api = api(Arc::clone(&status));
+        assert_eq!(api.respond("/frame.png").code, 503);
+
+        status.set_frame(FrameBuffer::default());
+        let reply = api.respond("/frame.png");
+        assert_eq!(reply.code, 200);
+        assert_eq!(reply.content_type, "image/png");
+        assert!(reply.body.starts_with(b"\x89PNG"));
+
+        // Width is the first field of the IHDR chunk
+        let reply = api.respond("/frame.png?scale=4");
+        assert_eq!(&reply.body[16..20], &512u32.to_be_bytes());
+    }
+
+    #[test]
+    fn test_frame_scale() {
+        assert_eq!(frame_scale(""), Some(1));
+        assert_eq!(frame_scale("scale=4"), Some(4));
+        assert_eq!(frame_scale("x=1&scale=2"), Some(2));
+        assert_eq!(frame_scale("scale=0"), None);
+        assert_eq!(frame_scale("scale=big"), None);
+        assert_eq!(api(fake_status()).respond("/frame.png?scale=99").code, 400);
+    }
+
+    #[test]
+    fn test_serves_over_http() {
+        let server = StatusServer::start("127.0.0.1:0".parse().unwrap(), api(fake_status())).unwrap();
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        write!(stream, "GET /status?pretty HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("application/json"));
+        assert!(response.contains("\"hostname\":\"pi-kitchen\""));
+
+        drop(server);
+    }
+}
@@ -0,0 +1,410 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tracing::{debug, info, warn};
+
+use crate::config::InfluxDbConfig;
+use crate::errors::Result;
+use crate::status::SnapshotSink;
+use crate::system_info::{DataSource, SystemSnapshot};
+
+const MEASUREMENT: &str = "raspi";
+
+// Snapshots waiting for the exporter thread; more are dropped rather than block the sampler
+const CHANNEL_CAPACITY: usize = 16;
+
+// Wait after the first failed write, doubled per failure up to the cap
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(300);
+
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Writes snapshots to InfluxDB 2.x in line protocol, a batch every
+/// `batch_intervals` snapshots, from its own thread. Dropping it makes a last
+/// attempt to write what is queued.
+pub struct InfluxExporter {
+    sender: Option<SyncSender<String>>,
+    handle: Option<JoinHandle<()>>,
+    tags: Vec<(&'static str, String)>,
+}
+
+impl InfluxExporter {
+    pub fn start(config: &InfluxDbConfig, data: &dyn DataSource) -> Result<Self> {
+        let identity = data.device_identity();
+        let tags = vec![
+            ("host", identity.hostname),
+            ("device_id", identity.id),
+            ("model", identity.model),
+        ];
+
+        let (sender, lines) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let writer = Writer::new(config);
+        let batch_intervals = config.batch_intervals.max(1);
+        let max_queue = config.max_queue;
+        let handle = thread::Builder::new()
+            .name("influxdb".to_string())
+            .spawn(move || run_exporter(lines, writer, batch_intervals, max_queue))?;
+
+        info!("Writing stats to InfluxDB at {} (bucket {})", config.url, config.bucket);
+        Ok(Self {
+            sender: Some(sender),
+            handle: Some(handle),
+            tags,
+        })
+    }
+}
+
+impl SnapshotSink for InfluxExporter {
+    fn publish(&self, snapshot: &SystemSnapshot) {
+        let tags: Vec<(&str, &str)> = self.tags.iter().map(|(key, value)| (*key, value.as_str())).collect();
+        let line = to_line(snapshot, &tags, SystemTime::now());
+        if let Some(sender) = &self.sender {
+            if sender.try_send(line).is_err() {
+                debug!("InfluxDB exporter is behind, dropped a point");
+            }
+        }
+    }
+}
+
+impl Drop for InfluxExporter {
+    fn drop(&mut self) {
+        // Closing the channel ends the thread's loop
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run_exporter(lines: Receiver<String>, writer: Writer, batch_intervals: u32, max_queue: usize) {
+    let mut queue = WriteQueue::new(max_queue);
+    let mut retry = Retry::default();
+    let mut pending = 0;
+
+    while let Ok(line) = lines.recv() {
+        queue.push(line);
+        pending += 1;
+        if pending >= batch_intervals && retry.ready(Instant::now()) {
+            pending = 0;
+            flush(&writer, &mut queue, &mut retry);
+        }
+    }
+
+    // Shutting down: one last try, whatever the backoff says
+    if !queue.is_empty() {
+        flush(&writer, &mut queue, &mut retry);
+    }
+}
+
+fn flush(writer: &Writer, queue: &mut WriteQueue, retry: &mut Retry) {
+    match writer.write(&queue.body()) {
+        Ok(()) => {
+            debug!("Wrote {} point(s) to InfluxDB", queue.len());
+            queue.clear();
+            retry.succeeded();
+        }
+        Err(WriteError::Rejected(reason)) => {
+            // Retrying data the server refused will not help
+            warn!("InfluxDB rejected {} point(s), dropping them: {}", queue.len(), reason);
+            queue.clear();
+            retry.succeeded();
+        }
+        Err(WriteError::Unavailable(reason)) => {
+            let delay = retry.failed(Instant::now());
+            warn!(
+                "InfluxDB write failed: {}; keeping {} point(s), retrying in {}s",
+                reason, queue.len(), delay.as_secs()
+            );
+        }
+    }
+}
+
+enum WriteError {
+    // The server answered and refused the write (bad data, bad token, ...)
+    Rejected(String),
+    // Network trouble, overload or a server error; worth retrying
+    Unavailable(String),
+}
+
+struct Writer {
+    agent: ureq::Agent,
+    url: String,
+    org: String,
+    bucket: String,
+    token: Option<String>,
+}
+
+impl Writer {
+    fn new(config: &InfluxDbConfig) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(WRITE_TIMEOUT).build(),
+            url: format!("{}/api/v2/write", config.url.trim_end_matches('/')),
+            org: config.org.clone(),
+            bucket: config.bucket.clone(),
+            token: config.token.clone(),
+        }
+    }
+
+    fn write(&self, body: &str) -> std::result::Result<(), WriteError> {
+        let mut request = self.agent.post(&self.url)
+            .query("org", &self.org)
+            .query("bucket", &self.bucket)
+            .query("precision", "ns")
+            .set("Content-Type", "text/plain; charset=utf-8");
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Token {}", token));
+        }
+
+        match request.send_string(body) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(code, response)) => {
+                let reason = format!("HTTP {}: {}", code, response.into_string().unwrap_or_default().trim());
+                if code == 429 || code >= 500 {
+                    Err(WriteError::Unavailable(reason))
+                } else {
+                    Err(WriteError::Rejected(reason))
+                }
+            }
+            Err(e) => Err(WriteError::Unavailable(e.to_string())),
+        }
+    }
+}
+
+/// Lines waiting to be written, bounded so an outage cannot grow memory without limit.
+struct WriteQueue {
+    lines: VecDeque<String>,
+    max_len: usize,
+    dropped: u64,
+}
+
+impl WriteQueue {
+    fn new(max_len: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            max_len: max_len.max(1),
+            dropped: 0,
+        }
+    }
+
+    // Makes room by dropping the oldest line
+    fn push(&mut self, line: String) {
+        if self.lines.len() >= self.max_len {
+            self.lines.pop_front();
+            self.dropped += 1;
+            if self.dropped.is_power_of_two() {
+                warn!("InfluxDB queue full, dropped {} point(s) so far", self.dropped);
+            }
+        }
+        self.lines.push_back(line);
+    }
+
+    fn body(&self) -> String {
+        let mut body = String::new();
+        for line in &self.lines {
+            body.push_str(line);
+            body.push('\n');
+        }
+        body
+    }
+
+    fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+/// Capped exponential backoff between failed writes.
+#[derive(Debug, Default)]
+struct Retry {
+    failures: u32,
+    next_attempt: Option<Instant>,
+}
+
+impl Retry {
+    fn ready(&self, now: Instant) -> bool {
+        self.next_attempt.is_none_or(|at| now >= at)
+    }
+
+    fn failed(&mut self, now: Instant) -> Duration {
+        self.failures += 1;
+        let delay = INITIAL_RETRY_BACKOFF
+            .saturating_mul(1u32.checked_shl(self.failures - 1).unwrap_or(u32::MAX))
+            .min(MAX_RETRY_BACKOFF);
+        self.next_attempt = Some(now + delay);
+        delay
+    }
+
+    fn succeeded(&mut self) {
+        self.failures = 0;
+        self.next_attempt = None;
+    }
+}
+
+/// One line-protocol point for `snapshot`. Readings that are not available are
+/// left out, as are tags with empty values, which the protocol does not allow.
+pub fn to_line(snapshot: &SystemSnapshot, tags: &[(&str, &str)], timestamp: SystemTime) -> String {
+    let mut line = escape(MEASUREMENT, &[',', ' ']);
+    for (key, value) in tags {
+        if !value.is_empty() {
+            let _ = write!(line, ",{}={}", escape_key(key), escape_key(value));
+        }
+    }
+
+    let mut fields = Vec::new();
+    let mut float = |key: &str, value: Option<f64>| {
+        if let Some(value) = value.filter(|value| value.is_finite()) {
+            fields.push(format!("{}={}", key, value));
+        }
+    };
+    float("cpu_temp_celsius", snapshot.cpu_temp_celsius.map(round_f32));
+    float("gpu_temp_celsius", snapshot.gpu_temp_celsius.map(round_f32));
+    float("uptime_secs", snapshot.uptime_secs);
+    float("clock_offset_ms", snapshot.clock_offset_ms);
+
+    let mut integer = |key: &str, value: Option<u64>| {
+        if let Some(value) = value {
+            fields.push(format!("{}={}i", key, value.min(i64::MAX as u64)));
+        }
+    };
+    integer("memory_used_bytes", Some(snapshot.memory_used_bytes));
+    integer("memory_total_bytes", Some(snapshot.memory_total_bytes));
+    integer("disk_used_bytes", snapshot.disk_used_bytes);
+    integer("disk_total_bytes", snapshot.disk_total_bytes);
+    integer("throttled", snapshot.throttled.map(u64::from));
+
+    let nanos = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let _ = write!(line, " {} {}", fields.join(","), nanos);
+    line
+}
+
+// f32 -> f64 widening shows representation noise (41.9 becomes 41.900001525878906)
+fn round_f32(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(value as f64)
+}
+
+// Tag keys, tag values and field keys escape commas, equals signs and spaces
+fn escape_key(value: &str) -> String {
+    escape(value, &[',', '=', ' '])
+}
+
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn snapshot() -> SystemSnapshot {
+        SystemSnapshot {
+            cpu_temp_celsius: Some(41.9),
+            memory_used_bytes: 512,
+            memory_total_bytes: 1024,
+            uptime_secs: Some(90.5),
+            throttled: Some(0x5),
+            ..SystemSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn test_to_line() {
+        let timestamp = UNIX_EPOCH + Duration::new(1_700_000_000, 123);
+        let line = to_line(&snapshot(), &[("host", "pi-kitchen")], timestamp);
+        assert_eq!(
+            line,
+            "raspi,host=pi-kitchen cpu_temp_celsius=41.9,uptime_secs=90.5,memory_used_bytes=512i,\
+             memory_total_bytes=1024i,throttled=5i 1700000000000000123"
+        );
+    }
+
+    #[test]
+    fn test_tags_escaped_and_empty_dropped() {
+        let tags = [("host", "pi,one"), ("model", "Raspberry Pi 4 Model B"), ("site", "a=b"), ("rack", "")];
+        let line = to_line(&snapshot(), &tags, UNIX_EPOCH);
+        assert!(line.starts_with("raspi,host=pi\\,one,model=Raspberry\\ Pi\\ 4\\ Model\\ B,site=a\\=b "));
+        assert!(!line.contains("rack"));
+        assert!(line.ends_with(" 0"));
+    }
+
+    #[test]
+    fn test_non_finite_floats_skipped() {
+        let snapshot = SystemSnapshot { cpu_temp_celsius: Some(f32::NAN), ..SystemSnapshot::default() };
+        let line = to_line(&snapshot, &[], UNIX_EPOCH);
+        assert_eq!(line, "raspi memory_used_bytes=0i,memory_total_bytes=0i 0");
+    }
+
+    #[test]
+    fn test_queue_drops_oldest() {
+        let mut queue = WriteQueue::new(2);
+        queue.push("a".to_string());
+        queue.push("b".to_string());
+        queue.push("c".to_string());
+        assert_eq!(queue.body(), "b\nc\n");
+        assert_eq!(queue.dropped, 1);
+    }
+
+    #[test]
+    fn test_retry_backoff_capped() {
+        let now = Instant::now();
+        let mut retry = Retry::default();
+        assert!(retry.ready(now));
+
+        assert_eq!(retry.failed(now), Duration::from_secs(5));
+        assert!(!retry.ready(now));
+        assert!(retry.ready(now + Duration::from_secs(5)));
+        assert_eq!(retry.failed(now), Duration::from_secs(10));
+        for _ in 0..20 {
+            retry.failed(now);
+        }
+        assert_eq!(retry.failed(now), MAX_RETRY_BACKOFF);
+
+        retry.succeeded();
+        assert!(retry.ready(now));
+    }
+
+    #[test]
+    fn test_writes_batch_to_server() {
+        let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+        let port = server.server_addr().to_ip().unwrap().port();
+        let config = InfluxDbConfig {
+            enabled: true,
+            url: format!("http://127.0.0.1:{}/", port),
+            org: "home lab".to_string(),
+            bucket: "pi".to_string(),
+            token: Some("secret".to_string()),
+            ..InfluxDbConfig::default()
+        };
+
+        let writer = Writer::new(&config);
+        let client = thread::spawn(move || writer.write("raspi uptime_secs=1 0\n").is_ok());
+
+        let mut request = server.recv().unwrap();
+        assert_eq!(request.url(), "/api/v2/write?org=home+lab&bucket=pi&precision=ns");
+        let auth = request.headers().iter().find(|header| header.field.equiv("Authorization")).unwrap();
+        assert_eq!(auth.value.as_str(), "Token secret");
+        let mut body = String::new();
+        request.as_reader().read_to_string(&mut body).unwrap();
+        assert_eq!(body, "raspi uptime_secs=1 0\n");
+        request.respond(tiny_http::Response::empty(204)).unwrap();
+
+        assert!(client.join().unwrap());
+    }
+}
@@ -1,10 +1,123 @@
-pub mod tca9548a;
-pub mod system_info;
-pub mod screens;
-pub mod screen_factory;
-pub mod screen_manager;
-pub mod display;
+//! System information screens for SSD1306 OLED displays on a Raspberry Pi.
+//!
+//! The `info_display` binary is a thin wrapper around [`Application`]. To add
+//! screens of your own, register them next to the built-in ones and run the
+//! application with that registry:
+//!
+//! ```no_run
+//! use info_display::{Application, CliParser, Screen, ScreenRegistry};
+//! use info_display::system_info::{DataSource, SystemSnapshot};
+//!
+//! struct Greeting;
+//!
+//! impl Screen for Greeting {
+//!     fn name(&self) -> &'static str {
+//!         "greeting"
+//!     }
+//!
+//!     fn render(&mut self, _snapshot: &SystemSnapshot, data: &dyn DataSource) -> anyhow::Result<String> {
+//!         Ok(format!("Hello from\n{}", data.hostname()))
+//!     }
+//! }
+//!
+//! fn main() -> info_display::Result<()> {
+//!     let mut registry = ScreenRegistry::default();
+//!     registry.register("greeting", "Says hello", |_options| Ok(Box::new(Greeting)));
+//!
+//!     // e.g. --screens greeting,overview
+//!     let config = CliParser::parse_args(std::env::args())?;
+//!     let mut app = Application::with_config(config, registry)?;
+//!     app.initialize()?;
+//!     app.run()
+//! }
+//! ```
+//!
+//! Programs with a loop of their own can drive the pieces directly: a
+//! [`ScreenManager`] picks and renders the screens, a [`DisplayManager`] draws
+//! them and a [`system_info::DataSource`] supplies the readings.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use info_display::{AppConfig, DisplayManager, ScreenErrorPolicy, ScreenManager};
+//! use info_display::system_info::{DataSource, LiveDataSource};
+//! use sysinfo::System;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let config = AppConfig::default();
+//!     // Placeholder text, command timeout and the like, as Application would
+//!     config.apply_process_settings();
+//!     let mut screens = ScreenManager::new(
+//!         vec!["overview", "network"], None, Duration::from_secs(5), ScreenErrorPolicy::Skip, &config.screen_options(),
+//!     )?;
+//!     let mut display = DisplayManager::new(&config.multiplexer)?;
+//!     let data = LiveDataSource::new();
+//!     let mut sys = System::new();
+//!     loop {
+//!         sys.refresh_memory();
+//!         let snapshot = data.snapshot(&sys, screens.refresh_requirements());
+//!         let rendered = screens.render_current_screen(&snapshot, &data)?;
+//!         display.render_content(rendered.title.as_deref(), &rendered.content, rendered.canvas)?;
+//!         if screens.should_switch_screen() {
+//!             screens.next_screen();
+//!         }
+//!         std::thread::sleep(Duration::from_secs(1));
+//!     }
+//! }
+//! ```
+//!
+//! # Stable API
+//!
+//! Semver covers what is re-exported at the crate root and from
+//! [`system_info`] (its readings, `get_*` functions and [`system_info::DataSource`]),
+//! plus the [`Screen`] trait. The items of the other public modules, including the
+//! `system_info` submodules and the `get_*_display` wrappers left in them, are there
+//! for the binary and for screens that need them, and may change in a minor release.
+
 pub mod cli;
 pub mod config;
+pub mod display;
 pub mod errors;
-pub mod app;
\ No newline at end of file
+pub mod framebuffer;
+pub mod screen_factory;
+pub mod screen_manager;
+pub mod screens;
+pub mod system_info;
+pub mod utils;
+
+mod alerts;
+mod app;
+mod fan;
+mod http_status;
+mod logging;
+mod prometheus;
+mod remote;
+mod status;
+mod stall;
+mod systemd;
+mod timing;
+mod tca9548a;
+mod waybar;
+#[cfg(feature = "otel")]
+mod telemetry;
+#[cfg(feature = "otel")]
+mod metrics;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(feature = "influxdb")]
+mod influxdb;
+#[cfg(feature = "webhook")]
+mod webhook;
+#[cfg(feature = "ssh")]
+mod ssh;
+#[cfg(feature = "epd")]
+mod epd;
+
+pub use app::Application;
+pub use cli::CliParser;
+pub use config::{AppConfig, ConfigBuilder, ConfigError, ScreenErrorPolicy};
+pub use display::{DisplayManager, TextLayout};
+pub use errors::{AppError, Result};
+pub use framebuffer::FrameBuffer;
+pub use screen_factory::{ScreenConstructor, ScreenFactory, ScreenRegistry};
+pub use screen_manager::{RenderedScreen, ScreenManager, ScreenOverride};
+pub use screens::{RefreshRequirements, Screen, ScreenOptions};
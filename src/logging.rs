@@ -0,0 +1,67 @@
+use std::env;
+use std::ffi::OsString;
+use std::io::IsTerminal;
+
+use tracing::Subscriber;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::errors::{AppError, Result};
+
+const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// Log filter: an explicit level (--log-level) wins over RUST_LOG, which wins over the default.
+pub fn env_filter(level: Option<&str>) -> EnvFilter {
+    match level {
+        Some(level) => EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_LEVEL)),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_LEVEL)),
+    }
+}
+
+/// Human-readable log output on stderr. Colours are only used on a terminal and when
+/// NO_COLOR is not set, and timestamps are left out under journald, which adds its own.
+pub fn fmt_layer<S>() -> Box<dyn Layer<S> + Send + Sync + 'static>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(use_colour(env::var_os("NO_COLOR"), std::io::stderr().is_terminal()))
+        .with_span_events(FmtSpan::CLOSE);
+
+    if env::var_os("JOURNAL_STREAM").is_some() {
+        layer.with_timer(()).boxed()
+    } else {
+        layer.boxed()
+    }
+}
+
+// https://no-color.org: a NO_COLOR that is set and not empty turns colours off
+fn use_colour(no_color: Option<OsString>, is_terminal: bool) -> bool {
+    is_terminal && no_color.is_none_or(|value| value.is_empty())
+}
+
+/// Install the plain fmt subscriber, used when OpenTelemetry export is not enabled.
+pub fn init(level: Option<&str>) -> Result<()> {
+    tracing_subscriber::registry()
+        .with(env_filter(level))
+        .with(fmt_layer())
+        .try_init()
+        .map_err(|e| AppError::application(&format!("Logging init failed: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_use_colour() {
+        assert!(use_colour(None, true));
+        assert!(use_colour(Some(OsString::new()), true));
+        assert!(!use_colour(Some(OsString::from("1")), true));
+        assert!(!use_colour(None, false));
+    }
+}
@@ -1,23 +1,11 @@
-mod tca9548a;
-mod system_info;
-mod screens;
-mod screen_factory;
-mod screen_manager;
-mod display;
-mod cli;
-mod config;
-mod errors;
-mod app;
-
-use errors::Result;
-use app::Application;
+use info_display::{Application, Result};
 
 fn main() -> Result<()> {
     match run() {
         Ok(_) => Ok(()),
         Err(e) => {
             eprintln!("Error: {}", e);
-            std::process::exit(1);
+            std::process::exit(e.exit_code());
         }
     }
 }
@@ -27,4 +15,3 @@ fn run() -> Result<()> {
     app.initialize()?;
     app.run()
 }
-
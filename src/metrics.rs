@@ -0,0 +1,342 @@
+use opentelemetry::global;
+use opentelemetry::metrics::{Counter, Gauge, Meter};
+use opentelemetry::KeyValue;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::status::SnapshotSink;
+use crate::system_info::{get_source_failures, DeviceIdentity, SystemSnapshot, THROTTLE_FLAGS};
+
+// Metric names and their units (UCUM, as OTLP expects)
+
+/// CPU temperature from the cpu-thermal zone.
+pub const CPU_TEMPERATURE: &str = "raspi.cpu.temperature";
+/// GPU temperature reported by vcgencmd.
+pub const GPU_TEMPERATURE: &str = "raspi.gpu.temperature";
+pub const TEMPERATURE_UNIT: &str = "Cel";
+
+/// Memory in use.
+pub const MEMORY_USED: &str = "raspi.memory.used";
+/// Installed memory.
+pub const MEMORY_TOTAL: &str = "raspi.memory.total";
+/// Space in use, summed over all disks.
+pub const DISK_USED: &str = "raspi.disk.used";
+/// Disk capacity, summed over all disks.
+pub const DISK_TOTAL: &str = "raspi.disk.total";
+pub const BYTES_UNIT: &str = "By";
+
+/// Time since boot.
+pub const UPTIME: &str = "raspi.uptime";
+pub const SECONDS_UNIT: &str = "s";
+
+/// Processes, threads not counted.
+pub const PROCESSES: &str = "raspi.processes";
+/// Processes that exited without being reaped.
+pub const ZOMBIE_PROCESSES: &str = "raspi.processes.zombie";
+
+/// How far the clock is ahead of its NTP source, negative when behind.
+pub const CLOCK_OFFSET: &str = "raspi.clock.offset";
+/// 1 while the clock is synchronized, 0 otherwise.
+pub const CLOCK_SYNCED: &str = "raspi.clock.synced";
+
+/// Raw `vcgencmd get_throttled` bitfield.
+pub const THROTTLED: &str = "raspi.throttled";
+/// One series per throttle condition, labelled by `flag`; 1 while set, 0 otherwise.
+pub const THROTTLE_FLAG: &str = "raspi.throttle.flag";
+pub const DIMENSIONLESS_UNIT: &str = "1";
+
+/// Failed reads since startup, one series per source of readings labelled by `source`.
+pub const SOURCE_FAILURES: &str = "raspi.source.failures";
+
+// Attribute names
+pub const HOST_NAME_KEY: &str = "host.name";
+pub const THROTTLE_FLAG_KEY: &str = "flag";
+pub const SOURCE_KEY: &str = "source";
+
+/// Records a SystemSnapshot as OTLP gauges. With no meter provider installed the
+/// global meter is a no-op, so recording costs nothing when telemetry is off.
+pub struct MetricsRecorder {
+    host: KeyValue,
+    cpu_temperature: Gauge<f64>,
+    gpu_temperature: Gauge<f64>,
+    memory_used: Gauge<u64>,
+    memory_total: Gauge<u64>,
+    disk_used: Gauge<u64>,
+    disk_total: Gauge<u64>,
+    uptime: Gauge<f64>,
+    processes: Gauge<u64>,
+    zombie_processes: Gauge<u64>,
+    clock_offset: Gauge<f64>,
+    clock_synced: Gauge<u64>,
+    throttled: Gauge<u64>,
+    throttle_flag: Gauge<u64>,
+    source_failures: Counter<u64>,
+    // Failures per source already added to the counter
+    failures_recorded: Mutex<HashMap<String, u64>>,
+}
+
+impl MetricsRecorder {
+    pub fn new(meter: &Meter, hostname: &str) -> Self {
+        Self {
+            host: KeyValue::new(HOST_NAME_KEY, hostname.to_string()),
+            cpu_temperature: meter.f64_gauge(CPU_TEMPERATURE)
+                .with_unit(TEMPERATURE_UNIT)
+                .with_description("CPU temperature")
+                .init(),
+            gpu_temperature: meter.f64_gauge(GPU_TEMPERATURE)
+                .with_unit(TEMPERATURE_UNIT)
+                .with_description("GPU temperature")
+                .init(),
+            memory_used: meter.u64_gauge(MEMORY_USED)
+                .with_unit(BYTES_UNIT)
+                .with_description("Memory in use")
+                .init(),
+            memory_total: meter.u64_gauge(MEMORY_TOTAL)
+                .with_unit(BYTES_UNIT)
+                .with_description("Installed memory")
+                .init(),
+            disk_used: meter.u64_gauge(DISK_USED)
+                .with_unit(BYTES_UNIT)
+                .with_description("Disk space in use")
+                .init(),
+            disk_total: meter.u64_gauge(DISK_TOTAL)
+                .with_unit(BYTES_UNIT)
+                .with_description("Disk capacity")
+                .init(),
+            uptime: meter.f64_gauge(UPTIME)
+                .with_unit(SECONDS_UNIT)
+                .with_description("Time since boot")
+                .init(),
+            processes: meter.u64_gauge(PROCESSES)
+                .with_unit(DIMENSIONLESS_UNIT)
+                .with_description("Processes")
+                .init(),
+            zombie_processes: meter.u64_gauge(ZOMBIE_PROCESSES)
+                .with_unit(DIMENSIONLESS_UNIT)
+                .with_description("Zombie processes")
+                .init(),
+            clock_offset: meter.f64_gauge(CLOCK_OFFSET)
+                .with_unit(SECONDS_UNIT)
+                .with_description("Clock offset from the NTP source")
+                .init(),
+            clock_synced: meter.u64_gauge(CLOCK_SYNCED)
+                .with_unit(DIMENSIONLESS_UNIT)
+                .with_description("Clock synchronized")
+                .init(),
+            throttled: meter.u64_gauge(THROTTLED)
+                .with_unit(DIMENSIONLESS_UNIT)
+                .with_description("vcgencmd get_throttled bitfield")
+                .init(),
+            throttle_flag: meter.u64_gauge(THROTTLE_FLAG)
+                .with_unit(DIMENSIONLESS_UNIT)
+                .with_description("Throttle condition set")
+                .init(),
+            source_failures: meter.u64_counter(SOURCE_FAILURES)
+                .with_unit(DIMENSIONLESS_UNIT)
+                .with_description("Failed reads")
+                .init(),
+            failures_recorded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Recorder on the global meter provider installed by telemetry::init.
+    pub fn from_global(identity: &DeviceIdentity) -> Self {
+        Self::new(&global::meter("info_display"), &identity.hostname)
+    }
+
+    pub fn record(&self, snapshot: &SystemSnapshot) {
+        let host = std::slice::from_ref(&self.host);
+
+        if let Some(temp) = snapshot.cpu_temp_celsius {
+            self.cpu_temperature.record(temp as f64, host);
+        }
+        if let Some(temp) = snapshot.gpu_temp_celsius {
+            self.gpu_temperature.record(temp as f64, host);
+        }
+        self.memory_used.record(snapshot.memory_used_bytes, host);
+        self.memory_total.record(snapshot.memory_total_bytes, host);
+        if let Some(used) = snapshot.disk_used_bytes {
+            self.disk_used.record(used, host);
+        }
+        if let Some(total) = snapshot.disk_total_bytes {
+            self.disk_total.record(total, host);
+        }
+        if let Some(uptime) = snapshot.uptime_secs {
+            self.uptime.record(uptime, host);
+        }
+        if let Some(processes) = &snapshot.processes {
+            self.processes.record(processes.total as u64, host);
+            self.zombie_processes.record(processes.zombies as u64, host);
+        }
+        if let Some(offset) = snapshot.clock_offset_ms {
+            self.clock_offset.record(offset / 1000.0, host);
+        }
+        if let Some(synced) = snapshot.clock_synced {
+            self.clock_synced.record(synced as u64, host);
+        }
+        if let Some(throttled) = snapshot.throttled {
+            self.throttled.record(throttled as u64, host);
+            for (bit, label, ..) in THROTTLE_FLAGS {
+                let attributes = [self.host.clone(), KeyValue::new(THROTTLE_FLAG_KEY, label)];
+                self.throttle_flag.record((throttled & bit != 0) as u64, &attributes);
+            }
+        }
+
+        // The failures are counted as they happen; the counter gets the ones since the last snapshot
+        let mut recorded = self.failures_recorded.lock().unwrap_or_else(|e| e.into_inner());
+        for failures in get_source_failures() {
+            let previous = recorded.insert(failures.source.clone(), failures.count).unwrap_or(0);
+            if failures.count > previous {
+                let attributes = [self.host.clone(), KeyValue::new(SOURCE_KEY, failures.source)];
+                self.source_failures.add(failures.count - previous, &attributes);
+            }
+        }
+    }
+}
+
+impl SnapshotSink for MetricsRecorder {
+    fn publish(&self, snapshot: &SystemSnapshot) {
+        self.record(snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::metrics::MeterProvider as _;
+    use opentelemetry_sdk::metrics::data::{self, ResourceMetrics, Temporality};
+    use opentelemetry_sdk::metrics::reader::{AggregationSelector, MetricReader, TemporalitySelector};
+    use opentelemetry_sdk::metrics::{Aggregation, InstrumentKind, ManualReader, Pipeline, SdkMeterProvider};
+    use opentelemetry_sdk::Resource;
+    use std::sync::{Arc, Weak};
+
+    // The provider takes ownership of its reader; share it so the test can collect
+    #[derive(Debug, Clone)]
+    struct SharedReader(Arc<ManualReader>);
+
+    impl TemporalitySelector for SharedReader {
+        fn temporality(&self, kind: InstrumentKind) -> Temporality {
+            self.0.temporality(kind)
+        }
+    }
+
+    impl AggregationSelector for SharedReader {
+        fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+            self.0.aggregation(kind)
+        }
+    }
+
+    impl MetricReader for SharedReader {
+        fn register_pipeline(&self, pipeline: Weak<Pipeline>) {
+            self.0.register_pipeline(pipeline)
+        }
+
+        fn collect(&self, rm: &mut ResourceMetrics) -> opentelemetry::metrics::Result<()> {
+            self.0.collect(rm)
+        }
+
+        fn force_flush(&self) -> opentelemetry::metrics::Result<()> {
+            self.0.force_flush()
+        }
+
+        fn shutdown(&self) -> opentelemetry::metrics::Result<()> {
+            self.0.shutdown()
+        }
+    }
+
+    fn record(snapshot: &SystemSnapshot) -> Vec<data::Metric> {
+        let reader = SharedReader(Arc::new(ManualReader::default()));
+        let provider = SdkMeterProvider::builder().with_reader(reader.clone()).build();
+        MetricsRecorder::new(&provider.meter("test"), "pi-test").record(snapshot);
+
+        let mut collected = ResourceMetrics {
+            resource: Resource::empty(),
+            scope_metrics: Vec::new(),
+        };
+        reader.collect(&mut collected).unwrap();
+        collected.scope_metrics.into_iter().flat_map(|scope| scope.metrics).collect()
+    }
+
+    fn find<'a>(metrics: &'a [data::Metric], name: &str) -> Option<&'a data::Metric> {
+        metrics.iter().find(|metric| metric.name == name)
+    }
+
+    fn points<T: 'static>(metric: &data::Metric) -> &[data::DataPoint<T>] {
+        &metric.data.as_any().downcast_ref::<data::Gauge<T>>().unwrap().data_points
+    }
+
+    fn full_snapshot() -> SystemSnapshot {
+        SystemSnapshot {
+            cpu_temp_celsius: Some(51.5),
+            gpu_temp_celsius: Some(50.0),
+            memory_used_bytes: 512,
+            memory_total_bytes: 2048,
+            disk_used_bytes: Some(10),
+            disk_total_bytes: Some(100),
+            uptime_secs: Some(3600.0),
+            throttled: Some(0x5),
+            ..SystemSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn test_records_gauges_with_units() {
+        let metrics = record(&full_snapshot());
+
+        let cpu = find(&metrics, CPU_TEMPERATURE).unwrap();
+        assert_eq!(cpu.unit, TEMPERATURE_UNIT);
+        assert_eq!(points::<f64>(cpu)[0].value, 51.5);
+
+        let memory = find(&metrics, MEMORY_USED).unwrap();
+        assert_eq!(memory.unit, BYTES_UNIT);
+        assert_eq!(points::<u64>(memory)[0].value, 512);
+
+        assert_eq!(points::<u64>(find(&metrics, DISK_TOTAL).unwrap())[0].value, 100);
+        assert_eq!(points::<f64>(find(&metrics, UPTIME).unwrap())[0].value, 3600.0);
+        assert_eq!(points::<u64>(find(&metrics, THROTTLED).unwrap())[0].value, 0x5);
+    }
+
+    #[test]
+    fn test_tags_hostname() {
+        let metrics = record(&full_snapshot());
+        let point = &points::<f64>(find(&metrics, CPU_TEMPERATURE).unwrap())[0];
+        assert!(point.attributes.contains(&KeyValue::new(HOST_NAME_KEY, "pi-test")));
+    }
+
+    #[test]
+    fn test_throttle_flags_labelled() {
+        let metrics = record(&full_snapshot());
+        let flags = points::<u64>(find(&metrics, THROTTLE_FLAG).unwrap());
+        assert_eq!(flags.len(), THROTTLE_FLAGS.len());
+
+        let flag_value = |label: &'static str| {
+            flags.iter()
+                .find(|point| point.attributes.contains(&KeyValue::new(THROTTLE_FLAG_KEY, label)))
+                .map(|point| point.value)
+        };
+        assert_eq!(flag_value("under_voltage"), Some(1));
+        assert_eq!(flag_value("arm_freq_capped"), Some(0));
+        assert_eq!(flag_value("currently_throttled"), Some(1));
+        assert_eq!(flag_value("soft_temp_limit"), Some(0));
+    }
+
+    #[test]
+    fn test_source_failures_counted() {
+        crate::system_info::failures::record_failure("test-otel-source", "timed out");
+        let metrics = record(&SystemSnapshot::default());
+        let sums = &find(&metrics, SOURCE_FAILURES).unwrap().data.as_any().downcast_ref::<data::Sum<u64>>().unwrap().data_points;
+        let point = sums.iter()
+            .find(|point| point.attributes.contains(&KeyValue::new(SOURCE_KEY, "test-otel-source")))
+            .unwrap();
+        assert_eq!(point.value, 1);
+    }
+
+    #[test]
+    fn test_missing_readings_not_recorded() {
+        let metrics = record(&SystemSnapshot::default());
+        assert!(find(&metrics, CPU_TEMPERATURE).is_none());
+        assert!(find(&metrics, DISK_USED).is_none());
+        assert!(find(&metrics, THROTTLE_FLAG).is_none());
+        assert!(find(&metrics, MEMORY_TOTAL).is_some());
+    }
+}
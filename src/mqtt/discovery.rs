@@ -0,0 +1,230 @@
+use serde::Serialize;
+
+use super::Topics;
+use crate::system_info::DeviceIdentity;
+
+/// Device block shared by every sensor, so Home Assistant groups them under one device
+#[derive(Debug, Clone, Serialize)]
+pub struct Device {
+    pub identifiers: Vec<String>,
+    pub name: String,
+    pub model: String,
+    pub manufacturer: String,
+    pub sw_version: String,
+}
+
+/// Payload of a `homeassistant/sensor/<object_id>/config` message
+#[derive(Debug, Serialize)]
+pub struct SensorConfig {
+    pub name: &'static str,
+    pub unique_id: String,
+    pub object_id: String,
+    pub state_topic: String,
+    pub value_template: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit_of_measurement: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_class: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_class: Option<&'static str>,
+    pub availability_topic: String,
+    pub device: Device,
+}
+
+// One sensor per value in the state document
+struct SensorSpec {
+    key: &'static str,
+    name: &'static str,
+    value_template: &'static str,
+    unit: Option<&'static str>,
+    device_class: Option<&'static str>,
+    state_class: Option<&'static str>,
+}
+
+const SENSORS: [SensorSpec; 8] = [
+    SensorSpec {
+        key: "cpu_temp",
+        name: "CPU temperature",
+        value_template: "{{ value_json.cpu_temp_celsius }}",
+        unit: Some("°C"),
+        device_class: Some("temperature"),
+        state_class: Some("measurement"),
+    },
+    SensorSpec {
+        key: "gpu_temp",
+        name: "GPU temperature",
+        value_template: "{{ value_json.gpu_temp_celsius }}",
+        unit: Some("°C"),
+        device_class: Some("temperature"),
+        state_class: Some("measurement"),
+    },
+    SensorSpec {
+        key: "memory_used",
+        name: "Memory used",
+        value_template: "{{ (value_json.memory_used_bytes / 1048576) | round(0) }}",
+        unit: Some("MiB"),
+        device_class: Some("data_size"),
+        state_class: Some("measurement"),
+    },
+    SensorSpec {
+        key: "memory_total",
+        name: "Memory total",
+        value_template: "{{ (value_json.memory_total_bytes / 1048576) | round(0) }}",
+        unit: Some("MiB"),
+        device_class: Some("data_size"),
+        state_class: None,
+    },
+    SensorSpec {
+        key: "disk_used",
+        name: "Disk used",
+        value_template: "{{ (value_json.disk_used_bytes / 1073741824) | round(1) }}",
+        unit: Some("GiB"),
+        device_class: Some("data_size"),
+        state_class: Some("measurement"),
+    },
+    SensorSpec {
+        key: "disk_total",
+        name: "Disk total",
+        value_template: "{{ (value_json.disk_total_bytes / 1073741824) | round(1) }}",
+        unit: Some("GiB"),
+        device_class: Some("data_size"),
+        state_class: None,
+    },
+    SensorSpec {
+        key: "uptime",
+        name: "Uptime",
+        value_template: "{{ value_json.uptime_secs | int }}",
+        unit: Some("s"),
+        device_class: Some("duration"),
+        state_class: None,
+    },
+    SensorSpec {
+        key: "throttled",
+        name: "Throttled",
+        value_template: "{{ value_json.throttled }}",
+        unit: None,
+        device_class: None,
+        state_class: None,
+    },
+];
+
+impl Device {
+    pub fn new(identity: &DeviceIdentity) -> Self {
+        Self {
+            identifiers: vec![format!("raspi_{}", identity.id)],
+            name: identity.hostname.clone(),
+            model: identity.model.clone(),
+            manufacturer: "Raspberry Pi Ltd".to_string(),
+            sw_version: format!("info_display {}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+/// Retained discovery messages (topic, payload) for every sensor
+pub fn discovery_messages(prefix: &str, device_id: &str, topics: &Topics, device: &Device) -> Vec<(String, Vec<u8>)> {
+    SENSORS.iter()
+        .map(|spec| {
+            let config = sensor_config(spec, device_id, topics, device);
+            let payload = serde_json::to_vec(&config).expect("sensor config serializes");
+            (config_topic(prefix, &config.object_id), payload)
+        })
+        .collect()
+}
+
+/// Topics that discovery publishes to; an empty retained payload on them removes the sensors
+pub fn discovery_topics(prefix: &str, device_id: &str) -> Vec<String> {
+    SENSORS.iter()
+        .map(|spec| config_topic(prefix, &object_id(device_id, spec.key)))
+        .collect()
+}
+
+fn sensor_config(spec: &SensorSpec, device_id: &str, topics: &Topics, device: &Device) -> SensorConfig {
+    let object_id = object_id(device_id, spec.key);
+    SensorConfig {
+        name: spec.name,
+        unique_id: format!("{}_{}", device.identifiers[0], spec.key),
+        object_id,
+        state_topic: topics.state.clone(),
+        value_template: spec.value_template,
+        unit_of_measurement: spec.unit,
+        device_class: spec.device_class,
+        state_class: spec.state_class,
+        availability_topic: topics.availability.clone(),
+        device: device.clone(),
+    }
+}
+
+fn config_topic(prefix: &str, object_id: &str) -> String {
+    format!("{}/sensor/{}/config", prefix.trim_end_matches('/'), object_id)
+}
+
+// Home Assistant only accepts [a-zA-Z0-9_-] in object ids
+fn object_id(device_id: &str, key: &str) -> String {
+    let id: String = device_id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    format!("raspi_{}_{}", id, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn messages() -> Vec<(String, Value)> {
+        let identity = DeviceIdentity {
+            id: "c0ffee42".to_string(),
+            hostname: "pi-kitchen".to_string(),
+            serial: Some("10000000c0ffee42".to_string()),
+            mac: None,
+            model: "Raspberry Pi 4 Model B Rev 1.4".to_string(),
+        };
+        let topics = Topics::new("raspi", &identity.id);
+        discovery_messages("homeassistant", &identity.id, &topics, &Device::new(&identity))
+            .into_iter()
+            .map(|(topic, payload)| (topic, serde_json::from_slice(&payload).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_cpu_temp_config() {
+        let messages = messages();
+        let (topic, payload) = &messages[0];
+        assert_eq!(topic, "homeassistant/sensor/raspi_c0ffee42_cpu_temp/config");
+        assert_eq!(payload["unique_id"], "raspi_c0ffee42_cpu_temp");
+        assert_eq!(payload["state_topic"], "raspi/c0ffee42/state");
+        assert_eq!(payload["availability_topic"], "raspi/c0ffee42/availability");
+        assert_eq!(payload["unit_of_measurement"], "°C");
+        assert_eq!(payload["device_class"], "temperature");
+        assert_eq!(payload["value_template"], "{{ value_json.cpu_temp_celsius }}");
+    }
+
+    #[test]
+    fn test_device_block() {
+        for (_, payload) in messages() {
+            assert_eq!(payload["device"]["identifiers"], json!(["raspi_c0ffee42"]));
+            assert_eq!(payload["device"]["model"], "Raspberry Pi 4 Model B Rev 1.4");
+            assert_eq!(payload["device"]["name"], "pi-kitchen");
+        }
+    }
+
+    #[test]
+    fn test_optional_fields_omitted() {
+        let messages = messages();
+        let (_, throttled) = messages.iter().find(|(topic, _)| topic.contains("_throttled/")).unwrap();
+        assert!(throttled.get("unit_of_measurement").is_none());
+        assert!(throttled.get("device_class").is_none());
+    }
+
+    #[test]
+    fn test_discovery_topics_match_messages() {
+        let topics: Vec<String> = messages().into_iter().map(|(topic, _)| topic).collect();
+        assert_eq!(discovery_topics("homeassistant", "c0ffee42"), topics);
+    }
+
+    #[test]
+    fn test_object_id_sanitized() {
+        assert_eq!(object_id("c0ffee42", "uptime"), "raspi_c0ffee42_uptime");
+        assert_eq!(object_id("pi.lan", "uptime"), "raspi_pi_lan_uptime");
+    }
+}
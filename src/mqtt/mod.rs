@@ -0,0 +1,243 @@
+pub mod discovery;
+
+use std::thread;
+use std::time::Duration;
+
+use rumqttc::{Client, Connection, Event, LastWill, MqttOptions, Outgoing, Packet, QoS};
+use tracing::{debug, info, warn};
+
+use crate::config::MqttConfig;
+use crate::errors::{AppError, Result};
+use crate::status::SnapshotSink;
+use crate::system_info::{DataSource, DeviceIdentity, SystemSnapshot};
+use discovery::Device;
+
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+// Cap on a single wait between reconnect attempts
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(60);
+
+// Publishes queued while the broker is unreachable; further ones are dropped.
+// Leaves room for the discovery announcements sent on every connect.
+const REQUEST_CAPACITY: usize = 32;
+
+/// Topics used for one device, below the configured base topic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topics {
+    /// JSON document with the latest snapshot
+    pub state: String,
+    /// "online" while connected, "offline" (last will) otherwise; retained
+    pub availability: String,
+}
+
+impl Topics {
+    pub fn new(base_topic: &str, device_id: &str) -> Self {
+        let prefix = format!("{}/{}", base_topic.trim_end_matches('/'), device_id);
+        Self {
+            state: format!("{}/state", prefix),
+            availability: format!("{}/availability", prefix),
+        }
+    }
+}
+
+/// Publishes every snapshot to MQTT. The connection, including reconnects, is
+/// driven on its own thread, and publishing never waits for the broker.
+pub struct MqttPublisher {
+    client: Client,
+    topics: Topics,
+    qos: QoS,
+}
+
+impl MqttPublisher {
+    pub fn start(config: &MqttConfig, data: &dyn DataSource) -> Result<Self> {
+        // Topics go by the device ID, so they stay put when the hostname changes
+        let identity = data.device_identity();
+        let qos = qos_from_level(config.qos)?;
+        let topics = Topics::new(&config.base_topic, &identity.id);
+
+        // Home Assistant discovery, sent on every (re)connect in case the broker lost it
+        let announcements = if config.discovery {
+            discovery::discovery_messages(&config.discovery_prefix, &identity.id, &topics, &Device::new(&identity))
+        } else {
+            Vec::new()
+        };
+
+        let mut options = mqtt_options(config, format!("info_display-{}", identity.id))?;
+        options.set_last_will(LastWill::new(&topics.availability, OFFLINE, qos, true));
+
+        let (client, connection) = Client::new(options, REQUEST_CAPACITY);
+        thread::Builder::new()
+            .name("mqtt".to_string())
+            .spawn({
+                let client = client.clone();
+                let availability = topics.availability.clone();
+                move || run_connection(connection, client, availability, announcements, qos)
+            })?;
+
+        info!("Publishing stats to MQTT broker {} on {}", config.broker_url, topics.state);
+        Ok(Self { client, topics, qos })
+    }
+}
+
+impl SnapshotSink for MqttPublisher {
+    fn publish(&self, snapshot: &SystemSnapshot) {
+        let payload = match state_payload(snapshot) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to encode MQTT state: {}", e);
+                return;
+            }
+        };
+
+        // Fails only when the request queue is full, i.e. the broker has been away for a while
+        if let Err(e) = self.client.try_publish(&self.topics.state, self.qos, false, payload) {
+            debug!("Dropped MQTT state update: {}", e);
+        }
+    }
+}
+
+impl Drop for MqttPublisher {
+    fn drop(&mut self) {
+        // Going away cleanly does not trigger the last will, so announce it ourselves
+        let _ = self.client.try_publish(&self.topics.availability, self.qos, true, OFFLINE);
+        let _ = self.client.try_disconnect();
+    }
+}
+
+/// Remove this device's Home Assistant discovery entries by publishing empty retained
+/// payloads on their config topics, then disconnect.
+pub fn clear_discovery(config: &MqttConfig, identity: &DeviceIdentity) -> Result<()> {
+    let qos = qos_from_level(config.qos)?;
+    let topics = discovery::discovery_topics(&config.discovery_prefix, &identity.id);
+
+    let options = mqtt_options(config, format!("info_display-{}-clear", identity.id))?;
+    let (client, mut connection) = Client::new(options, topics.len() + 1);
+    for topic in &topics {
+        client.try_publish(topic, qos, true, Vec::new())
+            .map_err(|e| AppError::application(&format!("Failed to queue MQTT message: {}", e)))?;
+    }
+    client.try_disconnect()
+        .map_err(|e| AppError::application(&format!("Failed to queue MQTT disconnect: {}", e)))?;
+
+    // Requests go out in order, so once the disconnect is sent the removals are too
+    for event in connection.iter() {
+        match event {
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+            Ok(_) => {}
+            Err(e) => {
+                return Err(AppError::application(&format!("MQTT connection to {} failed: {}", config.broker_url, e)));
+            }
+        }
+    }
+
+    info!("Removed {} Home Assistant discovery entries for {} ({})", topics.len(), identity.hostname, identity.id);
+    Ok(())
+}
+
+fn mqtt_options(config: &MqttConfig, client_id: String) -> Result<MqttOptions> {
+    let (host, port) = config.broker_address()?;
+    let mut options = MqttOptions::new(client_id, host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if let Some(username) = &config.username {
+        options.set_credentials(username, config.password.clone().unwrap_or_default());
+    }
+    Ok(options)
+}
+
+// rumqttc reconnects on the next poll after an error; this adds the backoff between attempts
+// and announces the device again after every (re)connect
+fn run_connection(
+    mut connection: Connection,
+    client: Client,
+    availability: String,
+    announcements: Vec<(String, Vec<u8>)>,
+    qos: QoS,
+) {
+    let mut failures: u32 = 0;
+
+    for event in connection.iter() {
+        match event {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                info!("Connected to MQTT broker");
+                failures = 0;
+                for (topic, payload) in &announcements {
+                    if let Err(e) = client.try_publish(topic, qos, true, payload.clone()) {
+                        warn!("Failed to publish MQTT discovery on {}: {}", topic, e);
+                    }
+                }
+                if let Err(e) = client.try_publish(&availability, qos, true, ONLINE) {
+                    warn!("Failed to publish MQTT availability: {}", e);
+                }
+            }
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+            Ok(_) => {}
+            Err(e) => {
+                failures += 1;
+                let delay = reconnect_backoff(failures);
+                warn!("MQTT connection error: {}; reconnecting in {}s", e, delay.as_secs());
+                thread::sleep(delay);
+            }
+        }
+    }
+}
+
+// Wait after the given number of consecutive failures: 1s, 2s, 4s, ... capped at MAX_RECONNECT_BACKOFF
+fn reconnect_backoff(failures: u32) -> Duration {
+    let secs = 1u64.checked_shl(failures.saturating_sub(1)).unwrap_or(u64::MAX);
+    Duration::from_secs(secs).min(MAX_RECONNECT_BACKOFF)
+}
+
+fn qos_from_level(level: u8) -> Result<QoS> {
+    match level {
+        0 => Ok(QoS::AtMostOnce),
+        1 => Ok(QoS::AtLeastOnce),
+        2 => Ok(QoS::ExactlyOnce),
+        _ => Err(AppError::Config(crate::config::ConfigError::InvalidMqttQos(level))),
+    }
+}
+
+fn state_payload(snapshot: &SystemSnapshot) -> serde_json::Result<Vec<u8>> {
+    serde_json::to_vec(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topics() {
+        let topics = Topics::new("raspi/", "c0ffee42");
+        assert_eq!(topics.state, "raspi/c0ffee42/state");
+        assert_eq!(topics.availability, "raspi/c0ffee42/availability");
+    }
+
+    #[test]
+    fn test_reconnect_backoff() {
+        assert_eq!(reconnect_backoff(1), Duration::from_secs(1));
+        assert_eq!(reconnect_backoff(3), Duration::from_secs(4));
+        assert_eq!(reconnect_backoff(7), MAX_RECONNECT_BACKOFF);
+        assert_eq!(reconnect_backoff(200), MAX_RECONNECT_BACKOFF);
+    }
+
+    #[test]
+    fn test_qos_from_level() {
+        assert_eq!(qos_from_level(1).unwrap(), QoS::AtLeastOnce);
+        assert!(qos_from_level(3).is_err());
+    }
+
+    #[test]
+    fn test_state_payload() {
+        let snapshot = SystemSnapshot {
+            cpu_temp_celsius: Some(42.5),
+            memory_used_bytes: 512,
+            throttled: Some(0),
+            ..SystemSnapshot::default()
+        };
+        let payload: serde_json::Value = serde_json::from_slice(&state_payload(&snapshot).unwrap()).unwrap();
+        assert_eq!(payload["cpu_temp_celsius"], 42.5);
+        assert_eq!(payload["memory_used_bytes"], 512);
+        assert_eq!(payload["throttled"], 0);
+        assert!(payload["gpu_temp_celsius"].is_null());
+    }
+}
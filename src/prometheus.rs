@@ -0,0 +1,233 @@
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use tiny_http::{Header, Response, Server};
+use tracing::{info, warn};
+
+use crate::errors::{AppError, Result};
+use crate::status::Status;
+use crate::system_info::{get_source_failures, THROTTLE_FLAGS};
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Serves `/metrics` in the Prometheus text format from its own thread.
+/// Dropping it stops the server and waits for the thread to finish.
+pub struct MetricsServer {
+    server: Arc<Server>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    pub fn start(addr: SocketAddr, status: Arc<Status>) -> Result<Self> {
+        let server = Server::http(addr)
+            .map_err(|e| AppError::application(&format!("Failed to start metrics server on {}: {}", addr, e)))?;
+        let server = Arc::new(server);
+        let local_addr = server.server_addr().to_ip().unwrap_or(addr);
+
+        let handle = thread::Builder::new()
+            .name("metrics-http".to_string())
+            .spawn({
+                let server = Arc::clone(&server);
+                move || serve(&server, &status)
+            })?;
+
+        info!("Serving Prometheus metrics on http://{}/metrics", local_addr);
+        Ok(Self {
+            server,
+            handle: Some(handle),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.server_addr().to_ip().unwrap()
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn serve(server: &Server, status: &Status) {
+    // Returns once the server is unblocked on shutdown
+    for request in server.incoming_requests() {
+        let response = if request.url() == "/metrics" || request.url().starts_with("/metrics?") {
+            let content_type = Header::from_bytes("Content-Type", CONTENT_TYPE).expect("valid header");
+            Response::from_string(render_metrics(status)).with_header(content_type)
+        } else {
+            Response::from_string("Not Found\n").with_status_code(404)
+        };
+
+        if let Err(e) = request.respond(response) {
+            warn!("Failed to answer metrics request: {}", e);
+        }
+    }
+}
+
+/// The current snapshot and counters in the Prometheus text exposition format.
+/// Readings that are not available are left out.
+pub fn render_metrics(status: &Status) -> String {
+    let mut out = String::new();
+
+    if let Some(snapshot) = status.snapshot() {
+        if let Some(temp) = snapshot.cpu_temp_celsius {
+            gauge(&mut out, "cpu_temp_celsius", "CPU temperature in degrees Celsius", temp);
+        }
+        if let Some(temp) = snapshot.gpu_temp_celsius {
+            gauge(&mut out, "gpu_temp_celsius", "GPU temperature in degrees Celsius", temp);
+        }
+        if let Some(percent) = snapshot.cpu_usage_percent {
+            gauge(&mut out, "cpu_usage_percent", "Busy time of all CPUs since the previous sample", percent);
+        }
+        gauge(&mut out, "memory_used_bytes", "Memory in use", snapshot.memory_used_bytes);
+        gauge(&mut out, "memory_total_bytes", "Installed memory", snapshot.memory_total_bytes);
+        if let Some(used) = snapshot.disk_used_bytes {
+            gauge(&mut out, "disk_used_bytes", "Disk space in use, all disks", used);
+        }
+        if let Some(total) = snapshot.disk_total_bytes {
+            gauge(&mut out, "disk_total_bytes", "Disk capacity, all disks", total);
+        }
+        if let Some(uptime) = snapshot.uptime_secs {
+            gauge(&mut out, "uptime_seconds", "Time since boot", uptime);
+        }
+        if let Some(processes) = &snapshot.processes {
+            gauge(&mut out, "processes", "Processes, threads not counted", processes.total);
+            gauge(&mut out, "zombie_processes", "Processes that exited without being reaped", processes.zombies);
+        }
+        if let Some(synced) = snapshot.clock_synced {
+            gauge(&mut out, "clock_synced", "1 while the clock is synchronized with NTP", synced as u8);
+        }
+        if let Some(offset) = snapshot.clock_offset_ms {
+            gauge(&mut out, "clock_offset_seconds", "How far the clock is ahead of its NTP source", offset / 1000.0);
+        }
+        if let Some(throttled) = snapshot.throttled {
+            header(&mut out, "throttled", "Throttle conditions reported by vcgencmd, 1 while set", "gauge");
+            for (bit, label, ..) in THROTTLE_FLAGS {
+                let _ = writeln!(out, "throttled{{flag=\"{}\"}} {}", label, (throttled & bit != 0) as u8);
+            }
+        }
+    }
+
+    counter(&mut out, "frames_rendered_total", "Frames drawn to the display", status.frames_rendered());
+    counter(&mut out, "render_failures_total", "Display updates where no screen could be rendered", status.render_failures());
+    let failures = get_source_failures();
+    if !failures.is_empty() {
+        header(&mut out, "source_failures_total", "Failed reads since startup, by source of the reading", "counter");
+        for failures in failures {
+            let _ = writeln!(out, "source_failures_total{{source=\"{}\"}} {}", failures.source, failures.count);
+        }
+    }
+    out
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    header(out, name, help, "gauge");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, help, "counter");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::{ProcessSummary, SystemSnapshot};
+    use crate::system_info::failures::record_failure;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn fake_status() -> Arc<Status> {
+        let status = Arc::new(Status::default());
+        status.set_snapshot(SystemSnapshot {
+            cpu_temp_celsius: Some(42.5),
+            gpu_temp_celsius: None,
+            cpu_usage_percent: Some(12.5),
+            memory_used_bytes: 512,
+            memory_total_bytes: 1024,
+            disk_used_bytes: Some(2048),
+            disk_total_bytes: Some(4096),
+            uptime_secs: Some(90.0),
+            throttled: Some(0x4),
+            processes: Some(ProcessSummary { total: 140, zombies: 2, ..ProcessSummary::default() }),
+            clock_synced: Some(true),
+            clock_offset_ms: Some(-2.5),
+            ..SystemSnapshot::default()
+        });
+        status.record_frame();
+        status.record_frame();
+        status.record_render_failure();
+        status
+    }
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_render_metrics() {
+        let text = render_metrics(&fake_status());
+        assert!(text.contains("# TYPE cpu_temp_celsius gauge\ncpu_temp_celsius 42.5\n"));
+        assert!(text.contains("cpu_usage_percent 12.5\n"));
+        assert!(text.contains("memory_used_bytes 512\n"));
+        assert!(text.contains("uptime_seconds 90\n"));
+        assert!(text.contains("# TYPE processes gauge\nprocesses 140\n"));
+        assert!(text.contains("zombie_processes 2\n"));
+        assert!(text.contains("clock_synced 1\n"));
+        assert!(text.contains("clock_offset_seconds -0.0025\n"));
+        assert!(text.contains("throttled{flag=\"currently_throttled\"} 1\n"));
+        assert!(text.contains("throttled{flag=\"under_voltage\"} 0\n"));
+        assert!(text.contains("frames_rendered_total 2\n"));
+        assert!(text.contains("render_failures_total 1\n"));
+        assert!(!text.contains("gpu_temp_celsius"));
+    }
+
+    #[test]
+    fn test_render_source_failures() {
+        record_failure("test-metrics-source", "timed out");
+        record_failure("test-metrics-source", "timed out");
+        let text = render_metrics(&Status::default());
+        assert!(text.contains("# TYPE source_failures_total counter\n"));
+        assert!(text.contains("source_failures_total{source=\"test-metrics-source\"} 2\n"));
+    }
+
+    #[test]
+    fn test_render_metrics_before_first_snapshot() {
+        let text = render_metrics(&Status::default());
+        assert!(!text.contains("cpu_temp_celsius"));
+        assert!(text.contains("frames_rendered_total 0\n"));
+    }
+
+    #[test]
+    fn test_scrape_endpoint() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap(), fake_status()).unwrap();
+
+        let response = get(server.local_addr(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("text/plain; version=0.0.4"));
+        assert!(response.contains("disk_used_bytes 2048\n"));
+
+        let response = get(server.local_addr(), "/other");
+        assert!(response.starts_with("HTTP/1.1 404"));
+
+        // Dropping stops the server thread
+        drop(server);
+    }
+}
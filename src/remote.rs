@@ -0,0 +1,348 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::errors::{AppError, Result};
+use crate::screen_manager::ScreenOverride;
+
+pub const DEFAULT_DURATION_SECS: u64 = 10;
+pub const MAX_DURATION_SECS: u64 = 3600;
+
+// Limits on a single message, so a misbehaving client cannot flood the display
+const MAX_MESSAGE_BYTES: usize = 4096;
+const MAX_TITLE_CHARS: usize = 32;
+const MAX_LINES: usize = 8;
+const MAX_LINE_CHARS: usize = 64;
+
+// Idle clients are disconnected after this long
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+// Clients connected at once; more are turned away rather than each getting a thread
+const MAX_CLIENTS: usize = 8;
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One newline-delimited JSON message of the remote display protocol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub lines: Vec<String>,
+    // Seconds to show the message for
+    #[serde(default = "default_duration")]
+    pub duration: u64,
+}
+
+fn default_duration() -> u64 {
+    DEFAULT_DURATION_SECS
+}
+
+/// Parse and check one message line. The error is sent back to the client as is.
+pub fn parse_message(line: &str, token: Option<&str>) -> std::result::Result<ScreenOverride, String> {
+    let message: RemoteMessage = serde_json::from_str(line).map_err(|e| format!("invalid message: {}", e))?;
+
+    if let Some(expected) = token {
+        if message.token.as_deref() != Some(expected) {
+            return Err("invalid token".to_string());
+        }
+    }
+    if message.title.chars().count() > MAX_TITLE_CHARS {
+        return Err(format!("title longer than {} characters", MAX_TITLE_CHARS));
+    }
+    if message.lines.len() > MAX_LINES {
+        return Err(format!("more than {} lines", MAX_LINES));
+    }
+    if message.lines.iter().any(|line| line.chars().count() > MAX_LINE_CHARS || line.contains('\n')) {
+        return Err(format!("lines must be single lines of at most {} characters", MAX_LINE_CHARS));
+    }
+    if !(1..=MAX_DURATION_SECS).contains(&message.duration) {
+        return Err(format!("duration must be 1-{} seconds", MAX_DURATION_SECS));
+    }
+
+    Ok(ScreenOverride {
+        title: message.title,
+        content: message.lines.join("\n"),
+        duration: Duration::from_secs(message.duration),
+    })
+}
+
+/// Accepts remote display messages on its own thread and passes the valid ones on as
+/// screen overrides. Dropping it stops the listener and waits for the thread to finish.
+pub struct RemoteServer {
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RemoteServer {
+    pub fn start(addr: SocketAddr, token: Option<String>, overrides: Sender<ScreenOverride>) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| AppError::application(&format!("Failed to listen for remote messages on {}: {}", addr, e)))?;
+        let local_addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = thread::Builder::new()
+            .name("remote-display".to_string())
+            .spawn({
+                let stop = Arc::clone(&stop);
+                let token: Arc<Option<String>> = Arc::new(token);
+                move || serve(&listener, &stop, &token, &overrides)
+            })?;
+
+        info!("Accepting remote display messages on {}", local_addr);
+        Ok(Self {
+            local_addr,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // accept() has no timeout, so wake it with a connection of our own
+        let _ = TcpStream::connect_timeout(&wake_addr(self.local_addr), SEND_TIMEOUT);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// A connectable address for the listener, which may be bound to the unspecified address
+fn wake_addr(local_addr: SocketAddr) -> SocketAddr {
+    match local_addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => SocketAddr::new(Ipv4Addr::LOCALHOST.into(), local_addr.port()),
+        IpAddr::V6(ip) if ip.is_unspecified() => SocketAddr::new(Ipv6Addr::LOCALHOST.into(), local_addr.port()),
+        _ => local_addr,
+    }
+}
+
+fn serve(listener: &TcpListener, stop: &AtomicBool, token: &Arc<Option<String>>, overrides: &Sender<ScreenOverride>) {
+    let clients = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Failed to accept remote display connection: {}", e);
+                continue;
+            }
+        };
+
+        if clients.fetch_add(1, Ordering::Relaxed) >= MAX_CLIENTS {
+            clients.fetch_sub(1, Ordering::Relaxed);
+            debug!("Turned away remote display client {:?}: too many clients", stream.peer_addr());
+            let _ = writeln!(&stream, "error: more than {} clients", MAX_CLIENTS);
+            continue;
+        }
+        let slot = ClientSlot(Arc::clone(&clients));
+
+        // One thread per client, so a slow one cannot hold up the others
+        let token = Arc::clone(token);
+        let overrides = overrides.clone();
+        let spawned = thread::Builder::new()
+            .name("remote-client".to_string())
+            .spawn(move || {
+                let _slot = slot;
+                let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+                if let Err(e) = handle_client(stream, token.as_deref(), &overrides) {
+                    debug!("Remote display client {} disconnected: {}", peer, e);
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start remote display client thread: {}", e);
+        }
+    }
+}
+
+// Holds a place among the connected clients until dropped
+struct ClientSlot(Arc<AtomicUsize>);
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Answer every line with "ok" or "error: <reason>"; bad messages never reach the display
+fn handle_client(stream: TcpStream, token: Option<&str>, overrides: &Sender<ScreenOverride>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let peer = stream.peer_addr()?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    loop {
+        let mut line = String::new();
+        let read = (&mut reader).take(MAX_MESSAGE_BYTES as u64 + 1).read_line(&mut line)?;
+        if read == 0 {
+            return Ok(());
+        }
+        if read > MAX_MESSAGE_BYTES && !line.ends_with('\n') {
+            // The rest of the oversized line cannot be told apart from the next message
+            writeln!(writer, "error: message longer than {} bytes", MAX_MESSAGE_BYTES)?;
+            return Ok(());
+        }
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match parse_message(line, token) {
+            Ok(screen_override) => {
+                debug!("Remote message '{}' from {}", screen_override.title, peer);
+                if overrides.send(screen_override).is_err() {
+                    // The display loop has finished
+                    return Ok(());
+                }
+                writeln!(writer, "ok")?;
+            }
+            Err(reason) => {
+                warn!("Rejected remote message from {}: {}", peer, reason);
+                writeln!(writer, "error: {}", reason)?;
+            }
+        }
+    }
+}
+
+/// Send `message` to the remote display at `addr` (host:port) and wait for it to be accepted.
+pub fn send(addr: &str, message: &RemoteMessage) -> Result<()> {
+    let target = addr.to_socket_addrs()?
+        .next()
+        .ok_or_else(|| AppError::application(&format!("{} did not resolve to an address", addr)))?;
+    let stream = TcpStream::connect_timeout(&target, SEND_TIMEOUT)?;
+    stream.set_read_timeout(Some(SEND_TIMEOUT))?;
+    stream.set_write_timeout(Some(SEND_TIMEOUT))?;
+
+    let mut writer = &stream;
+    let body = serde_json::to_string(message)
+        .map_err(|e| AppError::application(&format!("Failed to encode remote message: {}", e)))?;
+    writeln!(writer, "{}", body)?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        reply => Err(AppError::application(&format!("{} rejected the message: {}", addr, reply))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn message(title: &str) -> RemoteMessage {
+        RemoteMessage {
+            token: Some("secret".to_string()),
+            title: title.to_string(),
+            lines: vec!["CPU 41C".to_string(), "Up 3d".to_string()],
+            duration: 5,
+        }
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let parsed = parse_message(r#"{"title": "nas", "lines": ["a", "b"], "duration": 3}"#, None).unwrap();
+        assert_eq!(parsed.title, "nas");
+        assert_eq!(parsed.content, "a\nb");
+        assert_eq!(parsed.duration, Duration::from_secs(3));
+
+        let parsed = parse_message(r#"{"title": "nas"}"#, None).unwrap();
+        assert_eq!(parsed.duration, Duration::from_secs(DEFAULT_DURATION_SECS));
+    }
+
+    #[test]
+    fn test_parse_message_rejects_bad_input() {
+        assert!(parse_message("not json", None).is_err());
+        assert!(parse_message(r#"{"title": "nas", "colour": "red"}"#, None).is_err());
+        assert!(parse_message(r#"{"title": "nas", "duration": 0}"#, None).is_err());
+        assert!(parse_message(&format!(r#"{{"title": "{}"}}"#, "x".repeat(40)), None).is_err());
+        assert!(parse_message(r#"{"title": "nas", "lines": ["a\nb"]}"#, None).is_err());
+        let many_lines = serde_json::to_string(&RemoteMessage { lines: vec!["a".to_string(); 9], ..message("nas") }).unwrap();
+        assert!(parse_message(&many_lines, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_message_checks_token() {
+        let line = serde_json::to_string(&message("nas")).unwrap();
+        assert!(parse_message(&line, Some("secret")).is_ok());
+        assert_eq!(parse_message(&line, Some("other")).unwrap_err(), "invalid token");
+        assert!(parse_message(r#"{"title": "nas"}"#, Some("secret")).is_err());
+    }
+
+    #[test]
+    fn test_send_to_server() {
+        let (overrides, received) = mpsc::channel();
+        let server = RemoteServer::start("127.0.0.1:0".parse().unwrap(), Some("secret".to_string()), overrides).unwrap();
+        let addr = server.local_addr().to_string();
+
+        send(&addr, &message("nas")).unwrap();
+        let screen_override = received.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(screen_override.title, "nas");
+        assert_eq!(screen_override.content, "CPU 41C\nUp 3d");
+
+        // Rejected messages are reported to the sender and never reach the display
+        let err = send(&addr, &RemoteMessage { token: None, ..message("nas") }).unwrap_err();
+        assert!(err.to_string().contains("invalid token"));
+        assert!(received.try_recv().is_err());
+
+        drop(server);
+    }
+
+    #[test]
+    fn test_bad_line_keeps_connection() {
+        let (overrides, received) = mpsc::channel();
+        let server = RemoteServer::start("127.0.0.1:0".parse().unwrap(), None, overrides).unwrap();
+
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        let mut writer = &stream;
+        let mut reader = BufReader::new(&stream);
+        let mut reply = String::new();
+
+        writeln!(writer, "{{broken").unwrap();
+        reader.read_line(&mut reply).unwrap();
+        assert!(reply.starts_with("error: "));
+
+        reply.clear();
+        writeln!(writer, r#"{{"title": "after"}}"#).unwrap();
+        reader.read_line(&mut reply).unwrap();
+        assert_eq!(reply, "ok\n");
+        assert_eq!(received.recv_timeout(Duration::from_secs(5)).unwrap().title, "after");
+    }
+
+    #[test]
+    fn test_too_many_clients() {
+        let (overrides, received) = mpsc::channel();
+        let server = RemoteServer::start("127.0.0.1:0".parse().unwrap(), None, overrides).unwrap();
+
+        // Idle clients keep their places until they disconnect
+        let idle: Vec<TcpStream> = (0..MAX_CLIENTS).map(|_| TcpStream::connect(server.local_addr()).unwrap()).collect();
+        let err = send(&server.local_addr().to_string(), &RemoteMessage { token: None, ..message("nas") }).unwrap_err();
+        assert!(err.to_string().contains("more than 8 clients"));
+        assert!(received.try_recv().is_err());
+
+        drop(idle);
+        let started = std::time::Instant::now();
+        while send(&server.local_addr().to_string(), &RemoteMessage { token: None, ..message("nas") }).is_err() {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(received.recv_timeout(Duration::from_secs(5)).unwrap().title, "nas");
+    }
+}
@@ -1,34 +1,162 @@
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
 use crate::screens::*;
+use crate::system_info::Dht22Sensor;
 
+/// Builds one screen from the shared screen options.
+pub type ScreenConstructor = Box<dyn Fn(&ScreenOptions) -> Result<Box<dyn Screen>> + Send + Sync>;
+
+struct RegisteredScreen {
+    name: String,
+    description: String,
+    constructor: ScreenConstructor,
+}
+
+/// The screens that can be enabled by name. Starts out with the built-in
+/// screens; applications embedding the library register their own next to them.
+pub struct ScreenRegistry {
+    screens: Vec<RegisteredScreen>,
+}
+
+impl Default for ScreenRegistry {
+    fn default() -> Self {
+        let descriptions = ScreenFactory::get_screen_descriptions();
+        let mut registry = Self::empty();
+        for name in ScreenFactory::get_available_screens() {
+            registry.register(name, descriptions.get(name).copied().unwrap_or_default(), move |options| {
+                ScreenFactory::create_screen(name, options)
+            });
+        }
+        registry
+    }
+}
+
+impl ScreenRegistry {
+    /// A registry without the built-in screens.
+    pub fn empty() -> Self {
+        Self { screens: Vec::new() }
+    }
+
+    /// Make `name` available, replacing any screen registered under it before.
+    pub fn register<F>(&mut self, name: &str, description: &str, constructor: F)
+    where
+        F: Fn(&ScreenOptions) -> Result<Box<dyn Screen>> + Send + Sync + 'static,
+    {
+        let screen = RegisteredScreen {
+            name: name.to_string(),
+            description: description.to_string(),
+            constructor: Box::new(constructor),
+        };
+        match self.screens.iter_mut().find(|registered| registered.name == name) {
+            Some(existing) => *existing = screen,
+            None => self.screens.push(screen),
+        }
+    }
+
+    /// Pass every screen created as `name` through `wrapper`, e.g. to give it another data source.
+    /// Returns false if no such screen is registered.
+    pub fn wrap<F>(&mut self, name: &str, wrapper: F) -> bool
+    where
+        F: Fn(Box<dyn Screen>) -> Box<dyn Screen> + Send + Sync + 'static,
+    {
+        let Some(registered) = self.screens.iter_mut().find(|registered| registered.name == name) else {
+            return false;
+        };
+        let inner = std::mem::replace(&mut registered.constructor, Box::new(|_| Err(anyhow!("screen is being wrapped"))));
+        registered.constructor = Box::new(move |options| inner(options).map(&wrapper));
+        true
+    }
+
+    pub fn create(&self, name: &str, options: &ScreenOptions) -> Result<Box<dyn Screen>> {
+        let registered = self.screens.iter()
+            .find(|registered| registered.name == name)
+            .ok_or_else(|| anyhow!("Unknown screen type: {}", name))?;
+        (registered.constructor)(options.for_screen(name))
+    }
+
+    pub fn create_all(&self, names: &[&str], options: &ScreenOptions) -> Result<Vec<Box<dyn Screen>>> {
+        names.iter().map(|name| self.create(name, options)).collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.screens.iter().any(|registered| registered.name == name)
+    }
+
+    /// Registered names, in registration order.
+    pub fn names(&self) -> Vec<&str> {
+        self.screens.iter().map(|registered| registered.name.as_str()).collect()
+    }
+
+    pub fn description(&self, name: &str) -> Option<&str> {
+        self.screens.iter()
+            .find(|registered| registered.name == name)
+            .map(|registered| registered.description.as_str())
+    }
+}
+
+/// Constructors for the built-in screens.
 pub struct ScreenFactory;
 
 impl ScreenFactory {
-    pub fn create_screen(screen_type: &str) -> Result<Box<dyn Screen>> {
+    /// Build `screen_type` with `options` as they are; see `create_screens` for per-screen options.
+    pub fn create_screen(screen_type: &str, options: &ScreenOptions) -> Result<Box<dyn Screen>> {
         match screen_type {
-            "network" => Ok(Box::new(NetworkScreen)),
-            "system" => Ok(Box::new(SystemScreen)),
-            "storage" => Ok(Box::new(StorageScreen)),
+            "network" => Ok(Box::new(NetworkScreen {
+                interface: options.network_interface.clone(),
+                ip_version: options.ip_version,
+                max_columns: options.max_columns,
+            })),
+            "system" => Ok(Box::new(SystemScreen {
+                uptime: options.uptime.clone(),
+                process_warning_count: options.process_warning_count,
+            })),
+            "storage" => Ok(Box::new(StorageScreen {
+                show_percent: options.show_percent,
+                inode_warning_percent: options.inode_warning_percent,
+            })),
             "hardware" => Ok(Box::new(HardwareScreen)),
-            "temperature" => Ok(Box::new(TemperatureScreen)),
-            "gpio" => Ok(Box::new(GPIOScreen)),
-            "overview" => Ok(Box::new(OverviewScreen)),
+            "temperature" => Ok(Box::new(TemperatureScreen {
+                max_columns: options.max_columns,
+                margin_warning: options.thermal_margin_warning,
+            })),
+            "gpio" => Ok(Box::new(GPIOScreen::new(
+                options.i2c_probe, options.i2c_buses.clone(), options.w1_family, options.max_columns,
+            ))),
+            "overview" => Ok(Box::new(OverviewScreen {
+                show_percent: options.show_percent,
+                fields: options.overview_fields.clone(),
+                uptime: options.uptime.clone(),
+            })),
+            "about" => Ok(Box::new(AboutScreen)),
+            "clock" => Ok(Box::new(ClockScreen)),
+            "thermal" => Ok(Box::new(ThermalScreen)),
+            "ambient" => {
+                let pin = options.dht22_gpio
+                    .ok_or_else(|| anyhow!("The ambient screen needs a DHT22 pin (--dht22-gpio)"))?;
+                Ok(Box::new(AmbientScreen { sensor: Dht22Sensor::start(pin)? }))
+            }
+            "qr" => Ok(Box::new(QrScreen { content: options.qr_content.clone() })),
+            "battery" => Ok(Box::new(BatteryScreen {
+                hat: options.battery_hat,
+                address: options.battery_address.unwrap_or_else(|| options.battery_hat.default_address()),
+            })),
+            "connections" => Ok(Box::new(ConnectionsScreen)),
+            "diagnostics" => Ok(Box::new(DiagnosticsScreen { max_columns: options.max_columns })),
+            "debug" => Ok(Box::new(DebugScreen { max_columns: options.max_columns })),
             _ => Err(anyhow!("Unknown screen type: {}", screen_type)),
         }
     }
 
-    pub fn create_screens(screen_types: &[&str]) -> Result<Vec<Box<dyn Screen>>> {
+    pub fn create_screens(screen_types: &[&str], options: &ScreenOptions) -> Result<Vec<Box<dyn Screen>>> {
         screen_types.iter()
-            .map(|&screen_type| Self::create_screen(screen_type))
+            .map(|&screen_type| Self::create_screen(screen_type, options.for_screen(screen_type)))
             .collect()
     }
 
     pub fn get_available_screens() -> Vec<&'static str> {
-        vec!["network", "system", "storage", "hardware", "temperature", "gpio", "overview"]
+        vec!["network", "system", "storage", "hardware", "temperature", "gpio", "overview", "about", "clock", "thermal", "ambient", "qr", "battery", "connections", "diagnostics", "debug"]
     }
 
-    #[allow(dead_code)]
     pub fn get_screen_descriptions() -> HashMap<&'static str, &'static str> {
         let mut descriptions = HashMap::new();
         descriptions.insert("network", "Display hostname, domain, IP address, and MAC address");
@@ -38,6 +166,15 @@ impl ScreenFactory {
         descriptions.insert("temperature", "Display CPU/GPU temperatures, frequency, and throttling status");
         descriptions.insert("gpio", "Show I2C devices, GPIO states, SPI devices, and 1-Wire sensors");
         descriptions.insert("overview", "Combined view with all essential system information");
+        descriptions.insert("about", "Show version, build date, and target of this build");
+        descriptions.insert("clock", "Full-height clock with the current time and date");
+        descriptions.insert("thermal", "List the temperature of every thermal zone");
+        descriptions.insert("ambient", "Temperature and humidity from a DHT22 sensor");
+        descriptions.insert("qr", "QR code of the device URL or configured content");
+        descriptions.insert("battery", "Charge of a UPS/battery HAT such as a PiSugar");
+        descriptions.insert("connections", "Number of established TCP connections");
+        descriptions.insert("diagnostics", "Sources of readings that keep failing, with their last error");
+        descriptions.insert("debug", "How long the last update and the slowest screens took");
         descriptions
     }
 
@@ -52,20 +189,20 @@ mod tests {
 
     #[test]
     fn test_create_valid_screen() {
-        let screen = ScreenFactory::create_screen("network");
+        let screen = ScreenFactory::create_screen("network", &ScreenOptions::default());
         assert!(screen.is_ok());
         assert_eq!(screen.unwrap().name(), "network");
     }
 
     #[test]
     fn test_create_invalid_screen() {
-        let screen = ScreenFactory::create_screen("invalid");
+        let screen = ScreenFactory::create_screen("invalid", &ScreenOptions::default());
         assert!(screen.is_err());
     }
 
     #[test]
     fn test_create_multiple_screens() {
-        let screens = ScreenFactory::create_screens(&["network", "system"]);
+        let screens = ScreenFactory::create_screens(&["network", "system"], &ScreenOptions::default());
         assert!(screens.is_ok());
         let screens = screens.unwrap();
         assert_eq!(screens.len(), 2);
@@ -73,6 +210,89 @@ mod tests {
         assert_eq!(screens[1].name(), "system");
     }
 
+    #[test]
+    fn test_create_screens_with_per_screen_options() {
+        let mut options = ScreenOptions::default();
+        let ambient = ScreenOptions { dht22_gpio: Some(4), ..ScreenOptions::default() };
+        options.per_screen.insert("ambient".to_string(), ambient);
+
+        let screens = ScreenFactory::create_screens(&["ambient", "clock"], &options).unwrap();
+        assert_eq!(screens[0].name(), "ambient");
+        assert!(ScreenFactory::create_screen("ambient", &options).is_err());
+    }
+
+    struct CustomScreen;
+
+    impl Screen for CustomScreen {
+        fn name(&self) -> &'static str {
+            "custom"
+        }
+
+        fn render(&mut self, _snapshot: &crate::system_info::SystemSnapshot, _data: &dyn crate::system_info::DataSource) -> Result<String> {
+            Ok("custom".to_string())
+        }
+    }
+
+    #[test]
+    fn test_registry_has_builtin_screens() {
+        let registry = ScreenRegistry::default();
+        assert_eq!(registry.names(), ScreenFactory::get_available_screens());
+        assert_eq!(registry.create("clock", &ScreenOptions::default()).unwrap().name(), "clock");
+        assert!(registry.description("network").unwrap().contains("hostname"));
+        assert!(registry.create("custom", &ScreenOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_registry_custom_screen() {
+        let mut registry = ScreenRegistry::default();
+        registry.register("custom", "A screen from outside the crate", |_options| Ok(Box::new(CustomScreen)));
+        assert!(registry.contains("custom"));
+        assert_eq!(registry.names().last(), Some(&"custom"));
+
+        let screens = registry.create_all(&["custom", "network"], &ScreenOptions::default()).unwrap();
+        assert_eq!(screens[0].name(), "custom");
+        assert_eq!(screens[1].name(), "network");
+
+        // Registering a name again replaces the screen
+        registry.register("network", "Replaced", |_options| Ok(Box::new(CustomScreen)));
+        assert_eq!(registry.create("network", &ScreenOptions::default()).unwrap().name(), "custom");
+        assert_eq!(registry.names().len(), ScreenFactory::get_available_screens().len() + 1);
+    }
+
+    #[test]
+    fn test_registry_wrap() {
+        // Stands in for a wrapper that changes behaviour but keeps the name
+        struct Wrapped(Box<dyn Screen>);
+
+        impl Screen for Wrapped {
+            fn name(&self) -> &'static str {
+                self.0.name()
+            }
+
+            fn render(&mut self, _snapshot: &crate::system_info::SystemSnapshot, _data: &dyn crate::system_info::DataSource) -> Result<String> {
+                Ok("wrapped".to_string())
+            }
+        }
+
+        let mut registry = ScreenRegistry::default();
+        assert!(registry.wrap("clock", |screen| Box::new(Wrapped(screen))));
+        assert!(!registry.wrap("custom", |screen| screen));
+
+        let mut screen = registry.create("clock", &ScreenOptions::default()).unwrap();
+        assert_eq!(screen.name(), "clock");
+        let rendered = screen.render(&crate::system_info::SystemSnapshot::default(), &crate::system_info::DemoDataSource::new()).unwrap();
+        assert_eq!(rendered, "wrapped");
+    }
+
+    #[test]
+    fn test_ambient_needs_pin() {
+        assert!(ScreenFactory::create_screen("ambient", &ScreenOptions::default()).is_err());
+
+        let options = ScreenOptions { dht22_gpio: Some(4), ..ScreenOptions::default() };
+        let screen = ScreenFactory::create_screen("ambient", &options).unwrap();
+        assert_eq!(screen.name(), "ambient");
+    }
+
     #[test]
     fn test_validate_screen_types() {
         assert!(ScreenFactory::validate_screen_type("network"));
@@ -85,6 +305,7 @@ mod tests {
         let screens = ScreenFactory::get_available_screens();
         assert!(screens.contains(&"network"));
         assert!(screens.contains(&"overview"));
-        assert_eq!(screens.len(), 7);
+        assert!(screens.contains(&"about"));
+        assert_eq!(screens.len(), 16);
     }
 }
\ No newline at end of file
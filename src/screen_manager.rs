@@ -1,8 +1,86 @@
-use std::time::Duration;
-use sysinfo::System;
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
 use anyhow::Result;
-use crate::screens::Screen;
-use crate::screen_factory::ScreenFactory;
+use tracing::{debug_span, info, warn};
+use crate::config::ScreenErrorPolicy;
+use crate::framebuffer::FrameBuffer;
+use crate::screens::{RefreshRequirements, Screen, ScreenOptions};
+use crate::screen_factory::ScreenRegistry;
+use crate::system_info::{placeholder, DataSource, SystemSnapshot};
+
+// Overrides waiting their turn; older ones are dropped beyond this
+const MAX_PENDING_OVERRIDES: usize = 16;
+
+// Shown in exclusive mode until the first override arrives
+const WAITING_TITLE: &str = "Remote";
+const WAITING_CONTENT: &str = "Waiting for\nmessages";
+
+/// What a screen produced for one frame
+#[derive(Debug, Default)]
+pub struct RenderedScreen {
+    // None for screens that hide the title bar
+    pub title: Option<String>,
+    pub content: String,
+    // Graphics the text is drawn over; blank for text-only screens
+    pub canvas: FrameBuffer,
+}
+
+impl RenderedScreen {
+    fn text(title: &str, content: &str) -> Self {
+        Self {
+            title: Some(title.to_string()),
+            content: content.to_string(),
+            canvas: FrameBuffer::default(),
+        }
+    }
+}
+
+/// Content pushed from outside the rotation, shown instead of it for `duration`.
+/// Overrides with the same title as one already showing or queued replace it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenOverride {
+    pub title: String,
+    pub content: String,
+    pub duration: Duration,
+}
+
+// Where overrides come from and which one is showing
+struct Overrides {
+    receiver: Receiver<ScreenOverride>,
+    pending: VecDeque<ScreenOverride>,
+    active: Option<(ScreenOverride, Instant)>,
+    // Never fall back to the local screens; the last override stays up until replaced
+    exclusive: bool,
+}
+
+impl Overrides {
+    // The override to show now, if any, after taking in newly received ones
+    fn current(&mut self) -> Option<&ScreenOverride> {
+        for received in self.receiver.try_iter() {
+            // An update of what is showing or queued replaces it rather than queueing behind it
+            if let Some((active, shown_at)) = self.active.as_mut().filter(|(active, _)| active.title == received.title) {
+                *active = received;
+                *shown_at = Instant::now();
+            } else if let Some(queued) = self.pending.iter_mut().find(|queued| queued.title == received.title) {
+                *queued = received;
+            } else {
+                if self.pending.len() == MAX_PENDING_OVERRIDES {
+                    self.pending.pop_front();
+                }
+                self.pending.push_back(received);
+            }
+        }
+
+        let expired = self.active.as_ref()
+            .is_some_and(|(active, shown_at)| shown_at.elapsed() >= active.duration);
+        if self.active.is_none() || (expired && (!self.exclusive || !self.pending.is_empty())) {
+            self.active = self.pending.pop_front().map(|next| (next, Instant::now()));
+        }
+
+        self.active.as_ref().map(|(active, _)| active)
+    }
+}
 
 // Screen manager to handle cycling through screens
 pub struct ScreenManager {
@@ -10,46 +88,457 @@ pub struct ScreenManager {
     current_index: usize,
     last_switch_time: std::time::Instant,
     screen_duration: Duration,
+    // Screens shown longer or shorter than screen_duration, by name
+    screen_durations: HashMap<String, Duration>,
+    error_policy: ScreenErrorPolicy,
+    fallback_screen: Option<Box<dyn Screen>>,
+    overrides: Option<Overrides>,
 }
 
 impl ScreenManager {
-    pub fn new(enabled_screen_names: Vec<&str>, screen_duration_secs: u64) -> Result<Self> {
-        let screens = ScreenFactory::create_screens(&enabled_screen_names)
-            .unwrap_or_else(|_| {
+    /// Rotate through built-in screens
+    pub fn new(
+        enabled_screen_names: Vec<&str>,
+        first_screen: Option<&str>,
+        screen_duration: Duration,
+        error_policy: ScreenErrorPolicy,
+        options: &ScreenOptions,
+    ) -> Result<Self> {
+        let registry = ScreenRegistry::default();
+        Self::with_registry(&registry, enabled_screen_names, first_screen, screen_duration, error_policy, options)
+    }
+
+    /// Rotate through screens looked up in `registry`, starting at `first_screen` when
+    /// it is one of them and at the first one otherwise
+    pub fn with_registry(
+        registry: &ScreenRegistry,
+        enabled_screen_names: Vec<&str>,
+        first_screen: Option<&str>,
+        screen_duration: Duration,
+        error_policy: ScreenErrorPolicy,
+        options: &ScreenOptions,
+    ) -> Result<Self> {
+        let (screens, first_index) = match registry.create_all(&enabled_screen_names, options) {
+            Ok(screens) => {
+                let first_index = first_screen.and_then(|first| {
+                    let index = enabled_screen_names.iter().position(|name| *name == first);
+                    if index.is_none() {
+                        warn!("First screen {} is not enabled, starting with the first in the list", first);
+                    }
+                    index
+                });
+                (screens, first_index.unwrap_or(0))
+            }
+            Err(e) => {
                 // Fallback to overview screen if there's an error
-                vec![ScreenFactory::create_screen("overview").unwrap()]
-            });
-        
+                warn!("Failed to create screens, showing the overview instead: {}", e);
+                (vec![registry.create("overview", options)?], 0)
+            }
+        };
+
+        let mut manager = Self::with_screens(registry, screens, screen_duration, error_policy, options)?;
+        manager.current_index = first_index;
+        Ok(manager)
+    }
+
+    fn with_screens(
+        registry: &ScreenRegistry,
+        screens: Vec<Box<dyn Screen>>,
+        screen_duration: Duration,
+        error_policy: ScreenErrorPolicy,
+        options: &ScreenOptions,
+    ) -> Result<Self> {
+        let fallback_screen = match &error_policy {
+            ScreenErrorPolicy::Fallback(name) => Some(registry.create(name, options)?),
+            _ => None,
+        };
+
         Ok(Self {
             screens,
             current_index: 0,
             last_switch_time: std::time::Instant::now(),
-            screen_duration: Duration::from_secs(screen_duration_secs),
+            screen_duration,
+            screen_durations: HashMap::new(),
+            error_policy,
+            fallback_screen,
+            overrides: None,
         })
     }
-    
+
+    /// Show the named screens for these durations instead of the screen duration.
+    pub fn set_screen_durations(&mut self, durations: HashMap<String, Duration>) {
+        self.screen_durations = durations;
+    }
+
+    /// Drop the screens that have nothing to show on this machine from the rotation,
+    /// staying on the current screen or the next one kept. When none is available all
+    /// are kept, so there is still something on the display.
+    pub fn skip_unavailable(&mut self) {
+        let available: Vec<bool> = self.screens.iter().map(|screen| screen.is_available()).collect();
+        if available.iter().all(|&available| available) {
+            return;
+        }
+        if !available.contains(&true) {
+            warn!("None of the enabled screens is available, showing them anyway");
+            return;
+        }
+
+        let skipped: Vec<&str> = self.screens.iter().zip(&available)
+            .filter(|(_, available)| !**available)
+            .map(|(screen, _)| screen.name())
+            .collect();
+        info!("Skipping screens not available on this machine: {}", skipped.join(", "));
+
+        // The current screen, or the first available after it, keeps its place
+        let current = (self.current_index..available.len()).find(|&index| available[index]);
+        self.current_index = current.map_or(0, |current| available[..current].iter().filter(|&&available| available).count());
+        let mut flags = available.into_iter();
+        self.screens.retain(|_| flags.next().unwrap_or(true));
+    }
+
+    /// Show overrides sent on `receiver` in place of the rotation, each for its duration.
+    /// With `exclusive` the local screens are never shown.
+    pub fn set_overrides(&mut self, receiver: Receiver<ScreenOverride>, exclusive: bool) {
+        self.overrides = Some(Overrides {
+            receiver,
+            pending: VecDeque::new(),
+            active: None,
+            exclusive,
+        });
+    }
+
+    /// Whether the last render showed an override rather than a screen.
+    pub fn showing_override(&self) -> bool {
+        self.overrides.as_ref()
+            .is_some_and(|overrides| overrides.exclusive || overrides.active.is_some())
+    }
+
+    /// What the screens in the rotation, and the fallback, need refreshed before each frame.
+    pub fn refresh_requirements(&self) -> RefreshRequirements {
+        self.screens.iter()
+            .chain(self.fallback_screen.as_ref())
+            .fold(RefreshRequirements::NONE, |needed, screen| needed | screen.refresh_requirements())
+    }
+
     pub fn should_switch_screen(&self) -> bool {
-        self.screens.len() > 1 && self.last_switch_time.elapsed() >= self.screen_duration
+        self.screens.len() > 1 && self.last_switch_time.elapsed() >= self.current_screen_duration()
     }
-    
+
+    fn current_screen_duration(&self) -> Duration {
+        let Some(screen) = self.current_screen() else {
+            return self.screen_duration;
+        };
+        let duration = self.screen_durations.get(screen.name()).copied().unwrap_or(self.screen_duration);
+        match screen.max_duration() {
+            Some(max) => max.min(duration),
+            None => duration,
+        }
+    }
+
     pub fn next_screen(&mut self) {
         if self.screens.len() > 1 {
             self.current_index = (self.current_index + 1) % self.screens.len();
             self.last_switch_time = std::time::Instant::now();
         }
     }
-    
+
     pub fn current_screen(&self) -> Option<&dyn Screen> {
         self.screens.get(self.current_index).map(|s| s.as_ref())
     }
-    
-    pub fn render_current_screen(&self, sys: &System) -> Result<(String, String)> {
-        if let Some(screen) = self.current_screen() {
-            let title = screen.title()?;
-            let content = screen.render(sys)?;
-            Ok((title, content))
+
+    /// Render the current screen, applying the configured error policy if it fails.
+    pub fn render_current_screen(&mut self, snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<RenderedScreen> {
+        if let Some(overrides) = self.overrides.as_mut() {
+            let exclusive = overrides.exclusive;
+            match overrides.current() {
+                Some(current) => return Ok(RenderedScreen::text(&current.title, &current.content)),
+                None if exclusive => return Ok(RenderedScreen::text(WAITING_TITLE, WAITING_CONTENT)),
+                None => {}
+            }
+        }
+
+        let index = self.current_index;
+        let screen = match self.screens.get_mut(index) {
+            Some(screen) => screen,
+            None => return Ok(RenderedScreen::text("No Screen", "No screens enabled")),
+        };
+
+        let err = match Self::render_screen(screen.as_mut(), snapshot, data) {
+            Ok(output) => return Ok(output),
+            Err(err) => err,
+        };
+        warn!("Screen '{}' failed to render: {}", screen.name(), err);
+
+        match &self.error_policy {
+            ScreenErrorPolicy::Skip => self.render_next_available(snapshot, data).ok_or(err),
+            ScreenErrorPolicy::Placeholder => {
+                let screen = &self.screens[index];
+                let title = screen.title(data).unwrap_or_else(|_| screen.name().to_string());
+                Ok(RenderedScreen::text(&title, placeholder()))
+            }
+            ScreenErrorPolicy::Fallback(_) => match &mut self.fallback_screen {
+                Some(fallback) => Self::render_screen(fallback.as_mut(), snapshot, data),
+                None => Err(err),
+            },
+        }
+    }
+
+    // Advance through the rotation until a screen renders, making it the current one
+    fn render_next_available(&mut self, snapshot: &SystemSnapshot, data: &dyn DataSource) -> Option<RenderedScreen> {
+        for offset in 1..self.screens.len() {
+            let index = (self.current_index + offset) % self.screens.len();
+            let screen = self.screens[index].as_mut();
+            match Self::render_screen(screen, snapshot, data) {
+                Ok(output) => {
+                    self.current_index = index;
+                    self.last_switch_time = std::time::Instant::now();
+                    return Some(output);
+                }
+                Err(err) => warn!("Screen '{}' failed to render: {}", screen.name(), err),
+            }
+        }
+        None
+    }
+
+    fn render_screen(screen: &mut dyn Screen, snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<RenderedScreen> {
+        let _span = debug_span!("render_screen", screen = screen.name()).entered();
+        let title = if screen.show_title() {
+            Some(screen.title(data)?)
         } else {
-            Ok(("No Screen".to_string(), "No screens enabled".to_string()))
+            None
+        };
+        let content = screen.render(snapshot, data)?;
+        let mut canvas = FrameBuffer::default();
+        screen.draw(data, &mut canvas)?;
+        Ok(RenderedScreen { title, content, canvas })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::DemoDataSource;
+    use anyhow::anyhow;
+
+    struct FailingScreen;
+
+    impl Screen for FailingScreen {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn render(&mut self, _snapshot: &SystemSnapshot, _data: &dyn DataSource) -> Result<String> {
+            Err(anyhow!("source unavailable"))
         }
     }
-}
\ No newline at end of file
+
+    struct StaticScreen;
+
+    impl Screen for StaticScreen {
+        fn name(&self) -> &'static str {
+            "static"
+        }
+
+        fn render(&mut self, _snapshot: &SystemSnapshot, _data: &dyn DataSource) -> Result<String> {
+            Ok("ok".to_string())
+        }
+    }
+
+    // Remembers how often it was shown
+    struct CountingScreen(u32);
+
+    impl Screen for CountingScreen {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn render(&mut self, _snapshot: &SystemSnapshot, _data: &dyn DataSource) -> Result<String> {
+            self.0 += 1;
+            Ok(format!("frame {}", self.0))
+        }
+    }
+
+    // Hardware this machine does not have
+    struct UnavailableScreen;
+
+    impl Screen for UnavailableScreen {
+        fn name(&self) -> &'static str {
+            "unavailable"
+        }
+
+        fn render(&mut self, _snapshot: &SystemSnapshot, _data: &dyn DataSource) -> Result<String> {
+            Ok("no hardware".to_string())
+        }
+
+        fn is_available(&self) -> bool {
+            false
+        }
+    }
+
+    fn screen_names(manager: &ScreenManager) -> Vec<&'static str> {
+        manager.screens.iter().map(|screen| screen.name()).collect()
+    }
+
+    fn manager(screens: Vec<Box<dyn Screen>>, policy: ScreenErrorPolicy) -> ScreenManager {
+        ScreenManager::with_screens(&ScreenRegistry::default(), screens, Duration::from_secs(10), policy, &ScreenOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_screen_keeps_state_between_frames() {
+        let mut manager = manager(vec![Box::new(CountingScreen(0))], ScreenErrorPolicy::Skip);
+        manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        let rendered = manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        assert_eq!(rendered.content, "frame 2");
+    }
+
+    #[test]
+    fn test_refresh_requirements() {
+        let rotation = manager(vec![Box::new(CountingScreen(0))], ScreenErrorPolicy::Skip);
+        assert_eq!(rotation.refresh_requirements(), RefreshRequirements::NONE);
+
+        let storage = crate::screens::StorageScreen { show_percent: false, inode_warning_percent: 90 };
+        let rotation = manager(vec![Box::new(CountingScreen(0)), Box::new(storage)], ScreenErrorPolicy::Skip);
+        assert_eq!(
+            rotation.refresh_requirements(),
+            RefreshRequirements::MEMORY | RefreshRequirements::DISK | RefreshRequirements::INODES | RefreshRequirements::MOUNTS
+        );
+
+        // The fallback screen has to render too
+        let rotation = manager(vec![Box::new(CountingScreen(0))], ScreenErrorPolicy::Fallback("storage".to_string()));
+        assert!(rotation.refresh_requirements().contains(RefreshRequirements::MEMORY));
+    }
+
+    #[test]
+    fn test_skip_moves_to_next_screen() {
+        let mut manager = manager(vec![Box::new(FailingScreen), Box::new(StaticScreen)], ScreenErrorPolicy::Skip);
+        let rendered = manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        assert_eq!(rendered.title.as_deref(), Some("static"));
+        assert_eq!(rendered.content, "ok");
+        assert_eq!(manager.current_screen().unwrap().name(), "static");
+    }
+
+    #[test]
+    fn test_skip_with_no_working_screen_errors() {
+        let mut manager = manager(vec![Box::new(FailingScreen)], ScreenErrorPolicy::Skip);
+        assert!(manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).is_err());
+    }
+
+    #[test]
+    fn test_placeholder_keeps_title() {
+        let mut manager = manager(vec![Box::new(FailingScreen)], ScreenErrorPolicy::Placeholder);
+        let rendered = manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        assert_eq!(rendered.title.as_deref(), Some("failing"));
+        assert_eq!(rendered.content, placeholder());
+    }
+
+    #[test]
+    fn test_fallback_screen_is_created() {
+        let manager = manager(vec![Box::new(FailingScreen)], ScreenErrorPolicy::Fallback("network".to_string()));
+        assert_eq!(manager.fallback_screen.as_ref().unwrap().name(), "network");
+    }
+
+    fn remote(title: &str, duration: Duration) -> ScreenOverride {
+        ScreenOverride { title: title.to_string(), content: "line".to_string(), duration }
+    }
+
+    #[test]
+    fn test_override_interleaves_with_screens() {
+        let mut manager = manager(vec![Box::new(StaticScreen)], ScreenErrorPolicy::Skip);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        manager.set_overrides(receiver, false);
+
+        sender.send(remote("first", Duration::ZERO)).unwrap();
+        sender.send(remote("second", Duration::from_secs(60))).unwrap();
+        let rendered = manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        assert_eq!(rendered.title.as_deref(), Some("first"));
+        assert!(manager.showing_override());
+
+        // The first has expired, so the next one in line is shown
+        let rendered = manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        assert_eq!(rendered.title.as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn test_override_with_same_title_replaces() {
+        let mut manager = manager(vec![Box::new(StaticScreen)], ScreenErrorPolicy::Skip);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        manager.set_overrides(receiver, false);
+
+        sender.send(remote("nas", Duration::from_secs(60))).unwrap();
+        manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        sender.send(ScreenOverride { content: "updated".to_string(), ..remote("nas", Duration::from_secs(60)) }).unwrap();
+        sender.send(remote("router", Duration::from_secs(60))).unwrap();
+        sender.send(ScreenOverride { content: "updated".to_string(), ..remote("router", Duration::from_secs(60)) }).unwrap();
+
+        let rendered = manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        assert_eq!(rendered.content, "updated");
+        let overrides = manager.overrides.as_ref().unwrap();
+        assert_eq!(overrides.pending.len(), 1);
+        assert_eq!(overrides.pending[0].content, "updated");
+    }
+
+    #[test]
+    fn test_expired_override_returns_to_rotation() {
+        let mut manager = manager(vec![Box::new(StaticScreen)], ScreenErrorPolicy::Skip);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        manager.set_overrides(receiver, false);
+
+        sender.send(remote("remote", Duration::ZERO)).unwrap();
+        manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        let rendered = manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        assert_eq!(rendered.title.as_deref(), Some("static"));
+        assert!(!manager.showing_override());
+    }
+
+    #[test]
+    fn test_exclusive_overrides() {
+        let mut manager = manager(vec![Box::new(StaticScreen)], ScreenErrorPolicy::Skip);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        manager.set_overrides(receiver, true);
+
+        let rendered = manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        assert_eq!(rendered.title.as_deref(), Some(WAITING_TITLE));
+
+        // An expired override stays up until another one arrives
+        sender.send(remote("remote", Duration::ZERO)).unwrap();
+        manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        let rendered = manager.render_current_screen(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        assert_eq!(rendered.title.as_deref(), Some("remote"));
+    }
+
+    #[test]
+    fn test_first_screen() {
+        let screens = vec!["clock", "network", "storage"];
+        let options = ScreenOptions::default();
+        let manager = ScreenManager::new(screens.clone(), Some("storage"), Duration::from_secs(10), ScreenErrorPolicy::Skip, &options).unwrap();
+        assert_eq!(manager.current_screen().unwrap().name(), "storage");
+
+        // A screen that is not enabled leaves the list order alone
+        let manager = ScreenManager::new(screens, Some("hardware"), Duration::from_secs(10), ScreenErrorPolicy::Skip, &options).unwrap();
+        assert_eq!(manager.current_screen().unwrap().name(), "clock");
+    }
+
+    #[test]
+    fn test_screen_durations() {
+        let mut manager = manager(vec![Box::new(StaticScreen), Box::new(CountingScreen(0))], ScreenErrorPolicy::Skip);
+        manager.set_screen_durations(HashMap::from([("counting".to_string(), Duration::from_secs(3))]));
+        assert_eq!(manager.current_screen_duration(), Duration::from_secs(10));
+        manager.current_index = 1;
+        assert_eq!(manager.current_screen_duration(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_skip_unavailable() {
+        let mut rotation = manager(vec![Box::new(StaticScreen), Box::new(UnavailableScreen), Box::new(CountingScreen(0))],
+                                  ScreenErrorPolicy::Skip);
+        rotation.current_index = 1;
+        rotation.skip_unavailable();
+        assert_eq!(screen_names(&rotation), vec!["static", "counting"]);
+        assert_eq!(rotation.current_screen().unwrap().name(), "counting");
+
+        // Something has to be shown
+        let mut only_unavailable = manager(vec![Box::new(UnavailableScreen)], ScreenErrorPolicy::Skip);
+        only_unavailable.skip_unavailable();
+        assert_eq!(screen_names(&only_unavailable), vec!["unavailable"]);
+    }
+}
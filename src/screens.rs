@@ -1,113 +1,562 @@
+use std::collections::HashMap;
+use std::time::Duration;
 use anyhow::Result;
-use sysinfo::System;
-use crate::system_info::*;
+use chrono::{DateTime, Local};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, primitives::{PrimitiveStyle, Rectangle}};
+use qrcode::{Color, QrCode};
+use tracing::debug;
+use crate::display::{LINE_CHARS, TITLE_CHARS};
+use crate::framebuffer::{self, FrameBuffer};
+use crate::system_info::battery::read_battery;
+use crate::system_info::{
+    format_boot_time, format_cpu_freq, format_cpu_temp, format_disk, format_gpu_temp, format_i2c_devices, format_memory, format_swap,
+    format_uptime, placeholder, BatteryHat, DataSource, Dht22Sensor, SystemSnapshot, ThrottleConditions,
+};
+pub use crate::system_info::RefreshRequirements;
+use crate::system_info::sensors::has_expansion_buses;
+use crate::timing::{format_duration_short, loop_timings, LoopTimings};
+use crate::utils::{elide_middle, format_temp, truncate_chars};
+
+pub const DEFAULT_INODE_WARNING_PERCENT: u8 = 90;
+// A Pi running a desktop has around 250, kernel threads included
+pub const DEFAULT_PROCESS_WARNING_COUNT: usize = 400;
+pub const DEFAULT_THERMAL_MARGIN_WARNING: f32 = 10.0;
+
+// Settings that influence how screens format their content
+#[derive(Debug, Clone)]
+pub struct ScreenOptions {
+    // Append the percentage used to memory, swap and disk figures
+    pub show_percent: bool,
+    // The storage screen shows root inode usage above this percentage
+    pub inode_warning_percent: u8,
+    // The system screen shows the process count above this many, and zombies always
+    pub process_warning_count: usize,
+    // The temperature screen marks the margin to the CPU's trip point below this many degrees
+    pub thermal_margin_warning: f32,
+    // Lines of the overview screen, in order
+    pub overview_fields: Vec<OverviewField>,
+    // BCM GPIO of the DHT22 read by the ambient screen
+    pub dht22_gpio: Option<u8>,
+    // What the QR screen encodes; None for http://<ip>
+    pub qr_content: Option<String>,
+    // UPS HAT read by the battery screen, at its default address unless one is given
+    pub battery_hat: BatteryHat,
+    pub battery_address: Option<u8>,
+    // Probe these I2C buses for the GPIO screen, the header's bus when empty; probing is off unless enabled
+    pub i2c_probe: bool,
+    pub i2c_buses: Vec<u8>,
+    // 1-Wire family the GPIO screen lists; None for every device
+    pub w1_family: Option<u8>,
+    // Interface of the network screen; None for the first wired one
+    pub network_interface: Option<String>,
+    // Addresses the network screen shows
+    pub ip_version: IpVersion,
+    // Characters that fit a content line; screens cut longer lines to this
+    pub max_columns: usize,
+    // How the system and overview screens show uptime
+    pub uptime: UptimeFormat,
+    // Options set for a single screen in its [screens.<name>] section, by screen name
+    pub per_screen: HashMap<String, ScreenOptions>,
+}
+
+impl ScreenOptions {
+    /// The options `screen` is created with: its own section's if it has one.
+    pub fn for_screen(&self, screen: &str) -> &ScreenOptions {
+        self.per_screen.get(screen).unwrap_or(self)
+    }
+}
+
+impl Default for ScreenOptions {
+    fn default() -> Self {
+        Self {
+            show_percent: false,
+            inode_warning_percent: DEFAULT_INODE_WARNING_PERCENT,
+            process_warning_count: DEFAULT_PROCESS_WARNING_COUNT,
+            thermal_margin_warning: DEFAULT_THERMAL_MARGIN_WARNING,
+            overview_fields: OverviewField::DEFAULT.to_vec(),
+            dht22_gpio: None,
+            qr_content: None,
+            battery_hat: BatteryHat::default(),
+            battery_address: None,
+            i2c_probe: false,
+            i2c_buses: Vec::new(),
+            w1_family: None,
+            network_interface: None,
+            ip_version: IpVersion::default(),
+            max_columns: LINE_CHARS,
+            uptime: UptimeFormat::default(),
+            per_screen: HashMap::new(),
+        }
+    }
+}
+
+pub const DEFAULT_DATE_FORMAT: &str = "%d %b %H:%M";
+
+/// Whether uptime is shown as a duration or as the time of boot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UptimeStyle {
+    /// "3d4h12m", with seconds under an hour: "4m12s"
+    #[default]
+    Compact,
+    /// "Since: 01 May 09:30"
+    Since,
+}
+
+impl UptimeStyle {
+    pub const ALL: [UptimeStyle; 2] = [UptimeStyle::Compact, UptimeStyle::Since];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|style| style.name() == name.trim().to_lowercase())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UptimeStyle::Compact => "compact",
+            UptimeStyle::Since => "since",
+        }
+    }
+}
+
+/// Uptime as the system and overview screens show it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UptimeFormat {
+    pub style: UptimeStyle,
+    // strftime format of the boot time in the since style
+    pub date_format: String,
+}
+
+impl Default for UptimeFormat {
+    fn default() -> Self {
+        Self { style: UptimeStyle::default(), date_format: DEFAULT_DATE_FORMAT.to_string() }
+    }
+}
+
+impl UptimeFormat {
+    // "<label>: 4m12s", or "Since: 01 May 09:30" whatever the label
+    fn line(&self, label: &str, uptime: Option<Duration>, now: DateTime<Local>) -> String {
+        match self.style {
+            UptimeStyle::Compact => format!("{}: {}", label, some_or_placeholder(uptime, format_uptime)),
+            UptimeStyle::Since => format!(
+                "Since: {}",
+                some_or_placeholder(uptime, |uptime| format_boot_time(uptime, now, &self.date_format))
+            ),
+        }
+    }
+}
+
+/// Which addresses the network screen shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IpVersion {
+    #[default]
+    V4,
+    V6,
+    Both,
+}
+
+impl IpVersion {
+    pub const ALL: [IpVersion; 3] = [IpVersion::V4, IpVersion::V6, IpVersion::Both];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|version| version.name() == name.trim().to_lowercase())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IpVersion::V4 => "v4",
+            IpVersion::V6 => "v6",
+            IpVersion::Both => "both",
+        }
+    }
+}
+
+/// A line the overview screen can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverviewField {
+    Ip,
+    Temp,
+    Mem,
+    Disk,
+    Uptime,
+}
+
+impl OverviewField {
+    /// Every field, in the default order
+    pub const ALL: [OverviewField; 5] = [
+        OverviewField::Ip,
+        OverviewField::Temp,
+        OverviewField::Mem,
+        OverviewField::Disk,
+        OverviewField::Uptime,
+    ];
+
+    /// As many as fit below the title
+    pub const DEFAULT: [OverviewField; 4] = [OverviewField::Ip, OverviewField::Temp, OverviewField::Mem, OverviewField::Disk];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|field| field.name() == name.trim())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OverviewField::Ip => "ip",
+            OverviewField::Temp => "temp",
+            OverviewField::Mem => "mem",
+            OverviewField::Disk => "disk",
+            OverviewField::Uptime => "uptime",
+        }
+    }
+
+    fn render(&self, snapshot: &SystemSnapshot, data: &dyn DataSource, show_percent: bool, uptime: &UptimeFormat) -> String {
+        match self {
+            OverviewField::Ip => ip_address_or_na(data),
+            OverviewField::Temp => some_or_placeholder(snapshot.cpu_temp_celsius, format_cpu_temp),
+            OverviewField::Mem => some_or_placeholder(snapshot.memory(), |usage| format_memory(&usage, show_percent)),
+            OverviewField::Disk => some_or_placeholder(snapshot.disk(), |usage| format_disk(&usage, show_percent)),
+            OverviewField::Uptime => uptime.line("Up", snapshot.uptime(), Local::now()),
+        }
+    }
+
+    // The snapshot reading the line shows; the address comes from the data source
+    fn requirements(&self) -> RefreshRequirements {
+        match self {
+            OverviewField::Ip => RefreshRequirements::NONE,
+            OverviewField::Temp => RefreshRequirements::CPU_TEMP,
+            OverviewField::Mem => RefreshRequirements::MEMORY,
+            OverviewField::Disk => RefreshRequirements::DISK,
+            OverviewField::Uptime => RefreshRequirements::UPTIME,
+        }
+    }
+}
 
 // Screen trait for modular display screens
 pub trait Screen {
     fn name(&self) -> &'static str;
-    fn title(&self) -> Result<String> {
+    fn title(&self, _data: &dyn DataSource) -> Result<String> {
         Ok(self.name().to_string())
     }
-    fn render(&self, sys: &System) -> Result<String>;
+    // Called once per frame with the readings taken for it; screens can keep state
+    // between frames, e.g. the previous sample to show a rate
+    fn render(&mut self, snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String>;
+    // The readings that have to be in the snapshot passed to render
+    fn refresh_requirements(&self) -> RefreshRequirements {
+        RefreshRequirements::NONE
+    }
+    // Screens that return false get the full display height for their content
+    fn show_title(&self) -> bool {
+        true
+    }
+    // Upper bound on how long the screen stays up; None uses the configured screen duration
+    fn max_duration(&self) -> Option<Duration> {
+        None
+    }
+    // Screens with nothing to show on this machine, e.g. for missing hardware, return
+    // false and are left out of the rotation
+    fn is_available(&self) -> bool {
+        true
+    }
+    // Graphics for screens that are more than text; the content is drawn over them
+    fn draw(&self, _data: &dyn DataSource, _canvas: &mut FrameBuffer) -> Result<()> {
+        Ok(())
+    }
 }
 
-// Network information screen
-pub struct NetworkScreen;
+// Interface enumeration can fail transiently, which should not take the screen down
+// A reading formatted for display, or the placeholder when it could not be taken
+fn or_placeholder<T>(reading: Result<T>, format: impl FnOnce(T) -> String) -> String {
+    reading.map(format).unwrap_or_else(|_| placeholder().to_string())
+}
+
+// The same for a snapshot reading, which is None when it could not be taken
+fn some_or_placeholder<T>(reading: Option<T>, format: impl FnOnce(T) -> String) -> String {
+    reading.map(format).unwrap_or_else(|| placeholder().to_string())
+}
+
+fn ip_address_or_na(data: &dyn DataSource) -> String {
+    data.ip_address().unwrap_or_else(|_| placeholder().to_string())
+}
+
+// "label value", with the value cut so the line fits `max_columns`
+fn fit_line(label: &str, value: &str, max_columns: usize) -> String {
+    format!("{}{}", label, truncate_chars(value, max_columns.saturating_sub(label.chars().count())))
+}
+
+// Network information screen, for the wired interface unless one is named
+pub struct NetworkScreen {
+    pub interface: Option<String>,
+    pub ip_version: IpVersion,
+    pub max_columns: usize,
+}
+
+impl Default for NetworkScreen {
+    fn default() -> Self {
+        Self { interface: None, ip_version: IpVersion::default(), max_columns: LINE_CHARS }
+    }
+}
 
 impl Screen for NetworkScreen {
     fn name(&self) -> &'static str {
         "network"
     }
     
-    fn render(&self, _sys: &System) -> Result<String> {
-        let hostname = hostname::get()
-            .unwrap()
-            .to_string_lossy()
-            .into_owned();
-        let domain = get_domain();
-        let ip_address = get_ip_address()?;
-        let mac_address = get_mac_address();
+    fn render(&mut self, _snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        let hostname = data.hostname();
+        let domain = data.domain();
+        let (ip_address, mac_address, link_speed) = match &self.interface {
+            Some(name) => {
+                let info = data.interface(name);
+                (info.ip, info.mac.unwrap_or_else(|| placeholder().to_string()), info.link_speed.map(|link| format!("{} {}", name, link)))
+            }
+            None => (data.ip_address().ok(), data.mac_address(), data.link_speed()),
+        };
+        // Without an address, a blocked radio is the likely reason; the full
+        // "WiFi BLOCKED (no country)" does not fit a line
+        let ip_address = ip_address.unwrap_or_else(|| match data.wifi_block() {
+            Some(block) => format!("WiFi BLOCKED\n({})", block.reason()),
+            None => placeholder().to_string(),
+        });
         
-        Ok(format!(
-            "{}.{}\n{}\n{}",
-            hostname, domain, ip_address, mac_address
-        ))
+        let ipv6_address = || data.ipv6_address(self.interface.as_deref())
+            .map(|address| elide_middle(&address, self.max_columns))
+            .unwrap_or_else(|_| placeholder().to_string());
+        let addresses = match self.ip_version {
+            IpVersion::V4 => ip_address,
+            IpVersion::V6 => ipv6_address(),
+            IpVersion::Both => format!("{}\n{}", ip_address, ipv6_address()),
+        };
+
+        let mut content = format!(
+            "{}\n{}\n{}",
+            truncate_chars(&format!("{}.{}", hostname, domain), self.max_columns), addresses, mac_address
+        );
+        if let Some(link) = link_speed {
+            content.push_str(&format!("\n{}", link));
+        }
+        Ok(content)
+    }
+}
+
+// Established TCP connections, for a server's display
+pub struct ConnectionsScreen;
+
+impl Screen for ConnectionsScreen {
+    fn name(&self) -> &'static str {
+        "connections"
+    }
+
+    fn title(&self, _data: &dyn DataSource) -> Result<String> {
+        Ok("Connections".to_string())
+    }
+
+    fn render(&mut self, _snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        let count = or_placeholder(data.connection_count(), |count| count.to_string());
+        Ok(format!("TCP established: {}", count))
+    }
+}
+
+// As many as fit below the title
+const DIAGNOSTIC_SOURCES: usize = 4;
+
+/// The sources of readings that failed most since startup, with the last error of each,
+/// to tell a reading that is never there from one that fails now and then
+pub struct DiagnosticsScreen {
+    pub max_columns: usize,
+}
+
+impl Screen for DiagnosticsScreen {
+    fn name(&self) -> &'static str {
+        "diagnostics"
+    }
+
+    fn title(&self, _data: &dyn DataSource) -> Result<String> {
+        Ok("Diagnostics".to_string())
+    }
+
+    fn render(&mut self, _snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        let failures = data.source_failures();
+        if failures.is_empty() {
+            return Ok("No failed reads".to_string());
+        }
+        Ok(failures.iter()
+            .take(DIAGNOSTIC_SOURCES)
+            .map(|source| {
+                // Command errors start with the program, which is the source already
+                let error = source.last_error.strip_prefix(&source.source)
+                    .map_or(source.last_error.as_str(), |rest| rest.trim_start_matches([':', ' ']));
+                fit_line(&format!("{} {}: ", source.source, source.count), error, self.max_columns)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+// Screens listed by the debug screen under the loop line
+const DEBUG_SCREENS: usize = 3;
+
+/// How long the last update and cycle took, and the slowest screens to render, for
+/// tuning the interval without an OpenTelemetry collector
+pub struct DebugScreen {
+    pub max_columns: usize,
+}
+
+impl Screen for DebugScreen {
+    fn name(&self) -> &'static str {
+        "debug"
+    }
+
+    fn title(&self, _data: &dyn DataSource) -> Result<String> {
+        Ok("Debug".to_string())
+    }
+
+    fn render(&mut self, _snapshot: &SystemSnapshot, _data: &dyn DataSource) -> Result<String> {
+        Ok(debug_lines(&loop_timings(), self.max_columns))
+    }
+}
+
+// "Loop 14ms/5.0s", then the slowest screens as "network 3.2ms"
+fn debug_lines(timings: &LoopTimings, max_columns: usize) -> String {
+    let format = |duration: Option<Duration>| duration.map_or_else(|| placeholder().to_string(), format_duration_short);
+    let mut lines = vec![format!("Loop {}/{}", format(timings.last_update), format(timings.last_cycle))];
+    let mut renders: Vec<&(String, Duration)> = timings.renders.iter().collect();
+    renders.sort_by_key(|(_, took)| std::cmp::Reverse(*took));
+    lines.extend(renders.into_iter().take(DEBUG_SCREENS).map(|(name, took)| {
+        // The name gives way, the time is what the screen is for
+        let took = format_duration_short(*took);
+        format!("{} {}", truncate_chars(name, max_columns.saturating_sub(took.len() + 1)), took)
+    }));
+    lines.join("\n")
+}
+
+// "/: READ-ONLY!" for the first read-only mount, with how many more there are
+fn readonly_warning(snapshot: &SystemSnapshot) -> Option<String> {
+    let mounts = snapshot.readonly_mounts.as_ref()?;
+    let first = mounts.first()?;
+    Some(match mounts.len() {
+        1 => format!("{}: READ-ONLY!", first),
+        count => format!("{}: READ-ONLY! +{}", first, count - 1),
+    })
+}
+
+// "! 812 procs 3 zombie" when there are zombies or more processes than `warning_count`
+fn process_warning(snapshot: &SystemSnapshot, warning_count: usize) -> Option<String> {
+    let processes = snapshot.processes.as_ref()?;
+    match processes.zombies {
+        0 if processes.total > warning_count => Some(format!("! {} procs", processes.total)),
+        0 => None,
+        zombies => Some(format!("! {} procs {} zombie", processes.total, zombies)),
     }
 }
 
 // System information screen
-pub struct SystemScreen;
+pub struct SystemScreen {
+    pub uptime: UptimeFormat,
+    pub process_warning_count: usize,
+}
 
 impl Screen for SystemScreen {
     fn name(&self) -> &'static str {
         "system"
     }
     
-    fn render(&self, _sys: &System) -> Result<String> {
-        let cpu_temp = get_cpu_temp().unwrap_or_else(|_| "N/A".to_string());
-        let uptime = get_uptime();
-        let boot_part = get_boot_partition();
+    fn render(&mut self, snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        let mut cpu = some_or_placeholder(snapshot.cpu_temp_celsius, format_cpu_temp);
+        // Usage is unknown until the second snapshot
+        if let Some(percent) = snapshot.cpu_usage_percent {
+            cpu.push_str(&format!(" {:.0}%", percent));
+        }
+        let uptime = self.uptime.line("Uptime", snapshot.uptime(), Local::now());
+        let boot_part = data.boot_partition();
         
         // Extract just device name from boot partition
-        let boot_device = if let Some(dev_name) = boot_part.split('/').last() {
-            dev_name.to_string()
-        } else {
-            boot_part
-        };
+        let boot_device = boot_part.split('/').next_back().unwrap_or(&boot_part);
         
-        Ok(format!(
-            "CPU: {}\nUptime: {}\nBoot: {}",
-            cpu_temp, uptime, boot_device
-        ))
+        let mut content = format!(
+            "CPU: {}\n{}\nBoot: {}",
+            cpu, uptime, boot_device
+        );
+        // Only takes the last line when there is something to say, a read-only root
+        // being more urgent than runaway processes, and those than a reboot
+        if let Some(warning) = readonly_warning(snapshot) {
+            content.push_str(&format!("\n{}", warning));
+        } else if let Some(warning) = process_warning(snapshot, self.process_warning_count) {
+            content.push_str(&format!("\n{}", warning));
+        } else if let Some(notice) = data.reboot_notice() {
+            content.push_str(&format!("\n! {}", notice));
+        }
+        Ok(content)
+    }
+
+    fn refresh_requirements(&self) -> RefreshRequirements {
+        RefreshRequirements::CPU_TEMP
+            | RefreshRequirements::CPU
+            | RefreshRequirements::UPTIME
+            | RefreshRequirements::MOUNTS
+            | RefreshRequirements::PROCESSES
     }
 }
 
 // Memory and storage screen
-pub struct StorageScreen;
+pub struct StorageScreen {
+    pub show_percent: bool,
+    pub inode_warning_percent: u8,
+}
 
 impl Screen for StorageScreen {
     fn name(&self) -> &'static str {
         "storage"
     }
     
-    fn render(&self, sys: &System) -> Result<String> {
-        let memory_info = get_memory_info(sys);
-        let disk_usage = get_disk_usage();
+    fn render(&mut self, snapshot: &SystemSnapshot, _data: &dyn DataSource) -> Result<String> {
+        let memory_info = some_or_placeholder(snapshot.memory(), |usage| format_memory(&usage, self.show_percent));
+        let swap_info = some_or_placeholder(snapshot.swap(), |usage| format_swap(usage.as_ref(), self.show_percent));
+        let disk_usage = some_or_placeholder(snapshot.disk(), |usage| format_disk(&usage, self.show_percent));
         
-        Ok(format!(
-            "Memory: {}\nDisk: {}",
-            memory_info, disk_usage
-        ))
+        let mut content = format!(
+            "Memory: {}\nSwap: {}\nDisk: {}",
+            memory_info, swap_info, disk_usage
+        );
+        // Running out of inodes fails writes just like a full disk, with plenty of space left;
+        // a read-only filesystem fails them all
+        if let Some(warning) = readonly_warning(snapshot) {
+            content.push_str(&format!("\n{}", warning));
+        } else if let Some(percent) = snapshot.inodes().and_then(|usage| usage.percent_exact())
+            .filter(|percent| *percent > f32::from(self.inode_warning_percent))
+        {
+            content.push_str(&format!("\nInode: {:.0}%", percent));
+        }
+        Ok(content)
+    }
+
+    fn refresh_requirements(&self) -> RefreshRequirements {
+        RefreshRequirements::MEMORY | RefreshRequirements::DISK | RefreshRequirements::INODES | RefreshRequirements::MOUNTS
     }
 }
 
-// Combined overview screen (original layout)
-pub struct OverviewScreen;
+// Combined overview screen, one line per configured field
+pub struct OverviewScreen {
+    pub show_percent: bool,
+    pub fields: Vec<OverviewField>,
+    pub uptime: UptimeFormat,
+}
 
 impl Screen for OverviewScreen {
     fn name(&self) -> &'static str {
         "overview"
     }
     
-    fn title(&self) -> Result<String> {
+    fn title(&self, data: &dyn DataSource) -> Result<String> {
         // Use hostname as title for overview screen
-        Ok(hostname::get()
-            .unwrap()
-            .to_string_lossy()
-            .into_owned())
+        Ok(truncate_chars(&data.hostname(), TITLE_CHARS))
     }
     
-    fn render(&self, sys: &System) -> Result<String> {
-        let ip_address = get_ip_address()?;
-        let cpu_temp = get_cpu_temp().unwrap_or_else(|_| "N/A".to_string());
-        let memory_info = get_memory_info(sys);
-        let disk_usage = get_disk_usage();
-        let uptime = get_uptime();
-        
-        Ok(format!(
-            "{}\n{}\n{}\n{}\nUp: {}",
-            ip_address, cpu_temp, memory_info, disk_usage, uptime
-        ))
+    fn render(&mut self, snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        let lines: Vec<String> = self.fields.iter()
+            .map(|field| field.render(snapshot, data, self.show_percent, &self.uptime))
+            .collect();
+        Ok(lines.join("\n"))
+    }
+
+    fn refresh_requirements(&self) -> RefreshRequirements {
+        self.fields.iter().fold(RefreshRequirements::NONE, |needed, field| needed | field.requirements())
     }
 }
 
@@ -119,17 +568,15 @@ impl Screen for HardwareScreen {
         "hardware"
     }
     
-    fn render(&self, _sys: &System) -> Result<String> {
-        let pi_model = get_pi_model();
-        let serial = get_serial_number();
-        let firmware = get_firmware_version();
+    fn render(&mut self, _snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        let pi_model = data.pi_model();
+        let serial = data.serial_number();
+        let firmware = data.firmware_version();
         
-        // Extract model name (remove "Raspberry Pi" prefix if present)
-        let short_model = if pi_model.starts_with("Raspberry Pi ") {
-            pi_model.strip_prefix("Raspberry Pi ").unwrap_or(&pi_model)
-        } else {
-            &pi_model
-        };
+        // Extract model name (remove "Raspberry Pi" prefix if present), without the
+        // revision, which the RAM line shows
+        let short_model = pi_model.strip_prefix("Raspberry Pi ").unwrap_or(&pi_model);
+        let short_model = short_model.split(" Rev ").next().unwrap_or(short_model);
         
         // Truncate serial to last 8 characters if longer
         let short_serial = if serial.len() > 8 {
@@ -139,82 +586,674 @@ impl Screen for HardwareScreen {
         };
         
         // Extract year from firmware version if it contains a date
-        let short_firmware = if firmware.contains("202") {
-            // Try to find a 4-digit year
-            if let Some(year_pos) = firmware.find("202") {
-                firmware[year_pos..year_pos + 4].to_string()
-            } else {
-                firmware
-            }
-        } else {
-            firmware
+        let short_firmware = match firmware.find("202") {
+            Some(year_pos) if firmware.len() >= year_pos + 4 => &firmware[year_pos..year_pos + 4],
+            _ => &firmware,
         };
         
+        // e.g. "RAM: 4GB, rev 1.4"; the model line already names the board
+        let board = some_or_placeholder(data.revision_info(), |info| format!("{}, rev {}", info.memory(), info.revision));
+
         Ok(format!(
-            "Model: {}\nSerial: {}\nFW: {}",
-            short_model, short_serial, short_firmware
+            "Model: {}\nSerial: {}\nFW: {}\nRAM: {}",
+            short_model, short_serial, short_firmware, board
         ))
     }
 }
 
 // Temperature information screen
-pub struct TemperatureScreen;
+pub struct TemperatureScreen {
+    pub max_columns: usize,
+    // Degrees from the trip point below which the margin line is marked
+    pub margin_warning: f32,
+}
+
+impl Default for TemperatureScreen {
+    fn default() -> Self {
+        Self { max_columns: LINE_CHARS, margin_warning: DEFAULT_THERMAL_MARGIN_WARNING }
+    }
+}
+
+const THROTTLE_LABEL: &str = "Throttle: ";
 
 impl Screen for TemperatureScreen {
     fn name(&self) -> &'static str {
         "temperature"
     }
     
-    fn render(&self, _sys: &System) -> Result<String> {
-        let cpu_temp = get_cpu_temp().unwrap_or_else(|_| "N/A".to_string());
-        let gpu_temp = get_gpu_temp();
-        let cpu_freq = get_cpu_freq();
-        let throttle = get_throttle_status();
-        
-        // Truncate throttle status if too long
-        let short_throttle = if throttle.len() > 20 {
-            format!("{}...", &throttle[..17])
-        } else {
-            throttle
+    fn render(&mut self, snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        let cpu_temp = some_or_placeholder(snapshot.cpu_temp_celsius, format_cpu_temp);
+        let gpu_temp = some_or_placeholder(snapshot.gpu_temp_celsius, format_gpu_temp);
+        let cpu_freq = some_or_placeholder(snapshot.cpu_freq_hz, format_cpu_freq);
+        // The conditions present now, as codes when the descriptions do not fit the line
+        let throttle = some_or_placeholder(snapshot.throttle_flags(), |flags| {
+            let text = flags.current.to_string();
+            if THROTTLE_LABEL.len() + text.chars().count() <= self.max_columns { text } else { flags.current.codes() }
+        });
+        let throttle_line = match snapshot.thermal_margin() {
+            // Until something throttles, how far the CPU is from the kernel throttling it,
+            // on machines with a trip point; a row of its own would not fit below the title
+            Some(margin) if snapshot.throttle_flags().is_none_or(|flags| flags.current.is_empty()) => {
+                let line = format!("Trip margin: {}", format_temp(margin));
+                if margin < self.margin_warning { format!("! {}", line) } else { line }
+            }
+            _ => format!("{}{}", THROTTLE_LABEL, throttle),
         };
-        
-        Ok(format!(
-            "CPU: {} ({})\nGPU: {}\nThrottle: {}",
-            cpu_temp, cpu_freq, gpu_temp, short_throttle
-        ))
+
+        // No parentheses around the clock, so "CPU: 85.0°C 1800 MHz" stays within a line
+        let mut content = format!("CPU: {} {}\nGPU: {}\n{}", cpu_temp, cpu_freq, gpu_temp, throttle_line);
+        // Under-voltage now and then is the usual sign of a weak power supply
+        if let Some(events) = data.throttle_events() {
+            content.push_str(&format!("\nUV events: {} (24h)", events.count(ThrottleConditions::UNDER_VOLTAGE)));
+        }
+        Ok(content)
+    }
+
+    fn refresh_requirements(&self) -> RefreshRequirements {
+        RefreshRequirements::CPU_TEMP
+            | RefreshRequirements::GPU_TEMP
+            | RefreshRequirements::CPU_FREQ
+            | RefreshRequirements::THROTTLE
     }
 }
 
 // GPIO and sensor information screen
-pub struct GPIOScreen;
+pub struct GPIOScreen {
+    // Probing writes to every address, so it is only done when asked for
+    i2c_probe: bool,
+    // Empty for the bus on the header, which is not i2c-1 on every Pi
+    i2c_buses: Vec<u8>,
+    w1_family: Option<u8>,
+    max_columns: usize,
+}
+
+impl GPIOScreen {
+    pub fn new(i2c_probe: bool, i2c_buses: Vec<u8>, w1_family: Option<u8>, max_columns: usize) -> Self {
+        Self { i2c_probe, i2c_buses, w1_family, max_columns }
+    }
+
+    // Devices per bus, labelled with the bus number when there is more than one
+    fn i2c_summary(&self, data: &dyn DataSource) -> String {
+        if !self.i2c_probe {
+            return "off".to_string();
+        }
+        let buses = if self.i2c_buses.is_empty() { vec![data.header_i2c_bus()] } else { self.i2c_buses.clone() };
+        let labelled = buses.len() > 1;
+        buses.iter().map(|&bus| {
+            let devices = data.i2c_devices(bus)
+                .map(|devices| format_i2c_devices(&devices))
+                .unwrap_or_else(|_| placeholder().to_string());
+            if labelled { format!("{}: {}", bus, devices) } else { devices }
+        }).collect::<Vec<_>>().join(" | ")
+    }
+}
 
 impl Screen for GPIOScreen {
     fn name(&self) -> &'static str {
         "gpio"
     }
     
-    fn render(&self, _sys: &System) -> Result<String> {
-        let i2c_devices = get_i2c_devices();
-        let gpio_states = get_gpio_states();
-        let spi_devices = get_spi_devices();
-        let wire_sensors = get_1wire_sensors();
-        
+    fn render(&mut self, _snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        let i2c_devices = self.i2c_summary(data);
+        let spi_devices = data.spi_devices();
+        let wire_sensors = data.one_wire_sensors(self.w1_family);
+
         // Truncate long lists
-        let short_i2c = if i2c_devices.len() > 15 {
-            format!("{}...", &i2c_devices[..12])
-        } else {
-            i2c_devices
-        };
-        
-        let short_gpio = if gpio_states.len() > 20 {
-            format!("{}...", &gpio_states[..17])
-        } else {
-            gpio_states
-        };
-        
+        let mut lines = vec![fit_line("I2C: ", &i2c_devices, self.max_columns)];
+        // Watched inputs, one per line, in place of the exported pins
+        let inputs = data.gpio_inputs();
+        if inputs.is_empty() {
+            lines.push(fit_line("GPIO: ", &data.gpio_states(), self.max_columns));
+        }
+        lines.extend(inputs.iter().map(|(label, state)| fit_line(&format!("{}: ", label), state, self.max_columns)));
+        lines.extend([
+            fit_line("SPI: ", &spi_devices, self.max_columns),
+            fit_line("1-Wire: ", &wire_sensors, self.max_columns),
+        ]);
+        Ok(lines.join("\n"))
+    }
+
+    fn is_available(&self) -> bool {
+        has_expansion_buses()
+    }
+}
+
+// Build and runtime information screen
+pub struct AboutScreen;
+
+impl Screen for AboutScreen {
+    fn name(&self) -> &'static str {
+        "about"
+    }
+
+    fn title(&self, _data: &dyn DataSource) -> Result<String> {
+        Ok("About".to_string())
+    }
+
+    fn render(&mut self, _snapshot: &SystemSnapshot, _data: &dyn DataSource) -> Result<String> {
+        // Drop the vendor part so the triple fits on one line (aarch64-linux-gnu)
+        let target = env!("INFO_DISPLAY_BUILD_TARGET").replace("-unknown", "");
+
         Ok(format!(
-            "I2C: {}\nGPIO: {}\nSPI: {}\n1-Wire: {}",
-            short_i2c, short_gpio, spi_devices, wire_sensors
+            "Version: {}\nBuilt: {}\nTarget:\n{}",
+            env!("CARGO_PKG_VERSION"),
+            env!("INFO_DISPLAY_BUILD_DATE"),
+            target
         ))
     }
-}
\ No newline at end of file
+
+    fn max_duration(&self) -> Option<Duration> {
+        // Static content, no need to keep it up for long
+        Some(Duration::from_secs(5))
+    }
+}
+
+// Full-height clock screen
+pub struct ClockScreen;
+
+impl Screen for ClockScreen {
+    fn name(&self) -> &'static str {
+        "clock"
+    }
+
+    fn render(&mut self, _snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        Ok(clock_content(Local::now(), data.time_synced()))
+    }
+
+    fn show_title(&self) -> bool {
+        false
+    }
+}
+
+// The time, with a warning in the gap below it while the clock may still be off
+fn clock_content(now: DateTime<Local>, synced: Option<bool>) -> String {
+    let warning = if synced == Some(false) { "Time not synced" } else { "" };
+    format!("{}\n{}\n{}\n{}", now.format("%H:%M:%S"), warning, now.format("%A"), now.format("%d %B %Y"))
+}
+
+// All thermal zones reported by the kernel (works on non-Pi boards too)
+pub struct ThermalScreen;
+
+// Leaves room for " -12.3°C" on a 21 character line
+const THERMAL_ZONE_NAME_WIDTH: usize = 13;
+
+impl Screen for ThermalScreen {
+    fn name(&self) -> &'static str {
+        "thermal"
+    }
+
+    fn title(&self, _data: &dyn DataSource) -> Result<String> {
+        Ok("Thermal".to_string())
+    }
+
+    fn render(&mut self, _snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        let zones = data.thermal_zones();
+        if zones.is_empty() {
+            return Ok("No thermal zones".to_string());
+        }
+
+        let lines: Vec<String> = zones.iter()
+            .map(|(zone_type, temp)| {
+                let short_name: String = zone_type.chars().take(THERMAL_ZONE_NAME_WIDTH).collect();
+                format!("{:<width$} {}", short_name, format_temp(*temp), width = THERMAL_ZONE_NAME_WIDTH)
+            })
+            .collect();
+
+        Ok(lines.join("\n"))
+    }
+}
+
+// Enclosure temperature and humidity from a DHT22
+pub struct AmbientScreen {
+    pub sensor: Dht22Sensor,
+}
+
+impl Screen for AmbientScreen {
+    fn name(&self) -> &'static str {
+        "ambient"
+    }
+
+    fn title(&self, _data: &dyn DataSource) -> Result<String> {
+        Ok("Ambient".to_string())
+    }
+
+    fn render(&mut self, _snapshot: &SystemSnapshot, _data: &dyn DataSource) -> Result<String> {
+        let Some((reading, age)) = self.sensor.read() else {
+            return Ok(format!("Temp: {0}\nHumidity: {0}\nGPIO {1}", placeholder(), self.sensor.pin()));
+        };
+
+        let mut lines = vec![
+            format!("Temp: {}", format_temp(reading.temperature_celsius)),
+            format!("Humidity: {:.1}%", reading.humidity_percent),
+        ];
+        if !age.is_zero() {
+            lines.push(format!("Last read {}s ago", age.as_secs()));
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+// Charge of a UPS/battery HAT
+pub struct BatteryScreen {
+    pub hat: BatteryHat,
+    pub address: u8,
+}
+
+impl Screen for BatteryScreen {
+    fn name(&self) -> &'static str {
+        "battery"
+    }
+
+    fn title(&self, _data: &dyn DataSource) -> Result<String> {
+        Ok("Battery".to_string())
+    }
+
+    fn render(&mut self, snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        // No HAT fitted is a normal situation, not a screen failure
+        let status = match read_battery(self.hat, data.header_i2c_bus(), self.address) {
+            Ok(status) => status.describe(),
+            Err(e) => {
+                debug!("Battery read failed: {}", e);
+                format!("Batt: {}", placeholder())
+            }
+        };
+        // An unattended Pi that browned out shows it here until the next reboot
+        let since_boot = some_or_placeholder(snapshot.throttle_flags(), |flags| flags.since_boot.codes());
+        Ok(format!("{}\n{} 0x{:02X}\nSince boot: {}", status, self.hat.label(), self.address, since_boot))
+    }
+
+    fn refresh_requirements(&self) -> RefreshRequirements {
+        RefreshRequirements::THROTTLE
+    }
+}
+
+// QR code of the device URL, so phones can connect without typing the address
+pub struct QrScreen {
+    pub content: Option<String>,
+}
+
+// Light modules around the code; the spec asks for 4, which would not fit
+const QR_QUIET_ZONE: u32 = 2;
+// Characters of FONT_6X10 that fit beside a 64px code
+const QR_TEXT_WIDTH: usize = 10;
+
+impl QrScreen {
+    // None when there is nothing to encode, i.e. no content configured and no IP address
+    fn payload(&self, data: &dyn DataSource) -> Option<String> {
+        if let Some(content) = &self.content {
+            return Some(content.clone());
+        }
+        let ip = ip_address_or_na(data);
+        (ip != placeholder()).then(|| format!("http://{}", ip))
+    }
+}
+
+impl Screen for QrScreen {
+    fn name(&self) -> &'static str {
+        "qr"
+    }
+
+    fn show_title(&self) -> bool {
+        false
+    }
+
+    fn render(&mut self, _snapshot: &SystemSnapshot, data: &dyn DataSource) -> Result<String> {
+        let Some(payload) = self.payload(data) else {
+            return Ok("No IP\naddress".to_string());
+        };
+
+        // The code takes the right half; wrap the text into the left half
+        let chars: Vec<char> = payload.trim_start_matches("http://").chars().collect();
+        let lines: Vec<String> = chars.chunks(QR_TEXT_WIDTH)
+            .map(|chunk| chunk.iter().collect())
+            .collect();
+        Ok(lines.join("\n"))
+    }
+
+    fn draw(&self, data: &dyn DataSource, canvas: &mut FrameBuffer) -> Result<()> {
+        let Some(payload) = self.payload(data) else {
+            return Ok(());
+        };
+
+        let code = QrCode::new(payload.as_bytes())?;
+        let modules = code.width() as u32;
+        let scale = framebuffer::HEIGHT / (modules + 2 * QR_QUIET_ZONE);
+        if scale == 0 {
+            anyhow::bail!("QR content too long to fit the display: {} modules", modules);
+        }
+
+        // Dark modules on a lit background, so scanners see the usual polarity
+        let size = (modules + 2 * QR_QUIET_ZONE) * scale;
+        let origin = Point::new((framebuffer::WIDTH - size) as i32, ((framebuffer::HEIGHT - size) / 2) as i32);
+        Rectangle::new(origin, Size::new(size, size))
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(canvas)?;
+
+        let dark = PrimitiveStyle::with_fill(BinaryColor::Off);
+        for (i, color) in code.to_colors().into_iter().enumerate() {
+            if color == Color::Dark {
+                let x = (i as u32 % modules + QR_QUIET_ZONE) * scale;
+                let y = (i as u32 / modules + QR_QUIET_ZONE) * scale;
+                Rectangle::new(origin + Point::new(x as i32, y as i32), Size::new(scale, scale))
+                    .into_styled(dark)
+                    .draw(canvas)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::system_info::{DemoDataSource, ProcessSummary, WifiBlock};
+    use sysinfo::System;
+
+    // Every reading the demo source has, as the display loop would collect them
+    fn demo_snapshot() -> SystemSnapshot {
+        DemoDataSource::new().snapshot(&System::new(), RefreshRequirements::ALL)
+    }
+
+    fn system_screen() -> SystemScreen {
+        SystemScreen { uptime: UptimeFormat::default(), process_warning_count: DEFAULT_PROCESS_WARNING_COUNT }
+    }
+
+    #[test]
+    fn test_network_renders_without_ip() {
+        let content = NetworkScreen::default().render(&demo_snapshot(), &DemoDataSource::new().without_network()).unwrap();
+        assert_eq!(content.lines().nth(1), Some(placeholder()));
+    }
+
+    #[test]
+    fn test_network_shows_wifi_block() {
+        let data = DemoDataSource::new().without_network().with_wifi_block(WifiBlock::NoCountry);
+        let content = NetworkScreen::default().render(&demo_snapshot(), &data).unwrap();
+        assert_eq!(content.lines().skip(1).take(2).collect::<Vec<_>>(), ["WiFi BLOCKED", "(no country)"]);
+
+        let mut screen = NetworkScreen { interface: Some("wlan0".to_string()), ..NetworkScreen::default() };
+        let data = DemoDataSource::new().with_wifi_block(WifiBlock::HardBlocked);
+        // The demo interface has an address, so the block is no cause for concern
+        assert_eq!(screen.render(&demo_snapshot(), &data).unwrap().lines().nth(1), Some("192.168.1.42"));
+    }
+
+    #[test]
+    fn test_gpio_i2c_line() {
+        let i2c_line = |mut screen: GPIOScreen| screen.render(&demo_snapshot(), &DemoDataSource::new()).unwrap().lines().next().unwrap().to_string();
+        assert_eq!(i2c_line(GPIOScreen::new(false, vec![1], None, LINE_CHARS)), "I2C: off");
+        assert_eq!(i2c_line(GPIOScreen::new(true, vec![1], None, LINE_CHARS)), "I2C: 0x3c, 0x70");
+        assert!(i2c_line(GPIOScreen::new(true, vec![1, 3], None, LINE_CHARS)).starts_with("I2C: 1: 0x3c"));
+        // No buses given: the one on the header
+        assert_eq!(i2c_line(GPIOScreen::new(true, Vec::new(), None, LINE_CHARS)), "I2C: 0x3c, 0x70");
+    }
+
+    #[test]
+    fn test_long_hostname_is_truncated() {
+        let hostname = "a-very-long-hostname-for-the-garage-pi-4";
+        assert_eq!(hostname.len(), 40);
+
+        let line = truncate_chars(&format!("{}.example.com", hostname), LINE_CHARS);
+        assert_eq!(line, "a-very-long-hostna...");
+        assert_eq!(line.chars().count(), LINE_CHARS);
+        assert_eq!(truncate_chars(hostname, TITLE_CHARS), "a-very-long-hos...");
+        // Cut by characters, not bytes
+        assert_eq!(truncate_chars("pi-küche-wohnzimmer-ost-1", LINE_CHARS), "pi-küche-wohnzimme...");
+        assert_eq!(truncate_chars("raspberrypi.local", LINE_CHARS), "raspberrypi.local");
+    }
+
+    #[test]
+    fn test_battery_shows_since_boot_flags() {
+        let mut screen = BatteryScreen { hat: BatteryHat::default(), address: 0x57 };
+        let content = screen.render(&demo_snapshot(), &DemoDataSource::new()).unwrap();
+        assert_eq!(content.lines().last(), Some("Since boot: None"));
+    }
+
+    #[test]
+    fn test_connections_screen() {
+        let content = ConnectionsScreen.render(&demo_snapshot(), &DemoDataSource::new()).unwrap();
+        assert_eq!(content, "TCP established: 7");
+    }
+
+    #[test]
+    fn test_diagnostics_screen() {
+        let content = DiagnosticsScreen { max_columns: LINE_CHARS }.render(&demo_snapshot(), &DemoDataSource::new()).unwrap();
+        assert_eq!(content, "vcgencmd 3: did no...");
+    }
+
+    #[test]
+    fn test_network_ip_version() {
+        let lines = |ip_version| {
+            let mut screen = NetworkScreen { ip_version, ..NetworkScreen::default() };
+            screen.render(&demo_snapshot(), &DemoDataSource::new()).unwrap().lines().map(String::from).collect::<Vec<_>>()
+        };
+        assert_eq!(lines(IpVersion::V4)[1], "192.168.1.42");
+        assert_eq!(lines(IpVersion::V6)[1], "2001:db8:...:370:7334");
+        assert_eq!(lines(IpVersion::Both)[1..3], ["192.168.1.42", "2001:db8:...:370:7334"]);
+        assert_eq!(lines(IpVersion::Both).len(), 5);
+
+        assert_eq!(IpVersion::from_name("Both"), Some(IpVersion::Both));
+        assert_eq!(IpVersion::from_name("v5"), None);
+    }
+
+    #[test]
+    fn test_network_shows_link_speed() {
+        let content = NetworkScreen::default().render(&demo_snapshot(), &DemoDataSource::new()).unwrap();
+        assert_eq!(content.lines().last(), Some("eth0 1Gb FD"));
+        let mut screen = NetworkScreen { interface: Some("eth1".to_string()), ..NetworkScreen::default() };
+        assert_eq!(screen.render(&demo_snapshot(), &DemoDataSource::new()).unwrap().lines().last(), Some("eth1 1Gb FD"));
+    }
+
+    #[test]
+    fn test_debug_lines() {
+        let timings = LoopTimings {
+            renders: vec![
+                ("network".to_string(), Duration::from_micros(3200)),
+                ("temperature".to_string(), Duration::from_millis(48)),
+                ("clock".to_string(), Duration::from_micros(150)),
+                ("storage".to_string(), Duration::from_millis(12)),
+            ],
+            last_update: Some(Duration::from_millis(61)),
+            last_cycle: Some(Duration::from_millis(5061)),
+        };
+        assert_eq!(debug_lines(&timings, 13), "Loop 61ms/5.1s\ntempe... 48ms\nstorage 12ms\nnetwork 3.2ms");
+        assert_eq!(debug_lines(&LoopTimings::default(), LINE_CHARS), format!("Loop {0}/{0}", placeholder()));
+    }
+
+    #[test]
+    fn test_system_shows_reboot_notice() {
+        let content = system_screen().render(&demo_snapshot(), &DemoDataSource::new()).unwrap();
+        assert_eq!(content.lines().count(), 3);
+        assert_eq!(content.lines().next(), Some("CPU: 42.0°C 12%"));
+
+        let content = system_screen().render(&demo_snapshot(), &DemoDataSource::new().with_reboot_notice("Reboot in 12m")).unwrap();
+        assert_eq!(content.lines().last(), Some("! Reboot in 12m"));
+    }
+
+    #[test]
+    fn test_system_process_warning() {
+        let busy = ProcessSummary { total: 812, ..ProcessSummary::default() };
+        let snapshot = SystemSnapshot { processes: Some(busy.clone()), ..demo_snapshot() };
+        let content = system_screen().render(&snapshot, &DemoDataSource::new().with_reboot_notice("Reboot in 12m")).unwrap();
+        assert_eq!(content.lines().last(), Some("! 812 procs"));
+        let mut screen = SystemScreen { process_warning_count: 1000, ..system_screen() };
+        assert_eq!(screen.render(&snapshot, &DemoDataSource::new()).unwrap().lines().count(), 3);
+
+        let snapshot = SystemSnapshot { processes: Some(ProcessSummary { total: 140, zombies: 3, ..busy }), ..snapshot };
+        assert_eq!(screen.render(&snapshot, &DemoDataSource::new()).unwrap().lines().last(), Some("! 140 procs 3 zombie"));
+    }
+
+    #[test]
+    fn test_overview_renders_without_ip() {
+        let mut screen = OverviewScreen { show_percent: false, fields: OverviewField::ALL.to_vec(), uptime: UptimeFormat::default() };
+        let content = screen.render(&demo_snapshot(), &DemoDataSource::new().without_network()).unwrap();
+        assert_eq!(content.lines().next(), Some(placeholder()));
+    }
+
+    #[test]
+    fn test_overview_with_demo_data() {
+        let mut screen = OverviewScreen { show_percent: false, fields: OverviewField::ALL.to_vec(), uptime: UptimeFormat::default() };
+        let demo = DemoDataSource::new();
+        assert_eq!(screen.title(&demo).unwrap(), "raspberrypi");
+        assert_eq!(
+            screen.render(&demo_snapshot(), &demo).unwrap(),
+            "192.168.1.42\n42.0°C\n512/1024MB\n12/29GB\nUp: 3d4h12m"
+        );
+    }
+
+    #[test]
+    fn test_uptime_styles() {
+        let now = Local.with_ymd_and_hms(2024, 5, 4, 13, 42, 0).unwrap();
+        let compact = UptimeFormat::default();
+        assert_eq!(compact.line("Up", Some(Duration::from_secs(252)), now), "Up: 4m12s");
+        assert_eq!(compact.line("Up", None, now), format!("Up: {}", placeholder()));
+
+        let since = UptimeFormat { style: UptimeStyle::Since, date_format: "%Y-%m-%d %H:%M".to_string() };
+        assert_eq!(since.line("Up", Some(Duration::from_secs(3 * 86400 + 4 * 3600 + 12 * 60)), now), "Since: 2024-05-01 09:30");
+        assert_eq!(UptimeStyle::from_name(" Since"), Some(UptimeStyle::Since));
+        assert_eq!(UptimeStyle::from_name("boot"), None);
+    }
+
+    #[test]
+    fn test_overview_field_order() {
+        let mut screen = OverviewScreen {
+            show_percent: true,
+            fields: vec![OverviewField::Uptime, OverviewField::Mem],
+            uptime: UptimeFormat::default(),
+        };
+        let content = screen.render(&demo_snapshot(), &DemoDataSource::new()).unwrap();
+        assert_eq!(content, "Up: 3d4h12m\n512/1024MB (50%)");
+        // Only the readings of the lines it shows are collected
+        assert_eq!(screen.refresh_requirements(), RefreshRequirements::UPTIME | RefreshRequirements::MEMORY);
+    }
+
+    #[test]
+    fn test_missing_readings_show_the_placeholder() {
+        let snapshot = SystemSnapshot { cpu_temp_celsius: Some(48.5), ..SystemSnapshot::default() };
+        let content = TemperatureScreen::default().render(&snapshot, &DemoDataSource::new()).unwrap();
+        assert_eq!(content, format!("CPU: 48.5°C {0}\nGPU: {0}\nThrottle: {0}\nUV events: 3 (24h)", placeholder()));
+    }
+
+    #[test]
+    fn test_temperature_trip_margin() {
+        let snapshot = SystemSnapshot {
+            cpu_temp_celsius: Some(52.3),
+            cpu_trip_celsius: Some(80.0),
+            throttled: Some(0),
+            ..SystemSnapshot::default()
+        };
+        let mut screen = TemperatureScreen::default();
+        assert_eq!(screen.render(&snapshot, &DemoDataSource::new()).unwrap().lines().nth(2), Some("Trip margin: 27.7°C"));
+
+        let snapshot = SystemSnapshot { cpu_temp_celsius: Some(76.0), ..snapshot };
+        assert_eq!(screen.render(&snapshot, &DemoDataSource::new()).unwrap().lines().nth(2), Some("! Trip margin: 4.0°C"));
+        screen.margin_warning = 3.0;
+        assert_eq!(screen.render(&snapshot, &DemoDataSource::new()).unwrap().lines().nth(2), Some("Trip margin: 4.0°C"));
+
+        // Once throttled, that is what matters
+        let snapshot = SystemSnapshot { throttled: Some(0x4), ..snapshot };
+        assert_eq!(screen.render(&snapshot, &DemoDataSource::new()).unwrap().lines().nth(2), Some("Throttle: TH"));
+    }
+
+    #[test]
+    fn test_clock_warns_when_time_not_synced() {
+        let now = Local.with_ymd_and_hms(2025, 3, 7, 9, 5, 0).unwrap();
+        assert_eq!(clock_content(now, Some(true)), "09:05:00\n\nFriday\n07 March 2025");
+        assert_eq!(clock_content(now, None), "09:05:00\n\nFriday\n07 March 2025");
+        assert_eq!(clock_content(now, Some(false)), "09:05:00\nTime not synced\nFriday\n07 March 2025");
+    }
+
+    #[test]
+    fn test_about_screen() {
+        let content = AboutScreen.render(&demo_snapshot(), &DemoDataSource::new()).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[0], format!("Version: {}", env!("CARGO_PKG_VERSION")));
+        assert_eq!(lines[2], "Target:");
+        assert!(!lines[3].contains("-unknown"));
+        assert!(lines.iter().all(|line| line.chars().count() <= LINE_CHARS));
+
+        // The build date comes from SOURCE_DATE_EPOCH when the build sets it
+        let built = lines[1].strip_prefix("Built: ").unwrap();
+        let date = chrono::NaiveDate::parse_from_str(built, "%Y-%m-%d").unwrap();
+        if let Some(epoch) = option_env!("SOURCE_DATE_EPOCH").and_then(|epoch| epoch.parse().ok()) {
+            assert_eq!(Some(date), DateTime::from_timestamp(epoch, 0).map(|time| time.date_naive()));
+        }
+
+        // Static content, so it gives way to the next screen early
+        assert_eq!(AboutScreen.max_duration(), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_hardware_screen() {
+        let content = HardwareScreen.render(&demo_snapshot(), &DemoDataSource::new()).unwrap();
+        assert_eq!(content, "Model: 4 Model B\nSerial: c0ffee42\nFW: 2024\nRAM: 4GB, rev 1.4");
+    }
+
+    #[test]
+    fn test_storage_inode_warning() {
+        let mut screen = StorageScreen { show_percent: false, inode_warning_percent: 90 };
+        // The demo root filesystem is far from its inode limit
+        let content = screen.render(&demo_snapshot(), &DemoDataSource::new()).unwrap();
+        assert_eq!(content.lines().count(), 3);
+
+        let snapshot = SystemSnapshot { inodes_used: Some(23), inodes_total: Some(25), ..demo_snapshot() };
+        let content = screen.render(&snapshot, &DemoDataSource::new()).unwrap();
+        assert_eq!(content.lines().last(), Some("Inode: 92%"));
+    }
+
+    #[test]
+    fn test_readonly_mount_warning() {
+        let snapshot = SystemSnapshot {
+            inodes_used: Some(23),
+            inodes_total: Some(25),
+            readonly_mounts: Some(vec!["/".to_string()]),
+            ..demo_snapshot()
+        };
+        let mut storage = StorageScreen { show_percent: false, inode_warning_percent: 90 };
+        assert_eq!(storage.render(&snapshot, &DemoDataSource::new()).unwrap().lines().last(), Some("/: READ-ONLY!"));
+
+        let snapshot = SystemSnapshot { readonly_mounts: Some(vec!["/".to_string(), "/boot/firmware".to_string()]), ..snapshot };
+        let content = system_screen().render(&snapshot, &DemoDataSource::new().with_reboot_notice("Reboot in 12m")).unwrap();
+        assert_eq!(content.lines().last(), Some("/: READ-ONLY! +1"));
+    }
+
+    #[test]
+    fn test_overview_field_from_name() {
+        assert_eq!(OverviewField::from_name("temp"), Some(OverviewField::Temp));
+        assert_eq!(OverviewField::from_name(" ip "), Some(OverviewField::Ip));
+        assert_eq!(OverviewField::from_name("cpu"), None);
+    }
+
+    #[test]
+    fn test_qr_screen_default_payload() {
+        let mut screen = QrScreen { content: None };
+        assert_eq!(screen.render(&demo_snapshot(), &DemoDataSource::new()).unwrap(), "192.168.1.\n42");
+
+        let mut canvas = FrameBuffer::default();
+        screen.draw(&DemoDataSource::new(), &mut canvas).unwrap();
+        // Code in the right half, text side left dark
+        assert!(canvas.pixels().any(|Pixel(point, color)| point.x >= 64 && color.is_on()));
+        assert!(!canvas.pixels().any(|Pixel(point, color)| point.x < 64 && color.is_on()));
+    }
+
+    #[test]
+    fn test_qr_screen_without_ip() {
+        let mut screen = QrScreen { content: None };
+        assert_eq!(screen.render(&demo_snapshot(), &DemoDataSource::new().without_network()).unwrap(), "No IP\naddress");
+
+        let mut canvas = FrameBuffer::default();
+        screen.draw(&DemoDataSource::new().without_network(), &mut canvas).unwrap();
+        assert_eq!(canvas, FrameBuffer::default());
+
+        // Configured content does not need the network
+        let screen = QrScreen { content: Some("https://example.com/kiosk".to_string()) };
+        let mut canvas = FrameBuffer::default();
+        screen.draw(&DemoDataSource::new().without_network(), &mut canvas).unwrap();
+        assert_ne!(canvas, FrameBuffer::default());
+    }
+
+    #[test]
+    fn test_qr_screen_content_too_long() {
+        let screen = QrScreen { content: Some("x".repeat(500)) };
+        assert!(screen.draw(&DemoDataSource::new(), &mut FrameBuffer::default()).is_err());
+    }
+}
@@ -0,0 +1,575 @@
+use std::env;
+use std::io::Read;
+use std::net::{Ipv6Addr, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ssh2::{CheckResult, KnownHostFileKind, Session};
+use sysinfo::System;
+use tracing::{info, warn};
+
+use crate::config::RemoteHostConfig;
+use crate::framebuffer::FrameBuffer;
+use crate::screens::Screen;
+use crate::system_info::hardware::hash_id;
+use crate::system_info::{
+    placeholder, DataSource, DeviceIdentity, InterfaceInfo, RefreshRequirements, RevisionInfo, SourceFailures, SystemSnapshot,
+    ThrottleEventSummary, ThrottleFlags, Usage, WifiBlock,
+};
+
+// Readings older than this many polls count as unreachable, but never less than the minimum
+const STALE_POLLS: u32 = 3;
+const MIN_STALE_AFTER: Duration = Duration::from_secs(30);
+
+// Everything is read with one command, one section per separator line
+const SECTION_SEPARATOR: &str = "@@";
+const COLLECT_COMMAND: &str = "hostname; echo @@; \
+    hostname -I 2>/dev/null; echo @@; \
+    cat /proc/uptime; echo @@; \
+    cat /proc/meminfo; echo @@; \
+    for zone in /sys/class/thermal/thermal_zone*; do \
+        [ -r \"$zone/temp\" ] && echo \"$(cat \"$zone/type\") $(cat \"$zone/temp\")\"; \
+    done; echo @@; \
+    df -P -B1 -x tmpfs -x devtmpfs -x squashfs -x overlay 2>/dev/null";
+
+/// What one collection read from the remote host.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemoteReadings {
+    pub hostname: String,
+    pub ip_addresses: Vec<String>,
+    pub uptime_secs: Option<f64>,
+    pub memory_used_bytes: u64,
+    pub memory_total_bytes: u64,
+    pub swap_used_bytes: u64,
+    pub swap_total_bytes: u64,
+    pub thermal_zones: Vec<(String, f32)>,
+    pub disk_used_bytes: u64,
+    pub disk_total_bytes: u64,
+}
+
+impl RemoteReadings {
+    // The zone that looks like the CPU package, otherwise the first one
+    fn cpu_temp(&self) -> Option<f32> {
+        const CPU_ZONES: [&str; 3] = ["cpu", "x86_pkg_temp", "k10temp"];
+        self.thermal_zones.iter()
+            .find(|(zone, _)| CPU_ZONES.iter().any(|cpu| zone.contains(cpu)))
+            .or(self.thermal_zones.first())
+            .map(|(_, temp)| *temp)
+    }
+}
+
+/// Parse the output of COLLECT_COMMAND.
+pub fn parse_readings(output: &str) -> Result<RemoteReadings> {
+    let sections: Vec<&str> = output.split(&format!("{}\n", SECTION_SEPARATOR)).collect();
+    let [hostname, ips, uptime, meminfo, zones, df] = sections[..] else {
+        bail!("unexpected output with {} sections", sections.len());
+    };
+
+    let meminfo_kb = |key: &str| -> Option<u64> {
+        meminfo.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+            .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+    };
+    let memory_total = meminfo_kb("MemTotal").context("MemTotal missing from /proc/meminfo")?;
+    let memory_available = meminfo_kb("MemAvailable").unwrap_or(memory_total);
+    let swap_total = meminfo_kb("SwapTotal").unwrap_or(0);
+    let swap_free = meminfo_kb("SwapFree").unwrap_or(swap_total);
+
+    let thermal_zones = zones.lines()
+        .filter_map(|line| {
+            let (zone, millidegrees) = line.rsplit_once(' ')?;
+            Some((zone.to_string(), millidegrees.trim().parse::<i32>().ok()? as f32 / 1000.0))
+        })
+        .collect();
+
+    // Size and used columns of every filesystem, after the header
+    let (disk_used, disk_total) = df.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            Some((columns.get(2)?.parse::<u64>().ok()?, columns.get(1)?.parse::<u64>().ok()?))
+        })
+        .fold((0, 0), |(used, total), (disk_used, disk_total)| (used + disk_used, total + disk_total));
+
+    Ok(RemoteReadings {
+        hostname: hostname.trim().to_string(),
+        ip_addresses: ips.split_whitespace().map(String::from).collect(),
+        uptime_secs: uptime.split_whitespace().next().and_then(|secs| secs.parse().ok()),
+        memory_used_bytes: memory_total.saturating_sub(memory_available),
+        memory_total_bytes: memory_total,
+        swap_used_bytes: swap_total.saturating_sub(swap_free),
+        swap_total_bytes: swap_total,
+        thermal_zones,
+        disk_used_bytes: disk_used,
+        disk_total_bytes: disk_total,
+    })
+}
+
+// Connect, verify the host key, authenticate and run the collection command. A new
+// session each time, so a dropped connection never needs detecting
+fn collect(config: &RemoteHostConfig) -> Result<RemoteReadings> {
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let addr = (config.host.as_str(), config.port).to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("{} did not resolve", config.host))?;
+    let tcp = TcpStream::connect_timeout(&addr, timeout)?;
+
+    let mut session = Session::new()?;
+    session.set_tcp_stream(tcp);
+    session.set_timeout(timeout.as_millis() as u32);
+    session.handshake()?;
+    verify_host_key(&session, config)?;
+
+    match &config.key {
+        Some(key) => session.userauth_pubkey_file(&config.user, None, key, None)?,
+        None => session.userauth_agent(&config.user)?,
+    }
+    if !session.authenticated() {
+        bail!("authentication as {} failed", config.user);
+    }
+
+    let mut channel = session.channel_session()?;
+    channel.exec(COLLECT_COMMAND)?;
+    let mut output = String::new();
+    channel.read_to_string(&mut output)?;
+    channel.wait_close()?;
+    parse_readings(&output)
+}
+
+fn verify_host_key(session: &Session, config: &RemoteHostConfig) -> Result<()> {
+    let path = match &config.known_hosts {
+        Some(path) => path.clone(),
+        None => PathBuf::from(env::var_os("HOME").context("HOME is not set")?).join(".ssh/known_hosts"),
+    };
+    let mut known_hosts = session.known_hosts()?;
+    known_hosts.read_file(&path, KnownHostFileKind::OpenSSH)
+        .with_context(|| format!("cannot read {}", path.display()))?;
+
+    let (key, _) = session.host_key().context("server sent no host key")?;
+    match known_hosts.check_port(&config.host, config.port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => bail!("{} is not in {}; connect once with ssh to add it", config.host, path.display()),
+        CheckResult::Mismatch => bail!("host key of {} does not match {}", config.host, path.display()),
+        CheckResult::Failure => bail!("could not check the host key of {}", config.host),
+    }
+}
+
+#[derive(Debug, Default)]
+struct RemoteState {
+    readings: Option<(RemoteReadings, Instant)>,
+    last_error: Option<String>,
+}
+
+/// A remote host's stats, collected over SSH by a background thread so that a slow or
+/// unreachable host never holds up rendering. Readings go stale after a few missed polls.
+pub struct SshDataSource {
+    host: String,
+    state: Arc<Mutex<RemoteState>>,
+    stale_after: Duration,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SshDataSource {
+    pub fn start(config: &RemoteHostConfig) -> crate::errors::Result<Self> {
+        let state = Arc::new(Mutex::new(RemoteState::default()));
+        let (stop, stopped) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("ssh-remote".to_string())
+            .spawn({
+                let config = config.clone();
+                let state = Arc::clone(&state);
+                move || run_poller(&config, &state, stopped)
+            })?;
+
+        info!("Reading {} over SSH as {} for: {}", config.host, config.user, config.screens.join(", "));
+        let poll = Duration::from_secs(config.poll_secs);
+        Ok(Self {
+            host: config.host.clone(),
+            state,
+            stale_after: poll.saturating_mul(STALE_POLLS).max(MIN_STALE_AFTER),
+            stop: Some(stop),
+            handle: Some(handle),
+        })
+    }
+
+    /// The latest readings, or why there are none that are recent enough.
+    pub fn readings(&self) -> std::result::Result<RemoteReadings, String> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match &state.readings {
+            Some((readings, taken_at)) if taken_at.elapsed() <= self.stale_after => Ok(readings.clone()),
+            _ => Err(state.last_error.clone().unwrap_or_else(|| "no data yet".to_string())),
+        }
+    }
+
+    fn with<T>(&self, read: impl FnOnce(&RemoteReadings) -> T) -> Option<T> {
+        self.readings().ok().as_ref().map(read)
+    }
+
+    // Everything the last readings have; they were all taken in one round trip
+    fn remote_snapshot(&self) -> SystemSnapshot {
+        let Ok(readings) = self.readings() else {
+            return SystemSnapshot { hostname: self.host.clone(), ..SystemSnapshot::default() };
+        };
+        let cpu_temp = readings.cpu_temp();
+        SystemSnapshot {
+            cpu_temp_celsius: cpu_temp,
+            hostname: readings.hostname,
+            ip_addresses: readings.ip_addresses,
+            gpu_temp_celsius: None,
+            cpu_temp_raw_celsius: cpu_temp,
+            gpu_temp_raw_celsius: None,
+            cpu_trip_celsius: None,
+            cpu_freq_hz: None,
+            cpu_usage_percent: None,
+            memory_used_bytes: readings.memory_used_bytes,
+            memory_total_bytes: readings.memory_total_bytes,
+            swap_used_bytes: Some(readings.swap_used_bytes),
+            swap_total_bytes: Some(readings.swap_total_bytes),
+            disk_used_bytes: (readings.disk_total_bytes > 0).then_some(readings.disk_used_bytes),
+            disk_total_bytes: (readings.disk_total_bytes > 0).then_some(readings.disk_total_bytes),
+            inodes_used: None,
+            inodes_total: None,
+            readonly_mounts: None,
+            uptime_secs: readings.uptime_secs,
+            throttled: None,
+            processes: None,
+            clock_synced: None,
+            clock_offset_ms: None,
+        }
+    }
+}
+
+impl Drop for SshDataSource {
+    fn drop(&mut self) {
+        // Closing the channel wakes the poller between collections
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run_poller(config: &RemoteHostConfig, state: &Mutex<RemoteState>, stopped: Receiver<()>) {
+    let poll = Duration::from_secs(config.poll_secs);
+    loop {
+        let result = collect(config);
+        {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            match result {
+                Ok(readings) => {
+                    if state.last_error.take().is_some() {
+                        info!("Remote host {} is reachable again", config.host);
+                    }
+                    state.readings = Some((readings, Instant::now()));
+                }
+                Err(e) => {
+                    let message = format!("{:#}", e);
+                    // Log once per distinct problem rather than every poll
+                    if state.last_error.as_ref() != Some(&message) {
+                        warn!("Cannot read remote host {}: {}", config.host, message);
+                    }
+                    state.last_error = Some(message);
+                }
+            }
+        }
+
+        match stopped.recv_timeout(poll) {
+            Err(RecvTimeoutError::Timeout) => continue,
+            _ => return,
+        }
+    }
+}
+
+impl DataSource for SshDataSource {
+    fn hostname(&self) -> String {
+        self.with(|readings| readings.hostname.clone())
+            .filter(|hostname| !hostname.is_empty())
+            .unwrap_or_else(|| self.host.clone())
+    }
+
+    fn domain(&self) -> String {
+        placeholder().to_string()
+    }
+
+    fn ip_address(&self) -> Result<String> {
+        self.with(|readings| readings.ip_addresses.first().cloned())
+            .flatten()
+            .ok_or_else(|| anyhow!("no IP address for {}", self.host))
+    }
+
+    fn ipv6_address(&self, _interface: Option<&str>) -> Result<String> {
+        self.with(|readings| readings.ip_addresses.iter()
+            .filter_map(|address| address.parse::<Ipv6Addr>().ok())
+            .find(|address| !address.is_loopback() && !address.is_unicast_link_local())
+            .map(|address| address.to_string()))
+            .flatten()
+            .ok_or_else(|| anyhow!("no IPv6 address for {}", self.host))
+    }
+
+    fn mac_address(&self) -> String {
+        placeholder().to_string()
+    }
+
+    fn link_speed(&self) -> Option<String> {
+        None
+    }
+
+    fn interface(&self, _name: &str) -> InterfaceInfo {
+        InterfaceInfo::default()
+    }
+
+    fn connection_count(&self) -> Result<usize> {
+        bail!("no connection count over SSH")
+    }
+
+    fn wifi_block(&self) -> Option<WifiBlock> {
+        None
+    }
+
+    fn cpu_temp(&self) -> Result<f32> {
+        self.with(RemoteReadings::cpu_temp)
+            .flatten()
+            .ok_or_else(|| anyhow!("no temperature for {}", self.host))
+    }
+
+    fn gpu_temp(&self) -> Result<f32> {
+        bail!("no GPU temperature over SSH")
+    }
+
+    fn cpu_freq(&self) -> Result<u64> {
+        bail!("no CPU frequency over SSH")
+    }
+
+    fn throttle_status(&self) -> Result<ThrottleFlags> {
+        bail!("no throttle status over SSH")
+    }
+
+    fn throttle_events(&self) -> Option<ThrottleEventSummary> {
+        None
+    }
+
+    fn thermal_zones(&self) -> Vec<(String, f32)> {
+        self.with(|readings| readings.thermal_zones.clone()).unwrap_or_default()
+    }
+
+    fn uptime(&self) -> Option<Duration> {
+        self.with(|readings| readings.uptime_secs.and_then(|secs| Duration::try_from_secs_f64(secs).ok()))
+            .flatten()
+    }
+
+    fn reboot_notice(&self) -> Option<String> {
+        None
+    }
+
+    fn time_synced(&self) -> Option<bool> {
+        None
+    }
+
+    fn memory_usage(&self, _sys: &System) -> Result<Usage> {
+        self.with(|readings| Usage { used: readings.memory_used_bytes, total: readings.memory_total_bytes })
+            .ok_or_else(|| anyhow!("no memory figures for {}", self.host))
+    }
+
+    fn swap_usage(&self, _sys: &System) -> Result<Option<Usage>> {
+        self.with(|readings| match readings.swap_total_bytes {
+            0 => None,
+            total => Some(Usage { used: readings.swap_used_bytes, total }),
+        })
+        .ok_or_else(|| anyhow!("no swap figures for {}", self.host))
+    }
+
+    fn disk_usage(&self) -> Result<Usage> {
+        self.with(|readings| Usage { used: readings.disk_used_bytes, total: readings.disk_total_bytes })
+            .filter(|usage| usage.total > 0)
+            .ok_or_else(|| anyhow!("no disk figures for {}", self.host))
+    }
+
+    fn pi_model(&self) -> String {
+        placeholder().to_string()
+    }
+
+    fn revision_info(&self) -> Option<RevisionInfo> {
+        None
+    }
+
+    fn serial_number(&self) -> String {
+        placeholder().to_string()
+    }
+
+    // Neither the serial nor the MAC address is read from the remote host
+    fn device_identity(&self) -> DeviceIdentity {
+        let hostname = self.hostname();
+        DeviceIdentity { id: hash_id(&hostname), hostname, serial: None, mac: None, model: self.pi_model() }
+    }
+
+    fn firmware_version(&self) -> String {
+        placeholder().to_string()
+    }
+
+    fn boot_partition(&self) -> String {
+        placeholder().to_string()
+    }
+
+    fn header_i2c_bus(&self) -> u8 {
+        1
+    }
+
+    fn i2c_devices(&self, _bus: u8) -> Result<Vec<u8>> {
+        Err(anyhow!("I2C devices are not collected from {}", self.host))
+    }
+
+    fn gpio_states(&self) -> String {
+        placeholder().to_string()
+    }
+
+    fn gpio_inputs(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn spi_devices(&self) -> String {
+        placeholder().to_string()
+    }
+
+    fn one_wire_sensors(&self, _family: Option<u8>) -> String {
+        placeholder().to_string()
+    }
+
+    fn source_failures(&self) -> Vec<SourceFailures> {
+        Vec::new()
+    }
+
+    fn snapshot(&self, _sys: &System, _needed: RefreshRequirements) -> SystemSnapshot {
+        self.remote_snapshot()
+    }
+}
+
+/// Shows a screen with the remote host's data instead of this machine's, or
+/// "remote unreachable" while there are no recent readings.
+pub struct SshScreen {
+    inner: Box<dyn Screen>,
+    source: Arc<SshDataSource>,
+}
+
+impl SshScreen {
+    pub fn new(inner: Box<dyn Screen>, source: Arc<SshDataSource>) -> Self {
+        Self { inner, source }
+    }
+}
+
+impl Screen for SshScreen {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn title(&self, _data: &dyn DataSource) -> Result<String> {
+        self.inner.title(self.source.as_ref())
+    }
+
+    fn render(&mut self, _snapshot: &SystemSnapshot, _data: &dyn DataSource) -> Result<String> {
+        match self.source.readings() {
+            Ok(_) => self.inner.render(&self.source.remote_snapshot(), self.source.as_ref()),
+            Err(_) => Ok(format!("{}\nremote unreachable", self.source.host)),
+        }
+    }
+
+    fn show_title(&self) -> bool {
+        self.inner.show_title()
+    }
+
+    fn max_duration(&self) -> Option<Duration> {
+        self.inner.max_duration()
+    }
+
+    fn draw(&self, _data: &dyn DataSource, canvas: &mut FrameBuffer) -> Result<()> {
+        if self.source.readings().is_ok() {
+            self.inner.draw(self.source.as_ref(), canvas)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screens::{SystemScreen, UptimeFormat, DEFAULT_PROCESS_WARNING_COUNT};
+    use crate::system_info::{format_disk, format_memory, format_uptime, DemoDataSource};
+
+    const OUTPUT: &str = "nas\n@@\n192.168.1.10 fd00::10 \n@@\n350000.12 700000.00\n@@\n\
+        MemTotal:       16384000 kB\nMemFree:         1000000 kB\nMemAvailable:   12288000 kB\n\
+        SwapTotal:       2097152 kB\nSwapFree:        1048576 kB\n@@\n\
+        acpitz 27800\nx86_pkg_temp 45000\n@@\n\
+        Filesystem 1-blocks Used Available Capacity Mounted on\n\
+        /dev/sda2 100000000000 40000000000 60000000000 40% /\n\
+        /dev/sda1 1000000000 500000000 500000000 50% /boot\n";
+
+    fn source(readings: Option<RemoteReadings>) -> SshDataSource {
+        SshDataSource {
+            host: "nas.lan".to_string(),
+            state: Arc::new(Mutex::new(RemoteState {
+                readings: readings.map(|readings| (readings, Instant::now())),
+                last_error: Some("connection refused".to_string()),
+            })),
+            stale_after: MIN_STALE_AFTER,
+            stop: None,
+            handle: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_readings() {
+        let readings = parse_readings(OUTPUT).unwrap();
+        assert_eq!(readings.hostname, "nas");
+        assert_eq!(readings.ip_addresses, vec!["192.168.1.10", "fd00::10"]);
+        assert_eq!(readings.uptime_secs, Some(350000.12));
+        assert_eq!(readings.memory_total_bytes, 16384000 * 1024);
+        assert_eq!(readings.memory_used_bytes, 4096000 * 1024);
+        assert_eq!(readings.swap_used_bytes, 1048576 * 1024);
+        assert_eq!(readings.thermal_zones.len(), 2);
+        assert_eq!(readings.cpu_temp(), Some(45.0));
+        assert_eq!(readings.disk_total_bytes, 101_000_000_000);
+        assert_eq!(readings.disk_used_bytes, 40_500_000_000);
+    }
+
+    #[test]
+    fn test_parse_readings_rejects_garbage() {
+        assert!(parse_readings("").is_err());
+        assert!(parse_readings("nas\n@@\n\n@@\n1 1\n@@\nMemFree: 1 kB\n@@\n@@\n").is_err());
+    }
+
+    #[test]
+    fn test_data_source() {
+        let source = source(Some(parse_readings(OUTPUT).unwrap()));
+        let sys = System::new();
+        assert_eq!(source.hostname(), "nas");
+        assert_eq!(source.cpu_temp().unwrap(), 45.0);
+        assert_eq!(source.uptime().map(format_uptime).as_deref(), Some("4d1h13m"));
+        assert_eq!(format_memory(&source.memory_usage(&sys).unwrap(), true), "4000/16000MB (25%)");
+        assert_eq!(format_disk(&source.disk_usage().unwrap(), false), "37/94GB");
+        assert_eq!(source.snapshot(&sys, RefreshRequirements::NONE).disk_total_bytes, Some(101_000_000_000));
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let source = Arc::new(source(None));
+        assert_eq!(source.readings().unwrap_err(), "connection refused");
+        assert_eq!(source.hostname(), "nas.lan");
+        assert!(source.cpu_temp().is_err());
+
+        let mut screen = SshScreen::new(Box::new(SystemScreen { uptime: UptimeFormat::default(), process_warning_count: DEFAULT_PROCESS_WARNING_COUNT }), Arc::clone(&source));
+        let rendered = screen.render(&SystemSnapshot::default(), &DemoDataSource::new()).unwrap();
+        assert_eq!(rendered, "nas.lan\nremote unreachable");
+    }
+
+    #[test]
+    fn test_stale_readings_are_unreachable() {
+        let source = source(Some(RemoteReadings::default()));
+        source.state.lock().unwrap().readings.as_mut().unwrap().1 -= MIN_STALE_AFTER * 2;
+        assert!(source.readings().is_err());
+    }
+}
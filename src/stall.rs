@@ -0,0 +1,81 @@
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+use crate::errors::STALL_EXIT_CODE;
+use crate::status::Status;
+
+// Checked this often at most, so a stall is noticed soon after the timeout
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Ends the process when the display loop has drawn no frame for `timeout`, so the
+/// service manager starts it afresh. A loop stuck in an I2C write or a hung read
+/// cannot be recovered from where it is; unlike the systemd watchdog this works
+/// without systemd, and it is the frames that count rather than the loop going round.
+pub struct StallWatchdog {
+    stop: Option<SyncSender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl StallWatchdog {
+    pub fn start(status: Arc<Status>, timeout: Duration) -> std::io::Result<Self> {
+        let (stop, stopped) = mpsc::sync_channel(1);
+        let started = Instant::now();
+        let check_interval = (timeout / 4).min(MAX_CHECK_INTERVAL);
+        info!("Exiting should no frame be drawn for {}s", timeout.as_secs_f64());
+        let handle = thread::Builder::new()
+            .name("stall-watchdog".to_string())
+            .spawn(move || {
+                // Ends once the watchdog, and with it the sender, is dropped
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(check_interval) {
+                    let stalled = stalled_for(status.last_frame_at(), started, Instant::now());
+                    if stalled >= timeout {
+                        error!("No frame drawn for {}s, exiting so the display can be restarted", stalled.as_secs());
+                        std::process::exit(STALL_EXIT_CODE);
+                    }
+                }
+            })?;
+        Ok(Self { stop: Some(stop), handle: Some(handle) })
+    }
+}
+
+impl Drop for StallWatchdog {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// Time since the last frame, or since watching started when there has been none
+fn stalled_for(last_frame: Option<Instant>, started: Instant, now: Instant) -> Duration {
+    let since = last_frame.map_or(started, |frame| frame.max(started));
+    now.saturating_duration_since(since)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_for() {
+        let started = Instant::now();
+        let now = started + Duration::from_secs(30);
+        assert_eq!(stalled_for(None, started, now), Duration::from_secs(30));
+        assert_eq!(stalled_for(Some(started + Duration::from_secs(25)), started, now), Duration::from_secs(5));
+        // A frame before watching started, e.g. a splash during display init, does not count
+        assert_eq!(stalled_for(Some(started), started + Duration::from_secs(10), now), Duration::from_secs(20));
+    }
+
+    #[test]
+    fn test_stops_when_dropped() {
+        let status = Arc::new(Status::default());
+        status.record_frame();
+        let started = Instant::now();
+        drop(StallWatchdog::start(status, Duration::from_secs(60)).unwrap());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
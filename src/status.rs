@@ -0,0 +1,159 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crate::framebuffer::FrameBuffer;
+use crate::system_info::SystemSnapshot;
+
+/// State shared between the display loop and the exporters that report on it.
+#[derive(Debug, Default)]
+pub struct Status {
+    snapshot: Mutex<Option<SystemSnapshot>>,
+    frames_rendered: AtomicU64,
+    render_failures: AtomicU64,
+    current_screen: Mutex<Option<String>>,
+    last_frame_at: Mutex<Option<Instant>>,
+    last_render_failed: AtomicBool,
+    frame: Mutex<Option<Arc<FrameBuffer>>>,
+}
+
+impl Status {
+    /// The latest snapshot, or None before the first one has been taken.
+    pub fn snapshot(&self) -> Option<SystemSnapshot> {
+        self.snapshot.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn set_snapshot(&self, snapshot: SystemSnapshot) {
+        *self.snapshot.lock().unwrap_or_else(|e| e.into_inner()) = Some(snapshot);
+    }
+
+    pub fn record_frame(&self) {
+        self.frames_rendered.fetch_add(1, Ordering::Relaxed);
+        self.last_render_failed.store(false, Ordering::Relaxed);
+        *self.last_frame_at.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+    }
+
+    pub fn record_render_failure(&self) {
+        self.render_failures.fetch_add(1, Ordering::Relaxed);
+        self.last_render_failed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_current_screen(&self, name: &str) {
+        *self.current_screen.lock().unwrap_or_else(|e| e.into_inner()) = Some(name.to_string());
+    }
+
+    /// Name of the screen on the display, or None before the first frame.
+    pub fn current_screen(&self) -> Option<String> {
+        self.current_screen.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// When the last frame was drawn, or None if none has been yet.
+    pub fn last_frame_at(&self) -> Option<Instant> {
+        *self.last_frame_at.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Replace the published frame; readers see either the old frame or the new one, never a mix.
+    pub fn set_frame(&self, frame: FrameBuffer) {
+        *self.frame.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(frame));
+    }
+
+    /// The last frame drawn to the display, or None before the first one.
+    pub fn frame(&self) -> Option<Arc<FrameBuffer>> {
+        self.frame.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Whether the most recent render attempt failed.
+    pub fn last_render_failed(&self) -> bool {
+        self.last_render_failed.load(Ordering::Relaxed)
+    }
+
+    pub fn frames_rendered(&self) -> u64 {
+        self.frames_rendered.load(Ordering::Relaxed)
+    }
+
+    pub fn render_failures(&self) -> u64 {
+        self.render_failures.load(Ordering::Relaxed)
+    }
+}
+
+/// Something that wants every snapshot as it is taken, e.g. a metrics exporter.
+pub trait SnapshotSink: Send {
+    fn publish(&self, snapshot: &SystemSnapshot);
+}
+
+/// Hands the display loop's snapshots to the exporters on a background thread, so a
+/// slow sink (a broker or database far away) never holds up the display loop and every
+/// exporter reports the readings the display shows. Dropping it stops the thread and
+/// the sinks it owns.
+pub struct Sampler {
+    snapshots: Option<SyncSender<SystemSnapshot>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Sampler {
+    pub fn spawn(status: Arc<Status>, sinks: Vec<Box<dyn SnapshotSink>>) -> std::io::Result<Self> {
+        let (snapshots, received) = mpsc::sync_channel::<SystemSnapshot>(1);
+        let handle = thread::Builder::new()
+            .name("sampler".to_string())
+            .spawn(move || {
+                // Ends once the sampler, and with it the sender, is dropped
+                for snapshot in received {
+                    for sink in &sinks {
+                        sink.publish(&snapshot);
+                    }
+                    status.set_snapshot(snapshot);
+                }
+            })?;
+        Ok(Self {
+            snapshots: Some(snapshots),
+            handle: Some(handle),
+        })
+    }
+
+    /// Publish `snapshot` without waiting for the sinks. Skipped if the previous one is still pending.
+    pub fn sample(&self, snapshot: &SystemSnapshot) {
+        if let Some(snapshots) = &self.snapshots {
+            let _ = snapshots.try_send(snapshot.clone());
+        }
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        // Closing the channel ends the thread's loop
+        self.snapshots.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recorder(Arc<Mutex<Vec<Option<f32>>>>);
+
+    impl SnapshotSink for Recorder {
+        fn publish(&self, snapshot: &SystemSnapshot) {
+            self.0.lock().unwrap().push(snapshot.cpu_temp_celsius);
+        }
+    }
+
+    #[test]
+    fn test_sampler_publishes_the_loops_snapshot() {
+        let status = Arc::new(Status::default());
+        let published = Arc::new(Mutex::new(Vec::new()));
+        let sampler = Sampler::spawn(Arc::clone(&status), vec![Box::new(Recorder(Arc::clone(&published)))]).unwrap();
+
+        let snapshot = SystemSnapshot { cpu_temp_celsius: Some(51.5), ..SystemSnapshot::default() };
+        sampler.sample(&snapshot);
+        // Dropping it waits for what was already handed over
+        drop(sampler);
+
+        assert_eq!(*published.lock().unwrap(), [Some(51.5)]);
+        assert_eq!(status.snapshot().and_then(|snapshot| snapshot.cpu_temp_celsius), Some(51.5));
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::{anyhow, Result};
+use embedded_hal::i2c::I2c;
+use linux_embedded_hal::I2cdev;
+use serde::Deserialize;
+
+
+// PiSugar 3: charge in percent, and a status register whose top bit is set on external power
+const PISUGAR3_PERCENT_REGISTER: u8 = 0x2A;
+const PISUGAR3_STATUS_REGISTER: u8 = 0x02;
+const PISUGAR3_POWER_CONNECTED: u8 = 0x80;
+
+// MAX17040/MAX17048 and CW2015 fuel gauges: state of charge as whole percent then 1/256ths
+const FUEL_GAUGE_SOC_REGISTER: u8 = 0x04;
+
+/// The UPS/battery HATs whose charge can be read over I2C.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BatteryHat {
+    /// PiSugar 3, which also reports whether it is charging
+    #[default]
+    Pisugar3,
+    /// MAX17040/MAX17048 fuel gauge, as on the UPS-Lite up to v1.2
+    Max17040,
+    /// CW2015 fuel gauge, as on the UPS-Lite v1.3
+    Cw2015,
+}
+
+impl BatteryHat {
+    pub fn default_address(&self) -> u8 {
+        match self {
+            BatteryHat::Pisugar3 => 0x57,
+            BatteryHat::Max17040 => 0x36,
+            BatteryHat::Cw2015 => 0x62,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BatteryHat::Pisugar3 => "PiSugar 3",
+            BatteryHat::Max17040 => "MAX17040",
+            BatteryHat::Cw2015 => "CW2015",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatteryStatus {
+    pub percent: u8,
+    // None when the HAT cannot tell
+    pub charging: Option<bool>,
+}
+
+impl BatteryStatus {
+    // "Batt: 82% (chg)"
+    pub fn describe(&self) -> String {
+        match self.charging {
+            Some(true) => format!("Batt: {}% (chg)", self.percent),
+            _ => format!("Batt: {}%", self.percent),
+        }
+    }
+}
+
+/// Read the charge of the `hat` at I2C address `addr` on I2C bus `bus`, the header's.
+pub fn read_battery(hat: BatteryHat, bus: u8, addr: u8) -> Result<BatteryStatus> {
+    let mut i2c = I2cdev::new(format!("/dev/i2c-{}", bus))?;
+    let mut read_register = |register: u8, buffer: &mut [u8]| {
+        i2c.write_read(addr, &[register], buffer)
+            .map_err(|e| anyhow!("{} at 0x{:02X}: {}", hat.label(), addr, e))
+    };
+
+    match hat {
+        BatteryHat::Pisugar3 => {
+            let mut percent = [0u8];
+            let mut status = [0u8];
+            read_register(PISUGAR3_PERCENT_REGISTER, &mut percent)?;
+            read_register(PISUGAR3_STATUS_REGISTER, &mut status)?;
+            decode_pisugar3(percent[0], status[0])
+        }
+        BatteryHat::Max17040 | BatteryHat::Cw2015 => {
+            let mut soc = [0u8; 2];
+            read_register(FUEL_GAUGE_SOC_REGISTER, &mut soc)?;
+            Ok(decode_fuel_gauge(soc))
+        }
+    }
+}
+
+fn decode_pisugar3(percent: u8, status: u8) -> Result<BatteryStatus> {
+    // Anything else is not a PiSugar answering
+    if percent > 100 {
+        return Err(anyhow!("PiSugar 3 reported {}%", percent));
+    }
+    Ok(BatteryStatus {
+        percent,
+        charging: Some(status & PISUGAR3_POWER_CONNECTED != 0),
+    })
+}
+
+// Gauges read a little over 100% when full, so round and cap
+fn decode_fuel_gauge(soc: [u8; 2]) -> BatteryStatus {
+    let percent = (soc[0] as u16 + (soc[1] >= 0x80) as u16).min(100);
+    BatteryStatus { percent: percent as u8, charging: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_pisugar3() {
+        let status = decode_pisugar3(82, 0x80).unwrap();
+        assert_eq!(status, BatteryStatus { percent: 82, charging: Some(true) });
+        assert_eq!(status.describe(), "Batt: 82% (chg)");
+
+        let status = decode_pisugar3(40, 0x00).unwrap();
+        assert_eq!(status.describe(), "Batt: 40%");
+
+        assert!(decode_pisugar3(0xFF, 0x00).is_err());
+    }
+
+    #[test]
+    fn test_decode_fuel_gauge() {
+        assert_eq!(decode_fuel_gauge([57, 0x40]).percent, 57);
+        assert_eq!(decode_fuel_gauge([57, 0x80]).percent, 58);
+        assert_eq!(decode_fuel_gauge([101, 0x10]).percent, 100);
+        assert_eq!(decode_fuel_gauge([57, 0x00]).describe(), "Batt: 57%");
+    }
+
+    #[test]
+    fn test_hat_names() {
+        #[derive(Deserialize)]
+        struct Section {
+            hat: BatteryHat,
+        }
+        let section: Section = toml::from_str("hat = \"cw2015\"").unwrap();
+        assert_eq!(section.hat, BatteryHat::Cw2015);
+        assert_eq!(section.hat.default_address(), 0x62);
+        assert!(toml::from_str::<Section>("hat = \"unknown\"").is_err());
+    }
+}
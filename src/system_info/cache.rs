@@ -0,0 +1,204 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::hardware::{get_boot_partition, get_firmware_version, get_revision_info, read_pi_model, read_serial_number, RevisionInfo};
+use super::network::{get_domain, read_mac_address};
+use super::placeholder::placeholder;
+use super::sensors::{get_header_gpio_base, get_header_i2c_bus};
+use super::system::get_time_synced;
+
+// Firmware can be updated, the boot disk remounted and the search domain handed out
+// again by DHCP, so these are re-read now and then instead of once
+const SEMI_STATIC_TTL: Duration = Duration::from_secs(60);
+
+/// A value that is read again once it is older than its TTL.
+pub struct CachedValue<T> {
+    // None keeps the first reading for good
+    ttl: Option<Duration>,
+    entry: Mutex<Option<(T, Instant)>>,
+}
+
+impl<T: Clone> CachedValue<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl: Some(ttl), entry: Mutex::new(None) }
+    }
+
+    /// For values that cannot change while the system is running.
+    pub fn forever() -> Self {
+        Self { ttl: None, entry: Mutex::new(None) }
+    }
+
+    /// The cached value, calling `read` first when there is none or it has expired.
+    /// The lock is held while reading so concurrent callers share one read.
+    pub fn get_or_refresh(&self, read: impl FnOnce() -> T) -> T {
+        let mut entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((value, read_at)) = entry.as_ref() {
+            if self.ttl.is_none_or(|ttl| read_at.elapsed() < ttl) {
+                return value.clone();
+            }
+        }
+        let value = read();
+        *entry = Some((value.clone(), Instant::now()));
+        value
+    }
+
+    /// Like `get_or_refresh`, but only a successful read is kept, so a failed one is
+    /// tried again next time instead of sticking.
+    pub fn get_or_try_refresh(&self, read: impl FnOnce() -> Option<T>) -> Option<T> {
+        let mut entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((value, read_at)) = entry.as_ref() {
+            if self.ttl.is_none_or(|ttl| read_at.elapsed() < ttl) {
+                return Some(value.clone());
+            }
+        }
+        let value = read()?;
+        *entry = Some((value.clone(), Instant::now()));
+        Some(value)
+    }
+}
+
+// Information that changes rarely or never. Each value is read (and any helper
+// process spawned) on first use, then reused for every render until it expires.
+// The model, revision, serial number and MAC are kept only once read successfully.
+pub struct SystemInfoCache {
+    pi_model: CachedValue<String>,
+    revision_info: CachedValue<RevisionInfo>,
+    serial_number: CachedValue<String>,
+    mac_address: CachedValue<String>,
+    firmware_version: CachedValue<String>,
+    boot_partition: CachedValue<String>,
+    domain: CachedValue<String>,
+    // Checked again now and then, as NTP may take a while after boot
+    time_synced: CachedValue<Option<bool>>,
+    header_gpio_base: CachedValue<u32>,
+    header_i2c_bus: CachedValue<u8>,
+}
+
+impl Default for SystemInfoCache {
+    fn default() -> Self {
+        Self {
+            pi_model: CachedValue::forever(),
+            revision_info: CachedValue::forever(),
+            serial_number: CachedValue::forever(),
+            mac_address: CachedValue::forever(),
+            firmware_version: CachedValue::new(SEMI_STATIC_TTL),
+            boot_partition: CachedValue::new(SEMI_STATIC_TTL),
+            domain: CachedValue::new(SEMI_STATIC_TTL),
+            time_synced: CachedValue::new(SEMI_STATIC_TTL),
+            header_gpio_base: CachedValue::forever(),
+            header_i2c_bus: CachedValue::forever(),
+        }
+    }
+}
+
+impl SystemInfoCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pi_model(&self) -> String {
+        self.pi_model.get_or_try_refresh(read_pi_model).unwrap_or_else(|| placeholder().to_string())
+    }
+
+    pub fn revision_info(&self) -> Option<RevisionInfo> {
+        self.revision_info.get_or_try_refresh(|| get_revision_info().ok())
+    }
+
+    pub fn header_gpio_base(&self) -> u32 {
+        self.header_gpio_base.get_or_refresh(get_header_gpio_base)
+    }
+
+    pub fn header_i2c_bus(&self) -> u8 {
+        self.header_i2c_bus.get_or_refresh(get_header_i2c_bus)
+    }
+
+    pub fn serial_number(&self) -> String {
+        self.serial_number.get_or_try_refresh(read_serial_number).unwrap_or_else(|| placeholder().to_string())
+    }
+
+    pub fn mac_address(&self) -> String {
+        self.mac_address.get_or_try_refresh(read_mac_address).unwrap_or_else(|| placeholder().to_string())
+    }
+
+    pub fn firmware_version(&self) -> String {
+        self.firmware_version.get_or_refresh(get_firmware_version)
+    }
+
+    pub fn boot_partition(&self) -> String {
+        self.boot_partition.get_or_refresh(get_boot_partition)
+    }
+
+    pub fn domain(&self) -> String {
+        self.domain.get_or_refresh(get_domain)
+    }
+
+    pub fn time_synced(&self) -> Option<bool> {
+        self.time_synced.get_or_refresh(get_time_synced)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn test_read_once_within_ttl() {
+        let cache = CachedValue::new(Duration::from_secs(60));
+        let mut reads = 0;
+        for _ in 0..3 {
+            let value = cache.get_or_refresh(|| {
+                reads += 1;
+                "4B".to_string()
+            });
+            assert_eq!(value, "4B");
+        }
+        assert_eq!(reads, 1);
+    }
+
+    #[test]
+    fn test_concurrent_callers_share_one_read() {
+        let cache = CachedValue::forever();
+        let reads = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let value = cache.get_or_refresh(|| {
+                        reads.fetch_add(1, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(5));
+                        "c0ffee42".to_string()
+                    });
+                    assert_eq!(value, "c0ffee42");
+                });
+            }
+        });
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_read_again_after_ttl() {
+        let cache = CachedValue::new(Duration::from_millis(10));
+        let mut reads = 0;
+        cache.get_or_refresh(|| { reads += 1; reads });
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(cache.get_or_refresh(|| { reads += 1; reads }), 2);
+        assert_eq!(cache.get_or_refresh(|| { reads += 1; reads }), 2);
+    }
+
+    #[test]
+    fn test_failed_read_is_not_kept() {
+        let cache = CachedValue::forever();
+        assert_eq!(cache.get_or_try_refresh(|| None::<String>), None);
+        assert_eq!(cache.get_or_try_refresh(|| Some("DC:A6:32:12:34:56".to_string())).as_deref(), Some("DC:A6:32:12:34:56"));
+        assert_eq!(cache.get_or_try_refresh(|| None).as_deref(), Some("DC:A6:32:12:34:56"));
+    }
+
+    #[test]
+    fn test_forever_never_expires() {
+        let cache = CachedValue::forever();
+        assert_eq!(cache.get_or_refresh(|| 1), 1);
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(cache.get_or_refresh(|| 2), 1);
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt;
+use std::io::{self, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::failures::track;
+
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_millis(500);
+
+// How often a running child is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+static COMMAND_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// How long `run_command` lets a program run. Only the first call has an effect.
+pub fn set_command_timeout(timeout: Duration) {
+    let _ = COMMAND_TIMEOUT.set(timeout);
+}
+
+/// The configured timeout for external commands, 500ms unless configured otherwise.
+pub fn command_timeout() -> Duration {
+    COMMAND_TIMEOUT.get().copied().unwrap_or(DEFAULT_COMMAND_TIMEOUT)
+}
+
+#[derive(Debug)]
+pub enum CommandError {
+    /// The program is not installed or not on the PATH
+    NotFound(String),
+    /// Still running at the deadline, so it was killed
+    Timeout { program: String, timeout: Duration },
+    /// Exited unsuccessfully; `code` is None when a signal ended it
+    Failed { program: String, code: Option<i32>, stderr: String },
+    Io { program: String, source: io::Error },
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::NotFound(program) => write!(f, "{} not found", program),
+            CommandError::Timeout { program, timeout } => {
+                write!(f, "{} did not finish within {}ms", program, timeout.as_millis())
+            }
+            CommandError::Failed { program, code, stderr } => {
+                match code {
+                    Some(code) => write!(f, "{} exited with status {}", program, code)?,
+                    None => write!(f, "{} was killed by a signal", program)?,
+                }
+                if !stderr.trim().is_empty() {
+                    write!(f, ": {}", stderr.trim())?;
+                }
+                Ok(())
+            }
+            CommandError::Io { program, source } => write!(f, "{}: {}", program, source),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CommandError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Run `program` with the configured timeout and return its stdout.
+/// Failures count against the program, see failures::get_source_failures.
+pub fn run_command(program: &str, args: &[&str]) -> Result<String, CommandError> {
+    track(program, run_command_with_timeout(program, args, command_timeout()))
+}
+
+/// Run `program` and return its stdout, killing it if it is still running after
+/// `timeout`. A wedged firmware can make vcgencmd hang, which must not stall the display.
+pub fn run_command_with_timeout(program: &str, args: &[&str], timeout: Duration) -> Result<String, CommandError> {
+    let io_error = |source: io::Error| match source.kind() {
+        io::ErrorKind::NotFound => CommandError::NotFound(program.to_string()),
+        _ => CommandError::Io { program: program.to_string(), source },
+    };
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(io_error)?;
+
+    // Drain the pipes while waiting so a chatty child cannot block on a full pipe
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(io_error)? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                kill(&mut child);
+                return Err(CommandError::Timeout { program: program.to_string(), timeout });
+            }
+            None => thread::sleep(POLL_INTERVAL),
+        }
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    if status.success() {
+        Ok(stdout)
+    } else {
+        Err(CommandError::Failed {
+            program: program.to_string(),
+            code: status.code(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+}
+
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut output);
+        }
+        String::from_utf8_lossy(&output).into_owned()
+    })
+}
+
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+    // Reap it so no zombie is left behind
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_returns_stdout() {
+        let output = run_command_with_timeout("echo", &["hello"], Duration::from_secs(5)).unwrap();
+        assert_eq!(output, "hello\n");
+    }
+
+    #[test]
+    fn test_kills_on_timeout() {
+        let started = Instant::now();
+        let err = run_command_with_timeout("sleep", &["5"], Duration::from_millis(50)).unwrap_err();
+        assert!(matches!(err, CommandError::Timeout { .. }));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_not_found() {
+        let err = run_command_with_timeout("info-display-no-such-program", &[], Duration::from_secs(1)).unwrap_err();
+        assert!(matches!(err, CommandError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_non_zero_exit() {
+        let err = run_command_with_timeout("sh", &["-c", "echo oops >&2; exit 3"], Duration::from_secs(5)).unwrap_err();
+        match err {
+            CommandError::Failed { code, stderr, .. } => {
+                assert_eq!(code, Some(3));
+                assert_eq!(stderr.trim(), "oops");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
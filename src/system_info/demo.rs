@@ -0,0 +1,267 @@
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use sysinfo::System;
+
+use super::hardware::{decode_revision, DeviceIdentity, RevisionInfo};
+use super::network::{InterfaceInfo, WifiBlock};
+use super::source::DataSource;
+use super::events::ThrottleEventSummary;
+use super::failures::SourceFailures;
+use super::sensors::ThrottleFlags;
+use super::storage::Usage;
+use super::{ProcessSummary, RefreshRequirements, SystemSnapshot};
+
+const MB: u64 = 1024 * 1024;
+const GB: u64 = 1024 * 1024 * 1024;
+
+const MEMORY_USED: u64 = 512 * MB;
+const MEMORY_TOTAL: u64 = 1024 * MB;
+const SWAP_USED: u64 = 12 * MB;
+const SWAP_TOTAL: u64 = 100 * MB;
+const DISK_USED: u64 = 12 * GB;
+const DISK_TOTAL: u64 = 29 * GB;
+const INODES_USED: u64 = 187_000;
+const INODES_TOTAL: u64 = 1_900_000;
+const UPTIME_SECS: f64 = 3.0 * 86400.0 + 4.0 * 3600.0 + 12.0 * 60.0;
+
+/// Plausible fixed values for demos and screenshots without Pi hardware.
+/// Nothing here touches the running system.
+#[derive(Debug, Default)]
+pub struct DemoDataSource {
+    reboot_notice: Option<String>,
+    network_down: bool,
+    wifi_block: Option<WifiBlock>,
+}
+
+impl DemoDataSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// With a reboot scheduled, announced as `notice`.
+    pub fn with_reboot_notice(mut self, notice: &str) -> Self {
+        self.reboot_notice = Some(notice.to_string());
+        self
+    }
+
+    /// With no IP address, as when the interfaces cannot be listed.
+    pub fn without_network(mut self) -> Self {
+        self.network_down = true;
+        self
+    }
+
+    /// With the wifi radio blocked.
+    pub fn with_wifi_block(mut self, block: WifiBlock) -> Self {
+        self.wifi_block = Some(block);
+        self
+    }
+}
+
+impl DataSource for DemoDataSource {
+    fn hostname(&self) -> String {
+        "raspberrypi".to_string()
+    }
+
+    fn domain(&self) -> String {
+        "local".to_string()
+    }
+
+    fn ip_address(&self) -> Result<String> {
+        if self.network_down {
+            return Err(anyhow!("interface enumeration failed"));
+        }
+        Ok("192.168.1.42".to_string())
+    }
+
+    fn ipv6_address(&self, _interface: Option<&str>) -> Result<String> {
+        if self.network_down {
+            return Err(anyhow!("interface enumeration failed"));
+        }
+        Ok("2001:db8:85a3::8a2e:370:7334".to_string())
+    }
+
+    fn mac_address(&self) -> String {
+        "DC:A6:32:12:34:56".to_string()
+    }
+
+    fn link_speed(&self) -> Option<String> {
+        Some("eth0 1Gb FD".to_string())
+    }
+
+    fn interface(&self, _name: &str) -> InterfaceInfo {
+        InterfaceInfo {
+            ip: self.ip_address().ok(),
+            mac: Some(self.mac_address()),
+            link_speed: Some("1Gb FD".to_string()),
+        }
+    }
+
+    fn connection_count(&self) -> Result<usize> {
+        Ok(7)
+    }
+
+    fn wifi_block(&self) -> Option<WifiBlock> {
+        self.wifi_block
+    }
+
+    fn cpu_temp(&self) -> Result<f32> {
+        Ok(42.0)
+    }
+
+    fn gpu_temp(&self) -> Result<f32> {
+        Ok(41.9)
+    }
+
+    fn cpu_freq(&self) -> Result<u64> {
+        Ok(1_500_000_000)
+    }
+
+    fn throttle_status(&self) -> Result<ThrottleFlags> {
+        Ok(ThrottleFlags::default())
+    }
+
+    fn throttle_events(&self) -> Option<ThrottleEventSummary> {
+        Some(ThrottleEventSummary::from_counts([3, 0, 1, 0]))
+    }
+
+    fn thermal_zones(&self) -> Vec<(String, f32)> {
+        vec![("cpu-thermal".to_string(), 42.0)]
+    }
+
+    fn uptime(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f64(UPTIME_SECS))
+    }
+
+    fn reboot_notice(&self) -> Option<String> {
+        self.reboot_notice.clone()
+    }
+
+    fn time_synced(&self) -> Option<bool> {
+        Some(true)
+    }
+
+    fn memory_usage(&self, _sys: &System) -> Result<Usage> {
+        Ok(Usage { used: MEMORY_USED, total: MEMORY_TOTAL })
+    }
+
+    fn swap_usage(&self, _sys: &System) -> Result<Option<Usage>> {
+        Ok(Some(Usage { used: SWAP_USED, total: SWAP_TOTAL }))
+    }
+
+    fn disk_usage(&self) -> Result<Usage> {
+        Ok(Usage { used: DISK_USED, total: DISK_TOTAL })
+    }
+
+    fn pi_model(&self) -> String {
+        "Raspberry Pi 4 Model B Rev 1.4".to_string()
+    }
+
+    fn revision_info(&self) -> Option<RevisionInfo> {
+        decode_revision(0xc03114)
+    }
+
+    fn serial_number(&self) -> String {
+        "10000000c0ffee42".to_string()
+    }
+
+    fn device_identity(&self) -> DeviceIdentity {
+        DeviceIdentity {
+            id: "c0ffee42".to_string(),
+            hostname: self.hostname(),
+            serial: Some(self.serial_number()),
+            mac: Some(self.mac_address()),
+            model: self.pi_model(),
+        }
+    }
+
+    fn firmware_version(&self) -> String {
+        "2024/09/10 (1e5fc2c2)".to_string()
+    }
+
+    fn boot_partition(&self) -> String {
+        "/dev/mmcblk0p1".to_string()
+    }
+
+    fn header_i2c_bus(&self) -> u8 {
+        1
+    }
+
+    fn i2c_devices(&self, _bus: u8) -> Result<Vec<u8>> {
+        Ok(vec![0x3c, 0x70])
+    }
+
+    fn gpio_states(&self) -> String {
+        "17: H, 27: L".to_string()
+    }
+
+    fn gpio_inputs(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn spi_devices(&self) -> String {
+        "spidev0.0".to_string()
+    }
+
+    fn one_wire_sensors(&self, _family: Option<u8>) -> String {
+        "None".to_string()
+    }
+
+    fn source_failures(&self) -> Vec<SourceFailures> {
+        vec![SourceFailures {
+            source: "vcgencmd".to_string(),
+            count: 3,
+            last_error: "vcgencmd did not finish within 500ms".to_string(),
+            last_failure: 1_760_000_000,
+        }]
+    }
+
+    // Everything, whatever is needed; none of it costs anything to read
+    fn snapshot(&self, _sys: &System, _needed: RefreshRequirements) -> SystemSnapshot {
+        SystemSnapshot {
+            hostname: self.hostname(),
+            ip_addresses: self.ip_address().into_iter().collect(),
+            cpu_temp_celsius: Some(42.0),
+            gpu_temp_celsius: Some(41.9),
+            cpu_temp_raw_celsius: Some(42.0),
+            cpu_trip_celsius: Some(85.0),
+            gpu_temp_raw_celsius: Some(41.9),
+            cpu_freq_hz: Some(1_500_000_000),
+            cpu_usage_percent: Some(12.0),
+            memory_used_bytes: MEMORY_USED,
+            memory_total_bytes: MEMORY_TOTAL,
+            swap_used_bytes: Some(SWAP_USED),
+            swap_total_bytes: Some(SWAP_TOTAL),
+            disk_used_bytes: Some(DISK_USED),
+            disk_total_bytes: Some(DISK_TOTAL),
+            inodes_used: Some(INODES_USED),
+            inodes_total: Some(INODES_TOTAL),
+            readonly_mounts: Some(Vec::new()),
+            uptime_secs: Some(UPTIME_SECS),
+            throttled: Some(0),
+            processes: Some(ProcessSummary {
+                total: 132,
+                running: 1,
+                zombies: 0,
+                top_cpu: Some(("info_display".to_string(), 3.5)),
+                top_mem: Some(("python3".to_string(), 48)),
+            }),
+            clock_synced: Some(true),
+            clock_offset_ms: Some(0.4),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::{format_disk, format_memory};
+
+    #[test]
+    fn test_demo_values() {
+        let demo = DemoDataSource::new();
+        let sys = System::new();
+        assert_eq!(demo.cpu_temp().unwrap(), 42.0);
+        assert_eq!(format_memory(&demo.memory_usage(&sys).unwrap(), true), "512/1024MB (50%)");
+        assert_eq!(format_disk(&demo.disk_usage().unwrap(), false), "12/29GB");
+    }
+}
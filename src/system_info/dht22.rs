@@ -0,0 +1,324 @@
+use anyhow::{anyhow, Result};
+use rppal::gpio::{self, Bias, Gpio, IoPin, Level, Mode};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::errors::AppError;
+
+// Highest BCM GPIO number on the 40-pin header
+pub const MAX_GPIO_PIN: u8 = 27;
+
+// The sensor needs about 2s between conversions, so retries wait that long
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+// Room and enclosure temperatures change slowly
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_ATTEMPTS: u32 = 3;
+
+// Longest any single level lasts in a healthy transmission
+const LEVEL_TIMEOUT: Duration = Duration::from_micros(200);
+// High pulses are ~27us for a 0 bit and ~70us for a 1 bit
+const ONE_BIT_THRESHOLD: Duration = Duration::from_micros(50);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dht22Reading {
+    pub temperature_celsius: f32,
+    pub humidity_percent: f32,
+}
+
+/// Read a DHT22 on BCM GPIO `pin` once by bit-banging its single-wire protocol.
+/// Linux scheduling can disturb the timing, so occasional failures are expected.
+pub fn read_dht22(pin: u8) -> Result<Dht22Reading> {
+    let gpio = Gpio::new().map_err(|e| match e {
+        gpio::Error::PermissionDenied(path) => anyhow::Error::new(AppError::permission_denied(&path, Some("gpio"))),
+        e => e.into(),
+    })?;
+    let mut pin = gpio.get(pin)?.into_io(Mode::Output);
+
+    // Start signal: hold the line low for at least 1ms, then release it
+    pin.set_low();
+    thread::sleep(Duration::from_micros(1_100));
+    pin.set_high();
+    pin.set_mode(Mode::Input);
+    pin.set_bias(Bias::PullUp);
+
+    // The sensor answers with ~80us low and ~80us high before the data
+    wait_for(&pin, Level::Low)?;
+    wait_for(&pin, Level::High)?;
+    wait_for(&pin, Level::Low)?;
+
+    // 40 bits, each a ~50us low followed by a high whose length encodes the bit
+    let mut high_times = [Duration::ZERO; 40];
+    for high_time in high_times.iter_mut() {
+        wait_for(&pin, Level::High)?;
+        let started = Instant::now();
+        wait_for(&pin, Level::Low)?;
+        *high_time = started.elapsed();
+    }
+
+    decode(bits_to_bytes(&high_times))
+}
+
+fn wait_for(pin: &IoPin, level: Level) -> Result<()> {
+    let started = Instant::now();
+    while pin.read() != level {
+        if started.elapsed() > LEVEL_TIMEOUT {
+            return Err(anyhow!("DHT22 timed out waiting for {:?}", level));
+        }
+    }
+    Ok(())
+}
+
+fn bits_to_bytes(high_times: &[Duration; 40]) -> [u8; 5] {
+    let mut bytes = [0u8; 5];
+    for (i, high_time) in high_times.iter().enumerate() {
+        if *high_time > ONE_BIT_THRESHOLD {
+            bytes[i / 8] |= 0x80 >> (i % 8);
+        }
+    }
+    bytes
+}
+
+// Humidity and temperature are big-endian tenths; the temperature's top bit is its sign
+fn decode(bytes: [u8; 5]) -> Result<Dht22Reading> {
+    let checksum = bytes[..4].iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    if checksum != bytes[4] {
+        return Err(anyhow!("DHT22 checksum mismatch: {:02X?}", bytes));
+    }
+
+    let humidity = u16::from_be_bytes([bytes[0], bytes[1]]) as f32 / 10.0;
+    let magnitude = u16::from_be_bytes([bytes[2] & 0x7F, bytes[3]]) as f32 / 10.0;
+    let temperature = if bytes[2] & 0x80 != 0 { -magnitude } else { magnitude };
+
+    if humidity > 100.0 {
+        return Err(anyhow!("DHT22 humidity out of range: {}", humidity));
+    }
+
+    Ok(Dht22Reading {
+        temperature_celsius: temperature,
+        humidity_percent: humidity,
+    })
+}
+
+/// A DHT22 read by a background thread, which retries failed reads, so rendering never
+/// waits on the sensor. Falls back to the last good reading.
+pub struct Dht22Sensor {
+    pin: u8,
+    state: Arc<Mutex<Dht22State>>,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug, Default)]
+struct Dht22State {
+    last_good: Option<(Dht22Reading, Instant)>,
+    // Whether the latest poll read the sensor
+    current: bool,
+}
+
+impl Dht22Sensor {
+    pub fn start(pin: u8) -> crate::errors::Result<Self> {
+        Self::with_reader(pin, read_dht22, RETRY_DELAY)
+    }
+
+    // For tests, which cannot have a sensor answer
+    #[doc(hidden)]
+    pub fn with_reader(pin: u8, reader: fn(u8) -> Result<Dht22Reading>, retry_delay: Duration) -> crate::errors::Result<Self> {
+        let state = Arc::new(Mutex::new(Dht22State::default()));
+        let (stop, stopped) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("dht22".to_string())
+            .spawn({
+                let state = Arc::clone(&state);
+                move || {
+                    while poll(pin, reader, retry_delay, &state, &stopped) {
+                        match stopped.recv_timeout(POLL_INTERVAL) {
+                            Err(RecvTimeoutError::Timeout) => continue,
+                            _ => return,
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            pin,
+            state,
+            stop: Some(stop),
+            handle: Some(handle),
+        })
+    }
+
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// The latest reading and its age: zero when the latest poll read the sensor,
+    /// otherwise how old the last good reading is. None if it was never read.
+    pub fn read(&self) -> Option<(Dht22Reading, Duration)> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.last_good.map(|(reading, taken_at)| {
+            (reading, if state.current { Duration::ZERO } else { taken_at.elapsed() })
+        })
+    }
+}
+
+impl Drop for Dht22Sensor {
+    fn drop(&mut self) {
+        // Closing the channel wakes the poller between reads
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// One poll, with retries. False once the sensor was dropped, or when it cannot be read
+// at all and polling again would only repeat the warning.
+fn poll(
+    pin: u8,
+    reader: fn(u8) -> Result<Dht22Reading>,
+    retry_delay: Duration,
+    state: &Mutex<Dht22State>,
+    stopped: &Receiver<()>,
+) -> bool {
+    for attempt in 1..=MAX_ATTEMPTS {
+        match reader(pin) {
+            Ok(reading) => {
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                state.last_good = Some((reading, Instant::now()));
+                state.current = true;
+                return true;
+            }
+            // Neither retrying nor polling again will fix permissions
+            Err(e) if matches!(e.downcast_ref::<AppError>(), Some(AppError::Permission(_))) => {
+                warn!("DHT22 on GPIO {}: {}; no longer polling it", pin, e);
+                state.lock().unwrap_or_else(|e| e.into_inner()).current = false;
+                return false;
+            }
+            Err(e) => {
+                debug!("DHT22 read {} of {} on GPIO {} failed: {}", attempt, MAX_ATTEMPTS, pin, e);
+                if attempt < MAX_ATTEMPTS && !matches!(stopped.recv_timeout(retry_delay), Err(RecvTimeoutError::Timeout)) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    state.lock().unwrap_or_else(|e| e.into_inner()).current = false;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn pulses(bytes: [u8; 5]) -> [Duration; 40] {
+        let mut high_times = [Duration::from_micros(27); 40];
+        for (i, high_time) in high_times.iter_mut().enumerate() {
+            if bytes[i / 8] & (0x80 >> (i % 8)) != 0 {
+                *high_time = Duration::from_micros(70);
+            }
+        }
+        high_times
+    }
+
+    #[test]
+    fn test_decode_datasheet_example() {
+        // 65.2%RH, 35.1°C
+        let reading = decode(bits_to_bytes(&pulses([0x02, 0x8C, 0x01, 0x5F, 0xEE]))).unwrap();
+        assert_eq!(reading.humidity_percent, 65.2);
+        assert_eq!(reading.temperature_celsius, 35.1);
+    }
+
+    #[test]
+    fn test_decode_negative_temperature() {
+        let reading = decode([0x02, 0x8C, 0x80, 0x65, 0x73]).unwrap();
+        assert_eq!(reading.temperature_celsius, -10.1);
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_frames() {
+        assert!(decode([0x02, 0x8C, 0x01, 0x5F, 0xEF]).is_err());
+        // 110% humidity with a valid checksum
+        assert!(decode([0x04, 0x4C, 0x00, 0x00, 0x50]).is_err());
+    }
+
+    static FAILURES_LEFT: AtomicU32 = AtomicU32::new(0);
+
+    fn flaky_reader(_pin: u8) -> Result<Dht22Reading> {
+        if FAILURES_LEFT.load(Ordering::SeqCst) > 0 {
+            FAILURES_LEFT.fetch_sub(1, Ordering::SeqCst);
+            return Err(anyhow!("timed out"));
+        }
+        Ok(Dht22Reading { temperature_celsius: 21.5, humidity_percent: 40.0 })
+    }
+
+    #[test]
+    fn test_retry_and_fallback() {
+        let state = Mutex::new(Dht22State::default());
+        let (_stop, stopped) = mpsc::channel();
+
+        // Never read and every attempt fails
+        FAILURES_LEFT.store(MAX_ATTEMPTS, Ordering::SeqCst);
+        assert!(poll(4, flaky_reader, Duration::ZERO, &state, &stopped));
+        assert!(state.lock().unwrap().last_good.is_none());
+
+        // Succeeds on the last attempt
+        FAILURES_LEFT.store(MAX_ATTEMPTS - 1, Ordering::SeqCst);
+        poll(4, flaky_reader, Duration::ZERO, &state, &stopped);
+        let state_now = state.lock().unwrap();
+        assert_eq!(state_now.last_good.unwrap().0.temperature_celsius, 21.5);
+        assert!(state_now.current);
+        drop(state_now);
+
+        // Keeps the last good reading
+        FAILURES_LEFT.store(MAX_ATTEMPTS, Ordering::SeqCst);
+        poll(4, flaky_reader, Duration::ZERO, &state, &stopped);
+        let state_now = state.lock().unwrap();
+        assert_eq!(state_now.last_good.unwrap().0.humidity_percent, 40.0);
+        assert!(!state_now.current);
+    }
+
+    #[test]
+    fn test_stops_between_retries() {
+        let state = Mutex::new(Dht22State::default());
+        let (stop, stopped) = mpsc::channel();
+        drop(stop);
+
+        // A day between retries would hang the test if the wait were not cut short
+        assert!(!poll(4, |_| Err(anyhow!("timed out")), Duration::from_secs(86_400), &state, &stopped));
+    }
+
+    #[test]
+    fn test_reads_in_the_background() {
+        let sensor = Dht22Sensor::with_reader(4, |_| Ok(Dht22Reading { temperature_celsius: 19.0, humidity_percent: 55.0 }), Duration::ZERO).unwrap();
+        let started = Instant::now();
+        while sensor.read().is_none() && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let (reading, age) = sensor.read().unwrap();
+        assert_eq!(reading.temperature_celsius, 19.0);
+        assert!(age.is_zero());
+    }
+
+    static PERMISSION_ATTEMPTS: AtomicU32 = AtomicU32::new(0);
+
+    fn denied_reader(_pin: u8) -> Result<Dht22Reading> {
+        PERMISSION_ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+        Err(anyhow::Error::new(AppError::permission_denied("/dev/gpiomem", Some("gpio"))))
+    }
+
+    #[test]
+    fn test_permission_error_is_not_retried() {
+        let state = Mutex::new(Dht22State::default());
+        let (_stop, stopped) = mpsc::channel();
+
+        // The poller stops, so the warning is logged once rather than every POLL_INTERVAL
+        assert!(!poll(4, denied_reader, Duration::ZERO, &state, &stopped));
+        assert!(state.lock().unwrap().last_good.is_none());
+        assert_eq!(PERMISSION_ATTEMPTS.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+use super::sensors::{ThrottleConditions, THROTTLE_FLAGS};
+
+pub const DEFAULT_STATE_DIR: &str = "/var/lib/info-display";
+pub const DEFAULT_EVENT_RETENTION_DAYS: u32 = 7;
+
+const EVENTS_FILE: &str = "events.jsonl";
+
+// Kept whatever the retention, should a condition flap on and off for days
+const MAX_EVENTS: usize = 10_000;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+static THROTTLE_LOG: OnceLock<Mutex<ThrottleEventLog>> = OnceLock::new();
+
+/// A throttle condition coming on; one line of events.jsonl
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThrottleEvent {
+    /// Unix seconds
+    pub time: u64,
+    /// Metric label from THROTTLE_FLAGS, e.g. `under_voltage`
+    pub flag: String,
+}
+
+/// How often each throttle condition came on in the last 24 hours
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThrottleEventSummary {
+    // In THROTTLE_FLAGS order
+    counts: [usize; THROTTLE_FLAGS.len()],
+}
+
+impl ThrottleEventSummary {
+    pub fn from_counts(counts: [usize; THROTTLE_FLAGS.len()]) -> Self {
+        Self { counts }
+    }
+
+    /// Events of the conditions in `conditions`, added up.
+    pub fn count(&self, conditions: ThrottleConditions) -> usize {
+        THROTTLE_FLAGS.iter()
+            .zip(self.counts)
+            .filter(|((bit, ..), _)| conditions.bits() & bit != 0)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// Throttle conditions as they come on, kept for `retention_days` in `events.jsonl`
+/// so the counts survive restarts. When the file cannot be written the log carries on
+/// in memory.
+pub struct ThrottleEventLog {
+    path: Option<PathBuf>,
+    retention_secs: u64,
+    events: Vec<ThrottleEvent>,
+    // Conditions present at the last sample; none before the first, so a condition
+    // already present at startup, e.g. under-voltage while booting, counts as well
+    previous: ThrottleConditions,
+}
+
+impl ThrottleEventLog {
+    /// The log in `state_dir`, created if needed, with the events that have not expired.
+    pub fn open(state_dir: &Path, retention_days: u32, now: u64) -> Self {
+        let mut log = Self::in_memory(retention_days);
+        let path = state_dir.join(EVENTS_FILE);
+        match fs::create_dir_all(state_dir).and_then(|_| read_events(&path)) {
+            Ok(events) => {
+                log.events = events;
+                log.path = Some(path);
+                if log.prune(now) {
+                    log.rewrite();
+                }
+            }
+            Err(e) => warn!("Throttle events are only counted in memory, {} is not usable: {}", path.display(), e),
+        }
+        log
+    }
+
+    pub fn in_memory(retention_days: u32) -> Self {
+        Self {
+            path: None,
+            retention_secs: u64::from(retention_days) * DAY_SECS,
+            events: Vec::new(),
+            previous: ThrottleConditions::default(),
+        }
+    }
+
+    /// Log the conditions that are present in `current` but were not at the last sample.
+    pub fn record(&mut self, current: ThrottleConditions, now: u64) {
+        let started = ThrottleConditions::from_bits(current.bits() & !self.previous.bits());
+        self.previous = current;
+        let new_events: Vec<ThrottleEvent> = THROTTLE_FLAGS.iter()
+            .filter(|(bit, ..)| started.bits() & bit != 0)
+            .map(|(_, label, ..)| ThrottleEvent { time: now, flag: label.to_string() })
+            .collect();
+        if new_events.is_empty() {
+            return;
+        }
+        for event in &new_events {
+            debug!("Throttle condition {} came on", event.flag);
+        }
+
+        self.events.extend(new_events.iter().cloned());
+        if self.prune(now) {
+            self.rewrite();
+        } else {
+            self.append(&new_events);
+        }
+    }
+
+    pub fn events(&self) -> &[ThrottleEvent] {
+        &self.events
+    }
+
+    pub fn summary(&self, now: u64) -> ThrottleEventSummary {
+        let since = now.saturating_sub(DAY_SECS);
+        let mut counts = [0; THROTTLE_FLAGS.len()];
+        for event in self.events.iter().filter(|event| event.time > since) {
+            if let Some(index) = THROTTLE_FLAGS.iter().position(|(_, label, ..)| *label == event.flag) {
+                counts[index] += 1;
+            }
+        }
+        ThrottleEventSummary { counts }
+    }
+
+    // Drop expired events and the oldest beyond MAX_EVENTS; true when any were dropped
+    fn prune(&mut self, now: u64) -> bool {
+        let before = self.events.len();
+        let oldest_kept = now.saturating_sub(self.retention_secs);
+        self.events.retain(|event| event.time >= oldest_kept);
+        if self.events.len() > MAX_EVENTS {
+            self.events.drain(..self.events.len() - MAX_EVENTS);
+        }
+        self.events.len() != before
+    }
+
+    fn append(&mut self, events: &[ThrottleEvent]) {
+        let result = match &self.path {
+            Some(path) => OpenOptions::new().create(true).append(true).open(path)
+                .and_then(|mut file| file.write_all(events_jsonl(events).as_bytes())),
+            None => return,
+        };
+        self.check_write(result);
+    }
+
+    // Write the whole log to a temporary file next to it and move that into place, so a
+    // crash halfway leaves the old log rather than half a new one
+    fn rewrite(&mut self) {
+        let result = match &self.path {
+            Some(path) => {
+                let temp = path.with_extension("jsonl.tmp");
+                fs::write(&temp, events_jsonl(&self.events)).and_then(|_| fs::rename(&temp, path))
+            }
+            None => return,
+        };
+        self.check_write(result);
+    }
+
+    fn check_write(&mut self, result: io::Result<()>) {
+        if let Err(e) = result {
+            if let Some(path) = self.path.take() {
+                warn!("Failed to write {}, counting throttle events in memory from now on: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+// A missing file is an empty log; lines that do not parse are skipped
+fn read_events(path: &Path) -> io::Result<Vec<ThrottleEvent>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn events_jsonl(events: &[ThrottleEvent]) -> String {
+    events.iter()
+        .filter_map(|event| serde_json::to_string(event).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Start logging throttle events to `state_dir`. Only the first call has an effect;
+/// until then nothing is logged and there is no summary.
+pub fn init_throttle_event_log(state_dir: &Path, retention_days: u32) {
+    THROTTLE_LOG.get_or_init(|| Mutex::new(ThrottleEventLog::open(state_dir, retention_days, unix_now())));
+}
+
+/// Compare a throttle sample with the previous one and log what came on.
+pub fn record_throttle_sample(current: ThrottleConditions) {
+    if let Some(log) = THROTTLE_LOG.get() {
+        log.lock().unwrap_or_else(|e| e.into_inner()).record(current, unix_now());
+    }
+}
+
+/// Throttle events of the last 24 hours; None when the log was not started.
+pub fn get_throttle_event_summary() -> Option<ThrottleEventSummary> {
+    THROTTLE_LOG.get().map(|log| log.lock().unwrap_or_else(|e| e.into_inner()).summary(unix_now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_760_000_000;
+
+    fn state_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("info_display_events_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_records_conditions_as_they_come_on() {
+        let mut log = ThrottleEventLog::in_memory(7);
+        let uv = ThrottleConditions::UNDER_VOLTAGE;
+        let uv_and_throttled = ThrottleConditions::from_bits(0x5);
+        log.record(uv, NOW);
+        log.record(uv, NOW + 5);
+        log.record(uv_and_throttled, NOW + 10);
+        log.record(ThrottleConditions::default(), NOW + 15);
+        log.record(uv, NOW + 20);
+
+        let flags: Vec<&str> = log.events().iter().map(|event| event.flag.as_str()).collect();
+        assert_eq!(flags, vec!["under_voltage", "currently_throttled", "under_voltage"]);
+        let summary = log.summary(NOW + 20);
+        assert_eq!(summary.count(uv), 2);
+        assert_eq!(summary.count(uv_and_throttled), 3);
+        assert_eq!(summary.count(ThrottleConditions::SOFT_TEMP_LIMIT), 0);
+        // A day later they no longer count, though they are still kept
+        assert_eq!(log.summary(NOW + 20 + DAY_SECS).count(uv), 0);
+    }
+
+    #[test]
+    fn test_file_format_and_reload() {
+        let dir = state_dir("reload");
+        let mut log = ThrottleEventLog::open(&dir, 7, NOW);
+        log.record(ThrottleConditions::from_bits(0x3), NOW);
+        assert_eq!(
+            fs::read_to_string(dir.join(EVENTS_FILE)).unwrap(),
+            format!("{{\"time\":{0},\"flag\":\"under_voltage\"}}\n{{\"time\":{0},\"flag\":\"arm_freq_capped\"}}\n", NOW)
+        );
+
+        // A line that does not parse is skipped rather than losing the log
+        let mut file = OpenOptions::new().append(true).open(dir.join(EVENTS_FILE)).unwrap();
+        file.write_all(b"{\"time\":\n").unwrap();
+        let reopened = ThrottleEventLog::open(&dir, 7, NOW + 60);
+        assert_eq!(reopened.events(), log.events());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prunes_expired_events() {
+        let dir = state_dir("prune");
+        fs::create_dir_all(&dir).unwrap();
+        let old = ThrottleEvent { time: NOW - 3 * DAY_SECS, flag: "under_voltage".to_string() };
+        let recent = ThrottleEvent { time: NOW - DAY_SECS, flag: "soft_temp_limit".to_string() };
+        fs::write(dir.join(EVENTS_FILE), events_jsonl(&[old, recent.clone()])).unwrap();
+
+        let mut log = ThrottleEventLog::open(&dir, 2, NOW);
+        assert_eq!(log.events(), std::slice::from_ref(&recent));
+        assert_eq!(fs::read_to_string(dir.join(EVENTS_FILE)).unwrap(), events_jsonl(std::slice::from_ref(&recent)));
+
+        // Expiring while running: pruned on the next write
+        log.record(ThrottleConditions::THROTTLED, NOW + 2 * DAY_SECS);
+        let remaining: Vec<&str> = log.events().iter().map(|event| event.flag.as_str()).collect();
+        assert_eq!(remaining, vec!["currently_throttled"]);
+        assert_eq!(read_events(&dir.join(EVENTS_FILE)).unwrap(), log.events());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unwritable_state_dir_counts_in_memory() {
+        // A file where the directory should be
+        let dir = state_dir("unwritable");
+        fs::write(&dir, "").unwrap();
+        let mut log = ThrottleEventLog::open(&dir.join("state"), 7, NOW);
+        log.record(ThrottleConditions::UNDER_VOLTAGE, NOW);
+        assert_eq!(log.summary(NOW).count(ThrottleConditions::UNDER_VOLTAGE), 1);
+        fs::remove_file(&dir).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+// Sources of readings other than external commands, which count under the program's name
+pub const THERMAL_SOURCE: &str = "thermal";
+pub const I2C_SOURCE: &str = "i2c";
+pub const NETWORK_SOURCE: &str = "network";
+pub const DISK_SOURCE: &str = "disk";
+pub const ONE_WIRE_SOURCE: &str = "1-wire";
+pub const PROC_SOURCE: &str = "proc";
+
+static FAILURES: Mutex<BTreeMap<String, SourceFailures>> = Mutex::new(BTreeMap::new());
+
+/// How often one source of readings failed since startup, e.g. `vcgencmd` or `thermal`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceFailures {
+    pub source: String,
+    pub count: u64,
+    pub last_error: String,
+    /// Unix seconds
+    pub last_failure: u64,
+}
+
+fn failures() -> MutexGuard<'static, BTreeMap<String, SourceFailures>> {
+    FAILURES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Count a failure to read from `source`.
+pub fn record_failure(source: &str, error: impl fmt::Display) {
+    let mut failures = failures();
+    let entry = failures.entry(source.to_string()).or_insert_with(|| SourceFailures {
+        source: source.to_string(),
+        count: 0,
+        last_error: String::new(),
+        last_failure: 0,
+    });
+    entry.count += 1;
+    entry.last_error = error.to_string();
+    entry.last_failure = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+    debug!("Reading from {} failed ({} so far): {}", source, entry.count, entry.last_error);
+}
+
+/// `result` as it is, counting an error as a failure of `source`.
+pub fn track<T, E: fmt::Display>(source: &str, result: Result<T, E>) -> Result<T, E> {
+    if let Err(e) = &result {
+        record_failure(source, e);
+    }
+    result
+}
+
+/// Every source that failed since startup, the most failures first.
+pub fn get_source_failures() -> Vec<SourceFailures> {
+    let mut sources: Vec<SourceFailures> = failures().values().cloned().collect();
+    // Stable, so sources with as many failures stay in name order
+    sources.sort_by_key(|failures| Reverse(failures.count));
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Other tests fail reads too, so only sources of their own are looked at
+    fn failures_of(source: &str) -> Option<SourceFailures> {
+        get_source_failures().into_iter().find(|failures| failures.source == source)
+    }
+
+    #[test]
+    fn test_counts_failures_per_source() {
+        assert_eq!(track::<u8, &str>("counts-ok", Ok(1)), Ok(1));
+        assert_eq!(failures_of("counts-ok"), None);
+
+        record_failure("counts-flaky", "timed out");
+        let _ = track::<u8, _>("counts-flaky", Err("no such file"));
+        let flaky = failures_of("counts-flaky").unwrap();
+        assert_eq!((flaky.count, flaky.last_error.as_str()), (2, "no such file"));
+        assert!(flaky.last_failure > 0);
+
+        for _ in 0..3 {
+            record_failure("counts-broken", "not found");
+        }
+        let sources: Vec<String> = get_source_failures().into_iter()
+            .map(|failures| failures.source)
+            .filter(|source| source.starts_with("counts-"))
+            .collect();
+        assert_eq!(sources, vec!["counts-broken", "counts-flaky"]);
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::info;
+
+use super::command::{command_timeout, run_command_with_timeout, CommandError};
+use super::failures::track;
+
+pub const DEFAULT_VCGENCMD_PATH: &str = "vcgencmd";
+
+// Failures count under this name wherever vcgencmd is installed
+pub const VCGENCMD_SOURCE: &str = "vcgencmd";
+
+// The throttle register as the Raspberry Pi firmware driver exposes it, in hex
+pub const FIRMWARE_THROTTLED_PATH: &str = "/sys/devices/platform/soc/soc:firmware/get_throttled";
+
+static VCGENCMD_PATH: OnceLock<String> = OnceLock::new();
+
+static SOURCES: OnceLock<FirmwareSources> = OnceLock::new();
+
+/// Runs external programs; tests hand in one that answers without running anything.
+pub trait CommandRunner {
+    fn run(&self, program: &str, args: &[&str]) -> Result<String, CommandError>;
+}
+
+/// Runs programs for real, with the configured timeout
+pub struct SystemCommands;
+
+impl CommandRunner for SystemCommands {
+    fn run(&self, program: &str, args: &[&str]) -> Result<String, CommandError> {
+        run_command_with_timeout(program, args, command_timeout())
+    }
+}
+
+/// Where vcgencmd is run from, e.g. /usr/bin/vcgencmd when it is not on the PATH.
+/// Only the first call has an effect.
+pub fn set_vcgencmd_path(path: &str) {
+    let _ = VCGENCMD_PATH.set(path.to_string());
+}
+
+pub fn vcgencmd_path() -> &'static str {
+    VCGENCMD_PATH.get().map_or(DEFAULT_VCGENCMD_PATH, String::as_str)
+}
+
+/// Where a firmware reading comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Sysfs,
+    Vcgencmd,
+    Unavailable,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Sysfs => write!(f, "sysfs"),
+            Source::Vcgencmd => write!(f, "vcgencmd"),
+            Source::Unavailable => write!(f, "nowhere"),
+        }
+    }
+}
+
+/// Which of the readings the kernel exposes in sysfs on this machine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SysfsReadings {
+    pub gpu_temp: bool,
+    pub cpu_freq: bool,
+    pub throttle: bool,
+}
+
+/// The source of each reading vcgencmd can provide, chosen once at startup so a
+/// missing vcgencmd is not spawned again on every update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirmwareSources {
+    pub vcgencmd: bool,
+    pub gpu_temp: Source,
+    pub cpu_freq: Source,
+    pub throttle: Source,
+}
+
+impl FirmwareSources {
+    /// sysfs is cheaper than a process for the temperature and clock; the throttle
+    /// register is read from vcgencmd where it runs, as it always was.
+    pub fn select(vcgencmd: bool, sysfs: SysfsReadings) -> Self {
+        let pick = |in_sysfs: bool, sysfs_first: bool| match (in_sysfs, vcgencmd) {
+            (true, false) => Source::Sysfs,
+            (true, true) if sysfs_first => Source::Sysfs,
+            (_, true) => Source::Vcgencmd,
+            (false, false) => Source::Unavailable,
+        };
+        Self {
+            vcgencmd,
+            gpu_temp: pick(sysfs.gpu_temp, true),
+            cpu_freq: pick(sysfs.cpu_freq, true),
+            throttle: pick(sysfs.throttle, false),
+        }
+    }
+
+    /// Runs vcgencmd from `path` once to see whether it works; installed without
+    /// access to the firmware it fails as surely as when it is missing.
+    pub fn detect(runner: &dyn CommandRunner, path: &str, sysfs: SysfsReadings) -> Self {
+        let vcgencmd = match runner.run(path, &["get_throttled"]) {
+            Ok(_) => true,
+            Err(e) => {
+                info!("vcgencmd is not usable, firmware readings come from sysfs where they can: {}", e);
+                false
+            }
+        };
+        Self::select(vcgencmd, sysfs)
+    }
+}
+
+impl fmt::Display for FirmwareSources {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GPU temperature from {}, CPU frequency from {}, throttling from {}",
+               self.gpu_temp, self.cpu_freq, self.throttle)
+    }
+}
+
+/// Pick the firmware reading sources and log them. Only the first call has an effect;
+/// readings taken before it pick them with the default vcgencmd path.
+pub fn init_firmware_sources(sysfs: SysfsReadings) -> FirmwareSources {
+    *SOURCES.get_or_init(|| {
+        let sources = FirmwareSources::detect(&SystemCommands, vcgencmd_path(), sysfs);
+        info!("Firmware readings: {}", sources);
+        sources
+    })
+}
+
+pub fn firmware_sources() -> Option<FirmwareSources> {
+    SOURCES.get().copied()
+}
+
+/// Run vcgencmd from the configured path; failures count against `vcgencmd`.
+pub fn run_vcgencmd(args: &[&str]) -> Result<String, CommandError> {
+    track(VCGENCMD_SOURCE, SystemCommands.run(vcgencmd_path(), args))
+}
+
+/// The throttle register from FIRMWARE_THROTTLED_PATH, e.g. "50005".
+pub fn parse_sysfs_throttled(contents: &str) -> Option<u32> {
+    let hex = contents.trim();
+    u32::from_str_radix(hex.strip_prefix("0x").unwrap_or(hex), 16).ok()
+}
+
+pub fn sysfs_throttled_present() -> bool {
+    Path::new(FIRMWARE_THROTTLED_PATH).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    // Answers get_throttled like a working vcgencmd at one path only, and remembers what ran
+    struct FakeRunner {
+        working_path: &'static str,
+        ran: RefCell<Vec<String>>,
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn run(&self, program: &str, args: &[&str]) -> Result<String, CommandError> {
+            self.ran.borrow_mut().push(format!("{} {}", program, args.join(" ")));
+            if program == self.working_path {
+                Ok("throttled=0x0\n".to_string())
+            } else {
+                Err(CommandError::NotFound(program.to_string()))
+            }
+        }
+    }
+
+    fn runner(working_path: &'static str) -> FakeRunner {
+        FakeRunner { working_path, ran: RefCell::new(Vec::new()) }
+    }
+
+    const ALL_SYSFS: SysfsReadings = SysfsReadings { gpu_temp: true, cpu_freq: true, throttle: true };
+
+    #[test]
+    fn test_prefers_sysfs_except_for_throttling() {
+        let fake = runner("vcgencmd");
+        let sources = FirmwareSources::detect(&fake, "vcgencmd", ALL_SYSFS);
+        assert!(sources.vcgencmd);
+        assert_eq!((sources.gpu_temp, sources.cpu_freq, sources.throttle), (Source::Sysfs, Source::Sysfs, Source::Vcgencmd));
+        assert_eq!(fake.ran.borrow().as_slice(), ["vcgencmd get_throttled"]);
+    }
+
+    #[test]
+    fn test_without_vcgencmd() {
+        // Ubuntu without libraspberrypi-bin: the kernel has everything but the throttle file
+        let sysfs = SysfsReadings { throttle: false, ..ALL_SYSFS };
+        let sources = FirmwareSources::detect(&runner("/usr/bin/vcgencmd"), "vcgencmd", sysfs);
+        assert!(!sources.vcgencmd);
+        assert_eq!((sources.gpu_temp, sources.cpu_freq, sources.throttle), (Source::Sysfs, Source::Sysfs, Source::Unavailable));
+        assert_eq!(sources.to_string(), "GPU temperature from sysfs, CPU frequency from sysfs, throttling from nowhere");
+
+        let sources = FirmwareSources::detect(&runner("/usr/bin/vcgencmd"), "vcgencmd", ALL_SYSFS);
+        assert_eq!(sources.throttle, Source::Sysfs);
+    }
+
+    #[test]
+    fn test_configured_path() {
+        let sources = FirmwareSources::detect(&runner("/usr/bin/vcgencmd"), "/usr/bin/vcgencmd", SysfsReadings::default());
+        assert_eq!((sources.gpu_temp, sources.cpu_freq, sources.throttle), (Source::Vcgencmd, Source::Vcgencmd, Source::Vcgencmd));
+    }
+
+    #[test]
+    fn test_parse_sysfs_throttled() {
+        assert_eq!(parse_sysfs_throttled("50005\n"), Some(0x50005));
+        assert_eq!(parse_sysfs_throttled("0x0\n"), Some(0));
+        assert_eq!(parse_sysfs_throttled(""), None);
+    }
+}
@@ -0,0 +1,271 @@
+use gpio_cdev::{Chip, EventRequestFlags, LineEventHandle, LineRequestFlags};
+use serde::Deserialize;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use super::placeholder;
+
+const GPIO_CHIP_PATH: &str = "/dev/gpiochip0";
+
+// Shown by gpioinfo as the consumer of the lines held
+const CONSUMER: &str = "info_display";
+
+// GPIOHANDLE_REQUEST_BIAS_* from linux/gpio.h, Linux 5.5 and later; gpio-cdev has no names for them
+const BIAS_PULL_UP: u32 = 1 << 5;
+const BIAS_PULL_DOWN: u32 = 1 << 6;
+const BIAS_DISABLE: u32 = 1 << 7;
+
+static GPIO_INPUTS: OnceLock<Mutex<Vec<WatchedInput>>> = OnceLock::new();
+
+/// Pull resistor of an input pin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GpioBias {
+    /// Left as the firmware or device tree set it
+    #[default]
+    AsIs,
+    PullUp,
+    PullDown,
+    Disabled,
+}
+
+impl GpioBias {
+    fn request_flags(self) -> u32 {
+        match self {
+            GpioBias::AsIs => 0,
+            GpioBias::PullUp => BIAS_PULL_UP,
+            GpioBias::PullDown => BIAS_PULL_DOWN,
+            GpioBias::Disabled => BIAS_DISABLE,
+        }
+    }
+}
+
+/// An input pin to watch, from a `[[gpio_inputs]]` entry of the config file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GpioInput {
+    /// BCM GPIO, the line on gpiochip0
+    pub gpio: u8,
+    pub label: String,
+    /// Active when the pin is low, e.g. a switch to ground with a pull-up
+    #[serde(default)]
+    pub active_low: bool,
+    #[serde(default)]
+    pub bias: GpioBias,
+    /// Shown while the input is active and inactive, e.g. "OPEN" and "CLOSED" for a door
+    #[serde(default = "default_active_text")]
+    pub active_text: String,
+    #[serde(default = "default_inactive_text")]
+    pub inactive_text: String,
+}
+
+fn default_active_text() -> String {
+    "ON".to_string()
+}
+
+fn default_inactive_text() -> String {
+    "OFF".to_string()
+}
+
+/// What is known about a watched input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputState {
+    /// The level, and when it last changed or else when watching started
+    Level { active: bool, since: Instant },
+    /// Held by another program or a kernel driver
+    Busy,
+    /// Could not be read
+    Unknown,
+}
+
+#[derive(Debug)]
+struct WatchedInput {
+    input: GpioInput,
+    state: InputState,
+}
+
+/// "CLOSED 2h": the input's state and how long it has been in it.
+pub fn describe_input(input: &GpioInput, state: InputState, now: Instant) -> String {
+    match state {
+        InputState::Level { active, since } => {
+            let text = if active { &input.active_text } else { &input.inactive_text };
+            format!("{} {}", text, format_age(now.saturating_duration_since(since)))
+        }
+        InputState::Busy => "busy".to_string(),
+        InputState::Unknown => placeholder().to_string(),
+    }
+}
+
+// The largest whole unit only, as it has to fit after the label
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// Start watching `inputs` for edges on a thread of its own. Only the first call has
+/// an effect; until then there are no inputs to show.
+pub fn start_gpio_watcher(inputs: &[GpioInput]) {
+    if inputs.is_empty() || GPIO_INPUTS.get().is_some() {
+        return;
+    }
+    let mut watched: Vec<WatchedInput> = inputs.iter()
+        .map(|input| WatchedInput { input: input.clone(), state: InputState::Unknown })
+        .collect();
+
+    let mut handles = Vec::new();
+    match Chip::new(GPIO_CHIP_PATH) {
+        Ok(mut chip) => {
+            for (index, watched) in watched.iter_mut().enumerate() {
+                let (state, handle) = request_input(&mut chip, &watched.input);
+                watched.state = state;
+                handles.extend(handle.map(|handle| (index, handle)));
+            }
+        }
+        Err(e) => warn!("GPIO inputs cannot be watched, {} is not usable: {}", GPIO_CHIP_PATH, e),
+    }
+
+    let states = GPIO_INPUTS.get_or_init(|| Mutex::new(watched));
+    if handles.is_empty() {
+        return;
+    }
+    info!("Watching {} GPIO input(s) on {}", handles.len(), GPIO_CHIP_PATH);
+    if let Err(e) = thread::Builder::new()
+        .name("gpio-watcher".to_string())
+        .spawn(move || watch_inputs(handles, states))
+    {
+        warn!("Failed to start the GPIO watcher: {}", e);
+    }
+}
+
+// Claim the line for edge events and read its level; a line that is in use is busy
+fn request_input(chip: &mut Chip, input: &GpioInput) -> (InputState, Option<LineEventHandle>) {
+    let line = match chip.get_line(u32::from(input.gpio)) {
+        Ok(line) => line,
+        Err(e) => {
+            warn!("GPIO input {} (GPIO {}): {}", input.label, input.gpio, e);
+            return (InputState::Unknown, None);
+        }
+    };
+    let mut flags = LineRequestFlags::INPUT.bits() | input.bias.request_flags();
+    if input.active_low {
+        flags |= LineRequestFlags::ACTIVE_LOW.bits();
+    }
+    let request = line.events(LineRequestFlags::from_bits_retain(flags), EventRequestFlags::BOTH_EDGES, CONSUMER);
+    match request.and_then(|handle| Ok((handle.get_value()?, handle))) {
+        Ok((value, handle)) => (InputState::Level { active: value == 1, since: Instant::now() }, Some(handle)),
+        Err(e) => {
+            if line.info().is_ok_and(|info| info.is_used()) {
+                warn!("GPIO input {} (GPIO {}) is in use by something else", input.label, input.gpio);
+                (InputState::Busy, None)
+            } else {
+                warn!("GPIO input {} (GPIO {}) cannot be watched: {}", input.label, input.gpio, e);
+                (InputState::Unknown, None)
+            }
+        }
+    }
+}
+
+// Wait for edges on all the lines at once and keep their states up to date
+fn watch_inputs(mut handles: Vec<(usize, LineEventHandle)>, states: &Mutex<Vec<WatchedInput>>) {
+    let mut fds: Vec<libc::pollfd> = handles.iter()
+        .map(|(_, handle)| libc::pollfd { fd: handle.file().as_raw_fd(), events: libc::POLLIN, revents: 0 })
+        .collect();
+    loop {
+        // SAFETY: fds is a valid array of fds.len() pollfds, and the descriptors stay open
+        // as long as the handles they belong to
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+        if ready < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            warn!("GPIO watcher stopped: {}", e);
+            return;
+        }
+
+        for (pollfd, (index, handle)) in fds.iter().zip(handles.iter_mut()) {
+            if pollfd.revents & libc::POLLIN == 0 {
+                continue;
+            }
+            // A bouncing contact sends a burst of edges; the level read after the
+            // event is what it settled on
+            let level = handle.get_event().and_then(|_| handle.get_value());
+            let mut states = states.lock().unwrap_or_else(|e| e.into_inner());
+            let watched = &mut states[*index];
+            match level {
+                Ok(value) => {
+                    let active = value == 1;
+                    if !matches!(watched.state, InputState::Level { active: was, .. } if was == active) {
+                        debug!("GPIO input {} is now {}", watched.input.label,
+                               if active { &watched.input.active_text } else { &watched.input.inactive_text });
+                        watched.state = InputState::Level { active, since: Instant::now() };
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to read GPIO input {}: {}", watched.input.label, e);
+                    watched.state = InputState::Unknown;
+                }
+            }
+        }
+    }
+}
+
+/// The watched inputs as (label, state) pairs, in the order they are configured; empty
+/// when the watcher was not started.
+pub fn get_gpio_inputs() -> Vec<(String, String)> {
+    let Some(states) = GPIO_INPUTS.get() else { return Vec::new() };
+    let now = Instant::now();
+    states.lock().unwrap_or_else(|e| e.into_inner()).iter()
+        .map(|watched| (watched.input.label.clone(), describe_input(&watched.input, watched.state, now)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn door() -> GpioInput {
+        GpioInput {
+            gpio: 17,
+            label: "Door".to_string(),
+            active_low: true,
+            bias: GpioBias::PullUp,
+            active_text: "OPEN".to_string(),
+            inactive_text: "CLOSED".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_describe_input() {
+        let since = Instant::now();
+        let now = since + Duration::from_secs(2 * 3600 + 59 * 60);
+        assert_eq!(describe_input(&door(), InputState::Level { active: false, since }, now), "CLOSED 2h");
+        assert_eq!(describe_input(&door(), InputState::Level { active: true, since: now }, now), "OPEN 0s");
+        assert_eq!(describe_input(&door(), InputState::Busy, now), "busy");
+        assert_eq!(describe_input(&door(), InputState::Unknown, now), placeholder());
+    }
+
+    #[test]
+    fn test_format_age() {
+        for (secs, expected) in [(59, "59s"), (60, "1m"), (3599, "59m"), (3600, "1h"), (86399, "23h"), (86400, "1d")] {
+            assert_eq!(format_age(Duration::from_secs(secs)), expected);
+        }
+    }
+
+    #[test]
+    fn test_bias_flags() {
+        assert_eq!(GpioBias::AsIs.request_flags(), 0);
+        assert_eq!(GpioBias::PullUp.request_flags(), 0x20);
+        assert_eq!(GpioBias::PullDown.request_flags(), 0x40);
+        assert_eq!(GpioBias::Disabled.request_flags(), 0x80);
+    }
+}
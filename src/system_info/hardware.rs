@@ -1,99 +1,601 @@
+use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use tracing::warn;
+
+use super::events::DEFAULT_STATE_DIR;
+use super::firmware::run_vcgencmd;
+use super::network::{get_hostname, read_mac_address};
+use super::placeholder;
+use super::sensors::get_firmware_sources;
+use super::storage::{read_mounts, MountEntry};
 
 pub fn get_pi_model() -> String {
+    read_pi_model().unwrap_or_else(|| placeholder().to_string())
+}
+
+/// The board's model name, None if neither the device tree nor /proc/cpuinfo has one.
+pub fn read_pi_model() -> Option<String> {
     // Try reading from device tree first
     if let Ok(model) = fs::read_to_string("/proc/device-tree/model") {
         let model_clean = model.replace('\0', "").trim().to_string();
         if !model_clean.is_empty() {
-            return model_clean;
+            return Some(model_clean);
         }
     }
     
     // Fallback to cpuinfo
-    if let Ok(cpuinfo) = fs::read_to_string("/proc/cpuinfo") {
-        for line in cpuinfo.lines() {
-            if line.starts_with("Model") {
-                if let Some(model) = line.split(':').nth(1) {
-                    return model.trim().to_string();
-                }
-            }
-        }
-    }
-    
-    "Unknown".to_string()
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo.lines()
+        .filter(|line| line.starts_with("Model"))
+        .find_map(|line| line.split(':').nth(1))
+        .map(|model| model.trim().to_string())
 }
 
 pub fn get_serial_number() -> String {
+    read_serial_number().unwrap_or_else(|| placeholder().to_string())
+}
+
+/// The board's serial number, None off a Pi.
+pub fn read_serial_number() -> Option<String> {
     // Try reading from device tree first
     if let Ok(serial) = fs::read_to_string("/proc/device-tree/serial-number") {
         let serial_clean = serial.replace('\0', "").trim().to_string();
         if !serial_clean.is_empty() {
-            return serial_clean;
+            return Some(serial_clean);
         }
     }
     
     // Fallback to cpuinfo
-    if let Ok(cpuinfo) = fs::read_to_string("/proc/cpuinfo") {
-        for line in cpuinfo.lines() {
-            if line.starts_with("Serial") {
-                if let Some(serial) = line.split(':').nth(1) {
-                    return serial.trim().to_string();
-                }
-            }
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo.lines()
+        .filter(|line| line.starts_with("Serial"))
+        .find_map(|line| line.split(':').nth(1))
+        .map(|serial| serial.trim().to_string())
+}
+
+/// What a board's revision code says about it, e.g. c03114 is a 4GB Pi 4B, rev 1.4,
+/// made by Sony UK.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevisionInfo {
+    pub code: u32,
+    pub model: &'static str,
+    pub revision: String,
+    pub memory_mb: u32,
+    pub manufacturer: &'static str,
+    pub processor: &'static str,
+}
+
+impl RevisionInfo {
+    /// "512MB", "4GB"
+    pub fn memory(&self) -> String {
+        if self.memory_mb >= 1024 {
+            format!("{}GB", self.memory_mb / 1024)
+        } else {
+            format!("{}MB", self.memory_mb)
         }
     }
-    
-    "Unknown".to_string()
 }
 
+impl fmt::Display for RevisionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}, rev {}", self.memory(), self.manufacturer, self.revision)
+    }
+}
+
+/// The board's revision code from /proc/cpuinfo, decoded.
+pub fn get_revision_info() -> Result<RevisionInfo> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").context("reading /proc/cpuinfo")?;
+    let code = cpuinfo_revision(&cpuinfo).ok_or_else(|| anyhow!("no revision code in /proc/cpuinfo"))?;
+    decode_revision(code).ok_or_else(|| anyhow!("unknown revision code {:x}", code))
+}
+
+// "Revision\t: c03114" as a number
+fn cpuinfo_revision(cpuinfo: &str) -> Option<u32> {
+    cpuinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim() != "Revision" {
+            return None;
+        }
+        u32::from_str_radix(value.trim(), 16).ok()
+    })
+}
+
+// New-style codes set bit 23 and pack the fields as NOQuuuWuFMMMCCCCPPPPTTTTTTTTRRRR:
+// revision, type, processor, manufacturer and memory size from the lowest bits up
+const NEW_STYLE_FLAG: u32 = 1 << 23;
+
+const PROCESSORS: [&str; 5] = ["BCM2835", "BCM2836", "BCM2837", "BCM2711", "BCM2712"];
+
+const MANUFACTURERS: [&str; 6] = ["Sony UK", "Egoman", "Embest", "Sony Japan", "Embest", "Stadium"];
+
+// Indexed by the type field; gaps are types that were never released
+const BOARD_TYPES: [Option<&str>; 27] = [
+    Some("A"), Some("B"), Some("A+"), Some("B+"), Some("2B"), Some("Alpha"), Some("CM1"), None,
+    Some("3B"), Some("Zero"), Some("CM3"), None, Some("Zero W"), Some("3B+"), Some("3A+"), Some("Internal"),
+    Some("CM3+"), Some("4B"), Some("Zero 2 W"), Some("400"), Some("CM4"), Some("CM4S"), Some("Internal"),
+    Some("5"), Some("CM5"), Some("500"), Some("CM5 Lite"),
+];
+
+// Boards from before the new-style codes: (code, model, revision, memory MB, manufacturer)
+const LEGACY_REVISIONS: [(u32, &str, &str, u32, &str); 18] = [
+    (0x0002, "B", "1.0", 256, "Egoman"),
+    (0x0003, "B", "1.0", 256, "Egoman"),
+    (0x0004, "B", "2.0", 256, "Sony UK"),
+    (0x0005, "B", "2.0", 256, "Qisda"),
+    (0x0006, "B", "2.0", 256, "Egoman"),
+    (0x0007, "A", "2.0", 256, "Egoman"),
+    (0x0008, "A", "2.0", 256, "Sony UK"),
+    (0x0009, "A", "2.0", 256, "Qisda"),
+    (0x000d, "B", "2.0", 512, "Egoman"),
+    (0x000e, "B", "2.0", 512, "Sony UK"),
+    (0x000f, "B", "2.0", 512, "Egoman"),
+    (0x0010, "B+", "1.2", 512, "Sony UK"),
+    (0x0011, "CM1", "1.0", 512, "Sony UK"),
+    (0x0012, "A+", "1.1", 256, "Sony UK"),
+    (0x0013, "B+", "1.2", 512, "Embest"),
+    (0x0014, "CM1", "1.0", 512, "Embest"),
+    (0x0015, "A+", "1.1", 256, "Embest"),
+    (0x0016, "B+", "1.2", 512, "Sony UK"),
+];
+
+/// Decode a revision code, or None for codes that are not documented.
+pub fn decode_revision(code: u32) -> Option<RevisionInfo> {
+    if code & NEW_STYLE_FLAG == 0 {
+        // Overvolted old boards have the warranty bit (24) set in front of their code
+        let legacy = code & 0x00FF_FFFF;
+        let &(_, model, revision, memory_mb, manufacturer) = LEGACY_REVISIONS.iter().find(|entry| entry.0 == legacy)?;
+        return Some(RevisionInfo {
+            code,
+            model,
+            revision: revision.to_string(),
+            memory_mb,
+            manufacturer,
+            processor: PROCESSORS[0],
+        });
+    }
+
+    let revision = code & 0xF;
+    let board_type = (code >> 4) & 0xFF;
+    let processor = (code >> 12) & 0xF;
+    let manufacturer = (code >> 16) & 0xF;
+    let memory = (code >> 20) & 0x7;
+    Some(RevisionInfo {
+        code,
+        model: (*BOARD_TYPES.get(board_type as usize)?)?,
+        revision: format!("1.{}", revision),
+        memory_mb: 256 << memory,
+        manufacturer: MANUFACTURERS.get(manufacturer as usize)?,
+        processor: PROCESSORS.get(processor as usize)?,
+    })
+}
+
+/// Where the header's GPIO and I2C come from: the Broadcom SoC itself up to the Pi 4,
+/// the RP1 I/O controller on the Pi 5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Broadcom,
+    Rp1,
+}
+
+impl Platform {
+    pub fn from_revision(info: &RevisionInfo) -> Self {
+        if info.processor == "BCM2712" { Platform::Rp1 } else { Platform::Broadcom }
+    }
+
+    /// Labels of the gpiochip that drives the 40-pin header.
+    pub fn header_gpio_labels(self) -> &'static [&'static str] {
+        match self {
+            Platform::Broadcom => &["pinctrl-bcm2711", "pinctrl-bcm2835"],
+            Platform::Rp1 => &["pinctrl-rp1"],
+        }
+    }
+}
+
+/// The platform from the revision code, or from the model name where there is no code.
+pub fn get_platform() -> Platform {
+    match get_revision_info() {
+        Ok(info) => Platform::from_revision(&info),
+        Err(_) if get_pi_model().starts_with("Raspberry Pi 5") => Platform::Rp1,
+        Err(_) => Platform::Broadcom,
+    }
+}
+
+// Where the firmware revision is recorded when vcgencmd is not available
+const FIRMWARE_REVISION_PATHS: [&str; 2] = ["/boot/firmware/.firmware_revision", "/boot/.firmware_revision"];
+const BOOTLOADER_VERSION_PATH: &str = "/proc/device-tree/chosen/bootloader/version";
+
+/// Firmware build date and revision, from the most specific source available:
+/// `vcgencmd version`, then the `.firmware_revision` file, then the bootloader
+/// version in the device tree.
 pub fn get_firmware_version() -> String {
-    match std::process::Command::new("vcgencmd")
-        .arg("version")
-        .output()
-    {
-        Ok(output) => {
-            let version_str = String::from_utf8_lossy(&output.stdout);
-            // Extract just the date part from the firmware version
-            for line in version_str.lines() {
-                if line.contains("version") {
-                    if let Some(date_start) = line.find("(") {
-                        if let Some(date_end) = line.find(")") {
-                            return line[date_start + 1..date_end].to_string();
-                        }
-                    }
-                }
+    if get_firmware_sources().vcgencmd {
+        if let Some(version) = run_vcgencmd(&["version"]).ok().and_then(|output| parse_vcgencmd_version(&output)) {
+            return version;
+        }
+    }
+
+    for path in FIRMWARE_REVISION_PATHS {
+        if let Ok(revision) = fs::read_to_string(path) {
+            if let Some(hash) = short_hash(revision.trim()) {
+                return hash.to_string();
             }
-            "Unknown".to_string()
         }
-        Err(_) => "N/A".to_string()
+    }
+
+    if let Ok(version) = fs::read_to_string(BOOTLOADER_VERSION_PATH) {
+        let version = version.replace('\0', "").trim().to_string();
+        if let Some(hash) = short_hash(&version) {
+            return hash.to_string();
+        }
+    }
+
+    placeholder().to_string()
+}
+
+// `vcgencmd version` prints the build date and time on the first line and the
+// source revision on a "version <hash> (...)" line:
+//   Mar 17 2023 10:50:39
+//   Copyright (c) 2012 Broadcom
+//   version 82f3750a65fadae9a38077e3c2e217ad158c8d54 (clean) (release) (start)
+// Returns "<date> (<short hash>)", or whichever of the two was found.
+fn parse_vcgencmd_version(output: &str) -> Option<String> {
+    let mut lines = output.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    let date = lines.next()
+        .filter(|line| line.chars().any(|c| c.is_ascii_digit()) && !line.starts_with("version"))
+        .map(strip_time);
+    let hash = output.lines()
+        .find_map(|line| line.trim().strip_prefix("version "))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(short_hash);
+
+    match (date, hash) {
+        (Some(date), Some(hash)) => Some(format!("{} ({})", date, hash)),
+        (Some(date), None) => Some(date.to_string()),
+        (None, Some(hash)) => Some(hash.to_string()),
+        (None, None) => None,
     }
 }
 
+// "Mar 17 2023 10:50:39" -> "Mar 17 2023"
+fn strip_time(line: &str) -> &str {
+    match line.rsplit_once(' ') {
+        Some((date, time)) if time.contains(':') => date.trim_end(),
+        _ => line,
+    }
+}
+
+// First 8 characters of a git revision, if the value looks like one
+fn short_hash(value: &str) -> Option<&str> {
+    if value.len() >= 7 && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(&value[..value.len().min(8)])
+    } else {
+        None
+    }
+}
+
+// Bookworm mounts the firmware partition at /boot/firmware, bullseye and older at /boot
+const BOOT_MOUNT_POINTS: [&str; 2] = ["/boot/firmware", "/boot"];
+const DISK_BY_PATH: &str = "/dev/disk";
+
 pub fn get_boot_partition() -> String {
-    // Try using findmnt first
-    if let Ok(output) = std::process::Command::new("findmnt")
-        .arg("-n")
-        .arg("-o")
-        .arg("SOURCE")
-        .arg("/boot")
-        .output()
-    {
-        let device = String::from_utf8_lossy(&output.stdout);
-        if !device.trim().is_empty() {
-            return device.trim().to_string();
-        }
+    let mounts = read_mounts();
+    BOOT_MOUNT_POINTS.iter()
+        .find_map(|mount_point| mount_source(&mounts, mount_point))
+        .map(|source| resolve_device(source, Path::new(DISK_BY_PATH)))
+        .unwrap_or_else(|| placeholder().to_string())
+}
+
+/// The block device the root filesystem is mounted from, e.g. /dev/mmcblk0p2.
+pub fn get_root_device() -> String {
+    let mounts = read_mounts();
+    let source = match mount_source(&mounts, "/") {
+        // Older kernels list the root as /dev/root; the kernel command line names it
+        Some("/dev/root") | None => fs::read_to_string("/proc/cmdline").ok()
+            .and_then(|cmdline| cmdline_root(&cmdline).map(str::to_string)),
+        Some(source) => Some(source.to_string()),
+    };
+    source
+        .map(|source| resolve_device(&source, Path::new(DISK_BY_PATH)))
+        .unwrap_or_else(|| placeholder().to_string())
+}
+
+/// The source of the last filesystem mounted at `mount_point`.
+pub fn mount_source<'a>(mounts: &'a [MountEntry], mount_point: &str) -> Option<&'a str> {
+    // A later mount hides the earlier ones at the same point
+    mounts.iter().rev()
+        .find(|entry| entry.mount_point == mount_point)
+        .map(|entry| entry.source.as_str())
+}
+
+// root= from the kernel command line, e.g. PARTUUID=4e639091-02
+fn cmdline_root(cmdline: &str) -> Option<&str> {
+    cmdline.split_whitespace().find_map(|arg| arg.strip_prefix("root="))
+}
+
+// PARTUUID=, UUID= and LABEL= sources as the device their /dev/disk/by-* link points to,
+// anything else (or a tag without a link) unchanged
+fn resolve_device(source: &str, disk_by: &Path) -> String {
+    let link = [("PARTUUID=", "by-partuuid"), ("UUID=", "by-uuid"), ("LABEL=", "by-label")]
+        .iter()
+        .find_map(|(tag, dir)| source.strip_prefix(tag).map(|value| disk_by.join(dir).join(value.trim_matches('"'))));
+    link.and_then(|link| fs::canonicalize(link).ok())
+        .map(|device| device.to_string_lossy().into_owned())
+        .unwrap_or_else(|| source.to_string())
+}
+
+// Holds the generated ID of a board without a serial number or MAC address
+const DEVICE_ID_FILE: &str = "device-id";
+
+static DEVICE_ID_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Who this device is, for the exporters to report it under. The ID stays the same
+/// when the hostname changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceIdentity {
+    /// 8 lowercase hex digits
+    pub id: String,
+    pub hostname: String,
+    pub serial: Option<String>,
+    pub mac: Option<String>,
+    pub model: String,
+}
+
+/// Where get_device_identity keeps a generated ID. Only the first call has an effect;
+/// until then the default state directory is used.
+pub fn set_device_id_dir(dir: &Path) {
+    let _ = DEVICE_ID_DIR.set(dir.to_path_buf());
+}
+
+pub fn get_device_identity() -> DeviceIdentity {
+    let serial = read_serial_number();
+    let mac = read_mac_address();
+    let state_dir = DEVICE_ID_DIR.get().map_or(Path::new(DEFAULT_STATE_DIR), PathBuf::as_path);
+    DeviceIdentity {
+        id: derive_device_id(serial.as_deref(), mac.as_deref(), state_dir),
+        hostname: get_hostname().unwrap_or_else(|| placeholder().to_string()),
+        serial,
+        mac,
+        model: get_pi_model(),
     }
-    
-    // Fallback to /proc/mounts
-    if let Ok(contents) = fs::read_to_string("/proc/mounts") {
-        for line in contents.lines() {
-            if line.contains(" /boot ") {
-                if let Some(device) = line.split_whitespace().next() {
-                    return device.to_string();
-                }
-            }
+}
+
+/// The last 8 hex digits of the CPU serial; without a usable serial a hash of the MAC
+/// address, and without either an ID generated once and kept in `state_dir`.
+pub fn derive_device_id(serial: Option<&str>, mac: Option<&str>, state_dir: &Path) -> String {
+    // Boards and VMs without a real serial report all zeros
+    let serial = serial.map(|serial| serial.trim().to_lowercase())
+        .filter(|serial| serial.len() >= 8 && serial.chars().all(|c| c.is_ascii_hexdigit()))
+        .filter(|serial| serial.chars().any(|c| c != '0'));
+    if let Some(serial) = serial {
+        return serial[serial.len() - 8..].to_string();
+    }
+
+    let mac = mac.map(|mac| mac.to_lowercase().replace([':', '-'], ""))
+        .filter(|mac| mac.len() == 12 && mac.chars().all(|c| c.is_ascii_hexdigit()))
+        .filter(|mac| mac.chars().any(|c| c != '0'));
+    if let Some(mac) = mac {
+        return hash_id(&mac);
+    }
+
+    persisted_device_id(state_dir)
+}
+
+/// 8 hex digits of a 32-bit FNV-1a hash, the same on every build unlike std's hasher.
+pub fn hash_id(value: &str) -> String {
+    let hash = value.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193));
+    format!("{:08x}", hash)
+}
+
+// The ID in `state_dir`, generated and written there first if there is none. When it
+// cannot be written the ID only lasts until the next restart.
+fn persisted_device_id(state_dir: &Path) -> String {
+    let path = state_dir.join(DEVICE_ID_FILE);
+    let saved = fs::read_to_string(&path).ok()
+        .map(|contents| contents.trim().to_lowercase())
+        .filter(|id| id.len() == 8 && id.chars().all(|c| c.is_ascii_hexdigit()));
+    if let Some(id) = saved {
+        return id;
+    }
+
+    let id = random_id();
+    if let Err(e) = fs::create_dir_all(state_dir).and_then(|_| fs::write(&path, format!("{}\n", id))) {
+        warn!("Device ID {} is not kept across restarts, {} is not writable: {}", id, path.display(), e);
+    }
+    id
+}
+
+// The kernel hands out a fresh random UUID on every read
+fn random_id() -> String {
+    fs::read_to_string("/proc/sys/kernel/random/uuid").ok()
+        .map(|uuid| uuid.trim().replace('-', ""))
+        .filter(|uuid| uuid.len() >= 8)
+        .map(|uuid| uuid[..8].to_string())
+        .unwrap_or_else(|| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            hash_id(&format!("{}-{}", now.as_nanos(), std::process::id()))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::storage::parse_mounts;
+
+    #[test]
+    fn test_decode_revision() {
+        let cases = [
+            (0xa02082, "3B", "1.2", 1024, "Sony UK", "1GB, Sony UK, rev 1.2"),
+            (0xa22082, "3B", "1.2", 1024, "Embest", "1GB, Embest, rev 1.2"),
+            (0xc03114, "4B", "1.4", 4096, "Sony UK", "4GB, Sony UK, rev 1.4"),
+            (0xd03140, "CM4", "1.0", 8192, "Sony UK", "8GB, Sony UK, rev 1.0"),
+            (0xc04170, "5", "1.0", 4096, "Sony UK", "4GB, Sony UK, rev 1.0"),
+            (0x900093, "Zero", "1.3", 512, "Sony UK", "512MB, Sony UK, rev 1.3"),
+            (0x902120, "Zero 2 W", "1.0", 512, "Sony UK", "512MB, Sony UK, rev 1.0"),
+            (0x9020e0, "3A+", "1.0", 512, "Sony UK", "512MB, Sony UK, rev 1.0"),
+            (0xa01041, "2B", "1.1", 1024, "Sony UK", "1GB, Sony UK, rev 1.1"),
+            (0x000e, "B", "2.0", 512, "Sony UK", "512MB, Sony UK, rev 2.0"),
+            (0x0013, "B+", "1.2", 512, "Embest", "512MB, Embest, rev 1.2"),
+            // Overvolted: the warranty bit in front of a legacy code
+            (0x1000002, "B", "1.0", 256, "Egoman", "256MB, Egoman, rev 1.0"),
+        ];
+        for (code, model, revision, memory_mb, manufacturer, display) in cases {
+            let info = decode_revision(code).unwrap_or_else(|| panic!("{:x} not decoded", code));
+            assert_eq!((info.model, info.revision.as_str(), info.memory_mb, info.manufacturer), (model, revision, memory_mb, manufacturer), "{:x}", code);
+            assert_eq!(info.to_string(), display, "{:x}", code);
         }
     }
-    
-    "Unknown".to_string()
-}
\ No newline at end of file
+
+    #[test]
+    fn test_platform_from_revision() {
+        let platform = |code| Platform::from_revision(&decode_revision(code).unwrap());
+        assert_eq!(platform(0xc04170), Platform::Rp1);
+        assert_eq!(platform(0xd04170), Platform::Rp1);
+        assert_eq!(platform(0xc03114), Platform::Broadcom);
+        assert_eq!(platform(0x000e), Platform::Broadcom);
+        assert_eq!(decode_revision(0xc03114).unwrap().processor, "BCM2711");
+    }
+
+    #[test]
+    fn test_decode_unknown_revision() {
+        assert_eq!(decode_revision(0x0001), None);
+        // Type 0x7 was never released, manufacturer 0xf does not exist
+        assert_eq!(decode_revision(0xa02070), None);
+        assert_eq!(decode_revision(0xaf2082), None);
+    }
+
+    #[test]
+    fn test_cpuinfo_revision() {
+        let cpuinfo = "processor\t: 3\nBogoMIPS\t: 108.00\n\nHardware\t: BCM2835\nRevision\t: c03114\nSerial\t\t: 10000000c0ffee42\n";
+        assert_eq!(cpuinfo_revision(cpuinfo), Some(0xc03114));
+        assert_eq!(cpuinfo_revision("processor\t: 0\n"), None);
+    }
+
+    #[test]
+    fn test_parse_vcgencmd_version_pi4() {
+        let output = "Mar 17 2023 10:50:39 \nCopyright (c) 2012 Broadcom\nversion 82f3750a65fadae9a38077e3c2e217ad158c8d54 (clean) (release) (start)\n";
+        assert_eq!(parse_vcgencmd_version(output).as_deref(), Some("Mar 17 2023 (82f3750a)"));
+    }
+
+    #[test]
+    fn test_parse_vcgencmd_version_pi5() {
+        let output = "2024/09/10 18:50:29 \nCopyright (c) 2012 Broadcom\nversion 1e5fc2c2 (release) (embedded)\n";
+        assert_eq!(parse_vcgencmd_version(output).as_deref(), Some("2024/09/10 (1e5fc2c2)"));
+    }
+
+    #[test]
+    fn test_parse_vcgencmd_version_partial() {
+        assert_eq!(
+            parse_vcgencmd_version("version 99c5a8f1f4fa4d7ca81ba2c8ccd2b46d5ab2b34a (clean)\n").as_deref(),
+            Some("99c5a8f1")
+        );
+        assert_eq!(parse_vcgencmd_version("Jan 27 2021 22:26:53\n").as_deref(), Some("Jan 27 2021"));
+    }
+
+    #[test]
+    fn test_parse_vcgencmd_version_unusable() {
+        assert_eq!(parse_vcgencmd_version(""), None);
+        assert_eq!(parse_vcgencmd_version("VCHI initialization failed\n"), None);
+    }
+
+    #[test]
+    fn test_short_hash() {
+        assert_eq!(short_hash("82f3750a65fadae9a38077e3c2e217ad158c8d54"), Some("82f3750a"));
+        assert_eq!(short_hash("clean"), None);
+    }
+
+    const BULLSEYE_MOUNTS: &str = "\
+/dev/root / ext4 rw,noatime 0 0
+devtmpfs /dev devtmpfs rw,relatime,size=1867796k,nr_inodes=466949,mode=755 0 0
+proc /proc proc rw,relatime 0 0
+/dev/mmcblk0p1 /boot vfat rw,relatime,fmask=0022,dmask=0022,codepage=437,iocharset=ascii,shortname=mixed,errors=remount-ro 0 0
+";
+
+    const BOOKWORM_MOUNTS: &str = "\
+/dev/mmcblk0p2 / ext4 rw,noatime 0 0
+devtmpfs /dev devtmpfs rw,relatime,size=3972548k,nr_inodes=993137,mode=755 0 0
+/dev/mmcblk0p1 /boot/firmware vfat rw,relatime,fmask=0022,dmask=0022,codepage=437,iocharset=ascii,shortname=mixed,errors=remount-ro 0 0
+tmpfs /run/user/1000 tmpfs rw,nosuid,nodev,relatime,size=794432k,nr_inodes=198608,mode=700,uid=1000,gid=1000 0 0
+";
+
+    fn boot_source(mounts: &[MountEntry]) -> Option<&str> {
+        BOOT_MOUNT_POINTS.iter().find_map(|mount_point| mount_source(mounts, mount_point))
+    }
+
+    #[test]
+    fn test_boot_partition_bullseye() {
+        let mounts = parse_mounts(BULLSEYE_MOUNTS);
+        assert_eq!(boot_source(&mounts), Some("/dev/mmcblk0p1"));
+        assert_eq!(mount_source(&mounts, "/"), Some("/dev/root"));
+    }
+
+    #[test]
+    fn test_boot_partition_bookworm() {
+        let mounts = parse_mounts(BOOKWORM_MOUNTS);
+        assert_eq!(boot_source(&mounts), Some("/dev/mmcblk0p1"));
+        assert_eq!(mount_source(&mounts, "/"), Some("/dev/mmcblk0p2"));
+        // /boot is a directory on the root filesystem here, not a mount
+        assert_eq!(mount_source(&mounts, "/boot"), None);
+    }
+
+    #[test]
+    fn test_cmdline_root() {
+        let cmdline = "console=serial0,115200 console=tty1 root=PARTUUID=4e639091-02 rootfstype=ext4 fsck.repair=yes rootwait\n";
+        assert_eq!(cmdline_root(cmdline), Some("PARTUUID=4e639091-02"));
+        assert_eq!(cmdline_root("quiet splash"), None);
+    }
+
+    #[test]
+    fn test_resolve_device() {
+        let base = std::env::temp_dir().join(format!("info_display_disk_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(base.join("by-partuuid")).unwrap();
+        fs::write(base.join("mmcblk0p1"), "").unwrap();
+        std::os::unix::fs::symlink("../mmcblk0p1", base.join("by-partuuid").join("4e639091-01")).unwrap();
+        let device = fs::canonicalize(base.join("mmcblk0p1")).unwrap();
+
+        assert_eq!(resolve_device("PARTUUID=4e639091-01", &base), device.to_string_lossy());
+        // Unknown tags and plain devices are shown as they are
+        assert_eq!(resolve_device("PARTUUID=deadbeef-01", &base), "PARTUUID=deadbeef-01");
+        assert_eq!(resolve_device("/dev/sda1", &base), "/dev/sda1");
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_device_id_fallbacks() {
+        let unused = Path::new("/nonexistent");
+        // The serial wins, whatever the MAC
+        assert_eq!(derive_device_id(Some("10000000C0FFEE42"), Some("DC:A6:32:12:34:56"), unused), "c0ffee42");
+        // No usable serial: the MAC's hash, however it is written
+        let from_mac = derive_device_id(Some("0000000000000000"), Some("DC:A6:32:12:34:56"), unused);
+        assert_eq!(from_mac, hash_id("dca632123456"));
+        assert_eq!(derive_device_id(None, Some("dc-a6-32-12-34-56"), unused), from_mac);
+        assert_eq!(derive_device_id(Some("n/a"), Some("DC:A6:32:12:34:56"), unused), from_mac);
+        assert_eq!(hash_id(""), "811c9dc5");
+    }
+
+    #[test]
+    fn test_device_id_persisted() {
+        let dir = std::env::temp_dir().join(format!("info_display_device_id_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let id = derive_device_id(None, Some("00:00:00:00:00:00"), &dir);
+        assert_eq!(id.len(), 8);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(fs::read_to_string(dir.join(DEVICE_ID_FILE)).unwrap(), format!("{}\n", id));
+        // Read back rather than generated again
+        assert_eq!(derive_device_id(None, None, &dir), id);
+
+        // A damaged file is replaced
+        fs::write(dir.join(DEVICE_ID_FILE), "not an id").unwrap();
+        let replaced = derive_device_id(None, None, &dir);
+        assert_eq!(fs::read_to_string(dir.join(DEVICE_ID_FILE)).unwrap().trim(), replaced);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
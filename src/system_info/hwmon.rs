@@ -0,0 +1,86 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const HWMON_SYSFS_PATH: &str = "/sys/class/hwmon";
+
+/// The hwmon device named like the first of `names` that has one, and that has `file`
+/// (e.g. `temp1_input` or `pwm1`). Devices are searched in hwmonN order.
+pub fn find_hwmon(names: &[&str], file: &str) -> Option<PathBuf> {
+    find_hwmon_in(Path::new(HWMON_SYSFS_PATH), names, file)
+}
+
+pub fn find_hwmon_in(base: &Path, names: &[&str], file: &str) -> Option<PathBuf> {
+    let devices = hwmon_devices(base);
+    names.iter().find_map(|wanted| {
+        devices.iter()
+            .find(|(name, path)| name == wanted && path.join(file).exists())
+            .map(|(_, path)| path.clone())
+    })
+}
+
+/// A temperature input such as `temp1_input`, in °C.
+pub fn read_hwmon_temp(device: &Path, input: &str) -> Result<f32> {
+    let millidegrees: i32 = fs::read_to_string(device.join(input))?.trim().parse()?;
+    Ok(millidegrees as f32 / 1000.0)
+}
+
+// (name, path) of every hwmonN directory, sorted numerically so hwmon10 comes after hwmon9
+fn hwmon_devices(base: &Path) -> Vec<(String, PathBuf)> {
+    let mut devices: Vec<_> = fs::read_dir(base)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let index = entry.file_name().to_str()?.strip_prefix("hwmon")?.parse::<u32>().ok()?;
+            let path = entry.path();
+            let name = fs::read_to_string(path.join("name")).ok()?.trim().to_string();
+            Some((index, name, path))
+        })
+        .collect();
+    devices.sort_by_key(|(index, _, _)| *index);
+    devices.into_iter().map(|(_, name, path)| (name, path)).collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::env;
+
+    // (index, name, files with their contents)
+    pub(crate) type FakeHwmon<'a> = (u32, &'a str, &'a [(&'a str, &'a str)]);
+
+    // A throwaway /sys/class/hwmon
+    pub(crate) fn fake_hwmon_tree(label: &str, devices: &[FakeHwmon]) -> PathBuf {
+        let base = env::temp_dir().join(format!("info_display_hwmon_{}_{}", label, std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        for (index, name, files) in devices {
+            let device = base.join(format!("hwmon{}", index));
+            fs::create_dir_all(&device).unwrap();
+            fs::write(device.join("name"), format!("{}\n", name)).unwrap();
+            for (file, contents) in *files {
+                fs::write(device.join(file), contents).unwrap();
+            }
+        }
+        base
+    }
+
+    #[test]
+    fn test_find_hwmon() {
+        let base = fake_hwmon_tree("find", &[
+            (0, "cpu_thermal", &[("temp1_input", "51900\n")]),
+            (2, "pwmfan", &[("fan1_input", "0\n")]),
+            (10, "pwmfan", &[("pwm1", "0\n")]),
+            (11, "rp1_adc", &[("temp1_input", "47200\n")]),
+        ]);
+
+        // The first name that matches wins, not the first device
+        assert_eq!(find_hwmon_in(&base, &["rp1_adc", "cpu_thermal"], "temp1_input"), Some(base.join("hwmon11")));
+        // Devices without the file are skipped
+        assert_eq!(find_hwmon_in(&base, &["pwmfan"], "pwm1"), Some(base.join("hwmon10")));
+        assert_eq!(find_hwmon_in(&base, &["vc4"], "temp1_input"), None);
+        assert_eq!(read_hwmon_temp(&base.join("hwmon0"), "temp1_input").unwrap(), 51.9);
+        assert!(read_hwmon_temp(&base.join("hwmon2"), "temp1_input").is_err());
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -1,11 +1,57 @@
+pub mod battery;
+pub mod cache;
+pub mod command;
+pub mod demo;
+pub mod dht22;
+pub mod events;
+pub mod failures;
+pub mod firmware;
+pub mod gpio;
+pub mod hwmon;
 pub mod network;
+pub mod placeholder;
+pub mod procfs;
+pub mod processes;
 pub mod hardware;
 pub mod sensors;
+pub mod smoothing;
+pub mod snapshot;
+pub mod source;
 pub mod storage;
 pub mod system;
+pub mod time;
 
-pub use network::*;
-pub use hardware::*;
-pub use sensors::*;
-pub use storage::*;
-pub use system::*;
\ No newline at end of file
+pub use battery::BatteryHat;
+pub use cache::SystemInfoCache;
+pub use demo::DemoDataSource;
+pub use dht22::Dht22Sensor;
+pub use events::{get_throttle_event_summary, ThrottleEventSummary};
+pub use failures::{get_source_failures, SourceFailures};
+pub use smoothing::{TempSmoother, TempSmoothing};
+pub use snapshot::{RefreshRequirements, SystemSnapshot};
+pub use source::{DataSource, LiveDataSource};
+pub use network::{
+    get_connection_count, get_domain, get_hostname, get_interface_info, get_interface_stats, get_ip_address, get_ip_addresses,
+    get_ipv6_address, get_link_speed, get_mac_address, get_primary_link, get_wifi_bitrate, get_wifi_block, get_wifi_country,
+    get_wired_interface, list_physical_interfaces, read_mac_address, HostnameSource, IfStats, InterfaceInfo, Ipv6Entry, RfkillState,
+    WifiBlock,
+};
+pub use placeholder::placeholder;
+pub use processes::{get_process_summary, ProcessSummary};
+pub use time::{get_clock_sync, ClockSync};
+// Screens read these through LiveDataSource, keep them available to library users.
+// The older `*_display` wrappers stay in their modules and are not part of this list.
+pub use hardware::{
+    decode_revision, get_boot_partition, get_device_identity, get_firmware_version, get_pi_model, get_platform, get_revision_info,
+    get_root_device, get_serial_number, read_pi_model, read_serial_number, DeviceIdentity, Platform, RevisionInfo,
+};
+pub use sensors::{
+    format_cpu_freq, format_cpu_temp, format_gpu_temp, format_i2c_devices, get_1wire_sensors, get_all_thermal_zones, get_cpu_freq,
+    get_cpu_temp, get_cpu_trip_points, get_firmware_sources, get_gpio_states, get_gpu_temp, get_header_gpio_base, get_header_i2c_bus,
+    get_i2c_devices, get_spi_devices, get_throttle_status, has_expansion_buses, ThrottleConditions, ThrottleFlags, TripPoint, THROTTLE_FLAGS,
+};
+pub use storage::{
+    format_disk, format_memory, format_swap, format_usage, get_disk_usage, get_memory_usage, get_mount_inode_usage, get_readonly_mounts,
+    get_root_inode_usage, get_swap_usage, percent_used, read_mounts, usage_percent, FsStats, MountEntry, Usage,
+};
+pub use system::{format_boot_time, format_uptime, get_reboot_notice, get_reboot_required, get_time_synced, get_uptime, read_uptime_secs};
\ No newline at end of file
@@ -1,9 +1,99 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use get_if_addrs::get_if_addrs;
+use std::ffi::{CStr, CString};
+use std::fmt;
 use std::fs;
+use std::net::Ipv6Addr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use super::command::{run_command, CommandError};
+use super::failures::{track, NETWORK_SOURCE};
+use super::placeholder;
+
+const HOSTNAME_FILE: &str = "/etc/hostname";
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+const FQDN_LOOKUP_TIMEOUT: Duration = Duration::from_millis(300);
+
+const NET_SYSFS_PATH: &str = "/sys/class/net";
+
+// Every IPv6 address with its interface, scope and flags; unlike getifaddrs() it tells
+// temporary (privacy) addresses apart
+const IF_INET6_PATH: &str = "/proc/net/if_inet6";
+const IFA_F_TEMPORARY: u32 = 0x01;
+const IFA_F_DEPRECATED: u32 = 0x20;
+const IFA_F_TENTATIVE: u32 = 0x40;
+
+// One directory per radio, with its type and whether it is soft (rfkill, NetworkManager)
+// or hard (driver, switch) blocked
+const RFKILL_SYSFS_PATH: &str = "/sys/class/rfkill";
+// Where Raspberry Pi OS up to Bullseye keeps the wifi country
+const WPA_SUPPLICANT_CONF: &str = "/etc/wpa_supplicant/wpa_supplicant.conf";
+
+const TCP_TABLES: [&str; 2] = ["/proc/net/tcp", "/proc/net/tcp6"];
+const TCP_ESTABLISHED: &str = "01";
+
+static HOSTNAME_SOURCE: OnceLock<HostnameSource> = OnceLock::new();
+
+// Set once `iw` turns out not to be installed, so it is not looked for on every update
+static IW_MISSING: AtomicBool = AtomicBool::new(false);
+
+/// Where the hostname shown on the screens comes from
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HostnameSource {
+    /// The kernel's (transient) hostname, as `hostname` prints it
+    #[default]
+    System,
+    /// The configured static hostname in /etc/hostname
+    File,
+    /// A fixed name
+    Literal(String),
+}
+
+impl HostnameSource {
+    /// `system`, `file` or `name:<hostname>`
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        match value.to_lowercase().as_str() {
+            "system" => Some(HostnameSource::System),
+            "file" => Some(HostnameSource::File),
+            _ => match value.strip_prefix("name:") {
+                Some(name) if !name.trim().is_empty() => Some(HostnameSource::Literal(name.trim().to_string())),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Where `get_hostname` reads the hostname from. Only the first call has an effect.
+pub fn set_hostname_source(source: HostnameSource) {
+    let _ = HOSTNAME_SOURCE.set(source);
+}
+
+/// The hostname from the configured source, the kernel's unless configured otherwise.
+pub fn get_hostname() -> Option<String> {
+    read_hostname(HOSTNAME_SOURCE.get().unwrap_or(&HostnameSource::System), Path::new(HOSTNAME_FILE))
+}
+
+fn read_hostname(source: &HostnameSource, hostname_file: &Path) -> Option<String> {
+    match source {
+        HostnameSource::Literal(name) => Some(name.clone()),
+        // Falls back to the kernel's name when the file is missing or empty
+        HostnameSource::File => fs::read_to_string(hostname_file).ok()
+            .and_then(|contents| contents.lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from))
+            .or_else(|| read_hostname(&HostnameSource::System, hostname_file)),
+        HostnameSource::System => hostname::get().ok().map(|name| name.to_string_lossy().into_owned()),
+    }
+}
 
 pub fn get_ip_address() -> Result<String> {
-    let interfaces = get_if_addrs()?;
+    let interfaces = track(NETWORK_SOURCE, get_if_addrs())?;
     
     for interface in interfaces {
         if !interface.is_loopback() && interface.ip().is_ipv4() {
@@ -11,55 +101,724 @@ pub fn get_ip_address() -> Result<String> {
         }
     }
     
-    Ok("N/A".to_string())
+    Ok(placeholder().to_string())
+}
+
+/// Every non-loopback address, IPv4 first
+pub fn get_ip_addresses() -> Vec<String> {
+    let mut addresses: Vec<_> = track(NETWORK_SOURCE, get_if_addrs())
+        .map(|interfaces| interfaces.into_iter()
+            .filter(|interface| !interface.is_loopback())
+            .map(|interface| interface.ip())
+            .collect())
+        .unwrap_or_default();
+    addresses.sort_by_key(|ip| ip.is_ipv6());
+    addresses.iter().map(|ip| ip.to_string()).collect()
 }
 
 pub fn get_domain() -> String {
-    // Try to read from /etc/resolv.conf first
-    if let Ok(contents) = fs::read_to_string("/etc/resolv.conf") {
-        for line in contents.lines() {
-            if line.trim().starts_with("search ") {
-                if let Some(domain) = line.split_whitespace().nth(1) {
-                    return domain.to_string();
-                }
-            }
+    let hostname = read_hostname(&HostnameSource::File, Path::new(HOSTNAME_FILE));
+    // A fully qualified /etc/hostname says it directly
+    hostname.as_deref().and_then(domain_of)
+        .or_else(|| fs::read_to_string(RESOLV_CONF).ok()
+            .and_then(|contents| parse_resolv_search(&contents).into_iter().next()))
+        .or_else(|| hostname.as_deref().and_then(lookup_fqdn).as_deref().and_then(domain_of))
+        .unwrap_or_else(|| "local".to_string())
+}
+
+/// The search list resolv.conf content gives, in order. `domain` and `search` replace each
+/// other, so the last one wins; systemd-resolved's "search ." means there is none.
+pub fn parse_resolv_search(contents: &str) -> Vec<String> {
+    let mut search = Vec::new();
+    for line in contents.lines() {
+        let line = line.split(['#', ';']).next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("domain") => search = words.take(1).map(String::from).collect(),
+            Some("search") => search = words.map(String::from).collect(),
+            _ => {}
         }
     }
-    
-    // Fallback to hostname command
-    if let Ok(output) = std::process::Command::new("hostname").arg("-d").output() {
-        let domain = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        if !domain.is_empty() {
-            return domain;
+    search.into_iter()
+        .map(|domain| domain.trim_end_matches('.').to_string())
+        .filter(|domain| !domain.is_empty())
+        .collect()
+}
+
+// "pi.home.arpa" -> "home.arpa"
+fn domain_of(name: &str) -> Option<String> {
+    let (_, domain) = name.trim_end_matches('.').split_once('.')?;
+    (!domain.is_empty()).then(|| domain.to_string())
+}
+
+// The resolver's canonical name for `host`. A slow DNS server gives up after a short wait
+// instead of holding up the render; the lookup thread finishes on its own.
+fn lookup_fqdn(host: &str) -> Option<String> {
+    let (sender, receiver) = mpsc::channel();
+    let host = host.to_string();
+    thread::spawn(move || {
+        let _ = sender.send(canonical_name(&host));
+    });
+    receiver.recv_timeout(FQDN_LOOKUP_TIMEOUT).ok().flatten()
+}
+
+fn canonical_name(host: &str) -> Option<String> {
+    let host = CString::new(host).ok()?;
+    // SAFETY: the hints are zeroed apart from the family and flags, as getaddrinfo expects,
+    // and the list it returns is only read before it is handed back to freeaddrinfo
+    unsafe {
+        let mut hints: libc::addrinfo = std::mem::zeroed();
+        hints.ai_family = libc::AF_UNSPEC;
+        hints.ai_flags = libc::AI_CANONNAME;
+        let mut result = std::ptr::null_mut();
+        if libc::getaddrinfo(host.as_ptr(), std::ptr::null(), &hints, &mut result) != 0 || result.is_null() {
+            return None;
         }
+        let name = (*result).ai_canonname;
+        let canonical = (!name.is_null()).then(|| CStr::from_ptr(name).to_string_lossy().into_owned());
+        libc::freeaddrinfo(result);
+        canonical
     }
-    
-    "local".to_string()
 }
 
-pub fn get_mac_address() -> String {
+/// The first wired interface, e.g. `eth0` or `enp1s0`
+pub fn get_wired_interface() -> Option<String> {
+    let mut names: Vec<String> = fs::read_dir("/sys/class/net").ok()?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+
     // Look for the first ethernet interface
-    let interfaces = ["eth0", "enp", "ens"];
-    
-    for interface_prefix in &interfaces {
-        // Try to find interface that starts with this prefix
-        if let Ok(entries) = fs::read_dir("/sys/class/net") {
-            for entry in entries.flatten() {
-                let name = entry.file_name().to_string_lossy().to_string();
-                if name.starts_with(interface_prefix) {
-                    let mac_path = format!("/sys/class/net/{}/address", name);
-                    if let Ok(mac) = fs::read_to_string(mac_path) {
-                        return mac.trim().to_uppercase();
-                    }
+    ["eth0", "enp", "ens"].iter()
+        .find_map(|prefix| names.iter().find(|name| name.starts_with(prefix)).cloned())
+}
+
+/// Traffic counters and link state of one interface
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IfStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    /// Link speed in Mb/s; None while the link is down or when the driver has none (wifi)
+    pub speed: Option<u32>,
+    /// As the kernel reports it: up, down, dormant, unknown, ...
+    pub operstate: String,
+}
+
+/// Counters and link state of `name`, from /sys/class/net/<name>.
+pub fn get_interface_stats(name: &str) -> Result<IfStats> {
+    read_interface_stats(Path::new(NET_SYSFS_PATH), name)
+}
+
+fn read_interface_stats(base: &Path, name: &str) -> Result<IfStats> {
+    let device = base.join(name);
+    let counter = |counter: &str| -> Result<u64> {
+        let path = device.join("statistics").join(counter);
+        let value = fs::read_to_string(&path).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+        value.trim().parse().map_err(|e| anyhow!("{}: {}", path.display(), e))
+    };
+
+    Ok(IfStats {
+        rx_bytes: counter("rx_bytes")?,
+        tx_bytes: counter("tx_bytes")?,
+        rx_errors: counter("rx_errors")?,
+        tx_errors: counter("tx_errors")?,
+        // Reading speed fails with EINVAL while the link is down, or says -1
+        speed: fs::read_to_string(device.join("speed")).ok()
+            .and_then(|speed| speed.trim().parse::<i64>().ok())
+            .and_then(|speed| u32::try_from(speed).ok()),
+        operstate: fs::read_to_string(device.join("operstate"))
+            .map(|state| state.trim().to_string())
+            .unwrap_or_else(|_| "unknown".to_string()),
+    })
+}
+
+/// Interfaces backed by hardware, sorted by name. Virtual ones (lo, bridges, veth,
+/// tun, docker0, ...) have no `device` link and are left out.
+pub fn list_physical_interfaces() -> Vec<String> {
+    list_physical_interfaces_in(Path::new(NET_SYSFS_PATH))
+}
+
+fn list_physical_interfaces_in(base: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(base)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().join("device").exists())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn get_mac_address() -> String {
+    read_mac_address().unwrap_or_else(|| placeholder().to_string())
+}
+
+/// MAC address of the wired interface, None without one.
+pub fn read_mac_address() -> Option<String> {
+    let interface = get_wired_interface().unwrap_or_else(|| "eth0".to_string());
+    fs::read_to_string(format!("/sys/class/net/{}/address", interface)).ok()
+        .map(|mac| mac.trim().to_uppercase())
+}
+
+/// An IPv6 address as listed in /proc/net/if_inet6
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Entry {
+    pub address: Ipv6Addr,
+    pub interface: String,
+    /// IFA_F_* flags, e.g. temporary or deprecated
+    pub flags: u32,
+}
+
+/// The IPv6 address to show for `interface` (any interface when None): a global,
+/// stable one if there is one. Link-local and loopback addresses are never picked;
+/// temporary ones only when nothing else is left.
+pub fn get_ipv6_address(interface: Option<&str>) -> Option<String> {
+    let contents = fs::read_to_string(IF_INET6_PATH).ok()?;
+    select_ipv6(&parse_if_inet6(&contents), interface).map(|address| address.to_string())
+}
+
+// "fe800000000000000a0027fffe4e1e5a 02 40 20 80 eth0": address, index, prefix length, scope, flags, name
+fn parse_if_inet6(contents: &str) -> Vec<Ipv6Entry> {
+    contents.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [address, _, _, _, flags, interface] = fields[..] else {
+                return None;
+            };
+            Some(Ipv6Entry {
+                address: Ipv6Addr::from(u128::from_str_radix(address, 16).ok()?),
+                interface: interface.to_string(),
+                flags: u32::from_str_radix(flags, 16).ok()?,
+            })
+        })
+        .collect()
+}
+
+pub fn select_ipv6(entries: &[Ipv6Entry], interface: Option<&str>) -> Option<Ipv6Addr> {
+    entries.iter()
+        .filter(|entry| interface.is_none_or(|name| entry.interface == name))
+        .filter(|entry| !entry.address.is_loopback() && !entry.address.is_unicast_link_local())
+        // Lowest rank wins: stable before temporary, usable before deprecated or still
+        // being checked, globally routable before unique local (fc00::/7)
+        .min_by_key(|entry| (
+            entry.flags & IFA_F_TEMPORARY != 0,
+            entry.flags & (IFA_F_DEPRECATED | IFA_F_TENTATIVE) != 0,
+            entry.address.is_unique_local(),
+        ))
+        .map(|entry| entry.address)
+}
+
+/// Established TCP connections over IPv4 and IPv6, 0 where /proc/net cannot be read.
+/// Only the state column is looked at, nothing is resolved.
+pub fn get_connection_count() -> usize {
+    TCP_TABLES.iter()
+        .map(|path| fs::read_to_string(path).map_or(0, |table| count_established(&table)))
+        .sum()
+}
+
+// A header line, then one socket per line with its state ("st", hex) in the fourth column
+fn count_established(table: &str) -> usize {
+    table.lines()
+        .skip(1)
+        .filter(|line| line.split_whitespace().nth(3) == Some(TCP_ESTABLISHED))
+        .count()
+}
+
+/// What the network screen shows for one interface; None where it has no such detail
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InterfaceInfo {
+    pub ip: Option<String>,
+    pub mac: Option<String>,
+    pub link_speed: Option<String>,
+}
+
+/// IPv4 address (or IPv6 when it has none), MAC address and link speed of `iface`.
+pub fn get_interface_info(iface: &str) -> InterfaceInfo {
+    let mut addresses: Vec<_> = get_if_addrs()
+        .map(|interfaces| interfaces.into_iter()
+            .filter(|interface| interface.name == iface)
+            .map(|interface| interface.ip())
+            .collect())
+        .unwrap_or_default();
+    addresses.sort_by_key(|ip| ip.is_ipv6());
+
+    InterfaceInfo {
+        ip: addresses.first().map(|ip| ip.to_string()),
+        mac: fs::read_to_string(format!("/sys/class/net/{}/address", iface))
+            .ok()
+            .map(|mac| mac.trim().to_uppercase()),
+        link_speed: get_link_speed(iface),
+    }
+}
+
+/// The link of `iface`: the negotiated speed and duplex of a wired one, e.g. `1Gb FD`,
+/// and the transmit bitrate of a wireless one, e.g. `72Mb`. None when the link is down.
+pub fn get_link_speed(iface: &str) -> Option<String> {
+    let base = Path::new(NET_SYSFS_PATH);
+    if is_wireless_in(base, iface) {
+        get_wifi_bitrate(iface)
+    } else {
+        read_link_speed(base, iface)
+    }
+}
+
+/// The wired interface, or else the first wireless one, with its link, e.g.
+/// `eth0 1Gb FD` or `wlan0 down`. None without either.
+pub fn get_primary_link() -> Option<String> {
+    let iface = get_wired_interface().or_else(|| {
+        list_physical_interfaces().into_iter().find(|name| is_wireless_in(Path::new(NET_SYSFS_PATH), name))
+    })?;
+    let link = get_link_speed(&iface).unwrap_or_else(|| "down".to_string());
+    Some(format!("{} {}", iface, link))
+}
+
+// Wireless interfaces have a `wireless` directory, wired ones do not
+fn is_wireless_in(base: &Path, iface: &str) -> bool {
+    base.join(iface).join("wireless").is_dir()
+}
+
+// Reading speed fails with EINVAL while the link is down, or says -1; so does duplex,
+// or it says "unknown"
+fn read_link_speed(base: &Path, iface: &str) -> Option<String> {
+    let device = base.join(iface);
+    let speed = fs::read_to_string(device.join("speed")).ok()?;
+    let duplex = fs::read_to_string(device.join("duplex")).unwrap_or_default();
+    format_link_speed(&speed, &duplex)
+}
+
+fn format_link_speed(speed: &str, duplex: &str) -> Option<String> {
+    // Some drivers report -1 as an unsigned 32-bit value
+    let speed: i64 = speed.trim().parse().ok()?;
+    if speed <= 0 || speed == u32::MAX as i64 {
+        return None;
+    }
+
+    let speed = format_mbps(speed as f64);
+    Some(match duplex.trim() {
+        "full" => format!("{} FD", speed),
+        "half" => format!("{} HD", speed),
+        _ => speed,
+    })
+}
+
+// "100Mb", "1Gb", "2.5Gb"
+fn format_mbps(mbps: f64) -> String {
+    if mbps < 1000.0 {
+        format!("{:.0}Mb", mbps)
+    } else {
+        let gbps = format!("{:.1}", mbps / 1000.0);
+        format!("{}Gb", gbps.trim_end_matches(".0"))
+    }
+}
+
+/// Transmit bitrate of the wireless `iface`, from `iw dev <iface> link`; None while it
+/// is not associated or without `iw`.
+pub fn get_wifi_bitrate(iface: &str) -> Option<String> {
+    if IW_MISSING.load(Ordering::Relaxed) {
+        return None;
+    }
+    match run_command("iw", &["dev", iface, "link"]) {
+        Ok(output) => parse_iw_bitrate(&output),
+        Err(CommandError::NotFound(_)) => {
+            IW_MISSING.store(true, Ordering::Relaxed);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+// "\ttx bitrate: 72.2 MBit/s MCS 7 short GI"; "Not connected." has none
+fn parse_iw_bitrate(output: &str) -> Option<String> {
+    let rate = output.lines().find_map(|line| line.trim().strip_prefix("tx bitrate:"))?;
+    let mbps: f64 = rate.split_whitespace().next()?.parse().ok()?;
+    (mbps > 0.0).then(|| format_mbps(mbps))
+}
+
+/// Why wifi cannot connect, when its radio is blocked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WifiBlock {
+    /// Soft-blocked with no regulatory domain set, as on a fresh Raspberry Pi OS
+    /// install until a wifi country is chosen
+    NoCountry,
+    /// Soft-blocked on purpose, e.g. `rfkill block wifi`
+    SoftBlocked,
+    HardBlocked,
+}
+
+impl WifiBlock {
+    /// "no country", "rfkill" or "hard block"
+    pub fn reason(&self) -> &'static str {
+        match self {
+            WifiBlock::NoCountry => "no country",
+            WifiBlock::SoftBlocked => "rfkill",
+            WifiBlock::HardBlocked => "hard block",
+        }
+    }
+}
+
+impl fmt::Display for WifiBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WiFi BLOCKED ({})", self.reason())
+    }
+}
+
+/// Block state of the wifi radios, any one of them blocked counting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RfkillState {
+    pub soft: bool,
+    pub hard: bool,
+}
+
+/// Why wifi is blocked, from sysfs and the regulatory domain; None when it is not or
+/// there is no wifi.
+pub fn get_wifi_block() -> Option<WifiBlock> {
+    let rfkill = read_wifi_rfkill_in(Path::new(RFKILL_SYSFS_PATH))?;
+    // Only looked up for a blocked radio, as it may run iw
+    wifi_block(rfkill, get_wifi_country)
+}
+
+fn wifi_block(rfkill: RfkillState, country: impl FnOnce() -> Option<String>) -> Option<WifiBlock> {
+    if rfkill.hard {
+        Some(WifiBlock::HardBlocked)
+    } else if rfkill.soft {
+        Some(if country().is_none() { WifiBlock::NoCountry } else { WifiBlock::SoftBlocked })
+    } else {
+        None
+    }
+}
+
+// None without a wlan radio
+fn read_wifi_rfkill_in(base: &Path) -> Option<RfkillState> {
+    let read = |path: &Path| fs::read_to_string(path).map(|value| value.trim().to_string()).unwrap_or_default();
+    let mut state = None;
+    for entry in fs::read_dir(base).ok()?.flatten() {
+        let radio = entry.path();
+        if read(&radio.join("type")) != "wlan" {
+            continue;
+        }
+        let current: &mut RfkillState = state.get_or_insert_default();
+        current.soft |= read(&radio.join("soft")) == "1";
+        current.hard |= read(&radio.join("hard")) == "1";
+    }
+    state
+}
+
+/// The wifi country, e.g. "GB", from `iw reg get`, or else wpa_supplicant.conf; None
+/// when it is not set.
+pub fn get_wifi_country() -> Option<String> {
+    if !IW_MISSING.load(Ordering::Relaxed) {
+        match run_command("iw", &["reg", "get"]) {
+            Ok(output) => {
+                if let Some(country) = parse_iw_reg_country(&output) {
+                    return Some(country);
                 }
             }
+            Err(CommandError::NotFound(_)) => IW_MISSING.store(true, Ordering::Relaxed),
+            Err(_) => {}
         }
     }
-    
-    // Fallback: try eth0 specifically
-    if let Ok(mac) = fs::read_to_string("/sys/class/net/eth0/address") {
-        return mac.trim().to_uppercase();
+    fs::read_to_string(WPA_SUPPLICANT_CONF).ok().and_then(|conf| parse_wpa_country(&conf))
+}
+
+// "country GB: DFS-ETSI"; "country 00: DFS-UNSET" is the world domain used until one is
+// set. A self-managed phy lists its own after the global one
+fn parse_iw_reg_country(output: &str) -> Option<String> {
+    output.lines()
+        .filter_map(|line| line.trim().strip_prefix("country ")?.split(':').next())
+        .find(|code| is_country_code(code))
+        .map(str::to_string)
+}
+
+// "country=GB" in wpa_supplicant.conf
+fn parse_wpa_country(conf: &str) -> Option<String> {
+    conf.lines()
+        .filter_map(|line| line.trim().strip_prefix("country="))
+        .map(|code| code.trim().trim_matches('"'))
+        .find(|code| is_country_code(code))
+        .map(str::to_uppercase)
+}
+
+fn is_country_code(code: &str) -> bool {
+    code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    // A throwaway /sys/class/net with a wired, a wifi and a virtual interface
+    fn fake_net_tree(label: &str) -> std::path::PathBuf {
+        let base = env::temp_dir().join(format!("info_display_net_{}_{}", label, std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let devices = base.join("devices");
+        for (name, physical, speed, duplex, operstate, counters) in [
+            ("eth0", true, "1000\n", "full\n", "up\n", [123_456, 7_890, 1, 0]),
+            ("wlan0", true, "", "", "dormant\n", [42, 24, 0, 3]),
+            ("docker0", false, "-1\n", "unknown\n", "down\n", [0, 0, 0, 0]),
+        ] {
+            let interface = base.join(name);
+            fs::create_dir_all(interface.join("statistics")).unwrap();
+            if physical {
+                fs::create_dir_all(devices.join(name)).unwrap();
+                std::os::unix::fs::symlink(devices.join(name), interface.join("device")).unwrap();
+            }
+            if !speed.is_empty() {
+                fs::write(interface.join("speed"), speed).unwrap();
+                fs::write(interface.join("duplex"), duplex).unwrap();
+            } else {
+                fs::create_dir_all(interface.join("wireless")).unwrap();
+            }
+            fs::write(interface.join("operstate"), operstate).unwrap();
+            for (counter, value) in ["rx_bytes", "tx_bytes", "rx_errors", "tx_errors"].into_iter().zip(counters) {
+                fs::write(interface.join("statistics").join(counter), format!("{}\n", value)).unwrap();
+            }
+        }
+        base
     }
-    
-    "N/A".to_string()
-}
\ No newline at end of file
+
+    #[test]
+    fn test_list_physical_interfaces() {
+        let base = fake_net_tree("list");
+        assert_eq!(list_physical_interfaces_in(&base), vec!["eth0", "wlan0"]);
+        fs::remove_dir_all(&base).unwrap();
+        assert!(list_physical_interfaces_in(&base).is_empty());
+    }
+
+    #[test]
+    fn test_read_interface_stats() {
+        let base = fake_net_tree("stats");
+        let eth0 = read_interface_stats(&base, "eth0").unwrap();
+        let wlan0 = read_interface_stats(&base, "wlan0").unwrap();
+        let docker0 = read_interface_stats(&base, "docker0").unwrap();
+        let missing = read_interface_stats(&base, "eth9");
+        fs::remove_file(base.join("eth0/statistics/tx_errors")).unwrap();
+        let incomplete = read_interface_stats(&base, "eth0");
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(eth0, IfStats {
+            rx_bytes: 123_456,
+            tx_bytes: 7_890,
+            rx_errors: 1,
+            tx_errors: 0,
+            speed: Some(1000),
+            operstate: "up".to_string(),
+        });
+        // No speed file, as with wifi, and a link that is down
+        assert_eq!((wlan0.speed, wlan0.operstate.as_str(), wlan0.tx_errors), (None, "dormant", 3));
+        assert_eq!((docker0.speed, docker0.operstate.as_str()), (None, "down"));
+        assert!(missing.is_err());
+        assert!(incomplete.unwrap_err().to_string().contains("tx_errors"));
+    }
+
+    fn entry(address: &str, interface: &str, flags: u32) -> Ipv6Entry {
+        Ipv6Entry { address: address.parse().unwrap(), interface: interface.to_string(), flags }
+    }
+
+    #[test]
+    fn test_parse_if_inet6() {
+        let contents = "\
+00000000000000000000000000000001 01 80 10 80       lo
+fe800000000000000a0027fffe4e1e5a 02 40 20 80     eth0
+20010db8000000010000000000000042 02 40 00 80     eth0
+20010db8000000015d3c9a0e8f3b21c4 02 40 00 01     eth0
+";
+        assert_eq!(parse_if_inet6(contents), vec![
+            entry("::1", "lo", 0x80),
+            entry("fe80::a00:27ff:fe4e:1e5a", "eth0", 0x80),
+            entry("2001:db8:0:1::42", "eth0", 0x80),
+            entry("2001:db8:0:1:5d3c:9a0e:8f3b:21c4", "eth0", 0x01),
+        ]);
+        assert!(parse_if_inet6("garbage\n").is_empty());
+    }
+
+    #[test]
+    fn test_select_ipv6() {
+        let entries = [
+            entry("::1", "lo", 0x80),
+            entry("fe80::a00:27ff:fe4e:1e5a", "eth0", 0x80),
+            entry("2001:db8:0:1:5d3c:9a0e:8f3b:21c4", "eth0", IFA_F_TEMPORARY),
+            entry("fd00::42", "eth0", 0x80),
+            entry("2001:db8:0:1::42", "eth0", 0x80),
+            entry("2001:db8:0:2::7", "wlan0", IFA_F_DEPRECATED),
+            entry("fe80::1", "wlan0", 0x80),
+        ];
+        let select = |interface| select_ipv6(&entries, interface).map(|address| address.to_string());
+
+        // Global and stable beats temporary and unique local
+        assert_eq!(select(None).as_deref(), Some("2001:db8:0:1::42"));
+        assert_eq!(select(Some("eth0")).as_deref(), Some("2001:db8:0:1::42"));
+        // A deprecated address is still better than none
+        assert_eq!(select(Some("wlan0")).as_deref(), Some("2001:db8:0:2::7"));
+        // Only link-local or loopback addresses: nothing to show
+        assert_eq!(select(Some("lo")), None);
+        assert_eq!(select_ipv6(&entries[..2], None), None);
+        // A temporary address only when it is all there is
+        assert_eq!(select_ipv6(&entries[..3], None).map(|a| a.to_string()).as_deref(), Some("2001:db8:0:1:5d3c:9a0e:8f3b:21c4"));
+        assert_eq!(select_ipv6(&entries[..4], None).map(|a| a.to_string()).as_deref(), Some("fd00::42"));
+    }
+
+    #[test]
+    fn test_count_established() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 16239 1 0000000000000000 100 0 0 10 0
+   1: 2A01A8C0:0016 0F01A8C0:D431 01 00000000:00000000 02:0009D6B7 00000000     0        0 61742 4 0000000000000000 20 4 31 10 -1
+   2: 2A01A8C0:9C4A 5DB8D8AC:01BB 01 00000000:00000000 00:00000000 00000000  1000        0 62011 1 0000000000000000 20 4 30 10 -1
+   3: 2A01A8C0:9C4C 5DB8D8AC:01BB 06 00000000:00000000 03:00000A3F 00000000     0        0 0 3 0000000000000000
+";
+        assert_eq!(count_established(table), 2);
+        assert_eq!(count_established(""), 0);
+    }
+
+    #[test]
+    fn test_parse_hostname_source() {
+        assert_eq!(HostnameSource::parse("System"), Some(HostnameSource::System));
+        assert_eq!(HostnameSource::parse("file"), Some(HostnameSource::File));
+        assert_eq!(HostnameSource::parse("name:kitchen-pi"), Some(HostnameSource::Literal("kitchen-pi".to_string())));
+        assert_eq!(HostnameSource::parse("name:"), None);
+        assert_eq!(HostnameSource::parse("dns"), None);
+    }
+
+    #[test]
+    fn test_read_hostname() {
+        let path = env::temp_dir().join(format!("info_display_hostname_{}", std::process::id()));
+        fs::write(&path, "# set by the imager\nkitchen-pi\n").unwrap();
+        let from_file = read_hostname(&HostnameSource::File, &path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(from_file, Some("kitchen-pi".to_string()));
+        assert_eq!(read_hostname(&HostnameSource::Literal("den".to_string()), &path), Some("den".to_string()));
+        // A missing file falls back to the kernel's hostname
+        assert_eq!(read_hostname(&HostnameSource::File, &path), read_hostname(&HostnameSource::System, &path));
+    }
+
+    #[test]
+    fn test_parse_resolv_search() {
+        // As written by dhcpcd on Raspberry Pi OS
+        let dhcpcd = "# Generated by resolvconf\ndomain home.arpa\nnameserver 192.168.1.1\n";
+        assert_eq!(parse_resolv_search(dhcpcd), vec!["home.arpa"]);
+
+        let multiple = "nameserver 10.0.0.1\nsearch lab.example.com example.com ; from DHCP\n";
+        assert_eq!(parse_resolv_search(multiple), vec!["lab.example.com", "example.com"]);
+
+        // domain and search replace each other, the last one counts
+        assert_eq!(parse_resolv_search("search a.example b.example\ndomain c.example\n"), vec!["c.example"]);
+        assert_eq!(parse_resolv_search("domain c.example\nsearch a.example.\n"), vec!["a.example"]);
+
+        assert!(parse_resolv_search("# search commented.example\nnameserver 1.1.1.1\n").is_empty());
+        assert!(parse_resolv_search("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_resolv_search_systemd_resolved() {
+        let stub = "\
+# This is /run/systemd/resolve/stub-resolv.conf managed by man:systemd-resolved(8).
+# Do not edit.
+#
+# Run \"resolvectl status\" to see details about the uplink DNS servers
+# currently in use.
+
+nameserver 127.0.0.53
+options edns0 trust-ad
+search .
+";
+        assert!(parse_resolv_search(stub).is_empty());
+        let with_domain = stub.replace("search .", "search fritz.box");
+        assert_eq!(parse_resolv_search(&with_domain), vec!["fritz.box"]);
+    }
+
+    #[test]
+    fn test_domain_of() {
+        assert_eq!(domain_of("pi.home.arpa"), Some("home.arpa".to_string()));
+        assert_eq!(domain_of("pi.example.com."), Some("example.com".to_string()));
+        assert_eq!(domain_of("raspberrypi"), None);
+        assert_eq!(domain_of("pi."), None);
+    }
+
+    #[test]
+    fn test_format_link_speed() {
+        assert_eq!(format_link_speed("1000\n", "full\n"), Some("1Gb FD".to_string()));
+        assert_eq!(format_link_speed("100", "half"), Some("100Mb HD".to_string()));
+        assert_eq!(format_link_speed("2500", "unknown"), Some("2.5Gb".to_string()));
+        assert_eq!(format_link_speed("-1\n", "unknown\n"), None);
+        assert_eq!(format_link_speed("4294967295", ""), None);
+        assert_eq!(format_link_speed("", ""), None);
+    }
+
+    #[test]
+    fn test_read_link_speed() {
+        let base = fake_net_tree("link");
+        let links = [read_link_speed(&base, "eth0"), read_link_speed(&base, "docker0"), read_link_speed(&base, "wlan0")];
+        let wireless = [is_wireless_in(&base, "eth0"), is_wireless_in(&base, "wlan0")];
+        fs::remove_dir_all(&base).unwrap();
+        // docker0 is down, wlan0 has no speed to read
+        assert_eq!(links, [Some("1Gb FD".to_string()), None, None]);
+        assert_eq!(wireless, [false, true]);
+    }
+
+    #[test]
+    fn test_parse_iw_bitrate() {
+        let output = "Connected to 3c:84:6a:12:34:56 (on wlan0)\n\
+                      \tSSID: home\n\
+                      \tsignal: -52 dBm\n\
+                      \trx bitrate: 65.0 MBit/s MCS 6 short GI\n\
+                      \ttx bitrate: 72.2 MBit/s MCS 7 short GI\n";
+        assert_eq!(parse_iw_bitrate(output).as_deref(), Some("72Mb"));
+        assert_eq!(parse_iw_bitrate("\ttx bitrate: 1200.9 MBit/s 80MHz HE-MCS 11\n").as_deref(), Some("1.2Gb"));
+        assert_eq!(parse_iw_bitrate("Not connected.\n"), None);
+    }
+
+    #[test]
+    fn test_parse_iw_reg_country() {
+        let unset = "global\ncountry 00: DFS-UNSET\n\t(2402 - 2472 @ 40), (6, 20), (N/A)\n";
+        assert_eq!(parse_iw_reg_country(unset), None);
+        let set = "global\ncountry GB: DFS-ETSI\n\t(2400 - 2483 @ 40), (N/A, 20), (N/A)\n";
+        assert_eq!(parse_iw_reg_country(set).as_deref(), Some("GB"));
+        let self_managed = "global\ncountry 00: DFS-UNSET\n\nphy#0 (self-managed)\ncountry DE: DFS-ETSI\n";
+        assert_eq!(parse_iw_reg_country(self_managed).as_deref(), Some("DE"));
+        assert_eq!(parse_iw_reg_country("phy#0 (self-managed)\ncountry 99: DFS-UNSET\n"), None);
+    }
+
+    #[test]
+    fn test_parse_wpa_country() {
+        let conf = "ctrl_interface=DIR=/var/run/wpa_supplicant GROUP=netdev\n\
+                    update_config=1\n\
+                    country=gb\n\n\
+                    network={\n\tssid=\"home\"\n}\n";
+        assert_eq!(parse_wpa_country(conf).as_deref(), Some("GB"));
+        assert_eq!(parse_wpa_country("# country=GB\nupdate_config=1\n"), None);
+        assert_eq!(parse_wpa_country("country=\n"), None);
+    }
+
+    #[test]
+    fn test_read_wifi_rfkill() {
+        let base = env::temp_dir().join(format!("info_display_rfkill_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        for (radio, kind, soft, hard) in [("rfkill0", "wlan", "1", "0"), ("rfkill1", "bluetooth", "0", "1")] {
+            let dir = base.join(radio);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("type"), format!("{}\n", kind)).unwrap();
+            fs::write(dir.join("soft"), format!("{}\n", soft)).unwrap();
+            fs::write(dir.join("hard"), format!("{}\n", hard)).unwrap();
+        }
+        let state = read_wifi_rfkill_in(&base);
+        fs::remove_dir_all(&base).unwrap();
+        // The hard-blocked bluetooth radio does not count
+        assert_eq!(state, Some(RfkillState { soft: true, hard: false }));
+        assert_eq!(read_wifi_rfkill_in(&env::temp_dir().join("info_display_no_rfkill")), None);
+    }
+
+    #[test]
+    fn test_wifi_block() {
+        let soft = RfkillState { soft: true, hard: false };
+        assert_eq!(wifi_block(soft, || None), Some(WifiBlock::NoCountry));
+        assert_eq!(wifi_block(soft, || Some("GB".to_string())), Some(WifiBlock::SoftBlocked));
+        assert_eq!(wifi_block(RfkillState { soft: true, hard: true }, || None), Some(WifiBlock::HardBlocked));
+        assert_eq!(wifi_block(RfkillState::default(), || panic!("not blocked, no need to ask")), None);
+        assert_eq!(WifiBlock::NoCountry.to_string(), "WiFi BLOCKED (no country)");
+    }
+}
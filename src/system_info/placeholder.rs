@@ -0,0 +1,16 @@
+use std::sync::OnceLock;
+
+pub const DEFAULT_PLACEHOLDER: &str = "N/A";
+
+static PLACEHOLDER: OnceLock<String> = OnceLock::new();
+
+/// Use `text` wherever a reading is missing. Only the first call has an effect, so
+/// set it before anything is read; the cached hardware details keep the text they got.
+pub fn set_placeholder(text: &str) {
+    let _ = PLACEHOLDER.set(text.to_string());
+}
+
+/// What to show for a reading that is unavailable, `N/A` unless configured otherwise.
+pub fn placeholder() -> &'static str {
+    PLACEHOLDER.get().map(String::as_str).unwrap_or(DEFAULT_PLACEHOLDER)
+}
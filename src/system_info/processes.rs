@@ -0,0 +1,143 @@
+use serde::Serialize;
+use sysinfo::{Process, ProcessStatus, System, ThreadKind};
+
+/// What the summary needs from a process, so it can be tested without a process table
+pub trait ProcessInfo {
+    fn name(&self) -> &str;
+    fn is_running(&self) -> bool;
+    fn is_zombie(&self) -> bool;
+    /// Threads of a process are listed next to it on Linux; they are not counted
+    fn is_thread(&self) -> bool;
+    fn cpu_percent(&self) -> f32;
+    fn memory_bytes(&self) -> u64;
+}
+
+impl ProcessInfo for Process {
+    fn name(&self) -> &str {
+        Process::name(self)
+    }
+
+    fn is_running(&self) -> bool {
+        self.status() == ProcessStatus::Run
+    }
+
+    fn is_zombie(&self) -> bool {
+        self.status() == ProcessStatus::Zombie
+    }
+
+    fn is_thread(&self) -> bool {
+        self.thread_kind() == Some(ThreadKind::Userland)
+    }
+
+    fn cpu_percent(&self) -> f32 {
+        self.cpu_usage()
+    }
+
+    fn memory_bytes(&self) -> u64 {
+        self.memory()
+    }
+}
+
+/// Counts from the process table, to tell a runaway cron job from a quiet machine
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProcessSummary {
+    pub total: usize,
+    pub running: usize,
+    pub zombies: usize,
+    /// Name and CPU usage of the busiest process; None without processes
+    pub top_cpu: Option<(String, f32)>,
+    /// Name and resident memory in MB of the largest process
+    pub top_mem: Option<(String, u64)>,
+}
+
+pub fn summarize<'a, P: ProcessInfo + 'a>(processes: impl IntoIterator<Item = &'a P>) -> ProcessSummary {
+    let mut summary = ProcessSummary::default();
+    let mut top_cpu: Option<&P> = None;
+    let mut top_mem: Option<&P> = None;
+    for process in processes.into_iter().filter(|process| !process.is_thread()) {
+        summary.total += 1;
+        summary.running += process.is_running() as usize;
+        summary.zombies += process.is_zombie() as usize;
+        if top_cpu.is_none_or(|top| process.cpu_percent() > top.cpu_percent()) {
+            top_cpu = Some(process);
+        }
+        if top_mem.is_none_or(|top| process.memory_bytes() > top.memory_bytes()) {
+            top_mem = Some(process);
+        }
+    }
+    summary.top_cpu = top_cpu.map(|process| (process.name().to_string(), process.cpu_percent()));
+    summary.top_mem = top_mem.map(|process| (process.name().to_string(), process.memory_bytes() / (1024 * 1024)));
+    summary
+}
+
+/// Summary of the process table of `sys`, which must have had its processes refreshed.
+/// CPU usage is measured between refreshes, so `sys` should be the one kept across updates.
+pub fn get_process_summary(sys: &System) -> ProcessSummary {
+    summarize(sys.processes().values())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeProcess {
+        name: &'static str,
+        status: char,
+        cpu: f32,
+        memory: u64,
+    }
+
+    impl ProcessInfo for FakeProcess {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn is_running(&self) -> bool {
+            self.status == 'R'
+        }
+
+        fn is_zombie(&self) -> bool {
+            self.status == 'Z'
+        }
+
+        fn is_thread(&self) -> bool {
+            self.status == 't'
+        }
+
+        fn cpu_percent(&self) -> f32 {
+            self.cpu
+        }
+
+        fn memory_bytes(&self) -> u64 {
+            self.memory
+        }
+    }
+
+    fn process(name: &'static str, status: char, cpu: f32, memory_mb: u64) -> FakeProcess {
+        FakeProcess { name, status, cpu, memory: memory_mb * 1024 * 1024 }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let processes = [
+            process("systemd", 'S', 0.1, 12),
+            process("info_display", 'R', 4.5, 9),
+            process("backup.sh", 'Z', 0.0, 0),
+            process("backup.sh", 'Z', 0.0, 0),
+            process("chromium", 'S', 2.0, 310),
+            // A thread of chromium, busier than any process
+            process("chromium", 't', 50.0, 310),
+        ];
+        let summary = summarize(&processes);
+        assert_eq!(summary.total, 5);
+        assert_eq!(summary.running, 1);
+        assert_eq!(summary.zombies, 2);
+        assert_eq!(summary.top_cpu, Some(("info_display".to_string(), 4.5)));
+        assert_eq!(summary.top_mem, Some(("chromium".to_string(), 310)));
+    }
+
+    #[test]
+    fn test_summarize_nothing() {
+        assert_eq!(summarize::<FakeProcess>(&[]), ProcessSummary::default());
+    }
+}
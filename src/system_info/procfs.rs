@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::sync::Mutex;
+
+use super::failures::{track, PROC_SOURCE};
+use super::Usage;
+
+const PROC_STAT_PATH: &str = "/proc/stat";
+pub const PROC_MEMINFO_PATH: &str = "/proc/meminfo";
+
+/// Time all CPUs together spent since boot, in clock ticks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuTimes {
+    pub busy: u64,
+    pub total: u64,
+}
+
+impl CpuTimes {
+    /// Percentage of the time since `previous` that the CPUs were busy; None when no
+    /// time passed, or the counters went back.
+    pub fn usage_since(&self, previous: &CpuTimes) -> Option<f32> {
+        let total = self.total.checked_sub(previous.total).filter(|total| *total > 0)?;
+        let busy = self.busy.checked_sub(previous.busy)?;
+        Some((busy as f64 / total as f64 * 100.0).min(100.0) as f32)
+    }
+}
+
+/// The aggregate "cpu" line of /proc/stat: user nice system idle iowait irq softirq steal.
+/// Guest time is already counted in user and nice, so it is left out.
+pub fn parse_cpu_times(stat: &str) -> Option<CpuTimes> {
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    let ticks: Vec<u64> = line.split_whitespace().skip(1).take(8).map(str::parse).collect::<Result<_, _>>().ok()?;
+    if ticks.len() < 4 {
+        return None;
+    }
+    let total = ticks.iter().sum();
+    // idle and iowait
+    let idle = ticks[3] + ticks.get(4).copied().unwrap_or(0);
+    Some(CpuTimes { busy: total - idle, total })
+}
+
+pub fn read_cpu_times() -> Result<CpuTimes> {
+    let stat = fs::read_to_string(PROC_STAT_PATH)?;
+    parse_cpu_times(&stat).ok_or_else(|| anyhow!("No cpu line in {}", PROC_STAT_PATH))
+}
+
+/// CPU usage between the samples of one collection path, each taken against the one
+/// before it. Kept by whoever takes the snapshots, so another reader of /proc/stat
+/// cannot shorten the interval.
+#[derive(Debug, Default)]
+pub struct CpuUsageTracker {
+    last: Mutex<Option<CpuTimes>>,
+}
+
+impl CpuUsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CPU usage since the previous call, from /proc/stat; None on the first call, as
+    /// the counters since boot say little about now.
+    pub fn usage(&self) -> Option<f32> {
+        let current = track(PROC_SOURCE, read_cpu_times()).ok()?;
+        self.usage_at(current)
+    }
+
+    fn usage_at(&self, current: CpuTimes) -> Option<f32> {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        last.replace(current).and_then(|previous| current.usage_since(&previous))
+    }
+}
+
+/// Memory and swap from /proc/meminfo, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemInfo {
+    pub memory: Usage,
+    /// A total of 0 means there is no swap
+    pub swap: Usage,
+}
+
+/// Memory the kernel can hand out without swapping counts as free, as in `free`.
+pub fn parse_meminfo(meminfo: &str) -> Option<MemInfo> {
+    let field = |name: &str| {
+        meminfo.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+    };
+    let total = field("MemTotal")?;
+    // MemAvailable is missing before Linux 3.14
+    let available = field("MemAvailable").or_else(|| Some(field("MemFree")? + field("Buffers")? + field("Cached")?))?;
+    let swap_total = field("SwapTotal").unwrap_or(0);
+    let swap_free = field("SwapFree").unwrap_or(0);
+    Some(MemInfo {
+        memory: Usage { used: total.saturating_sub(available), total },
+        swap: Usage { used: swap_total.saturating_sub(swap_free), total: swap_total },
+    })
+}
+
+pub fn read_meminfo() -> Result<MemInfo> {
+    let meminfo = fs::read_to_string(PROC_MEMINFO_PATH)
+        .map_err(anyhow::Error::from)
+        .and_then(|meminfo| parse_meminfo(&meminfo).ok_or_else(|| anyhow!("Unexpected format of {}", PROC_MEMINFO_PATH)));
+    track(PROC_SOURCE, meminfo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STAT: &str = "cpu  4705 356 584 3699 23 0 17 0 0 0\n\
+                        cpu0 1393 280 156 3566 23 0 17 0 0 0\n\
+                        intr 114930548 113199788 3 0 5 263 0 4 [...]\n";
+
+    const MEMINFO: &str = "MemTotal:        3884328 kB\n\
+                           MemFree:          203672 kB\n\
+                           MemAvailable:    2924504 kB\n\
+                           Buffers:          121544 kB\n\
+                           Cached:          2374224 kB\n\
+                           SwapCached:            0 kB\n\
+                           SwapTotal:         102396 kB\n\
+                           SwapFree:           92156 kB\n";
+
+    #[test]
+    fn test_parse_cpu_times() {
+        // Busy is everything but idle and iowait
+        assert_eq!(parse_cpu_times(STAT), Some(CpuTimes { busy: 5662, total: 9384 }));
+        assert_eq!(parse_cpu_times("cpu0 1 2 3 4\n"), None);
+        assert_eq!(parse_cpu_times("cpu  1 x 3 4\n"), None);
+    }
+
+    #[test]
+    fn test_usage_since() {
+        let previous = CpuTimes { busy: 100, total: 400 };
+        assert_eq!(CpuTimes { busy: 150, total: 600 }.usage_since(&previous), Some(25.0));
+        assert_eq!(previous.usage_since(&previous), None);
+        assert_eq!(CpuTimes { busy: 50, total: 300 }.usage_since(&previous), None);
+    }
+
+    #[test]
+    fn test_trackers_keep_their_own_previous_sample() {
+        let (display, other) = (CpuUsageTracker::new(), CpuUsageTracker::new());
+        assert_eq!(display.usage_at(CpuTimes { busy: 100, total: 400 }), None);
+        assert_eq!(other.usage_at(CpuTimes { busy: 140, total: 500 }), None);
+        assert_eq!(other.usage_at(CpuTimes { busy: 150, total: 520 }), Some(50.0));
+        // Measured over the display's whole interval, not from the other's latest sample
+        assert_eq!(display.usage_at(CpuTimes { busy: 150, total: 600 }), Some(25.0));
+    }
+
+    #[test]
+    fn test_parse_meminfo() {
+        let info = parse_meminfo(MEMINFO).unwrap();
+        assert_eq!(info.memory, Usage { used: (3884328 - 2924504) * 1024, total: 3884328 * 1024 });
+        assert_eq!(info.swap, Usage { used: 10240 * 1024, total: 102396 * 1024 });
+
+        let without_available = MEMINFO.replace("MemAvailable:    2924504 kB\n", "");
+        let info = parse_meminfo(&without_available).unwrap();
+        assert_eq!(info.memory.used, (3884328 - 203672 - 121544 - 2374224) * 1024);
+        assert_eq!(parse_meminfo("SwapTotal: 0 kB\n"), None);
+    }
+}
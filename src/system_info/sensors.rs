@@ -1,120 +1,436 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use embedded_hal::i2c::I2c;
+use linux_embedded_hal::I2cdev;
+use std::fmt;
 use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Once, OnceLock};
+use tracing::warn;
 
-pub fn get_cpu_temp() -> Result<String> {
-    let temp_str = fs::read_to_string("/sys/class/thermal/thermal_zone0/temp")?;
-    let temp: i32 = temp_str.trim().parse()?;
-    let temp_celsius = temp / 1000;
-    Ok(format!("{}°C", temp_celsius))
-}
-
-pub fn get_gpu_temp() -> String {
-    match std::process::Command::new("vcgencmd")
-        .arg("measure_temp")
-        .output()
-    {
-        Ok(output) => {
-            let temp_str = String::from_utf8_lossy(&output.stdout);
-            if let Some(temp_part) = temp_str.strip_prefix("temp=") {
-                if let Some(temp_val) = temp_part.strip_suffix("'C\n") {
-                    if let Ok(temp_float) = temp_val.parse::<f32>() {
-                        return format!("{:.1}°C", temp_float);
-                    }
-                }
-            }
-            "N/A".to_string()
-        }
-        Err(_) => "N/A".to_string()
-    }
-}
-
-pub fn get_throttle_status() -> String {
-    match std::process::Command::new("vcgencmd")
-        .arg("get_throttled")
-        .output()
-    {
-        Ok(output) => {
-            let throttle_str = String::from_utf8_lossy(&output.stdout);
-            if let Some(hex_part) = throttle_str.strip_prefix("throttled=0x") {
-                if let Ok(throttle_val) = u32::from_str_radix(hex_part.trim(), 16) {
-                    if throttle_val == 0 {
-                        return "None".to_string();
-                    } else {
-                        let mut status = Vec::new();
-                        if throttle_val & 0x1 != 0 { status.push("Under-voltage"); }
-                        if throttle_val & 0x2 != 0 { status.push("ARM freq capped"); }
-                        if throttle_val & 0x4 != 0 { status.push("Currently throttled"); }
-                        if throttle_val & 0x8 != 0 { status.push("Soft temp limit"); }
-                        return status.join(", ");
-                    }
-                }
-            }
-            "Unknown".to_string()
+use crate::errors::AppError;
+use crate::utils::format_temp;
+
+use super::failures::{record_failure, track, I2C_SOURCE, ONE_WIRE_SOURCE, THERMAL_SOURCE};
+use super::firmware::{
+    firmware_sources, init_firmware_sources, parse_sysfs_throttled, run_vcgencmd, sysfs_throttled_present,
+    FirmwareSources, Source, SysfsReadings, FIRMWARE_THROTTLED_PATH, VCGENCMD_SOURCE,
+};
+use super::hardware::{get_platform, Platform};
+use super::hwmon::{find_hwmon_in, read_hwmon_temp, HWMON_SYSFS_PATH};
+use super::placeholder;
+
+const THERMAL_SYSFS_PATH: &str = "/sys/class/thermal";
+
+const GPIO_SYSFS_PATH: &str = "/sys/class/gpio";
+
+const I2C_ADAPTER_PATH: &str = "/sys/class/i2c-adapter";
+
+const DEVICE_TREE_PATH: &str = "/proc/device-tree";
+
+const CPUFREQ_PATH: &str = "/sys/devices/system/cpu/cpu0/cpufreq/scaling_cur_freq";
+
+// hwmon devices with the GPU temperature, most specific first. The Pi's GPU has no
+// sensor of its own: `vcgencmd measure_temp` reads the SoC sensor behind cpu_thermal.
+const GPU_HWMON_NAMES: [&str; 3] = ["gpu_thermal", "vc4", "cpu_thermal"];
+
+// Throttle bits reported by `vcgencmd get_throttled`: bit, metric label, display text, short code
+pub const THROTTLE_FLAGS: [(u32, &str, &str, &str); 4] = [
+    (0x1, "under_voltage", "Under-voltage", "UV"),
+    (0x2, "arm_freq_capped", "ARM freq capped", "FC"),
+    (0x4, "currently_throttled", "Currently throttled", "TH"),
+    (0x8, "soft_temp_limit", "Soft temp limit", "ST"),
+];
+
+// The same conditions again from bit 16 up, set once they have occurred since boot
+const SINCE_BOOT_SHIFT: u32 = 16;
+const CONDITION_MASK: u32 = 0xF;
+
+/// CPU temperature in °C, from the cpu-thermal zone (thermal_zone0 if there is none).
+pub fn get_cpu_temp() -> Result<f32> {
+    track(THERMAL_SOURCE, read_zone_temp(cpu_zone()))
+}
+
+fn cpu_zone() -> &'static Path {
+    // Zones are registered at boot, so the one to read is looked up only once
+    static CPU_ZONE: OnceLock<PathBuf> = OnceLock::new();
+    CPU_ZONE.get_or_init(|| find_cpu_zone(Path::new(THERMAL_SYSFS_PATH)))
+}
+
+/// A trip point of a thermal zone: the kernel acts once the zone reaches its temperature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripPoint {
+    /// "active" (a fan), "passive" (throttling), "hot" or "critical" (shutdown)
+    pub kind: String,
+    pub celsius: f32,
+}
+
+/// Trip points of the cpu-thermal zone, in trip point order.
+pub fn get_cpu_trip_points() -> Vec<TripPoint> {
+    read_trip_points(cpu_zone())
+}
+
+// trip_point_<n>_temp and trip_point_<n>_type; a trip point disabled with a temperature
+// of 0 or below is left out
+fn read_trip_points(zone: &Path) -> Vec<TripPoint> {
+    (0..)
+        .map_while(|index| {
+            let read = |field: &str| fs::read_to_string(zone.join(format!("trip_point_{}_{}", index, field))).ok();
+            Some((read("temp")?, read("type")?))
+        })
+        .filter_map(|(temp, kind)| {
+            let millidegrees: i32 = temp.trim().parse().ok()?;
+            (millidegrees > 0).then(|| TripPoint { kind: kind.trim().to_string(), celsius: millidegrees as f32 / 1000.0 })
+        })
+        .collect()
+}
+
+/// The lowest trip point at which the kernel throttles (passive) or shuts down
+/// (critical), which is the one the temperature gets to first.
+pub fn throttle_trip_celsius(trips: &[TripPoint]) -> Option<f32> {
+    trips.iter()
+        .filter(|trip| trip.kind == "passive" || trip.kind == "critical")
+        .map(|trip| trip.celsius)
+        .min_by(f32::total_cmp)
+}
+
+pub fn get_cpu_temp_display() -> Result<String> {
+    get_cpu_temp().map(format_cpu_temp)
+}
+
+pub fn format_cpu_temp(celsius: f32) -> String {
+    format_temp(celsius)
+}
+
+// zone0 is not always the CPU; on some kernels it belongs to the wifi chip
+fn find_cpu_zone(base: &Path) -> PathBuf {
+    zone_dirs(base)
+        .into_iter()
+        .find(|(_, path)| {
+            fs::read_to_string(path.join("type")).is_ok_and(|zone_type| zone_type.contains("cpu-thermal"))
+        })
+        .map(|(_, path)| path)
+        .unwrap_or_else(|| base.join("thermal_zone0"))
+}
+
+// thermal_zone* directories with their index, in numeric order so zone10 comes after zone9
+fn zone_dirs(base: &Path) -> Vec<(u32, PathBuf)> {
+    let mut zones: Vec<_> = fs::read_dir(base)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let index = name.to_str()?.strip_prefix("thermal_zone")?.parse::<u32>().ok()?;
+            Some((index, entry.path()))
+        })
+        .collect();
+    zones.sort_by_key(|(index, _)| *index);
+    zones
+}
+
+// The kernel reports millidegrees
+fn read_zone_temp(zone: &Path) -> Result<f32> {
+    let millidegrees: i32 = fs::read_to_string(zone.join("temp"))?.trim().parse()?;
+    Ok(millidegrees as f32 / 1000.0)
+}
+
+/// Type and temperature (°C) of every thermal zone, in zone order.
+pub fn get_all_thermal_zones() -> Vec<(String, f32)> {
+    read_thermal_zones(Path::new(THERMAL_SYSFS_PATH))
+}
+
+fn read_thermal_zones(base: &Path) -> Vec<(String, f32)> {
+    zone_dirs(base)
+        .into_iter()
+        .filter_map(|(index, path)| {
+            let temp = read_zone_temp(&path).ok()?;
+            let zone_type = fs::read_to_string(path.join("type"))
+                .map(|t| t.trim().to_string())
+                .unwrap_or_else(|_| format!("thermal_zone{}", index));
+            Some((zone_type, temp))
+        })
+        .collect()
+}
+
+/// Where the GPU temperature, CPU frequency and throttle register are read from on
+/// this machine, picked on first use and logged.
+pub fn get_firmware_sources() -> FirmwareSources {
+    firmware_sources().unwrap_or_else(|| init_firmware_sources(SysfsReadings {
+        gpu_temp: find_hwmon_in(Path::new(HWMON_SYSFS_PATH), &GPU_HWMON_NAMES, "temp1_input").is_some(),
+        cpu_freq: Path::new(CPUFREQ_PATH).exists(),
+        throttle: sysfs_throttled_present(),
+    }))
+}
+
+// vcgencmd, unless it was found not to work; then there is nothing to spawn
+fn vcgencmd(args: &[&str], reading: &str) -> Result<String> {
+    if !get_firmware_sources().vcgencmd {
+        return Err(anyhow!("no source for the {}", reading));
+    }
+    Ok(run_vcgencmd(args)?)
+}
+
+/// GPU temperature in °C, from hwmon where the kernel exposes it, otherwise from vcgencmd.
+pub fn get_gpu_temp() -> Result<f32> {
+    if get_firmware_sources().gpu_temp == Source::Sysfs {
+        if let Some(celsius) = sysfs_gpu_temp(Path::new(HWMON_SYSFS_PATH)) {
+            return Ok(celsius);
         }
-        Err(_) => "N/A".to_string()
     }
+    let output = vcgencmd(&["measure_temp"], "GPU temperature")?;
+    track(VCGENCMD_SOURCE, parse_gpu_temp(&output).ok_or_else(|| anyhow!("unexpected vcgencmd output: {}", output.trim())))
 }
 
-pub fn get_cpu_freq() -> String {
-    match std::process::Command::new("vcgencmd")
-        .arg("measure_clock")
-        .arg("arm")
-        .output()
-    {
-        Ok(output) => {
-            let freq_str = String::from_utf8_lossy(&output.stdout);
-            if let Some(freq_part) = freq_str.strip_prefix("frequency(48)=") {
-                if let Ok(freq_hz) = freq_part.trim().parse::<u64>() {
-                    let freq_mhz = freq_hz / 1_000_000;
-                    return format!("{} MHz", freq_mhz);
-                }
-            }
-            "N/A".to_string()
+fn sysfs_gpu_temp(hwmon_base: &Path) -> Option<f32> {
+    let device = find_hwmon_in(hwmon_base, &GPU_HWMON_NAMES, "temp1_input")?;
+    read_hwmon_temp(&device, "temp1_input").ok()
+}
+
+pub fn get_gpu_temp_display() -> String {
+    get_gpu_temp().map(format_gpu_temp).unwrap_or_else(|_| placeholder().to_string())
+}
+
+pub fn format_gpu_temp(celsius: f32) -> String {
+    format_temp(celsius)
+}
+
+// "temp=48.3'C"
+fn parse_gpu_temp(output: &str) -> Option<f32> {
+    output.trim().strip_prefix("temp=")?.strip_suffix("'C")?.parse().ok()
+}
+
+/// A set of the throttle conditions in THROTTLE_FLAGS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThrottleConditions(u32);
+
+impl ThrottleConditions {
+    pub const UNDER_VOLTAGE: Self = Self(0x1);
+    pub const ARM_FREQ_CAPPED: Self = Self(0x2);
+    pub const THROTTLED: Self = Self(0x4);
+    pub const SOFT_TEMP_LIMIT: Self = Self(0x8);
+
+    /// Bits other than the four conditions are dropped.
+    pub fn from_bits(bits: u32) -> Self {
+        Self(bits & CONDITION_MASK)
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Display text of each condition in the set, in bit order.
+    pub fn descriptions(self) -> Vec<&'static str> {
+        THROTTLE_FLAGS.iter()
+            .filter(|(bit, ..)| self.0 & bit != 0)
+            .map(|(_, _, text, _)| *text)
+            .collect()
+    }
+
+    /// Short codes of the conditions, e.g. `UV TH`, or `None` for an empty set.
+    pub fn codes(self) -> String {
+        let codes: Vec<&str> = THROTTLE_FLAGS.iter()
+            .filter(|(bit, ..)| self.0 & bit != 0)
+            .map(|(.., code)| *code)
+            .collect();
+        if codes.is_empty() { "None".to_string() } else { codes.join(" ") }
+    }
+}
+
+impl fmt::Display for ThrottleConditions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            write!(f, "None")
+        } else {
+            write!(f, "{}", self.descriptions().join(", "))
         }
-        Err(_) => "N/A".to_string()
     }
 }
 
-pub fn get_i2c_devices() -> String {
-    match std::process::Command::new("i2cdetect")
-        .arg("-y")
-        .arg("1")
-        .output()
-    {
-        Ok(output) => {
-            let detect_str = String::from_utf8_lossy(&output.stdout);
-            let mut devices = Vec::new();
-            
-            for line in detect_str.lines() {
-                if line.starts_with(char::is_numeric) {
-                    for addr in line.split_whitespace().skip(1) {
-                        if addr != "--" && addr.len() == 2 {
-                            if let Ok(_) = u8::from_str_radix(addr, 16) {
-                                devices.push(format!("0x{}", addr));
-                            }
-                        }
-                    }
-                }
-            }
-            
-            if devices.is_empty() {
-                "None".to_string()
-            } else {
-                devices.join(", ")
-            }
+/// The `vcgencmd get_throttled` register: what is going on now, and what has happened since boot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThrottleFlags {
+    pub current: ThrottleConditions,
+    pub since_boot: ThrottleConditions,
+}
+
+impl ThrottleFlags {
+    pub fn from_bits(register: u32) -> Self {
+        Self {
+            current: ThrottleConditions::from_bits(register),
+            since_boot: ThrottleConditions::from_bits(register >> SINCE_BOOT_SHIFT),
+        }
+    }
+
+    /// The register as vcgencmd reports it.
+    pub fn bits(self) -> u32 {
+        self.current.bits() | self.since_boot.bits() << SINCE_BOOT_SHIFT
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.current.is_empty() && self.since_boot.is_empty()
+    }
+
+    /// Every condition seen since boot, marked with `!` while it is still present,
+    /// e.g. `UV! TH`; `OK` when nothing has happened.
+    pub fn short_code(self) -> String {
+        let codes: Vec<String> = THROTTLE_FLAGS.iter()
+            .filter(|(bit, ..)| (self.current.bits() | self.since_boot.bits()) & bit != 0)
+            .map(|(bit, .., code)| if self.current.bits() & bit != 0 { format!("{}!", code) } else { code.to_string() })
+            .collect();
+        if codes.is_empty() { "OK".to_string() } else { codes.join(" ") }
+    }
+}
+
+impl fmt::Display for ThrottleFlags {
+    // The conditions present now; since-boot ones are history rather than something to report
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.current.fmt(f)
+    }
+}
+
+/// The throttle register from vcgencmd, or from the firmware driver's sysfs file without it.
+pub fn get_throttle_status() -> Result<ThrottleFlags> {
+    if get_firmware_sources().throttle == Source::Sysfs {
+        let contents = fs::read_to_string(FIRMWARE_THROTTLED_PATH)?;
+        return parse_sysfs_throttled(&contents)
+            .map(ThrottleFlags::from_bits)
+            .ok_or_else(|| anyhow!("unexpected contents of {}: {}", FIRMWARE_THROTTLED_PATH, contents.trim()));
+    }
+    let output = vcgencmd(&["get_throttled"], "throttle status")?;
+    track(VCGENCMD_SOURCE, parse_throttled(&output)
+        .map(ThrottleFlags::from_bits)
+        .ok_or_else(|| anyhow!("unexpected vcgencmd output: {}", output.trim())))
+}
+
+pub fn get_throttle_status_display() -> String {
+    get_throttle_status().map(|flags| flags.to_string()).unwrap_or_else(|_| placeholder().to_string())
+}
+
+// "throttled=0x50005"
+fn parse_throttled(output: &str) -> Option<u32> {
+    let hex_part = output.strip_prefix("throttled=0x")?;
+    u32::from_str_radix(hex_part.trim(), 16).ok()
+}
+
+/// ARM core clock in Hz, from cpufreq where available, otherwise from vcgencmd.
+pub fn get_cpu_freq() -> Result<u64> {
+    if get_firmware_sources().cpu_freq == Source::Sysfs {
+        if let Some(hz) = sysfs_cpu_freq(Path::new(CPUFREQ_PATH)) {
+            return Ok(hz);
         }
-        Err(_) => "N/A".to_string()
     }
+    let output = vcgencmd(&["measure_clock", "arm"], "CPU frequency")?;
+    track(VCGENCMD_SOURCE, parse_cpu_freq(&output).ok_or_else(|| anyhow!("unexpected vcgencmd output: {}", output.trim())))
+}
+
+pub fn get_cpu_freq_display() -> String {
+    get_cpu_freq().map(format_cpu_freq).unwrap_or_else(|_| placeholder().to_string())
+}
+
+pub fn format_cpu_freq(hz: u64) -> String {
+    format!("{} MHz", hz / 1_000_000)
+}
+
+// scaling_cur_freq is in kHz
+fn sysfs_cpu_freq(path: &Path) -> Option<u64> {
+    let khz: u64 = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    Some(khz * 1000)
+}
+
+// "frequency(48)=1500398464"
+fn parse_cpu_freq(output: &str) -> Option<u64> {
+    output.trim().strip_prefix("frequency(48)=")?.parse().ok()
+}
+
+// i2cdetect reads from these rather than writing, as a write can corrupt an EEPROM
+// (0x50-0x5F) or lock up some sensors (0x30-0x37)
+fn probe_by_read(address: u8) -> bool {
+    matches!(address, 0x30..=0x37 | 0x50..=0x5F)
+}
+
+/// The 7-bit addresses that answer on /dev/i2c-`bus`, probed the way `i2cdetect -y` does:
+/// a zero-length write, or a one-byte read where a write is not safe.
+pub fn get_i2c_devices(bus: u8) -> Result<Vec<u8>> {
+    let path = format!("/dev/i2c-{}", bus);
+    let mut i2c = track(I2C_SOURCE, I2cdev::new(&path).map_err(|e| match std::io::Error::from(e) {
+        e if e.kind() == ErrorKind::PermissionDenied => anyhow::Error::new(AppError::permission_denied(&path, Some("i2c"))),
+        e => e.into(),
+    }))?;
+    Ok(probe_i2c_addresses(&mut i2c))
+}
+
+fn probe_i2c_addresses<I2C: I2c>(i2c: &mut I2C) -> Vec<u8> {
+    (0x03..=0x77)
+        .filter(|&address| if probe_by_read(address) {
+            i2c.read(address, &mut [0u8]).is_ok()
+        } else {
+            i2c.write(address, &[]).is_ok()
+        })
+        .collect()
+}
+
+/// Addresses as listed on the GPIO screen, e.g. `0x3c, 0x70`
+pub fn format_i2c_devices(devices: &[u8]) -> String {
+    if devices.is_empty() {
+        return "None".to_string();
+    }
+    devices.iter().map(|address| format!("0x{:02x}", address)).collect::<Vec<_>>().join(", ")
+}
+
+/// The number of the I2C bus on header pins 3 and 5, e.g. 1 up to the Pi 4. It is the
+/// adapter whose device tree node the `i2c_arm` alias names; 1 when there is no such alias.
+pub fn get_header_i2c_bus() -> u8 {
+    header_i2c_bus(Path::new(DEVICE_TREE_PATH), Path::new(I2C_ADAPTER_PATH)).unwrap_or(1)
 }
 
-pub fn get_gpio_states() -> String {
+fn header_i2c_bus(device_tree: &Path, adapters: &Path) -> Option<u8> {
+    let alias = fs::read_to_string(device_tree.join("aliases/i2c_arm")).ok()?;
+    let node = alias.trim_end_matches('\0').trim_start_matches('/');
+    let node = fs::canonicalize(device_tree.join(node)).ok()?;
+    fs::read_dir(adapters).ok()?.flatten().find_map(|entry| {
+        let bus = entry.file_name().to_str()?.strip_prefix("i2c-")?.parse().ok()?;
+        (fs::canonicalize(entry.path().join("of_node")).ok()? == node).then_some(bus)
+    })
+}
+
+/// The sysfs number of header GPIO 0: the base of the gpiochip driving the header,
+/// 0 on older kernels but e.g. 512 on a Pi 4 and 571 on a Pi 5 with 6.6.
+pub fn get_header_gpio_base() -> u32 {
+    header_gpio_base(Path::new(GPIO_SYSFS_PATH), get_platform())
+}
+
+fn header_gpio_base(sysfs: &Path, platform: Platform) -> u32 {
+    let Ok(entries) = fs::read_dir(sysfs) else { return 0 };
+    entries.flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("gpiochip"))
+        .find_map(|entry| {
+            let label = fs::read_to_string(entry.path().join("label")).ok()?;
+            if !platform.header_gpio_labels().contains(&label.trim()) {
+                return None;
+            }
+            fs::read_to_string(entry.path().join("base")).ok()?.trim().parse().ok()
+        })
+        .unwrap_or(0)
+}
+
+/// Levels of the exported header pins; `base` is the header's first GPIO in sysfs.
+pub fn get_gpio_states(base: u32) -> String {
     let mut states = Vec::new();
     
     // Check some common GPIO pins
     let pins = [2, 3, 4, 17, 18, 27, 22, 23, 24, 25];
     
     for pin in &pins {
-        let export_path = format!("/sys/class/gpio/gpio{}/value", pin);
+        let export_path = format!("{}/gpio{}/value", GPIO_SYSFS_PATH, base + pin);
         if let Ok(value) = fs::read_to_string(export_path) {
             let state = if value.trim() == "1" { "H" } else { "L" };
             states.push(format!("{}: {}", pin, state));
@@ -144,40 +460,339 @@ pub fn get_spi_devices() -> String {
                 devices.join(", ")
             }
         }
-        Err(_) => "N/A".to_string()
+        Err(_) => placeholder().to_string()
     }
 }
 
-pub fn get_1wire_sensors() -> String {
-    match fs::read_dir("/sys/bus/w1/devices") {
-        Ok(entries) => {
-            let mut sensors = Vec::new();
-            for entry in entries.flatten() {
-                let name = entry.file_name().to_string_lossy().to_string();
-                if name != "w1_bus_master1" {
-                    // Try to read temperature if it's a temperature sensor
-                    let temp_path = format!("/sys/bus/w1/devices/{}/w1_slave", name);
-                    if let Ok(content) = fs::read_to_string(temp_path) {
-                        if content.contains("YES") {
-                            if let Some(temp_pos) = content.find("t=") {
-                                if let Ok(temp_raw) = content[temp_pos + 2..].trim().parse::<i32>() {
-                                    let temp_c = temp_raw as f32 / 1000.0;
-                                    sensors.push(format!("{}: {:.1}°C", &name[..8], temp_c));
-                                    continue;
-                                }
-                            }
-                        }
-                    }
-                    // If not a temperature sensor or can't read temp, just show the ID
-                    sensors.push(name);
-                }
-            }
-            if sensors.is_empty() {
-                "None".to_string()
+const W1_DEVICES_PATH: &str = "/sys/bus/w1/devices";
+
+/// Whether there is any I2C adapter, SPI device, GPIO chip or 1-Wire bus master to show,
+/// which there is not off a Pi, e.g. in a container.
+pub fn has_expansion_buses() -> bool {
+    expansion_buses_present(Path::new("/dev"), Path::new(W1_DEVICES_PATH))
+}
+
+fn expansion_buses_present(dev: &Path, w1_devices: &Path) -> bool {
+    let any_named = |dir: &Path, prefixes: &[&str]| fs::read_dir(dir).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            prefixes.iter().any(|prefix| name.starts_with(prefix))
+        })
+    });
+    any_named(dev, &["i2c-", "spidev", "gpiochip"]) || any_named(w1_devices, &["w1_bus_master"])
+}
+
+/// Readings of the 1-Wire devices, limited to one family (e.g. 0x28 for DS18B20) if given.
+pub fn get_1wire_sensors(family: Option<u8>) -> String {
+    match read_1wire_sensors(Path::new(W1_DEVICES_PATH), family) {
+        Ok(sensors) if sensors.is_empty() => "None".to_string(),
+        Ok(sensors) => sensors.join(", "),
+        // Refreshed every few seconds, so only explain it once
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            record_failure(ONE_WIRE_SOURCE, &e);
+            static WARNED: Once = Once::new();
+            WARNED.call_once(|| warn!("1-Wire: {}", AppError::permission_denied(W1_DEVICES_PATH, None)));
+            placeholder().to_string()
+        }
+        Err(_) => "None".to_string()
+    }
+}
+
+// One entry per slave on any bus master, sorted by device ID
+fn read_1wire_sensors(base: &Path, family: Option<u8>) -> std::io::Result<Vec<String>> {
+    let prefix = family.map(|family| format!("{:02x}-", family));
+    let mut names: Vec<String> = fs::read_dir(base)?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        // Every master shows up here, w1_bus_master2 and up with a second GPIO or a DS2482
+        .filter(|name| !name.starts_with("w1_bus_master"))
+        .filter(|name| prefix.as_ref().is_none_or(|prefix| name.starts_with(prefix)))
+        .collect();
+    names.sort();
+
+    Ok(names.into_iter().map(|name| {
+        // Try to read temperature if it's a temperature sensor
+        match fs::read_to_string(base.join(&name).join("w1_slave")).ok().and_then(|content| w1_temp(&content)) {
+            Some(temp_c) => format!("{}: {}", name.get(..8).unwrap_or(&name), format_temp(temp_c)),
+            // If not a temperature sensor or can't read temp, just show the ID
+            None => name,
+        }
+    }).collect())
+}
+
+// The t= reading of a w1_slave file whose CRC check passed
+fn w1_temp(content: &str) -> Option<f32> {
+    if !content.contains("YES") {
+        return None;
+    }
+    let temp_raw: i32 = content[content.find("t=")? + 2..].trim().parse().ok()?;
+    Some(temp_raw as f32 / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::i2c::{ErrorKind, ErrorType, NoAcknowledgeSource, Operation};
+
+    // Devices that answer only the probe i2cdetect would use for their address
+    struct ProbeBus {
+        devices: Vec<u8>,
+        probes: Vec<(u8, bool)>,
+    }
+
+    impl ErrorType for ProbeBus {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for ProbeBus {
+        fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+            let is_read = matches!(operations, [Operation::Read(_)]);
+            self.probes.push((address, is_read));
+            if self.devices.contains(&address) && is_read == probe_by_read(address) {
+                Ok(())
             } else {
-                sensors.join(", ")
+                Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))
             }
         }
-        Err(_) => "None".to_string()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_probe_i2c_addresses() {
+        let mut bus = ProbeBus { devices: vec![0x3c, 0x50, 0x70], probes: Vec::new() };
+        assert_eq!(probe_i2c_addresses(&mut bus), vec![0x3c, 0x50, 0x70]);
+        assert_eq!(bus.probes.len(), 0x77 - 0x03 + 1);
+        assert!(bus.probes.contains(&(0x57, true)));
+        assert!(bus.probes.contains(&(0x3c, false)));
+    }
+
+    #[test]
+    fn test_format_i2c_devices() {
+        assert_eq!(format_i2c_devices(&[0x3c, 0x70]), "0x3c, 0x70");
+        assert_eq!(format_i2c_devices(&[0x08]), "0x08");
+        assert_eq!(format_i2c_devices(&[]), "None");
+    }
+
+    #[test]
+    fn test_parse_gpu_temp() {
+        assert_eq!(parse_gpu_temp("temp=48.3'C\n"), Some(48.3));
+        assert_eq!(parse_gpu_temp("error=1"), None);
+    }
+
+    #[test]
+    fn test_parse_throttled() {
+        assert_eq!(parse_throttled("throttled=0x50005\n"), Some(0x50005));
+        assert_eq!(parse_throttled("throttled=0x0\n"), Some(0));
+        assert_eq!(parse_throttled("garbage"), None);
+    }
+
+    #[test]
+    fn test_throttle_flags() {
+        // Under-voltage and throttled, now and since boot
+        let flags = ThrottleFlags::from_bits(0x50005);
+        assert_eq!(flags.current, ThrottleConditions::from_bits(0x5));
+        assert_eq!(flags.since_boot, flags.current);
+        assert!(flags.current.contains(ThrottleConditions::UNDER_VOLTAGE));
+        assert!(flags.current.contains(ThrottleConditions::THROTTLED));
+        assert!(!flags.current.contains(ThrottleConditions::ARM_FREQ_CAPPED));
+        assert_eq!(flags.current.descriptions(), vec!["Under-voltage", "Currently throttled"]);
+        assert_eq!(flags.to_string(), "Under-voltage, Currently throttled");
+        assert_eq!(flags.short_code(), "UV! TH!");
+        assert_eq!(flags.bits(), 0x50005);
+
+        // Under-voltage occurred and the frequency was capped earlier, nothing right now
+        let flags = ThrottleFlags::from_bits(0x30000);
+        assert!(flags.current.is_empty());
+        assert_eq!(flags.since_boot.codes(), "UV FC");
+        assert_eq!(flags.to_string(), "None");
+        assert_eq!(flags.short_code(), "UV FC");
+
+        // Soft temperature limit now, under-voltage earlier
+        let flags = ThrottleFlags::from_bits(0x10008);
+        assert_eq!(flags.short_code(), "UV ST!");
+        assert_eq!(flags.since_boot.to_string(), "Under-voltage");
+
+        // Undocumented bits in between are ignored
+        assert_eq!(ThrottleFlags::from_bits(0xFFF0).bits(), 0);
+        assert!(ThrottleFlags::from_bits(0).is_empty());
+        assert_eq!(ThrottleFlags::from_bits(0).short_code(), "OK");
+        assert_eq!(ThrottleConditions::default().codes(), "None");
+    }
+
+    #[test]
+    fn test_sysfs_gpu_temp() {
+        use crate::system_info::hwmon::tests::fake_hwmon_tree;
+
+        let base = fake_hwmon_tree("gpu", &[
+            (0, "cpu_thermal", &[("temp1_input", "51900\n")]),
+            (1, "rp1_adc", &[("temp1_input", "47200\n")]),
+        ]);
+        assert_eq!(sysfs_gpu_temp(&base), Some(51.9));
+        fs::remove_dir_all(&base).unwrap();
+
+        // Nothing usable, so get_gpu_temp falls back to vcgencmd
+        let base = fake_hwmon_tree("no_gpu", &[(0, "rp1_adc", &[("temp1_input", "47200\n")])]);
+        assert_eq!(sysfs_gpu_temp(&base), None);
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_sysfs_cpu_freq() {
+        let path = std::env::temp_dir().join(format!("info_display_cpufreq_{}", std::process::id()));
+        fs::write(&path, "1500000\n").unwrap();
+        let hz = sysfs_cpu_freq(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(hz, Some(1_500_000_000));
+        assert_eq!(sysfs_cpu_freq(&path), None);
+    }
+
+    #[test]
+    fn test_parse_cpu_freq() {
+        assert_eq!(parse_cpu_freq("frequency(48)=1500398464\n"), Some(1_500_398_464));
+        assert_eq!(parse_cpu_freq("frequency(48)=abc"), None);
+        assert_eq!(format_cpu_freq(1_500_398_464), "1500 MHz");
+    }
+
+    #[test]
+    fn test_format_temps() {
+        assert_eq!(format_cpu_temp(51.9), "51.9°C");
+        assert_eq!(format_gpu_temp(48.25), "48.2°C");
+    }
+
+    // A throwaway sysfs tree: (zone index, type, millidegrees)
+    fn fake_thermal_tree(name: &str, zones: &[(u32, &str, &str)]) -> PathBuf {
+        let base = std::env::temp_dir().join(format!("info_display_thermal_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        for (index, zone_type, temp) in zones {
+            let zone = base.join(format!("thermal_zone{}", index));
+            fs::create_dir_all(&zone).unwrap();
+            fs::write(zone.join("type"), format!("{}\n", zone_type)).unwrap();
+            fs::write(zone.join("temp"), format!("{}\n", temp)).unwrap();
+        }
+        base
+    }
+
+    #[test]
+    fn test_find_cpu_zone() {
+        let base = fake_thermal_tree("cpu", &[(0, "iwlwifi_1", "38000"), (1, "acpitz", "27800"), (2, "cpu-thermal", "51900")]);
+        let zone = find_cpu_zone(&base);
+        assert_eq!(zone, base.join("thermal_zone2"));
+        assert_eq!(read_zone_temp(&zone).unwrap(), 51.9);
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_find_cpu_zone_falls_back_to_zone0() {
+        let base = fake_thermal_tree("fallback", &[(0, "x86_pkg_temp", "45000"), (1, "acpitz", "27800")]);
+        assert_eq!(find_cpu_zone(&base), base.join("thermal_zone0"));
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_read_thermal_zones_in_numeric_order() {
+        let base = fake_thermal_tree("all", &[(10, "gpu-thermal", "49000"), (2, "cpu-thermal", "51900"), (9, "acpitz", "bogus")]);
+        assert_eq!(read_thermal_zones(&base), vec![("cpu-thermal".to_string(), 51.9), ("gpu-thermal".to_string(), 49.0)]);
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_read_trip_points() {
+        let base = fake_thermal_tree("trips", &[(0, "cpu-thermal", "52300")]);
+        let zone = base.join("thermal_zone0");
+        for (index, temp, kind) in [(0, "110000", "critical"), (1, "0", "passive"), (2, "60000", "active"), (3, "85000", "passive")] {
+            fs::write(zone.join(format!("trip_point_{}_temp", index)), format!("{}\n", temp)).unwrap();
+            fs::write(zone.join(format!("trip_point_{}_type", index)), format!("{}\n", kind)).unwrap();
+        }
+        // Reading stops at the first trip point missing a file
+        fs::write(zone.join("trip_point_5_temp"), "70000\n").unwrap();
+        let trips = read_trip_points(&zone);
+        fs::remove_dir_all(&base).unwrap();
+
+        let kinds: Vec<_> = trips.iter().map(|trip| (trip.kind.as_str(), trip.celsius)).collect();
+        assert_eq!(kinds, [("critical", 110.0), ("active", 60.0), ("passive", 85.0)]);
+        // The fan trip does not count
+        assert_eq!(throttle_trip_celsius(&trips), Some(85.0));
+        assert_eq!(throttle_trip_celsius(&trips[1..2]), None);
+        assert!(read_trip_points(&base.join("thermal_zone9")).is_empty());
+    }
+
+    #[test]
+    fn test_read_1wire_sensors() {
+        let base = std::env::temp_dir().join(format!("info_display_w1_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        for (name, slave) in [
+            ("w1_bus_master1", None),
+            ("w1_bus_master2", None),
+            ("28-0000072a1b2c", Some("72 01 4b 46 7f ff 0e 10 57 : crc=57 YES\n72 01 4b 46 7f ff 0e 10 57 t=23125\n")),
+            ("28-0000072a0001", Some("72 01 4b 46 7f ff 0e 10 57 : crc=00 NO\n72 01 4b 46 7f ff 0e 10 57 t=85000\n")),
+            ("3a-00000012ab34", None),
+        ] {
+            fs::create_dir_all(base.join(name)).unwrap();
+            if let Some(slave) = slave {
+                fs::write(base.join(name).join("w1_slave"), slave).unwrap();
+            }
+        }
+
+        assert_eq!(read_1wire_sensors(&base, None).unwrap(), vec!["28-0000072a0001", "28-00000: 23.1°C", "3a-00000012ab34"]);
+        assert_eq!(read_1wire_sensors(&base, Some(0x3a)).unwrap(), vec!["3a-00000012ab34"]);
+        assert!(read_1wire_sensors(&base, Some(0x10)).unwrap().is_empty());
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_expansion_buses_present() {
+        let base = std::env::temp_dir().join(format!("info_display_buses_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let (dev, w1) = (base.join("dev"), base.join("w1"));
+        fs::create_dir_all(&dev).unwrap();
+        fs::write(dev.join("tty0"), "").unwrap();
+        assert!(!expansion_buses_present(&dev, &w1));
+
+        fs::create_dir_all(w1.join("w1_bus_master1")).unwrap();
+        assert!(expansion_buses_present(&dev, &w1));
+        fs::remove_dir_all(&w1).unwrap();
+        for device in ["i2c-1", "spidev0.0", "gpiochip0"] {
+            fs::write(dev.join(device), "").unwrap();
+            assert!(expansion_buses_present(&dev, &w1), "{}", device);
+            fs::remove_file(dev.join(device)).unwrap();
+        }
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_header_gpio_base() {
+        let base = std::env::temp_dir().join(format!("info_display_gpio_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        for (chip, label, first) in [("gpiochip512", "pinctrl-bcm2711", "512"), ("gpiochip570", "raspberrypi-exp-gpio", "570"),
+                                     ("gpiochip571", "pinctrl-rp1", "571")] {
+            fs::create_dir_all(base.join(chip)).unwrap();
+            fs::write(base.join(chip).join("label"), format!("{}\n", label)).unwrap();
+            fs::write(base.join(chip).join("base"), format!("{}\n", first)).unwrap();
+        }
+
+        assert_eq!(header_gpio_base(&base, Platform::Rp1), 571);
+        assert_eq!(header_gpio_base(&base, Platform::Broadcom), 512);
+        fs::remove_dir_all(base.join("gpiochip512")).unwrap();
+        assert_eq!(header_gpio_base(&base, Platform::Broadcom), 0);
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_header_i2c_bus() {
+        let base = std::env::temp_dir().join(format!("info_display_i2c_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let (device_tree, adapters) = (base.join("device-tree"), base.join("i2c-adapter"));
+        fs::create_dir_all(device_tree.join("aliases")).unwrap();
+        for node in ["axi/pcie@120000/rp1/i2c@70000", "axi/pcie@120000/rp1/i2c@74000"] {
+            fs::create_dir_all(device_tree.join(node)).unwrap();
+        }
+        for (bus, node) in [("i2c-1", "i2c@70000"), ("i2c-13", "i2c@74000")] {
+            fs::create_dir_all(adapters.join(bus)).unwrap();
+            std::os::unix::fs::symlink(device_tree.join("axi/pcie@120000/rp1").join(node), adapters.join(bus).join("of_node")).unwrap();
+        }
+        assert_eq!(header_i2c_bus(&device_tree, &adapters), None);
+
+        fs::write(device_tree.join("aliases/i2c_arm"), "/axi/pcie@120000/rp1/i2c@74000\0").unwrap();
+        assert_eq!(header_i2c_bus(&device_tree, &adapters), Some(13));
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
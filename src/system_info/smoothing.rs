@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use super::SystemSnapshot;
+
+/// How temperatures are smoothed before the screens, exporters and alerts see them
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TempSmoothing {
+    #[default]
+    Off,
+    /// Exponential moving average: the weight of each new reading, above 0 and at most 1
+    Ema(f32),
+    /// Mean of the last N readings
+    Window(usize),
+}
+
+impl TempSmoothing {
+    /// `off`, `ema:<alpha>` e.g. `ema:0.3`, or `window:<n>` e.g. `window:5`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        if text == "off" {
+            return Some(TempSmoothing::Off);
+        }
+        let (kind, value) = text.split_once(':')?;
+        match kind {
+            "ema" => value.trim().parse().ok()
+                .filter(|alpha| *alpha > 0.0 && *alpha <= 1.0)
+                .map(TempSmoothing::Ema),
+            "window" => value.trim().parse().ok()
+                .filter(|samples| *samples > 0)
+                .map(TempSmoothing::Window),
+            _ => None,
+        }
+    }
+}
+
+/// Smooths one series of readings.
+#[derive(Debug, Clone)]
+pub struct Smoother {
+    smoothing: TempSmoothing,
+    average: Option<f32>,
+    window: VecDeque<f32>,
+}
+
+impl Smoother {
+    pub fn new(smoothing: TempSmoothing) -> Self {
+        Self { smoothing, average: None, window: VecDeque::new() }
+    }
+
+    /// Add a reading and return the smoothed value. The first reading is taken as it is.
+    pub fn add(&mut self, reading: f32) -> f32 {
+        // A bad read would stay in the average for a long time
+        if !reading.is_finite() {
+            return reading;
+        }
+        match self.smoothing {
+            TempSmoothing::Off => reading,
+            TempSmoothing::Ema(alpha) => {
+                let average = self.average.map_or(reading, |average| average + alpha * (reading - average));
+                self.average = Some(average);
+                average
+            }
+            TempSmoothing::Window(samples) => {
+                if self.window.len() == samples {
+                    self.window.pop_front();
+                }
+                self.window.push_back(reading);
+                self.window.iter().sum::<f32>() / self.window.len() as f32
+            }
+        }
+    }
+}
+
+/// Smooths the CPU and GPU temperatures of each snapshot taken, keeping the readings
+/// in the `_raw_` fields. Applied once per update, to the snapshot the screens,
+/// exporters and fan all use.
+#[derive(Debug)]
+pub struct TempSmoother {
+    cpu: Mutex<Smoother>,
+    gpu: Mutex<Smoother>,
+}
+
+impl TempSmoother {
+    pub fn new(smoothing: TempSmoothing) -> Self {
+        Self { cpu: Mutex::new(Smoother::new(smoothing)), gpu: Mutex::new(Smoother::new(smoothing)) }
+    }
+
+    pub fn apply(&self, snapshot: &mut SystemSnapshot) {
+        let smooth = |smoother: &Mutex<Smoother>, reading: Option<f32>| {
+            reading.map(|celsius| smoother.lock().unwrap_or_else(|e| e.into_inner()).add(celsius))
+        };
+        snapshot.cpu_temp_celsius = smooth(&self.cpu, snapshot.cpu_temp_raw_celsius);
+        snapshot.gpu_temp_celsius = smooth(&self.gpu, snapshot.gpu_temp_raw_celsius);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smoothed(smoothing: TempSmoothing, readings: &[f32]) -> Vec<f32> {
+        let mut smoother = Smoother::new(smoothing);
+        readings.iter().map(|&reading| smoother.add(reading)).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(TempSmoothing::parse("off"), Some(TempSmoothing::Off));
+        assert_eq!(TempSmoothing::parse("EMA:0.25"), Some(TempSmoothing::Ema(0.25)));
+        assert_eq!(TempSmoothing::parse("window:5"), Some(TempSmoothing::Window(5)));
+        for invalid in ["", "ema", "ema:0", "ema:1.5", "window:0", "window:-1", "median:3"] {
+            assert_eq!(TempSmoothing::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_off_passes_readings_through() {
+        assert_eq!(smoothed(TempSmoothing::Off, &[50.0, 54.0, 48.0]), vec![50.0, 54.0, 48.0]);
+    }
+
+    #[test]
+    fn test_ema() {
+        // 50, then 50 + 0.5 * (54 - 50), then 52 + 0.5 * (48 - 52)
+        assert_eq!(smoothed(TempSmoothing::Ema(0.5), &[50.0, 54.0, 48.0]), vec![50.0, 52.0, 50.0]);
+        assert_eq!(smoothed(TempSmoothing::Ema(1.0), &[50.0, 54.0]), vec![50.0, 54.0]);
+    }
+
+    #[test]
+    fn test_window() {
+        assert_eq!(smoothed(TempSmoothing::Window(3), &[50.0, 54.0, 49.0, 53.0]), vec![50.0, 52.0, 51.0, 52.0]);
+    }
+
+    #[test]
+    fn test_bad_readings_are_not_averaged() {
+        let values = smoothed(TempSmoothing::Window(2), &[50.0, f32::NAN, 52.0]);
+        assert!(values[1].is_nan());
+        assert_eq!(values[2], 51.0);
+    }
+
+    #[test]
+    fn test_apply_keeps_raw_readings() {
+        let smoother = TempSmoother::new(TempSmoothing::Ema(0.5));
+        let mut snapshot = SystemSnapshot { cpu_temp_raw_celsius: Some(50.0), ..SystemSnapshot::default() };
+        smoother.apply(&mut snapshot);
+        snapshot.cpu_temp_raw_celsius = Some(54.0);
+        smoother.apply(&mut snapshot);
+        assert_eq!(snapshot.cpu_temp_celsius, Some(52.0));
+        assert_eq!(snapshot.cpu_temp_raw_celsius, Some(54.0));
+        assert_eq!(snapshot.gpu_temp_celsius, None);
+    }
+}
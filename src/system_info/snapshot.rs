@@ -0,0 +1,241 @@
+use serde::Serialize;
+use std::ops::{BitOr, BitOrAssign};
+use std::path::Path;
+use std::time::Duration;
+use sysinfo::System;
+
+use super::{
+    get_cpu_freq, get_process_summary, get_cpu_temp, get_cpu_trip_points, get_disk_usage, get_gpu_temp, get_hostname, get_ip_addresses, get_memory_usage,
+    get_readonly_mounts, get_root_inode_usage, get_swap_usage, get_throttle_status, read_uptime_secs, ProcessSummary,
+    ThrottleFlags, Usage,
+};
+use super::sensors::throttle_trip_celsius;
+use super::procfs::{read_meminfo, CpuUsageTracker, PROC_MEMINFO_PATH};
+use super::time::get_clock_sync;
+
+/// The readings a screen uses. Only those are collected into the snapshot before each
+/// frame; enumerating every process and disk is slow on a Pi Zero, and vcgencmd runs a
+/// process per reading. Memory and CPU usage come straight from /proc, only processes
+/// need a refresh of `sysinfo::System`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshRequirements(u16);
+
+impl RefreshRequirements {
+    pub const NONE: Self = Self(0);
+    /// Memory and swap
+    pub const MEMORY: Self = Self(1);
+    /// CPU usage since the previous snapshot
+    pub const CPU: Self = Self(1 << 1);
+    /// Process counts and the busiest processes
+    pub const PROCESSES: Self = Self(1 << 2);
+    pub const CPU_TEMP: Self = Self(1 << 3);
+    pub const GPU_TEMP: Self = Self(1 << 4);
+    pub const CPU_FREQ: Self = Self(1 << 5);
+    pub const THROTTLE: Self = Self(1 << 6);
+    pub const DISK: Self = Self(1 << 7);
+    pub const UPTIME: Self = Self(1 << 8);
+    /// The addresses of every interface
+    pub const NETWORK: Self = Self(1 << 9);
+    /// Inodes of the root filesystem
+    pub const INODES: Self = Self(1 << 10);
+    /// Filesystems mounted read-only
+    pub const MOUNTS: Self = Self(1 << 11);
+    /// Clock offset from chrony or the kernel
+    pub const CLOCK: Self = Self(1 << 12);
+    pub const ALL: Self = Self((1 << 13) - 1);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Refresh the required parts of `sys`, leaving the rest as they are.
+    pub fn refresh(self, sys: &mut System) {
+        // Memory is read from /proc/meminfo where there is one
+        if self.contains(Self::MEMORY) && !Path::new(PROC_MEMINFO_PATH).exists() {
+            sys.refresh_memory();
+        }
+        if self.contains(Self::PROCESSES) {
+            sys.refresh_processes();
+        }
+    }
+}
+
+impl BitOr for RefreshRequirements {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for RefreshRequirements {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+/// Readings taken in one go, once per update, so screens and exporters share them
+/// instead of each reading the sensors again. Readings that are unavailable on this
+/// machine, or that nothing asked for, are None.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SystemSnapshot {
+    pub hostname: String,
+    pub ip_addresses: Vec<String>,
+    /// Smoothed when configured, see TempSmoother
+    pub cpu_temp_celsius: Option<f32>,
+    pub gpu_temp_celsius: Option<f32>,
+    /// The temperatures as read
+    pub cpu_temp_raw_celsius: Option<f32>,
+    pub gpu_temp_raw_celsius: Option<f32>,
+    /// The CPU zone's lowest throttling or critical trip point
+    pub cpu_trip_celsius: Option<f32>,
+    pub cpu_freq_hz: Option<u64>,
+    /// Busy time of all CPUs together since the previous snapshot; None in the first
+    pub cpu_usage_percent: Option<f32>,
+    pub memory_used_bytes: u64,
+    pub memory_total_bytes: u64,
+    /// A total of Some(0) means there is no swap
+    pub swap_used_bytes: Option<u64>,
+    pub swap_total_bytes: Option<u64>,
+    pub disk_used_bytes: Option<u64>,
+    pub disk_total_bytes: Option<u64>,
+    /// Of the root filesystem; None as well on filesystems without an inode limit
+    pub inodes_used: Option<u64>,
+    pub inodes_total: Option<u64>,
+    /// Mount points of filesystems that should be writable but are mounted read-only
+    pub readonly_mounts: Option<Vec<String>>,
+    pub uptime_secs: Option<f64>,
+    pub throttled: Option<u32>,
+    pub processes: Option<ProcessSummary>,
+    pub clock_synced: Option<bool>,
+    /// Positive when the clock is ahead of its NTP source
+    pub clock_offset_ms: Option<f64>,
+}
+
+impl SystemSnapshot {
+    /// Take the readings in `needed`. Memory figures come from /proc/meminfo, or else from
+    /// `sys`, which the caller refreshes, as are the processes. CPU usage is measured
+    /// since the previous snapshot taken with `cpu`.
+    pub fn collect(sys: &System, needed: RefreshRequirements, cpu: &CpuUsageTracker) -> Self {
+        let wants = |reading| needed.contains(reading);
+        let (memory, swap) = if wants(RefreshRequirements::MEMORY) {
+            match read_meminfo() {
+                Ok(info) => (Some(info.memory), Some((info.swap.total > 0).then_some(info.swap))),
+                Err(_) => (Some(get_memory_usage(sys)), Some(get_swap_usage(sys))),
+            }
+        } else {
+            (None, None)
+        };
+        let disk = wants(RefreshRequirements::DISK).then(get_disk_usage).and_then(Result::ok);
+        let inodes = wants(RefreshRequirements::INODES).then(get_root_inode_usage).and_then(Result::ok);
+        let cpu_temp = wants(RefreshRequirements::CPU_TEMP).then(get_cpu_temp).and_then(Result::ok);
+        let gpu_temp = wants(RefreshRequirements::GPU_TEMP).then(get_gpu_temp).and_then(Result::ok);
+        let clock = wants(RefreshRequirements::CLOCK).then(get_clock_sync).and_then(Result::ok);
+        Self {
+            hostname: get_hostname().unwrap_or_else(|| "Unknown".to_string()),
+            ip_addresses: if wants(RefreshRequirements::NETWORK) { get_ip_addresses() } else { Vec::new() },
+            cpu_temp_celsius: cpu_temp,
+            gpu_temp_celsius: gpu_temp,
+            cpu_temp_raw_celsius: cpu_temp,
+            gpu_temp_raw_celsius: gpu_temp,
+            cpu_trip_celsius: wants(RefreshRequirements::CPU_TEMP).then(|| throttle_trip_celsius(&get_cpu_trip_points())).flatten(),
+            cpu_freq_hz: wants(RefreshRequirements::CPU_FREQ).then(get_cpu_freq).and_then(Result::ok),
+            cpu_usage_percent: wants(RefreshRequirements::CPU).then(|| cpu.usage()).flatten(),
+            memory_used_bytes: memory.map_or(0, |usage| usage.used),
+            memory_total_bytes: memory.map_or(0, |usage| usage.total),
+            swap_used_bytes: swap.map(|usage| usage.map_or(0, |usage| usage.used)),
+            swap_total_bytes: swap.map(|usage| usage.map_or(0, |usage| usage.total)),
+            disk_used_bytes: disk.map(|usage| usage.used),
+            disk_total_bytes: disk.map(|usage| usage.total),
+            inodes_used: inodes.map(|usage| usage.used),
+            inodes_total: inodes.map(|usage| usage.total),
+            readonly_mounts: wants(RefreshRequirements::MOUNTS).then(get_readonly_mounts),
+            uptime_secs: wants(RefreshRequirements::UPTIME).then(read_uptime_secs).flatten(),
+            throttled: wants(RefreshRequirements::THROTTLE).then(get_throttle_status)
+                .and_then(Result::ok)
+                .map(|flags| flags.bits()),
+            processes: wants(RefreshRequirements::PROCESSES).then(|| get_process_summary(sys)),
+            clock_synced: clock.as_ref().map(|sync| sync.synced),
+            clock_offset_ms: clock.map(|sync| sync.offset_ms),
+        }
+    }
+
+    /// None when uptime was not read.
+    pub fn uptime(&self) -> Option<Duration> {
+        self.uptime_secs.and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+    }
+
+    /// Degrees the CPU can warm up by before it reaches its trip point; None without one.
+    pub fn thermal_margin(&self) -> Option<f32> {
+        Some(self.cpu_trip_celsius? - self.cpu_temp_celsius?)
+    }
+
+    /// None when memory was not read.
+    pub fn memory(&self) -> Option<Usage> {
+        (self.memory_total_bytes > 0).then_some(Usage { used: self.memory_used_bytes, total: self.memory_total_bytes })
+    }
+
+    /// None when swap was not read, Some(None) when there is no swap.
+    pub fn swap(&self) -> Option<Option<Usage>> {
+        match (self.swap_used_bytes, self.swap_total_bytes) {
+            (_, Some(0)) => Some(None),
+            (Some(used), Some(total)) => Some(Some(Usage { used, total })),
+            _ => None,
+        }
+    }
+
+    pub fn disk(&self) -> Option<Usage> {
+        Some(Usage { used: self.disk_used_bytes?, total: self.disk_total_bytes? })
+    }
+
+    pub fn inodes(&self) -> Option<Usage> {
+        Some(Usage { used: self.inodes_used?, total: self.inodes_total? })
+    }
+
+    pub fn throttle_flags(&self) -> Option<ThrottleFlags> {
+        self.throttled.map(ThrottleFlags::from_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::ThrottleConditions;
+
+    #[test]
+    fn test_collect_only_what_is_needed() {
+        let snapshot = SystemSnapshot::collect(&System::new(), RefreshRequirements::NONE, &CpuUsageTracker::new());
+        assert!(snapshot.cpu_temp_celsius.is_none());
+        assert!(snapshot.ip_addresses.is_empty());
+        assert_eq!(snapshot.memory(), None);
+        assert_eq!(snapshot.swap(), None);
+        assert_eq!(snapshot.disk(), None);
+        assert_eq!(snapshot.inodes(), None);
+        assert_eq!(snapshot.readonly_mounts, None);
+        assert_eq!(snapshot.throttle_flags(), None);
+        assert_eq!(snapshot.clock_offset_ms, None);
+    }
+
+    #[test]
+    fn test_usage_accessors() {
+        let snapshot = SystemSnapshot {
+            memory_used_bytes: 1,
+            memory_total_bytes: 4,
+            swap_used_bytes: Some(0),
+            swap_total_bytes: Some(0),
+            disk_used_bytes: Some(2),
+            disk_total_bytes: Some(8),
+            throttled: Some(0x50005),
+            ..SystemSnapshot::default()
+        };
+        assert_eq!(snapshot.memory(), Some(Usage { used: 1, total: 4 }));
+        assert_eq!(snapshot.swap(), Some(None));
+        assert_eq!(snapshot.disk(), Some(Usage { used: 2, total: 8 }));
+        let flags = snapshot.throttle_flags().unwrap();
+        assert!(flags.current.contains(ThrottleConditions::UNDER_VOLTAGE));
+        assert_eq!(flags.bits(), 0x50005);
+
+        let swapping = SystemSnapshot { swap_used_bytes: Some(3), swap_total_bytes: Some(9), ..snapshot };
+        assert_eq!(swapping.swap(), Some(Some(Usage { used: 3, total: 9 })));
+    }
+}
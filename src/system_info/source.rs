@@ -0,0 +1,241 @@
+use std::time::Duration;
+use anyhow::Result;
+use sysinfo::System;
+
+use super::*;
+use super::procfs::CpuUsageTracker;
+
+/// Where screens and exporters get their readings from. Everything goes through
+/// this trait so the live system can be swapped for synthetic data (`--demo`).
+pub trait DataSource: Send + Sync {
+    fn hostname(&self) -> String;
+    fn domain(&self) -> String;
+    fn ip_address(&self) -> Result<String>;
+    /// Global IPv6 address of `interface`, or of any interface when None
+    fn ipv6_address(&self, interface: Option<&str>) -> Result<String>;
+    fn mac_address(&self) -> String;
+    /// The wired interface and its speed and duplex, e.g. `eth0 1Gb FD` or `eth0 down`,
+    /// or else the wireless one and its bitrate; None without either
+    fn link_speed(&self) -> Option<String>;
+    /// Address, MAC and link speed of the named interface, for a screen showing just that one
+    fn interface(&self, name: &str) -> InterfaceInfo;
+    /// Established TCP connections
+    fn connection_count(&self) -> Result<usize>;
+    /// Why the wifi radio is blocked, None when it is not or there is none
+    fn wifi_block(&self) -> Option<WifiBlock>;
+
+    /// Degrees Celsius
+    fn cpu_temp(&self) -> Result<f32>;
+    fn gpu_temp(&self) -> Result<f32>;
+    /// ARM core clock in Hz
+    fn cpu_freq(&self) -> Result<u64>;
+    fn throttle_status(&self) -> Result<ThrottleFlags>;
+    /// Throttle conditions that came on in the last 24 hours; None when they are not logged
+    fn throttle_events(&self) -> Option<ThrottleEventSummary>;
+    fn thermal_zones(&self) -> Vec<(String, f32)>;
+    /// Time since boot, None when unknown
+    fn uptime(&self) -> Option<Duration>;
+    /// A pending or scheduled reboot, None when there is none
+    fn reboot_notice(&self) -> Option<String>;
+    /// Whether the clock has been synchronized, None when unknown
+    fn time_synced(&self) -> Option<bool>;
+
+    fn memory_usage(&self, sys: &System) -> Result<Usage>;
+    /// None when there is no swap
+    fn swap_usage(&self, sys: &System) -> Result<Option<Usage>>;
+    fn disk_usage(&self) -> Result<Usage>;
+
+    fn pi_model(&self) -> String;
+    /// The decoded revision code; None where there is none, e.g. off a Pi
+    fn revision_info(&self) -> Option<RevisionInfo>;
+    fn serial_number(&self) -> String;
+    /// The ID, name and hardware the exporters report this device under
+    fn device_identity(&self) -> DeviceIdentity;
+    fn firmware_version(&self) -> String;
+    fn boot_partition(&self) -> String;
+
+    /// The I2C bus on the 40-pin header: 1 up to the Pi 4, wherever RP1 puts it on a Pi 5
+    fn header_i2c_bus(&self) -> u8;
+    /// Addresses that answer on /dev/i2c-`bus`
+    fn i2c_devices(&self, bus: u8) -> Result<Vec<u8>>;
+    fn gpio_states(&self) -> String;
+    /// The `[[gpio_inputs]]` being watched, as (label, state) pairs
+    fn gpio_inputs(&self) -> Vec<(String, String)>;
+    fn spi_devices(&self) -> String;
+    fn one_wire_sensors(&self, family: Option<u8>) -> String;
+    /// Sources of readings that failed since startup, the most failures first
+    fn source_failures(&self) -> Vec<SourceFailures>;
+
+    /// The readings in `needed`, taken once per update for the screens and exporters;
+    /// `sys` is refreshed by the caller. Smoothed temperatures advance with every call,
+    /// so one update must not take two snapshots.
+    fn snapshot(&self, sys: &System, needed: RefreshRequirements) -> SystemSnapshot;
+}
+
+/// Reads the running system. Hardware details that rarely change are cached, see SystemInfoCache.
+pub struct LiveDataSource {
+    cache: SystemInfoCache,
+    cpu_usage: CpuUsageTracker,
+    smoother: TempSmoother,
+}
+
+impl LiveDataSource {
+    pub fn new() -> Self {
+        Self::with_temp_smoothing(TempSmoothing::Off)
+    }
+
+    /// Temperatures in the snapshots are smoothed over the snapshots taken before.
+    pub fn with_temp_smoothing(smoothing: TempSmoothing) -> Self {
+        Self { cache: SystemInfoCache::new(), cpu_usage: CpuUsageTracker::new(), smoother: TempSmoother::new(smoothing) }
+    }
+}
+
+impl Default for LiveDataSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DataSource for LiveDataSource {
+    fn hostname(&self) -> String {
+        get_hostname().unwrap_or_else(|| placeholder().to_string())
+    }
+
+    fn domain(&self) -> String {
+        self.cache.domain()
+    }
+
+    fn ip_address(&self) -> Result<String> {
+        get_ip_address()
+    }
+
+    fn ipv6_address(&self, interface: Option<&str>) -> Result<String> {
+        get_ipv6_address(interface).ok_or_else(|| anyhow::anyhow!("no global IPv6 address"))
+    }
+
+    fn mac_address(&self) -> String {
+        self.cache.mac_address()
+    }
+
+    fn link_speed(&self) -> Option<String> {
+        get_primary_link()
+    }
+
+    fn interface(&self, name: &str) -> InterfaceInfo {
+        get_interface_info(name)
+    }
+
+    fn connection_count(&self) -> Result<usize> {
+        Ok(get_connection_count())
+    }
+
+    fn wifi_block(&self) -> Option<WifiBlock> {
+        get_wifi_block()
+    }
+
+    fn cpu_temp(&self) -> Result<f32> {
+        get_cpu_temp()
+    }
+
+    fn gpu_temp(&self) -> Result<f32> {
+        get_gpu_temp()
+    }
+
+    fn cpu_freq(&self) -> Result<u64> {
+        get_cpu_freq()
+    }
+
+    fn throttle_status(&self) -> Result<ThrottleFlags> {
+        get_throttle_status()
+    }
+
+    fn throttle_events(&self) -> Option<ThrottleEventSummary> {
+        get_throttle_event_summary()
+    }
+
+    fn thermal_zones(&self) -> Vec<(String, f32)> {
+        get_all_thermal_zones()
+    }
+
+    fn uptime(&self) -> Option<Duration> {
+        get_uptime()
+    }
+
+    fn reboot_notice(&self) -> Option<String> {
+        get_reboot_notice()
+    }
+
+    fn time_synced(&self) -> Option<bool> {
+        self.cache.time_synced()
+    }
+
+    fn memory_usage(&self, sys: &System) -> Result<Usage> {
+        Ok(get_memory_usage(sys))
+    }
+
+    fn swap_usage(&self, sys: &System) -> Result<Option<Usage>> {
+        Ok(get_swap_usage(sys))
+    }
+
+    fn disk_usage(&self) -> Result<Usage> {
+        get_disk_usage()
+    }
+
+    fn pi_model(&self) -> String {
+        self.cache.pi_model()
+    }
+
+    fn revision_info(&self) -> Option<RevisionInfo> {
+        self.cache.revision_info()
+    }
+
+    fn serial_number(&self) -> String {
+        self.cache.serial_number()
+    }
+
+    fn device_identity(&self) -> DeviceIdentity {
+        get_device_identity()
+    }
+
+    fn firmware_version(&self) -> String {
+        self.cache.firmware_version()
+    }
+
+    fn boot_partition(&self) -> String {
+        self.cache.boot_partition()
+    }
+
+    fn header_i2c_bus(&self) -> u8 {
+        self.cache.header_i2c_bus()
+    }
+
+    fn i2c_devices(&self, bus: u8) -> Result<Vec<u8>> {
+        get_i2c_devices(bus)
+    }
+
+    fn gpio_states(&self) -> String {
+        get_gpio_states(self.cache.header_gpio_base())
+    }
+
+    fn gpio_inputs(&self) -> Vec<(String, String)> {
+        gpio::get_gpio_inputs()
+    }
+
+    fn spi_devices(&self) -> String {
+        get_spi_devices()
+    }
+
+    fn one_wire_sensors(&self, family: Option<u8>) -> String {
+        get_1wire_sensors(family)
+    }
+
+    fn source_failures(&self) -> Vec<SourceFailures> {
+        get_source_failures()
+    }
+
+    fn snapshot(&self, sys: &System, needed: RefreshRequirements) -> SystemSnapshot {
+        let mut snapshot = SystemSnapshot::collect(sys, needed, &self.cpu_usage);
+        self.smoother.apply(&mut snapshot);
+        snapshot
+    }
+}
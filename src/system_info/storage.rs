@@ -1,28 +1,354 @@
+use anyhow::{anyhow, Result};
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
 use sysinfo::{System, Disks};
 
-pub fn get_memory_info(sys: &System) -> String {
-    let used_mem = sys.used_memory();
-    let total_mem = sys.total_memory();
-    let used_mb = used_mem / 1024 / 1024;
-    let total_mb = total_mem / 1024 / 1024;
-    format!("{}/{}MB", used_mb, total_mb)
+use super::failures::{track, DISK_SOURCE};
+use super::placeholder;
+
+const MOUNTS_PATH: &str = "/proc/mounts";
+
+// Read-only by design rather than because something went wrong
+const READ_ONLY_FS_TYPES: [&str; 3] = ["squashfs", "iso9660", "erofs"];
+
+const MB: u64 = 1024 * 1024;
+const GB: u64 = 1024 * 1024 * 1024;
+
+/// Bytes in use out of a total, for memory, swap and disks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub used: u64,
+    pub total: u64,
+}
+
+impl Usage {
+    pub fn percent(&self) -> Option<u64> {
+        percent_used(self.used, self.total)
+    }
+
+    /// The unrounded percentage, for comparing against thresholds.
+    pub fn percent_exact(&self) -> Option<f32> {
+        usage_percent(self.used, self.total)
+    }
+}
+
+/// What statvfs(3) reports for a mounted filesystem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsStats {
+    pub fragment_size: u64,
+    pub blocks: u64,
+    pub blocks_free: u64,
+    pub files: u64,
+    pub files_free: u64,
+}
+
+impl FsStats {
+    /// Space counted the way df does, including blocks reserved for root.
+    pub fn space(&self) -> Usage {
+        Usage {
+            used: self.blocks.saturating_sub(self.blocks_free) * self.fragment_size,
+            total: self.blocks * self.fragment_size,
+        }
+    }
+
+    /// None on filesystems without a fixed inode table, such as btrfs, which report zero.
+    pub fn inodes(&self) -> Option<Usage> {
+        (self.files > 0).then(|| Usage { used: self.files.saturating_sub(self.files_free), total: self.files })
+    }
+}
+
+pub fn statvfs(path: &Path) -> io::Result<FsStats> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"))?;
+    let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: path is NUL-terminated and stats is only read after statvfs filled it in
+    let stats = unsafe {
+        if libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stats.assume_init()
+    };
+    // The field widths differ between 32 and 64-bit targets
+    #[allow(clippy::unnecessary_cast)]
+    Ok(FsStats {
+        fragment_size: stats.f_frsize as u64,
+        blocks: stats.f_blocks as u64,
+        blocks_free: stats.f_bfree as u64,
+        files: stats.f_files as u64,
+        files_free: stats.f_ffree as u64,
+    })
+}
+
+/// Inodes in use on the root filesystem.
+pub fn get_root_inode_usage() -> Result<Usage> {
+    statvfs(Path::new("/"))?.inodes().ok_or_else(|| anyhow!("the root filesystem has no inode limit"))
+}
+
+/// One line of /proc/mounts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountEntry {
+    pub source: String,
+    pub mount_point: String,
+    pub fs_type: String,
+    pub options: Vec<String>,
+}
+
+impl MountEntry {
+    pub fn read_only(&self) -> bool {
+        self.options.iter().any(|option| option == "ro")
+    }
+
+    // Backed by a device rather than e.g. proc or tmpfs
+    fn on_device(&self) -> bool {
+        self.source.starts_with("/dev/")
+    }
+}
+
+/// The current mounts, in mount order.
+pub fn read_mounts() -> Vec<MountEntry> {
+    parse_mounts(&fs::read_to_string(MOUNTS_PATH).unwrap_or_default())
 }
 
-pub fn get_disk_usage() -> String {
+/// Entries of /proc/mounts content; lines with too few fields are skipped.
+pub fn parse_mounts(contents: &str) -> Vec<MountEntry> {
+    contents.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            // Spaces in mount points are escaped as \040
+            let unescape = |field: &str| field.replace("\\040", " ");
+            Some(MountEntry {
+                source: unescape(fields.next()?),
+                mount_point: unescape(fields.next()?),
+                fs_type: fields.next()?.to_string(),
+                options: fields.next()?.split(',').map(str::to_string).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Inode usage of every mounted block device, by mount point. Pseudo filesystems
+/// such as proc and tmpfs are left out.
+pub fn get_mount_inode_usage() -> Vec<(String, Usage)> {
+    device_mount_points(&read_mounts()).into_iter()
+        .filter_map(|mount_point| {
+            let inodes = statvfs(Path::new(&mount_point)).ok()?.inodes()?;
+            Some((mount_point, inodes))
+        })
+        .collect()
+}
+
+// Mount points of entries backed by a device, each listed once
+fn device_mount_points(mounts: &[MountEntry]) -> Vec<String> {
+    let mut mount_points: Vec<String> = Vec::new();
+    for entry in mounts.iter().filter(|entry| entry.on_device()) {
+        if !mount_points.contains(&entry.mount_point) {
+            mount_points.push(entry.mount_point.clone());
+        }
+    }
+    mount_points
+}
+
+/// Mount points of device-backed filesystems that are mounted read-only, e.g. `/`
+/// after the kernel remounted it on errors from a failing SD card. Filesystems that
+/// are read-only by design, such as squashfs, are left out.
+pub fn get_readonly_mounts() -> Vec<String> {
+    readonly_mount_points(&read_mounts())
+}
+
+fn readonly_mount_points(mounts: &[MountEntry]) -> Vec<String> {
+    device_mount_points(mounts).into_iter()
+        .filter(|mount_point| {
+            // The last entry at a mount point is the one in effect
+            mounts.iter().rev().find(|entry| entry.mount_point == *mount_point).is_some_and(|entry| {
+                entry.read_only() && !READ_ONLY_FS_TYPES.contains(&entry.fs_type.as_str())
+            })
+        })
+        .collect()
+}
+
+pub fn get_memory_usage(sys: &System) -> Usage {
+    Usage { used: sys.used_memory(), total: sys.total_memory() }
+}
+
+/// None when the system has no swap.
+pub fn get_swap_usage(sys: &System) -> Option<Usage> {
+    (sys.total_swap() > 0).then(|| Usage { used: sys.used_swap(), total: sys.total_swap() })
+}
+
+/// Used and total bytes summed over all disks.
+pub fn get_disk_usage() -> Result<Usage> {
     let disks = Disks::new_with_refreshed_list();
-    let mut total_space = 0;
-    let mut used_space = 0;
+    let mut total = 0;
+    let mut used = 0;
     
     for disk in &disks {
-        total_space += disk.total_space();
-        used_space += disk.total_space() - disk.available_space();
+        total += disk.total_space();
+        used += disk.total_space() - disk.available_space();
     }
     
-    if total_space > 0 {
-        let used_gb = used_space / 1024 / 1024 / 1024;
-        let total_gb = total_space / 1024 / 1024 / 1024;
-        format!("{}/{}GB", used_gb, total_gb)
-    } else {
-        "N/A".to_string()
-    }
-}
\ No newline at end of file
+    if total == 0 {
+        return track(DISK_SOURCE, Err(anyhow!("no disk reports a size")));
+    }
+    Ok(Usage { used, total })
+}
+
+pub fn get_memory_display(sys: &System, show_percent: bool) -> String {
+    format_memory(&get_memory_usage(sys), show_percent)
+}
+
+pub fn get_swap_display(sys: &System, show_percent: bool) -> String {
+    format_swap(get_swap_usage(sys).as_ref(), show_percent)
+}
+
+pub fn get_disk_usage_display(show_percent: bool) -> String {
+    get_disk_usage()
+        .map(|usage| format_disk(&usage, show_percent))
+        .unwrap_or_else(|_| placeholder().to_string())
+}
+
+pub fn format_memory(usage: &Usage, show_percent: bool) -> String {
+    format_usage(usage.used, usage.total, MB, "MB", show_percent)
+}
+
+pub fn format_swap(usage: Option<&Usage>, show_percent: bool) -> String {
+    match usage {
+        Some(usage) => format_usage(usage.used, usage.total, MB, "MB", show_percent),
+        None => "None".to_string(),
+    }
+}
+
+pub fn format_disk(usage: &Usage, show_percent: bool) -> String {
+    format_usage(usage.used, usage.total, GB, "GB", show_percent)
+}
+
+/// Percentage of `total` that is used, or None when the total is zero.
+pub fn percent_used(used: u64, total: u64) -> Option<u64> {
+    if total == 0 {
+        return None;
+    }
+    Some((used as f64 / total as f64 * 100.0).round() as u64)
+}
+
+/// Unrounded percentage of `total` that is used, or None when the total is zero.
+pub fn usage_percent(used: u64, total: u64) -> Option<f32> {
+    (total > 0).then(|| used as f32 / total as f32 * 100.0)
+}
+
+// "used/totalUNIT", optionally followed by " (NN%)"
+pub fn format_usage(used: u64, total: u64, unit_size: u64, unit: &str, show_percent: bool) -> String {
+    let usage = format!("{}/{}{}", used / unit_size, total / unit_size, unit);
+    match percent_used(used, total) {
+        Some(percent) if show_percent => format!("{} ({}%)", usage, percent),
+        _ => usage,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_used() {
+        assert_eq!(percent_used(120, 512), Some(23));
+        assert_eq!(percent_used(512, 512), Some(100));
+        assert_eq!(percent_used(0, 512), Some(0));
+    }
+
+    #[test]
+    fn test_percent_used_zero_total() {
+        assert_eq!(percent_used(0, 0), None);
+        assert_eq!(percent_used(10, 0), None);
+    }
+
+    #[test]
+    fn test_usage_percent() {
+        assert_eq!(usage_percent(1, 8), Some(12.5));
+        assert_eq!(usage_percent(0, 0), None);
+        assert_eq!(Usage { used: 23, total: 25 }.percent_exact(), Some(92.0));
+    }
+
+    #[test]
+    fn test_fs_stats() {
+        let stats = FsStats { fragment_size: 4096, blocks: 1000, blocks_free: 250, files: 500, files_free: 40 };
+        assert_eq!(stats.space(), Usage { used: 750 * 4096, total: 1000 * 4096 });
+        assert_eq!(stats.inodes(), Some(Usage { used: 460, total: 500 }));
+        assert_eq!(stats.inodes().unwrap().percent(), Some(92));
+
+        let btrfs = FsStats { files: 0, files_free: 0, ..stats };
+        assert_eq!(btrfs.inodes(), None);
+    }
+
+    #[test]
+    fn test_statvfs() {
+        let stats = statvfs(Path::new("/")).unwrap();
+        assert!(stats.blocks >= stats.blocks_free);
+        assert!(stats.files >= stats.files_free);
+        assert!(statvfs(Path::new("/no/such/mount")).is_err());
+    }
+
+    const MOUNTS: &str = "\
+/dev/mmcblk0p2 / ext4 rw,noatime 0 0
+proc /proc proc rw,relatime 0 0
+tmpfs /run tmpfs rw,nosuid,nodev 0 0
+/dev/mmcblk0p1 /boot/firmware vfat rw,relatime 0 2
+/dev/sda1 /mnt/usb\\040disk ext4 rw,relatime 0 0
+/dev/mmcblk0p2 / ext4 ro,noatime 0 0
+/dev/loop0 /snap/core/1 squashfs ro,nodev,relatime 0 0
+sysfs /sys sysfs ro,nosuid 0 0
+/dev/sdb1 /mnt/backup ext4 ro 0 0
+/dev/sdb1 /mnt/backup ext4 rw,relatime 0 0
+";
+
+    #[test]
+    fn test_parse_mounts() {
+        let mounts = parse_mounts(MOUNTS);
+        assert_eq!(mounts.len(), 10);
+        assert_eq!(mounts[4], MountEntry {
+            source: "/dev/sda1".to_string(),
+            mount_point: "/mnt/usb disk".to_string(),
+            fs_type: "ext4".to_string(),
+            options: vec!["rw".to_string(), "relatime".to_string()],
+        });
+        assert!(!mounts[0].read_only());
+        assert!(mounts[5].read_only());
+        // Only the whole option counts
+        assert!(!parse_mounts("/dev/sda1 /mnt ext4 rw,errors=remount-ro 0 0")[0].read_only());
+        assert!(parse_mounts("/dev/sda1 /mnt\n").is_empty());
+    }
+
+    #[test]
+    fn test_device_mount_points() {
+        assert_eq!(
+            device_mount_points(&parse_mounts(MOUNTS)),
+            vec!["/", "/boot/firmware", "/mnt/usb disk", "/snap/core/1", "/mnt/backup"]
+        );
+    }
+
+    #[test]
+    fn test_readonly_mount_points() {
+        // The root was remounted read-only; the backup disk was made writable again
+        assert_eq!(readonly_mount_points(&parse_mounts(MOUNTS)), vec!["/"]);
+        assert!(readonly_mount_points(&parse_mounts(&MOUNTS.replace("ro,noatime", "rw,noatime"))).is_empty());
+    }
+
+    #[test]
+    fn test_format_usage() {
+        assert_eq!(format_usage(120 * MB, 512 * MB, MB, "MB", true), "120/512MB (23%)");
+        assert_eq!(format_usage(120 * MB, 512 * MB, MB, "MB", false), "120/512MB");
+        assert_eq!(format_usage(0, 0, MB, "MB", true), "0/0MB");
+    }
+
+    #[test]
+    fn test_usage_formatters() {
+        let memory = Usage { used: 120 * MB, total: 512 * MB };
+        assert_eq!(memory.percent(), Some(23));
+        assert_eq!(format_memory(&memory, true), "120/512MB (23%)");
+        assert_eq!(format_swap(Some(&memory), false), "120/512MB");
+        assert_eq!(format_swap(None, true), "None");
+        assert_eq!(format_disk(&Usage { used: 12 * GB, total: 29 * GB }, false), "12/29GB");
+    }
+}
@@ -1,23 +1,144 @@
 use std::fs;
+use chrono::{DateTime, Local, TimeDelta};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-pub fn get_uptime() -> String {
-    if let Ok(uptime_str) = fs::read_to_string("/proc/uptime") {
-        if let Some(uptime_seconds_str) = uptime_str.split_whitespace().next() {
-            if let Ok(uptime_seconds) = uptime_seconds_str.parse::<f64>() {
-                let days = (uptime_seconds / 86400.0) as u32;
-                let hours = ((uptime_seconds % 86400.0) / 3600.0) as u32;
-                let minutes = ((uptime_seconds % 3600.0) / 60.0) as u32;
-                
-                if days > 0 {
-                    return format!("{}d{}h{}m", days, hours, minutes);
-                } else if hours > 0 {
-                    return format!("{}h{}m", hours, minutes);
-                } else {
-                    return format!("{}m", minutes);
-                }
-            }
-        }
-    }
-    
-    "Unknown".to_string()
-}
\ No newline at end of file
+use super::command::run_command;
+
+// Created by Debian/Ubuntu package scripts when an update needs a reboot
+const REBOOT_REQUIRED_PATH: &str = "/var/run/reboot-required";
+// Written by systemd for `shutdown -r +N`
+const SCHEDULED_SHUTDOWN_PATH: &str = "/run/systemd/shutdown/scheduled";
+// Created by systemd-timesyncd once it has set the clock
+const TIMESYNC_SYNCHRONIZED_PATH: &str = "/run/systemd/timesync/synchronized";
+
+/// Time since boot, None when /proc/uptime cannot be read.
+pub fn get_uptime() -> Option<Duration> {
+    read_uptime_secs().and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+}
+
+// "3d4h12m", "4h12m", or with seconds under an hour, "4m12s" and "12s"
+pub fn format_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    let (days, hours, minutes, seconds) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60, secs % 60);
+
+    if days > 0 {
+        format!("{}d{}h{}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h{}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// When the system booted, `uptime` before `now`, as strftime `format` gives it.
+pub fn format_boot_time(uptime: Duration, now: DateTime<Local>, format: &str) -> String {
+    let boot = TimeDelta::from_std(uptime).ok()
+        .and_then(|uptime| now.checked_sub_signed(uptime))
+        .unwrap_or(now);
+    boot.format(format).to_string()
+}
+
+/// Seconds since boot, from /proc/uptime.
+pub fn read_uptime_secs() -> Option<f64> {
+    let uptime_str = fs::read_to_string("/proc/uptime").ok()?;
+    uptime_str.split_whitespace().next()?.parse().ok()
+}
+/// Whether installed updates are waiting for a reboot.
+pub fn get_reboot_required() -> bool {
+    Path::new(REBOOT_REQUIRED_PATH).exists()
+}
+
+/// A pending reboot as one short line: a countdown when one is scheduled, otherwise
+/// "Reboot required" after updates. None when there is nothing to report.
+pub fn get_reboot_notice() -> Option<String> {
+    let scheduled = fs::read_to_string(SCHEDULED_SHUTDOWN_PATH).ok()
+        .and_then(|contents| parse_scheduled_shutdown(&contents, SystemTime::now()));
+    match scheduled {
+        Some((mode, remaining)) => Some(format!("{} in {}", mode, format_uptime(remaining))),
+        None => get_reboot_required().then(|| "Reboot required".to_string()),
+    }
+}
+
+// "USEC=1700000000000000\nWARN_WALL=1\nMODE=reboot"; a time already passed counts as now
+fn parse_scheduled_shutdown(contents: &str, now: SystemTime) -> Option<(&'static str, Duration)> {
+    let value = |key: &str| contents.lines().find_map(|line| line.strip_prefix(key)?.strip_prefix('='));
+    let at = UNIX_EPOCH + Duration::from_micros(value("USEC")?.trim().parse().ok()?);
+    let mode = match value("MODE").map(str::trim) {
+        Some("reboot") | Some("kexec") => "Reboot",
+        _ => "Shutdown",
+    };
+    Some((mode, at.duration_since(now).unwrap_or_default()))
+}
+
+/// Whether the clock has been synchronized. A Pi has no RTC, so until NTP has set it
+/// the time carries on from the last shutdown. None when this cannot be told.
+pub fn get_time_synced() -> Option<bool> {
+    if Path::new(TIMESYNC_SYNCHRONIZED_PATH).exists() {
+        return Some(true);
+    }
+    // timedatectl asks the kernel, so it also knows when chrony or ntpd set the clock
+    run_command("timedatectl", &["show", "--property=NTPSynchronized", "--value"]).ok()
+        .and_then(|output| parse_ntp_synchronized(&output))
+}
+
+fn parse_ntp_synchronized(output: &str) -> Option<bool> {
+    match output.trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_format_uptime() {
+        let uptime = |secs| format_uptime(Duration::from_secs(secs));
+        assert_eq!(uptime(0), "0s");
+        assert_eq!(uptime(59), "59s");
+        assert_eq!(uptime(60), "1m0s");
+        assert_eq!(uptime(252), "4m12s");
+        assert_eq!(uptime(3599), "59m59s");
+        assert_eq!(uptime(3600), "1h0m");
+        assert_eq!(uptime(86399), "23h59m");
+        assert_eq!(uptime(86400), "1d0h0m");
+        assert_eq!(uptime(274_320), "3d4h12m");
+        // Fractions of a second are dropped rather than rounded up to the next minute
+        assert_eq!(format_uptime(Duration::from_secs_f64(59.9)), "59s");
+    }
+
+    #[test]
+    fn test_format_boot_time() {
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        assert_eq!(format_boot_time(Duration::from_secs(9000), now, "%Y-%m-%d %H:%M"), "2024-05-01 09:30");
+        assert_eq!(format_boot_time(Duration::from_secs(86400), now, "%d %b %H:%M"), "30 Apr 12:00");
+        assert_eq!(format_boot_time(Duration::ZERO, now, "%H:%M:%S"), "12:00:00");
+    }
+
+    #[test]
+    fn test_parse_scheduled_shutdown() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let (mode, remaining) = parse_scheduled_shutdown("USEC=1700000720000000\nWARN_WALL=1\nMODE=reboot\n", now).unwrap();
+        assert_eq!(mode, "Reboot");
+        assert_eq!(remaining, Duration::from_secs(720));
+
+        let (mode, remaining) = parse_scheduled_shutdown("USEC=1699999999000000\nMODE=poweroff\n", now).unwrap();
+        assert_eq!(mode, "Shutdown");
+        assert!(remaining.is_zero());
+
+        assert!(parse_scheduled_shutdown("MODE=reboot\n", now).is_none());
+    }
+
+    #[test]
+    fn test_parse_ntp_synchronized() {
+        assert_eq!(parse_ntp_synchronized("yes\n"), Some(true));
+        assert_eq!(parse_ntp_synchronized("no\n"), Some(false));
+        assert_eq!(parse_ntp_synchronized(""), None);
+    }
+}
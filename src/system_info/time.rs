@@ -0,0 +1,135 @@
+use anyhow::{anyhow, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::command::{run_command, CommandError};
+use super::system::get_time_synced;
+
+// Set once chronyc turned out not to be installed, so it is not looked for on every snapshot
+static CHRONY_MISSING: AtomicBool = AtomicBool::new(false);
+
+/// How far the clock is from the time its NTP source gives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockSync {
+    pub synced: bool,
+    /// Positive when the clock is ahead, negative when it is behind
+    pub offset_ms: f64,
+    /// "chrony", or "adjtimex" for what the kernel knows when systemd-timesyncd or ntpd keep the time
+    pub source: String,
+}
+
+/// Clock offset from chrony where it runs, otherwise from the kernel with the
+/// synchronized flag from timedatectl. Both commands run with the command timeout.
+pub fn get_clock_sync() -> Result<ClockSync> {
+    if !CHRONY_MISSING.load(Ordering::Relaxed) {
+        // -n: no DNS lookup of the source, which could outlast the timeout
+        match run_command("chronyc", &["-n", "-c", "tracking"]) {
+            Ok(output) => {
+                return parse_chronyc_tracking(&output).ok_or_else(|| anyhow!("Unexpected chronyc tracking output"));
+            }
+            Err(CommandError::NotFound(_)) => CHRONY_MISSING.store(true, Ordering::Relaxed),
+            // Installed but chronyd is not running, so something else keeps the time
+            Err(_) => {}
+        }
+    }
+    let kernel = read_kernel_clock()?;
+    Ok(kernel.clock_sync(get_time_synced()))
+}
+
+/// `chronyc -c tracking`: reference ID, name, stratum, reference time, system time,
+/// last offset, RMS offset, frequency, residual frequency, skew, root delay, root
+/// dispersion, update interval and leap status. System time is how far chrony is
+/// still slewing the clock, positive when it is slow.
+pub fn parse_chronyc_tracking(output: &str) -> Option<ClockSync> {
+    let fields: Vec<&str> = output.lines().next()?.split(',').collect();
+    if fields.len() < 14 {
+        return None;
+    }
+    let system_time: f64 = fields[4].trim().parse().ok()?;
+    Some(ClockSync {
+        synced: fields[13].trim() != "Not synchronised",
+        offset_ms: -system_time * 1000.0,
+        source: "chrony".to_string(),
+    })
+}
+
+/// The kernel's NTP state as adjtimex reports it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernelClock {
+    /// Return value of adjtimex, TIME_ERROR while unsynchronized
+    pub state: i32,
+    pub status: i32,
+    /// Correction still to be applied, in microseconds or with STA_NANO in nanoseconds
+    pub offset: i64,
+}
+
+impl KernelClock {
+    /// `synced` is what timedatectl says, or else the kernel's own flags.
+    pub fn clock_sync(&self, synced: Option<bool>) -> ClockSync {
+        let offset_ms = if self.status & libc::STA_NANO != 0 {
+            self.offset as f64 / 1_000_000.0
+        } else {
+            self.offset as f64 / 1000.0
+        };
+        let kernel_synced = self.state != libc::TIME_ERROR && self.status & libc::STA_UNSYNC == 0;
+        ClockSync {
+            synced: synced.unwrap_or(kernel_synced),
+            // The correction is what gets added, so a positive one means the clock is behind
+            offset_ms: -offset_ms,
+            source: "adjtimex".to_string(),
+        }
+    }
+}
+
+pub fn read_kernel_clock() -> Result<KernelClock> {
+    // modes 0 only reads
+    let mut timex: libc::timex = unsafe { std::mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut timex) };
+    if state < 0 {
+        return Err(anyhow!("adjtimex failed: {}", std::io::Error::last_os_error()));
+    }
+    Ok(KernelClock { state, status: timex.status, offset: timex.offset as i64 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACKING: &str = "A9FEA97B,169.254.169.123,4,1602692829.437634866,-0.000251000,-0.000001232,\
+                            0.000004158,-21.592,-0.001,0.059,0.000433839,0.000046217,64.5,Normal\n";
+
+    const TRACKING_UNSYNCED: &str = "00000000,,0,0.000000000,0.000000000,0.000000000,0.000000000,\
+                                     0.000,0.000,0.000,1.000000000,1.000000000,0.0,Not synchronised\n";
+
+    #[test]
+    fn test_parse_chronyc_tracking() {
+        let sync = parse_chronyc_tracking(TRACKING).unwrap();
+        assert!(sync.synced);
+        // chrony reports the clock fast as a negative system time
+        assert!((sync.offset_ms - 0.251).abs() < 1e-9);
+        assert_eq!(sync.source, "chrony");
+
+        let sync = parse_chronyc_tracking(TRACKING_UNSYNCED).unwrap();
+        assert!(!sync.synced);
+        assert_eq!(sync.offset_ms, 0.0);
+
+        assert_eq!(parse_chronyc_tracking("506 Cannot talk to daemon\n"), None);
+        assert_eq!(parse_chronyc_tracking(""), None);
+    }
+
+    #[test]
+    fn test_kernel_clock_sync() {
+        let clock = KernelClock { state: libc::TIME_OK, status: libc::STA_PLL, offset: 1500 };
+        let sync = clock.clock_sync(None);
+        assert!(sync.synced);
+        assert_eq!(sync.offset_ms, -1.5);
+        assert_eq!(sync.source, "adjtimex");
+
+        let nanos = KernelClock { status: libc::STA_PLL | libc::STA_NANO, offset: -2_000_000, ..clock };
+        assert_eq!(nanos.clock_sync(None).offset_ms, 2.0);
+
+        let unsynced = KernelClock { state: libc::TIME_ERROR, status: libc::STA_UNSYNC, offset: 0 };
+        assert!(!unsynced.clock_sync(None).synced);
+        // timedatectl has the final say
+        assert!(unsynced.clock_sync(Some(true)).synced);
+    }
+}
@@ -0,0 +1,164 @@
+use std::cell::Cell;
+use std::env;
+use std::os::unix::net::UnixDatagram;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// systemd notification protocol (sd_notify), enabled automatically when the
+// service manager passes NOTIFY_SOCKET, i.e. when running under Type=notify
+pub struct SystemdNotifier {
+    socket_path: Option<String>,
+    watchdog_interval: Option<Duration>,
+    last_watchdog: Cell<Option<Instant>>,
+}
+
+impl SystemdNotifier {
+    pub fn from_env() -> Self {
+        let socket_path = env::var("NOTIFY_SOCKET").ok().filter(|path| !path.is_empty());
+        let watchdog_interval = watchdog_ping_interval(
+            env::var("WATCHDOG_USEC").ok().as_deref(),
+            env::var("WATCHDOG_PID").ok().as_deref(),
+            std::process::id(),
+        );
+
+        Self {
+            socket_path,
+            watchdog_interval,
+            last_watchdog: Cell::new(None),
+        }
+    }
+
+    /// How often the watchdog must be pinged, if systemd enabled it for this process.
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        if self.socket_path.is_some() {
+            self.watchdog_interval
+        } else {
+            None
+        }
+    }
+
+    pub fn ready(&self) {
+        self.notify("READY=1");
+    }
+
+    pub fn stopping(&self) {
+        self.notify("STOPPING=1");
+    }
+
+    pub fn watchdog(&self) {
+        if self.watchdog_interval().is_some() {
+            self.notify("WATCHDOG=1");
+            self.last_watchdog.set(Some(Instant::now()));
+        }
+    }
+
+    /// Ping the watchdog only if the last ping is older than the ping interval.
+    pub fn watchdog_if_due(&self) {
+        if let Some(interval) = self.watchdog_interval() {
+            let due = self.last_watchdog.get().is_none_or(|last| last.elapsed() >= interval);
+            if due {
+                self.watchdog();
+            }
+        }
+    }
+
+    fn notify(&self, state: &str) {
+        let Some(path) = &self.socket_path else {
+            return;
+        };
+
+        if let Err(e) = send_notification(path, state) {
+            warn!("Failed to notify systemd ({}): {}", state, e);
+        }
+    }
+}
+
+fn send_notification(path: &str, state: &str) -> std::io::Result<()> {
+    let socket = UnixDatagram::unbound()?;
+
+    // A leading '@' denotes a socket in the abstract namespace
+    if let Some(name) = path.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+        socket.send_to_addr(state.as_bytes(), &addr)?;
+    } else {
+        socket.send_to(state.as_bytes(), path)?;
+    }
+    Ok(())
+}
+
+// systemd recommends pinging at half the configured WATCHDOG_USEC. The watchdog is meant
+// for a specific process when WATCHDOG_PID is set, so ignore it if that is not us.
+fn watchdog_ping_interval(watchdog_usec: Option<&str>, watchdog_pid: Option<&str>, pid: u32) -> Option<Duration> {
+    if let Some(watchdog_pid) = watchdog_pid {
+        if watchdog_pid.trim().parse::<u32>().ok() != Some(pid) {
+            return None;
+        }
+    }
+
+    let usec = watchdog_usec?.trim().parse::<u64>().ok()?;
+    if usec == 0 {
+        return None;
+    }
+    Some(Duration::from_micros(usec / 2))
+}
+
+/// Split a sleep into chunks no longer than `max_chunk`, so the caller can ping the
+/// watchdog and check for shutdown between chunks.
+pub fn sleep_chunks(total: Duration, max_chunk: Duration) -> Vec<Duration> {
+    if max_chunk.is_zero() {
+        return vec![total];
+    }
+
+    let mut chunks = Vec::new();
+    let mut remaining = total;
+    while !remaining.is_zero() {
+        let chunk = remaining.min(max_chunk);
+        chunks.push(chunk);
+        remaining -= chunk;
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sleep_chunks_splits_evenly() {
+        let chunks = sleep_chunks(Duration::from_secs(6), Duration::from_secs(2));
+        assert_eq!(chunks, vec![Duration::from_secs(2); 3]);
+    }
+
+    #[test]
+    fn test_sleep_chunks_remainder() {
+        let chunks = sleep_chunks(Duration::from_millis(5500), Duration::from_secs(2));
+        assert_eq!(
+            chunks,
+            vec![Duration::from_secs(2), Duration::from_secs(2), Duration::from_millis(1500)]
+        );
+        assert_eq!(chunks.iter().sum::<Duration>(), Duration::from_millis(5500));
+    }
+
+    #[test]
+    fn test_sleep_chunks_shorter_than_chunk() {
+        let chunks = sleep_chunks(Duration::from_millis(300), Duration::from_secs(2));
+        assert_eq!(chunks, vec![Duration::from_millis(300)]);
+    }
+
+    #[test]
+    fn test_sleep_chunks_edge_cases() {
+        assert!(sleep_chunks(Duration::ZERO, Duration::from_secs(1)).is_empty());
+        assert_eq!(sleep_chunks(Duration::from_secs(3), Duration::ZERO), vec![Duration::from_secs(3)]);
+    }
+
+    #[test]
+    fn test_watchdog_ping_interval() {
+        assert_eq!(watchdog_ping_interval(Some("10000000"), None, 42), Some(Duration::from_secs(5)));
+        assert_eq!(watchdog_ping_interval(Some("10000000"), Some("42"), 42), Some(Duration::from_secs(5)));
+        assert_eq!(watchdog_ping_interval(Some("10000000"), Some("7"), 42), None);
+        assert_eq!(watchdog_ping_interval(Some("0"), None, 42), None);
+        assert_eq!(watchdog_ping_interval(Some("bogus"), None, 42), None);
+        assert_eq!(watchdog_ping_interval(None, None, 42), None);
+    }
+}
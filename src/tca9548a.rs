@@ -1,91 +1,483 @@
 use linux_embedded_hal::I2cdev;
-use embedded_hal::i2c::I2c;
+use embedded_hal::i2c::{self, ErrorKind, ErrorType, I2c, Operation};
+use std::fmt;
 use std::sync::{Arc, Mutex};
 
 #[allow(dead_code)]
 pub const TCA9548A_ADDRESS: u8 = 0x70;
 
-pub struct Tca9548a {
-    i2c: Arc<Mutex<I2cdev>>,
+pub struct Tca9548a<I2C = I2cdev> {
+    i2c: Arc<Mutex<I2C>>,
     address: u8,
-    current_channel: Option<u8>,
+    // The control register as last read back, one bit per enabled channel; 0 until verified
+    channels: u8,
 }
 
-impl Tca9548a {
+impl<I2C: I2c> Tca9548a<I2C> {
     #[allow(dead_code)]
-    pub fn new(i2c: Arc<Mutex<I2cdev>>) -> Self {
+    pub fn new(i2c: Arc<Mutex<I2C>>) -> Self {
         Self::with_address(i2c, TCA9548A_ADDRESS)
     }
 
-    pub fn with_address(i2c: Arc<Mutex<I2cdev>>, address: u8) -> Self {
+    pub fn with_address(i2c: Arc<Mutex<I2C>>, address: u8) -> Self {
         Self {
             i2c,
             address,
-            current_channel: None,
+            channels: 0,
         }
     }
 
+    /// Enable `channel` only, then read the control register back to make sure it took.
     pub fn select_channel(&mut self, channel: u8) -> Result<(), Box<dyn std::error::Error>> {
-        if channel > 7 {
-            return Err("Channel must be between 0 and 7".into());
-        }
+        self.select_channels(channel_mask(channel)?)
+    }
 
-        let channel_mask = 1u8 << channel;
-        
-        let mut i2c = self.i2c.lock().unwrap();
-        i2c.write(self.address, &[channel_mask])?;
+    /// Enable exactly the channels in `mask`, one bit per channel, and verify it took.
+    /// Devices on the enabled channels share the bus, so their addresses must not overlap.
+    pub fn select_channels(&mut self, mask: u8) -> Result<(), Box<dyn std::error::Error>> {
+        let mut i2c = self.i2c.lock().map_err(|_| "I2C bus lock poisoned")?;
+        i2c.write(self.address, &[mask])
+            .map_err(|e| format!("Failed to select multiplexer channels 0x{:02X}: {:?}", mask, e))?;
         drop(i2c);
-        
-        self.current_channel = Some(channel);
+
+        let control = self.read_control_register()?;
+        if control != mask {
+            return Err(format!("Multiplexer reports 0x{:02X} after selecting channels 0x{:02X}", control, mask).into());
+        }
         Ok(())
     }
 
+    /// Enable `channel` as well as the channels already enabled.
     #[allow(dead_code)]
+    pub fn enable_channel(&mut self, channel: u8) -> Result<(), Box<dyn std::error::Error>> {
+        self.select_channels(self.channels | channel_mask(channel)?)
+    }
+
+    /// Disable `channel`, leaving the other enabled channels as they are.
+    #[allow(dead_code)]
+    pub fn disable_channel(&mut self, channel: u8) -> Result<(), Box<dyn std::error::Error>> {
+        self.select_channels(self.channels & !channel_mask(channel)?)
+    }
+
+    /// Make sure `channel` is still the one selected, selecting it again if something else
+    /// on the bus changed it. Returns true when it had been changed.
+    #[allow(dead_code)]
+    pub fn reselect_channel(&mut self, channel: u8) -> Result<bool, Box<dyn std::error::Error>> {
+        self.reselect_channels(channel_mask(channel)?)
+    }
+
+    /// Like reselect_channel, for the channels in `mask`.
+    pub fn reselect_channels(&mut self, mask: u8) -> Result<bool, Box<dyn std::error::Error>> {
+        let changed = self.read_control_register()? != mask;
+        self.select_channels(mask)?;
+        Ok(changed)
+    }
+
+    /// The control register, one bit per enabled channel, read from the device.
+    pub fn read_control_register(&mut self) -> Result<u8, Box<dyn std::error::Error>> {
+        let mut control = [0u8];
+        let mut i2c = self.i2c.lock().map_err(|_| "I2C bus lock poisoned")?;
+        i2c.read(self.address, &mut control)
+            .map_err(|e| format!("Failed to read multiplexer control register: {:?}", e))?;
+        drop(i2c);
+
+        self.channels = control[0];
+        Ok(control[0])
+    }
+
     pub fn disable_all_channels(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut i2c = self.i2c.lock().unwrap();
-        i2c.write(self.address, &[0x00])?;
+        let mut i2c = self.i2c.lock().map_err(|_| "I2C bus lock poisoned")?;
+        i2c.write(self.address, &[0x00])
+            .map_err(|e| format!("Failed to disable multiplexer channels: {:?}", e))?;
         drop(i2c);
-        
-        self.current_channel = None;
+
+        self.channels = 0;
         Ok(())
     }
 
+    /// Whether a device on the selected channel acknowledges `address`, probed with a
+    /// zero-length write so nothing is sent to it.
+    pub fn probe(&mut self, address: u8) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut i2c = self.i2c.lock().map_err(|_| "I2C bus lock poisoned")?;
+        Ok(i2c.write(address, &[]).is_ok())
+    }
+
+    /// The `addresses` that acknowledge on each channel, indexed by channel. All channels
+    /// are disabled afterwards, also when the scan fails part way.
+    pub fn scan(&mut self, addresses: &[u8]) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+        // Without this a missing multiplexer would look like eight empty channels
+        self.read_control_register()
+            .map_err(|_| format!("No multiplexer found at 0x{:02X}", self.address))?;
+
+        let found = self.scan_channels(addresses);
+        self.disable_all_channels()?;
+        found
+    }
+
+    fn scan_channels(&mut self, addresses: &[u8]) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+        let mux_address = self.address;
+        let mut found = Vec::new();
+        for channel in 0..8 {
+            self.select_channel(channel)?;
+            let mut acked = Vec::new();
+            // The multiplexer answers on every channel
+            for &address in addresses.iter().filter(|&&address| address != mux_address) {
+                if self.probe(address)? {
+                    acked.push(address);
+                }
+            }
+            found.push(acked);
+        }
+        Ok(found)
+    }
+
+    /// Disable every channel if any is enabled, returning whether one was.
+    pub fn ensure_disabled(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        if self.read_control_register()? == 0 {
+            return Ok(false);
+        }
+        self.disable_all_channels()?;
+        Ok(true)
+    }
+
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// The channel the hardware reported when last read, not merely the last one requested.
+    /// None unless exactly one channel is enabled.
     #[allow(dead_code)]
     pub fn get_current_channel(&self) -> Option<u8> {
-        self.current_channel
+        (self.channels.count_ones() == 1).then(|| self.channels.trailing_zeros() as u8)
+    }
+
+    /// The enabled channels as the hardware reported them when last read.
+    #[allow(dead_code)]
+    pub fn get_channel_mask(&self) -> u8 {
+        self.channels
     }
 
     #[allow(dead_code)]
-    pub fn get_i2c(&self) -> Arc<Mutex<I2cdev>> {
+    pub fn get_i2c(&self) -> Arc<Mutex<I2C>> {
         Arc::clone(&self.i2c)
     }
 }
 
-#[allow(dead_code)]
-pub struct MultiplexedI2c {
-    multiplexer: Arc<Mutex<Tca9548a>>,
+/// The control register bit for `channel`.
+pub fn channel_mask(channel: u8) -> Result<u8, Box<dyn std::error::Error>> {
+    if channel > 7 {
+        return Err("Channel must be between 0 and 7".into());
+    }
+    Ok(1 << channel)
+}
+
+/// One channel of a multiplexer as an I2C bus of its own, so device drivers can be built
+/// on top of it. Every transaction selects the channel first, holding the multiplexer
+/// for its duration so other channels cannot switch it in between.
+pub struct MultiplexedI2c<I2C = I2cdev> {
+    multiplexer: Arc<Mutex<Tca9548a<I2C>>>,
     channel: u8,
+    // Other channels left enabled alongside this one, e.g. for sensors
+    shared_channels: u8,
 }
 
-#[allow(dead_code)]
-impl MultiplexedI2c {
-    pub fn new(multiplexer: Arc<Mutex<Tca9548a>>, channel: u8) -> Self {
+impl<I2C: I2c> MultiplexedI2c<I2C> {
+    #[allow(dead_code)]
+    pub fn new(multiplexer: Arc<Mutex<Tca9548a<I2C>>>, channel: u8) -> Self {
+        Self::with_shared_channels(multiplexer, channel, 0)
+    }
+
+    /// A channel that is enabled together with the channels in `shared_channels`.
+    pub fn with_shared_channels(multiplexer: Arc<Mutex<Tca9548a<I2C>>>, channel: u8, shared_channels: u8) -> Self {
         Self {
             multiplexer,
             channel,
+            shared_channels,
         }
     }
 
+    // The control register value for a transfer on this channel
+    fn mask(&self) -> Result<u8, Box<dyn std::error::Error>> {
+        Ok(channel_mask(self.channel)? | self.shared_channels)
+    }
+
+    #[allow(dead_code)]
     pub fn with_channel<F, R>(&mut self, f: F) -> Result<R, Box<dyn std::error::Error>>
     where
-        F: FnOnce(&mut I2cdev) -> Result<R, Box<dyn std::error::Error>>,
+        F: FnOnce(&mut I2C) -> Result<R, Box<dyn std::error::Error>>,
     {
-        let mut mux = self.multiplexer.lock().unwrap();
-        mux.select_channel(self.channel)?;
-        
+        let mask = self.mask()?;
+        let mut mux = self.multiplexer.lock().map_err(|_| "Multiplexer lock poisoned")?;
+        mux.select_channels(mask)?;
+
+        let i2c = mux.get_i2c();
+        let mut i2c_lock = i2c.lock().map_err(|_| "I2C bus lock poisoned")?;
+
+        f(&mut i2c_lock)
+    }
+}
+
+/// Why a transaction on a multiplexer channel failed
+#[derive(Debug)]
+pub enum MultiplexedI2cError<E> {
+    /// The transfer to the device itself failed
+    Bus(E),
+    /// The multiplexer did not switch to the channel
+    Select(String),
+    /// A thread panicked while holding the bus or the multiplexer
+    Poisoned,
+}
+
+impl<E: fmt::Debug> fmt::Display for MultiplexedI2cError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MultiplexedI2cError::Bus(e) => write!(f, "I2C transfer failed: {:?}", e),
+            MultiplexedI2cError::Select(reason) => write!(f, "{}", reason),
+            MultiplexedI2cError::Poisoned => write!(f, "I2C bus lock poisoned"),
+        }
+    }
+}
+
+impl<E: fmt::Debug> std::error::Error for MultiplexedI2cError<E> {}
+
+impl<E: i2c::Error> i2c::Error for MultiplexedI2cError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            MultiplexedI2cError::Bus(e) => e.kind(),
+            MultiplexedI2cError::Select(_) | MultiplexedI2cError::Poisoned => ErrorKind::Other,
+        }
+    }
+}
+
+impl<I2C: I2c> ErrorType for MultiplexedI2c<I2C> {
+    type Error = MultiplexedI2cError<I2C::Error>;
+}
+
+impl<I2C: I2c> I2c for MultiplexedI2c<I2C> {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        let mask = self.mask().map_err(|e| MultiplexedI2cError::Select(e.to_string()))?;
+        let mut mux = self.multiplexer.lock().map_err(|_| MultiplexedI2cError::Poisoned)?;
+        mux.select_channels(mask)
+            .map_err(|e| MultiplexedI2cError::Select(e.to_string()))?;
+
         let i2c = mux.get_i2c();
-        let mut i2c_lock = i2c.lock().unwrap();
-        
-        f(&mut *i2c_lock)
+        let mut bus = i2c.lock().map_err(|_| MultiplexedI2cError::Poisoned)?;
+        bus.transaction(address, operations).map_err(MultiplexedI2cError::Bus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::i2c::Error;
+    use ssd1306::{prelude::*, I2CDisplayInterface, Ssd1306};
+    use std::thread;
+
+    const DISPLAY_ADDRESS: u8 = 0x3C;
+
+    // A bus with a TCA9548A on it that records which channels each device write went to
+    #[derive(Default)]
+    struct FakeBus {
+        control: u8,
+        writes: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl ErrorType for FakeBus {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for FakeBus {
+        fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+            for operation in operations {
+                match (address, operation) {
+                    (TCA9548A_ADDRESS, Operation::Write(bytes)) => self.control = bytes[0],
+                    (TCA9548A_ADDRESS, Operation::Read(buffer)) => buffer[0] = self.control,
+                    (_, Operation::Write(bytes)) => self.writes.push((self.control, bytes.to_vec())),
+                    (_, Operation::Read(_)) => return Err(ErrorKind::Other),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_select_channel_verifies() {
+        let bus = Arc::new(Mutex::new(FakeBus::default()));
+        let mut mux = Tca9548a::new(Arc::clone(&bus));
+        assert_eq!(mux.get_current_channel(), None);
+
+        mux.select_channel(3).unwrap();
+        assert_eq!(mux.read_control_register().unwrap(), 0x08);
+        assert_eq!(mux.get_current_channel(), Some(3));
+        assert!(mux.select_channel(8).is_err());
+    }
+
+    #[test]
+    fn test_channel_mask_arithmetic() {
+        let bus = Arc::new(Mutex::new(FakeBus::default()));
+        let mut mux = Tca9548a::new(Arc::clone(&bus));
+
+        mux.enable_channel(2).unwrap();
+        mux.enable_channel(5).unwrap();
+        assert_eq!(mux.get_channel_mask(), 0x24);
+        assert_eq!(bus.lock().unwrap().control, 0x24);
+        assert_eq!(mux.get_current_channel(), None);
+
+        // Enabling twice leaves the mask alone
+        mux.enable_channel(5).unwrap();
+        assert_eq!(mux.get_channel_mask(), 0x24);
+
+        mux.disable_channel(2).unwrap();
+        assert_eq!(mux.get_channel_mask(), 0x20);
+        assert_eq!(mux.get_current_channel(), Some(5));
+        mux.disable_channel(0).unwrap();
+        assert_eq!(mux.get_channel_mask(), 0x20);
+
+        // select_channel is exclusive
+        mux.select_channels(0x81).unwrap();
+        mux.select_channel(3).unwrap();
+        assert_eq!(bus.lock().unwrap().control, 0x08);
+
+        assert!(mux.enable_channel(8).is_err());
+        assert!(mux.disable_channel(8).is_err());
+    }
+
+    #[test]
+    fn test_ensure_disabled() {
+        let bus = Arc::new(Mutex::new(FakeBus { control: 0x04, ..FakeBus::default() }));
+        let mut mux = Tca9548a::new(Arc::clone(&bus));
+        assert!(mux.ensure_disabled().unwrap());
+        assert_eq!(bus.lock().unwrap().control, 0);
+        assert!(!mux.ensure_disabled().unwrap());
+    }
+
+    #[test]
+    fn test_shared_channels_stay_enabled() {
+        let bus = Arc::new(Mutex::new(FakeBus::default()));
+        let mux = Arc::new(Mutex::new(Tca9548a::new(Arc::clone(&bus))));
+        let mut display = MultiplexedI2c::with_shared_channels(Arc::clone(&mux), 2, 0x20);
+        display.write(DISPLAY_ADDRESS, &[0]).unwrap();
+        assert_eq!(bus.lock().unwrap().writes, vec![(0x24, vec![0])]);
+        assert!(!mux.lock().unwrap().reselect_channels(0x24).unwrap());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_reselect_after_interference() {
+        let bus = Arc::new(Mutex::new(FakeBus::default()));
+        let mut mux = Tca9548a::new(Arc::clone(&bus));
+        mux.select_channel(2).unwrap();
+        assert!(!mux.reselect_channel(2).unwrap());
+
+        // Another process switches the mux to its sensor channel
+        bus.lock().unwrap().control = 0x20;
+        mux.read_control_register().unwrap();
+        assert_eq!(mux.get_current_channel(), Some(5));
+
+        assert!(mux.reselect_channel(2).unwrap());
+        assert_eq!(bus.lock().unwrap().control, 0x04);
+        assert_eq!(mux.get_current_channel(), Some(2));
+    }
+
+    #[test]
+    fn test_two_displays_interleave() {
+        let bus = Arc::new(Mutex::new(FakeBus::default()));
+        let mux = Arc::new(Mutex::new(Tca9548a::new(Arc::clone(&bus))));
+        let mut left = MultiplexedI2c::new(Arc::clone(&mux), 0);
+        let mut right = MultiplexedI2c::new(Arc::clone(&mux), 6);
+
+        for frame in 0..3u8 {
+            left.with_channel(|i2c| i2c.write(DISPLAY_ADDRESS, &[frame]).map_err(|e| format!("{:?}", e).into())).unwrap();
+            right.with_channel(|i2c| i2c.write(DISPLAY_ADDRESS, &[0x80 | frame]).map_err(|e| format!("{:?}", e).into())).unwrap();
+        }
+
+        let writes = &bus.lock().unwrap().writes;
+        assert_eq!(writes.len(), 6);
+        for (control, bytes) in writes {
+            let expected = if bytes[0] & 0x80 == 0 { 0x01 } else { 0x40 };
+            assert_eq!(*control, expected);
+        }
+    }
+
+    #[test]
+    fn test_display_driver_over_channel() {
+        let bus = Arc::new(Mutex::new(FakeBus::default()));
+        let mux = Arc::new(Mutex::new(Tca9548a::new(Arc::clone(&bus))));
+        let interface = I2CDisplayInterface::new(MultiplexedI2c::new(Arc::clone(&mux), 4));
+        let mut display = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0)
+            .into_buffered_graphics_mode();
+        display.init().unwrap();
+        display.flush().unwrap();
+
+        let writes = &bus.lock().unwrap().writes;
+        assert!(writes.len() > 8);
+        assert!(writes.iter().all(|(control, _)| *control == 0x10));
+    }
+
+    #[test]
+    fn test_transaction_errors() {
+        let bus = Arc::new(Mutex::new(FakeBus::default()));
+        let mux = Arc::new(Mutex::new(Tca9548a::new(Arc::clone(&bus))));
+        let mut channel = MultiplexedI2c::new(Arc::clone(&mux), 1);
+
+        // The fake device cannot be read from
+        let err = channel.read(DISPLAY_ADDRESS, &mut [0u8]).unwrap_err();
+        assert!(matches!(err, MultiplexedI2cError::Bus(ErrorKind::Other)));
+
+        let mut bad_channel = MultiplexedI2c::new(Arc::clone(&mux), 9);
+        let err = bad_channel.write(DISPLAY_ADDRESS, &[0]).unwrap_err();
+        assert!(matches!(err, MultiplexedI2cError::Select(_)));
+        assert_eq!(err.kind(), ErrorKind::Other);
+
+        // A panic while holding the multiplexer must not take the other channels down with it
+        let poisoner = Arc::clone(&mux);
+        let _ = thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poison the multiplexer");
+        }).join();
+        assert!(matches!(channel.write(DISPLAY_ADDRESS, &[0]), Err(MultiplexedI2cError::Poisoned)));
+    }
+
+    // A bus with a multiplexer, or none, and devices at (channel, address)
+    struct ScanBus {
+        mux: Option<u8>,
+        devices: Vec<(u8, u8)>,
+    }
+
+    impl ErrorType for ScanBus {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for ScanBus {
+        fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+            let nack = ErrorKind::NoAcknowledge(embedded_hal::i2c::NoAcknowledgeSource::Address);
+            for operation in operations {
+                match (address, operation, &mut self.mux) {
+                    (TCA9548A_ADDRESS, Operation::Write(bytes), Some(control)) => *control = bytes[0],
+                    (TCA9548A_ADDRESS, Operation::Read(buffer), Some(control)) => buffer[0] = *control,
+                    (_, Operation::Write(_), Some(control)) if self.devices.contains(&(control.trailing_zeros() as u8, address)) => {}
+                    _ => return Err(nack),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_scan() {
+        let bus = Arc::new(Mutex::new(ScanBus { mux: Some(0), devices: vec![(2, 0x3C), (5, 0x3D), (5, 0x48)] }));
+        let mut mux = Tca9548a::new(Arc::clone(&bus));
+        let found = mux.scan(&[0x3C, 0x3D, 0x48, TCA9548A_ADDRESS]).unwrap();
+        assert_eq!(found.len(), 8);
+        assert_eq!(found[2], vec![0x3C]);
+        assert_eq!(found[5], vec![0x3D, 0x48]);
+        assert!(found[0].is_empty());
+        assert_eq!(bus.lock().unwrap().mux, Some(0));
+    }
+
+    #[test]
+    fn test_scan_without_multiplexer() {
+        let bus = Arc::new(Mutex::new(ScanBus { mux: None, devices: Vec::new() }));
+        let err = Tca9548a::new(bus).scan(&[0x3C]).unwrap_err();
+        assert_eq!(err.to_string(), "No multiplexer found at 0x70");
+    }
+}
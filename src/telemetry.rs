@@ -8,18 +8,43 @@ use opentelemetry_sdk::metrics::SdkMeterProvider;
 use opentelemetry_sdk::propagation::TraceContextPropagator;
 use opentelemetry_sdk::runtime::Tokio;
 use opentelemetry_sdk::{trace as sdktrace, Resource};
+use tokio::runtime::Runtime;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::EnvFilter;
 
 use crate::errors::{AppError, Result};
+use crate::logging;
+use crate::system_info::DeviceIdentity;
 
 pub struct Telemetry {
     _tracer_provider: sdktrace::TracerProvider,
-    _meter_provider: SdkMeterProvider,
+    meter_provider: SdkMeterProvider,
+    // The batch exporters run on this runtime, so it must outlive the providers
+    runtime: Runtime,
 }
 
-pub fn init() -> Result<Telemetry> {
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        // Flush pending spans and metrics before the exporters go away
+        let _guard = self.runtime.enter();
+        global::shutdown_tracer_provider();
+        if let Err(e) = self.meter_provider.shutdown() {
+            tracing::warn!("Telemetry metrics shutdown failed: {}", e);
+        }
+    }
+}
+
+/// Export traces and metrics as coming from the device `identity` describes.
+pub fn init(log_level: Option<&str>, identity: &DeviceIdentity) -> Result<Telemetry> {
+    // The rest of the application is synchronous; the exporters get a small runtime of their own
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("telemetry")
+        .enable_all()
+        .build()
+        .map_err(|e| AppError::application(&format!("Telemetry runtime init failed: {}", e)))?;
+    let _guard = runtime.enter();
+
     let service_name = env::var("OTEL_SERVICE_NAME")
         .unwrap_or_else(|_| "raspi-info-display".to_string());
     let endpoint = env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
@@ -27,7 +52,12 @@ pub fn init() -> Result<Telemetry> {
 
     global::set_text_map_propagator(TraceContextPropagator::new());
 
-    let resource = Resource::new(vec![KeyValue::new("service.name", service_name)]);
+    let resource = Resource::new(vec![
+        KeyValue::new("service.name", service_name),
+        KeyValue::new("host.id", identity.id.clone()),
+        KeyValue::new("host.name", identity.hostname.clone()),
+        KeyValue::new("device.model.name", identity.model.clone()),
+    ]);
 
     let tracer_provider = opentelemetry_otlp::new_pipeline()
         .tracing()
@@ -45,12 +75,9 @@ pub fn init() -> Result<Telemetry> {
         .map_err(|e| AppError::application(&format!("Telemetry trace init failed: {}", e)))?;
     let tracer = tracer_provider.tracer("info_display");
 
-    let env_filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info"));
-
     tracing_subscriber::registry()
-        .with(env_filter)
-        .with(tracing_subscriber::fmt::layer())
+        .with(logging::env_filter(log_level))
+        .with(logging::fmt_layer())
         .with(tracing_opentelemetry::layer().with_tracer(tracer))
         .try_init()
         .map_err(|e| AppError::application(&format!("Telemetry logging init failed: {}", e)))?;
@@ -69,6 +96,7 @@ pub fn init() -> Result<Telemetry> {
 
     Ok(Telemetry {
         _tracer_provider: tracer_provider,
-        _meter_provider: meter_provider,
+        meter_provider,
+        runtime,
     })
 }
@@ -0,0 +1,86 @@
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+static TIMINGS: Mutex<LoopTimings> = Mutex::new(LoopTimings::new());
+
+/// How long the display loop took, for tuning the update interval and screen durations
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoopTimings {
+    /// The last render of each screen, in the order they were first shown
+    pub renders: Vec<(String, Duration)>,
+    /// Snapshot, render and flush of the last update
+    pub last_update: Option<Duration>,
+    /// From the start of one update to the start of the next, sleep included
+    pub last_cycle: Option<Duration>,
+}
+
+impl LoopTimings {
+    const fn new() -> Self {
+        Self { renders: Vec::new(), last_update: None, last_cycle: None }
+    }
+}
+
+fn timings() -> MutexGuard<'static, LoopTimings> {
+    TIMINGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Note how long rendering `screen` took.
+pub fn record_render(screen: &str, took: Duration) {
+    let mut timings = timings();
+    match timings.renders.iter_mut().find(|(name, _)| name == screen) {
+        Some((_, last)) => *last = took,
+        None => timings.renders.push((screen.to_string(), took)),
+    }
+}
+
+/// Note how long an update took and, from the second on, the whole cycle before it.
+pub fn record_update(took: Duration, cycle: Option<Duration>) {
+    let mut timings = timings();
+    timings.last_update = Some(took);
+    if cycle.is_some() {
+        timings.last_cycle = cycle;
+    }
+}
+
+pub fn loop_timings() -> LoopTimings {
+    timings().clone()
+}
+
+/// "0.4ms", "12ms" or "5.0s", short enough to share a line with a screen name.
+pub fn format_duration_short(duration: Duration) -> String {
+    let millis = duration.as_secs_f64() * 1000.0;
+    if millis < 10.0 {
+        format!("{:.1}ms", millis)
+    } else if millis < 1000.0 {
+        format!("{:.0}ms", millis)
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_last_render_per_screen() {
+        record_render("timing-a", Duration::from_millis(5));
+        record_render("timing-b", Duration::from_millis(7));
+        record_render("timing-a", Duration::from_millis(3));
+        // Other tests render screens too
+        let renders: Vec<(String, Duration)> = loop_timings().renders.into_iter()
+            .filter(|(name, _)| name.starts_with("timing-"))
+            .collect();
+        assert_eq!(renders, vec![
+            ("timing-a".to_string(), Duration::from_millis(3)),
+            ("timing-b".to_string(), Duration::from_millis(7)),
+        ]);
+    }
+
+    #[test]
+    fn test_format_duration_short() {
+        assert_eq!(format_duration_short(Duration::from_micros(420)), "0.4ms");
+        assert_eq!(format_duration_short(Duration::from_micros(12_400)), "12ms");
+        assert_eq!(format_duration_short(Duration::from_millis(5000)), "5.0s");
+    }
+}
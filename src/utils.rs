@@ -0,0 +1,141 @@
+//! Formatting shared by the screens.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Binary steps, like the memory and disk figures
+const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+const UNIT_STEP: f64 = 1024.0;
+
+const ELLIPSIS: &str = "...";
+
+// Set when the display font has no degree sign
+static ASCII_SYMBOLS: AtomicBool = AtomicBool::new(false);
+
+/// Width of every `format_rate` result.
+pub const RATE_WIDTH: usize = 11;
+
+/// Cut `text` to at most `max` characters, ending in "..." when something was cut.
+pub fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max.saturating_sub(ELLIPSIS.len())).collect();
+    format!("{}{}", kept, ELLIPSIS)
+}
+
+/// Keep the start and end of `text`, which are what tell addresses apart, and cut the
+/// middle to fit `max` characters, e.g. `2001:db8...8a2e:7334`.
+pub fn elide_middle(text: &str, max: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max {
+        return text.to_string();
+    }
+    let kept = max.saturating_sub(ELLIPSIS.len());
+    let head: String = chars[..kept / 2].iter().collect();
+    let tail: String = chars[chars.len() - (kept - kept / 2)..].iter().collect();
+    format!("{}{}{}", head, ELLIPSIS, tail)
+}
+
+/// A size in the largest unit it reaches, e.g. "512B", "1.5KB" or "29.0GB".
+pub fn humanize_bytes(bytes: u64) -> String {
+    match scale(bytes as f64) {
+        (value, 0) => format!("{}{}", value, UNITS[0]),
+        (value, unit) => format!("{:.1}{}", value, UNITS[unit]),
+    }
+}
+
+/// A throughput such as `"   1.5 MB/s"`: one decimal, right-aligned to [`RATE_WIDTH`] so the
+/// text does not shift as the value changes between frames.
+pub fn format_rate(bytes_per_sec: f64) -> String {
+    // Counters that wrap or reset give a negative delta
+    let rate = if bytes_per_sec.is_finite() { bytes_per_sec.max(0.0) } else { 0.0 };
+    let (value, unit) = scale(rate);
+    format!("{:>6.1} {:>4}", value, format!("{}/s", UNITS[unit]))
+}
+
+/// Write "51.9C" instead of "51.9°C", for a display font without the degree sign.
+pub fn set_ascii_symbols(enabled: bool) {
+    ASCII_SYMBOLS.store(enabled, Ordering::Relaxed);
+}
+
+/// A temperature with one decimal, e.g. "51.9°C", or "51.9C" with ASCII symbols.
+pub fn format_temp(celsius: f32) -> String {
+    format_temp_in(celsius, ASCII_SYMBOLS.load(Ordering::Relaxed))
+}
+
+fn format_temp_in(celsius: f32, ascii: bool) -> String {
+    format!("{:.1}{}", celsius, if ascii { "C" } else { "°C" })
+}
+
+// `value` in the largest unit that keeps it at or above 1, with the index of that unit.
+// Compared after rounding to one decimal so 1023.96 KB shows as 1.0 MB, not 1024.0 KB.
+fn scale(mut value: f64) -> (f64, usize) {
+    let mut unit = 0;
+    while (value * 10.0).round() / 10.0 >= UNIT_STEP && unit < UNITS.len() - 1 {
+        value /= UNIT_STEP;
+        unit += 1;
+    }
+    (value, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("raspberrypi", 11), "raspberrypi");
+        assert_eq!(truncate_chars("raspberrypi", 10), "raspber...");
+        // Cut by characters, not bytes
+        assert_eq!(truncate_chars("küchenküche", 8), "küche...");
+        assert_eq!(truncate_chars("abcdef", 2), "...");
+    }
+
+    #[test]
+    fn test_elide_middle() {
+        assert_eq!(elide_middle("2001:db8::42", 21), "2001:db8::42");
+        assert_eq!(elide_middle("abcdefghij", 7), "ab...ij");
+        assert_eq!(elide_middle("abcdefghij", 8), "ab...hij");
+    }
+
+    #[test]
+    fn test_humanize_bytes() {
+        assert_eq!(humanize_bytes(0), "0B");
+        assert_eq!(humanize_bytes(1023), "1023B");
+        assert_eq!(humanize_bytes(1024), "1.0KB");
+        assert_eq!(humanize_bytes(1536), "1.5KB");
+        assert_eq!(humanize_bytes(29 * 1024 * 1024 * 1024), "29.0GB");
+        assert_eq!(humanize_bytes(3 * 1024u64.pow(4)), "3.0TB");
+    }
+
+    #[test]
+    fn test_format_rate_thresholds() {
+        assert_eq!(format_rate(0.0), "   0.0  B/s");
+        assert_eq!(format_rate(1023.0), "1023.0  B/s");
+        assert_eq!(format_rate(1024.0), "   1.0 KB/s");
+        assert_eq!(format_rate(1536.0), "   1.5 KB/s");
+        assert_eq!(format_rate(1024.0 * 1024.0 - 1.0), "   1.0 MB/s");
+        assert_eq!(format_rate(12.5 * 1024.0 * 1024.0), "  12.5 MB/s");
+        assert_eq!(format_rate(3.0 * GB), "   3.0 GB/s");
+        assert_eq!(format_rate(2048.0 * GB), "   2.0 TB/s");
+        assert_eq!(format_rate(-5.0), "   0.0  B/s");
+        assert_eq!(format_rate(f64::NAN), "   0.0  B/s");
+    }
+
+    #[test]
+    fn test_format_rate_is_fixed_width() {
+        for rate in [0.0, 9.9, 99.0, 999.0, 1000.0, 65_000.0, 1e7, 1e9, 1e11] {
+            assert_eq!(format_rate(rate).chars().count(), RATE_WIDTH, "{}", rate);
+        }
+    }
+
+    #[test]
+    fn test_format_temp() {
+        assert_eq!(format_temp(51.94), "51.9°C");
+        assert_eq!(format_temp(48.25), "48.2°C");
+        assert_eq!(format_temp(-3.0), "-3.0°C");
+        assert_eq!(format_temp_in(51.94, true), "51.9C");
+    }
+}
@@ -0,0 +1,109 @@
+use serde::Serialize;
+use tracing::debug;
+
+use crate::alerts::AlertRule;
+use crate::config::WebhookConfig;
+use crate::screens::Screen;
+use crate::system_info::{placeholder, DataSource, SystemSnapshot};
+
+/// One update of a Waybar custom module with `"return-type": "json"`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct WaybarOutput {
+    pub text: String,
+    pub tooltip: String,
+    /// `normal`, `warning` or `critical`, for styling the module
+    pub class: &'static str,
+}
+
+impl WaybarOutput {
+    /// The screens' content as one bar entry: the first line's value is the text and
+    /// every other line goes in the tooltip. The class follows the alert thresholds.
+    pub fn render(screens: &mut [Box<dyn Screen>], snapshot: &SystemSnapshot, data: &dyn DataSource, thresholds: &WebhookConfig) -> Self {
+        let mut lines: Vec<String> = Vec::new();
+        for screen in screens {
+            match screen.render(snapshot, data) {
+                Ok(content) => lines.extend(content.lines().map(String::from)),
+                Err(e) => {
+                    debug!("Screen {} failed to render: {}", screen.name(), e);
+                    lines.push(format!("{}: {}", screen.name(), placeholder()));
+                }
+            }
+        }
+
+        let text = lines.first()
+            .map(|line| value_of(line).to_string())
+            .unwrap_or_else(|| placeholder().to_string());
+        Self {
+            text,
+            tooltip: lines.iter().skip(1).map(String::as_str).collect::<Vec<_>>().join("\n"),
+            class: class(&AlertRule::from_config(thresholds), snapshot),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // Only strings, which always serialize
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+// "CPU: 52.3°C" shows as "52.3°C"; a bar has no room for the label
+fn value_of(line: &str) -> &str {
+    line.split_once(": ").map_or(line, |(_, value)| value).trim()
+}
+
+fn class(rules: &[AlertRule], snapshot: &SystemSnapshot) -> &'static str {
+    if rules.iter().any(|rule| rule.evaluate(snapshot).is_some_and(|(firing, _)| firing)) {
+        "critical"
+    } else if rules.iter().any(|rule| rule.nearing(snapshot)) {
+        "warning"
+    } else {
+        "normal"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screens::{NetworkScreen, TemperatureScreen};
+    use crate::system_info::{DemoDataSource, RefreshRequirements};
+    use sysinfo::System;
+
+    fn demo_snapshot() -> SystemSnapshot {
+        DemoDataSource::new().snapshot(&System::new(), RefreshRequirements::ALL)
+    }
+
+    fn thresholds(cpu_temp_celsius: f32) -> WebhookConfig {
+        WebhookConfig { cpu_temp_celsius: Some(cpu_temp_celsius), ..WebhookConfig::default() }
+    }
+
+    #[test]
+    fn test_render() {
+        let mut screens: Vec<Box<dyn Screen>> = vec![Box::new(TemperatureScreen::default())];
+        let output = WaybarOutput::render(&mut screens, &demo_snapshot(), &DemoDataSource::new(), &WebhookConfig::default());
+        assert_eq!(output, WaybarOutput {
+            text: "42.0°C 1500 MHz".to_string(),
+            tooltip: "GPU: 41.9°C\nTrip margin: 43.0°C\nUV events: 3 (24h)".to_string(),
+            class: "normal",
+        });
+        assert_eq!(
+            output.to_json(),
+            r#"{"text":"42.0°C 1500 MHz","tooltip":"GPU: 41.9°C\nTrip margin: 43.0°C\nUV events: 3 (24h)","class":"normal"}"#
+        );
+    }
+
+    #[test]
+    fn test_tooltip_covers_every_screen() {
+        let mut screens: Vec<Box<dyn Screen>> = vec![Box::new(TemperatureScreen::default()), Box::new(NetworkScreen::default())];
+        let output = WaybarOutput::render(&mut screens, &demo_snapshot(), &DemoDataSource::new(), &WebhookConfig::default());
+        assert!(output.tooltip.ends_with("192.168.1.42\nDC:A6:32:12:34:56\neth0 1Gb FD"));
+    }
+
+    #[test]
+    fn test_class_follows_thresholds() {
+        let mut screens: Vec<Box<dyn Screen>> = vec![Box::new(TemperatureScreen::default())];
+        let mut render = |limit| WaybarOutput::render(&mut screens, &demo_snapshot(), &DemoDataSource::new(), &thresholds(limit)).class;
+        assert_eq!(render(80.0), "normal");
+        assert_eq!(render(45.0), "warning");
+        assert_eq!(render(40.0), "critical");
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::alerts::AlertRule;
+use crate::config::WebhookConfig;
+use crate::errors::Result;
+use crate::status::SnapshotSink;
+use crate::system_info::{DataSource, SystemSnapshot};
+
+// Notifications waiting for the worker; more are dropped rather than block the sampler
+const CHANNEL_CAPACITY: usize = 16;
+
+// Delivery attempts per notification, waiting RETRY_BACKOFF, then twice that, in between
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BACKOFF: Duration = Duration::from_secs(2);
+
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AlertState {
+    Firing,
+    Resolved,
+}
+
+impl AlertState {
+    fn label(&self) -> &'static str {
+        match self {
+            AlertState::Firing => "firing",
+            AlertState::Resolved => "resolved",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RuleState {
+    // A firing notification went out and its recovery has not yet
+    notified: bool,
+    last_fired: Option<Instant>,
+}
+
+/// Decides which rule changes are notified: a condition at most once per
+/// `renotify_after` while it lasts, and its recovery once it clears.
+#[derive(Debug)]
+struct RateLimiter {
+    renotify_after: Duration,
+    rules: HashMap<&'static str, RuleState>,
+}
+
+impl RateLimiter {
+    fn new(renotify_after: Duration) -> Self {
+        Self {
+            renotify_after,
+            rules: HashMap::new(),
+        }
+    }
+
+    fn update(&mut self, rule: &'static str, firing: bool, now: Instant) -> Option<AlertState> {
+        let state = self.rules.entry(rule).or_default();
+        if firing {
+            let due = state.last_fired.is_none_or(|at| now.duration_since(at) >= self.renotify_after);
+            if !due {
+                return None;
+            }
+            state.last_fired = Some(now);
+            state.notified = true;
+            Some(AlertState::Firing)
+        } else if state.notified {
+            // A condition suppressed while flapping gets no recovery notice either
+            state.notified = false;
+            Some(AlertState::Resolved)
+        } else {
+            None
+        }
+    }
+}
+
+/// Generic JSON that Slack (`text`), Discord (`content`) and most other webhook
+/// receivers accept, with the details as separate fields for custom handlers.
+fn payload(hostname: &str, rule: &str, state: AlertState, detail: &str, timestamp: SystemTime) -> Value {
+    let text = format!("{}: {} {} - {}", hostname, rule, state.label(), detail);
+    json!({
+        "text": text,
+        "content": text,
+        "host": hostname,
+        "alert": rule,
+        "state": state.label(),
+        "detail": detail,
+        "timestamp": timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+    })
+}
+
+/// Checks every snapshot against the alert rules and POSTs the notifications the
+/// rate limiter lets through to the webhook, from its own thread.
+pub struct WebhookNotifier {
+    hostname: String,
+    rules: Vec<AlertRule>,
+    limiter: Mutex<RateLimiter>,
+    sender: Option<SyncSender<Value>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl WebhookNotifier {
+    pub fn start(config: &WebhookConfig, data: &dyn DataSource) -> Result<Self> {
+        let (sender, notifications) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let poster = Poster::new(&config.url);
+        let handle = thread::Builder::new()
+            .name("webhook".to_string())
+            .spawn(move || run_worker(notifications, poster))?;
+
+        let rules = AlertRule::from_config(config);
+        info!(
+            "Sending alerts ({}) to the webhook",
+            rules.iter().map(AlertRule::name).collect::<Vec<_>>().join(", ")
+        );
+        Ok(Self {
+            hostname: data.device_identity().hostname,
+            rules,
+            limiter: Mutex::new(RateLimiter::new(Duration::from_secs(config.renotify_minutes.saturating_mul(60)))),
+            sender: Some(sender),
+            handle: Some(handle),
+        })
+    }
+}
+
+impl SnapshotSink for WebhookNotifier {
+    fn publish(&self, snapshot: &SystemSnapshot) {
+        let mut limiter = self.limiter.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+
+        for rule in &self.rules {
+            let Some((firing, detail)) = rule.evaluate(snapshot) else {
+                continue;
+            };
+            let Some(state) = limiter.update(rule.name(), firing, now) else {
+                continue;
+            };
+
+            let body = payload(&self.hostname, rule.name(), state, &detail, SystemTime::now());
+            if let Some(sender) = &self.sender {
+                if sender.try_send(body).is_err() {
+                    warn!("Webhook worker is behind, dropped the {} {} notification", rule.name(), state.label());
+                }
+            }
+        }
+    }
+}
+
+impl Drop for WebhookNotifier {
+    fn drop(&mut self) {
+        // Closing the channel ends the worker once it has sent what is queued
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run_worker(notifications: Receiver<Value>, poster: Poster) {
+    while let Ok(body) = notifications.recv() {
+        let mut backoff = RETRY_BACKOFF;
+        for attempt in 1..=MAX_ATTEMPTS {
+            match poster.post(&body) {
+                Ok(()) => {
+                    debug!("Delivered webhook notification {}", body["text"]);
+                    break;
+                }
+                Err(PostError::Rejected(reason)) => {
+                    warn!("Webhook rejected the notification, dropping it: {}", reason);
+                    break;
+                }
+                Err(PostError::Unavailable(reason)) if attempt < MAX_ATTEMPTS => {
+                    warn!("Webhook delivery failed: {}; retrying in {}s", reason, backoff.as_secs());
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                Err(PostError::Unavailable(reason)) => {
+                    warn!("Webhook delivery failed after {} attempts, dropping it: {}", MAX_ATTEMPTS, reason);
+                }
+            }
+        }
+    }
+}
+
+enum PostError {
+    // The receiver answered and refused the notification
+    Rejected(String),
+    // Network trouble, rate limiting or a server error; worth retrying
+    Unavailable(String),
+}
+
+struct Poster {
+    agent: ureq::Agent,
+    url: String,
+}
+
+impl Poster {
+    fn new(url: &str) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(POST_TIMEOUT).build(),
+            url: url.to_string(),
+        }
+    }
+
+    fn post(&self, body: &Value) -> std::result::Result<(), PostError> {
+        let request = self.agent.post(&self.url).set("Content-Type", "application/json");
+        match request.send_string(&body.to_string()) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(code, response)) => {
+                let reason = format!("HTTP {}: {}", code, response.into_string().unwrap_or_default().trim());
+                if code == 429 || code >= 500 {
+                    Err(PostError::Unavailable(reason))
+                } else {
+                    Err(PostError::Rejected(reason))
+                }
+            }
+            Err(e) => Err(PostError::Unavailable(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_rate_limiter_renotifies_after_interval() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(30 * MINUTE);
+
+        assert_eq!(limiter.update("cpu_temp", false, start), None);
+        assert_eq!(limiter.update("cpu_temp", true, start), Some(AlertState::Firing));
+        assert_eq!(limiter.update("cpu_temp", true, start + 10 * MINUTE), None);
+        assert_eq!(limiter.update("cpu_temp", true, start + 30 * MINUTE), Some(AlertState::Firing));
+        assert_eq!(limiter.update("cpu_temp", false, start + 31 * MINUTE), Some(AlertState::Resolved));
+        assert_eq!(limiter.update("cpu_temp", false, start + 32 * MINUTE), None);
+    }
+
+    #[test]
+    fn test_rate_limiter_flapping() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(30 * MINUTE);
+
+        assert_eq!(limiter.update("under_voltage", true, start), Some(AlertState::Firing));
+        assert_eq!(limiter.update("under_voltage", false, start + MINUTE), Some(AlertState::Resolved));
+        // Firing again within the interval is suppressed, and so is its recovery
+        assert_eq!(limiter.update("under_voltage", true, start + 2 * MINUTE), None);
+        assert_eq!(limiter.update("under_voltage", false, start + 3 * MINUTE), None);
+        assert_eq!(limiter.update("under_voltage", true, start + 30 * MINUTE), Some(AlertState::Firing));
+
+        // Rules are limited independently
+        assert_eq!(limiter.update("cpu_temp", true, start + 2 * MINUTE), Some(AlertState::Firing));
+    }
+
+    #[test]
+    fn test_payload() {
+        let timestamp = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let body = payload("pi-kitchen", "under_voltage", AlertState::Firing, "Under-voltage detected", timestamp);
+        assert_eq!(body, json!({
+            "text": "pi-kitchen: under_voltage firing - Under-voltage detected",
+            "content": "pi-kitchen: under_voltage firing - Under-voltage detected",
+            "host": "pi-kitchen",
+            "alert": "under_voltage",
+            "state": "firing",
+            "detail": "Under-voltage detected",
+            "timestamp": 1_700_000_000,
+        }));
+    }
+
+    #[test]
+    fn test_posts_json() {
+        let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+        let port = server.server_addr().to_ip().unwrap().port();
+        let poster = Poster::new(&format!("http://127.0.0.1:{}/hook", port));
+        let body = payload("pi", "cpu_temp", AlertState::Resolved, "CPU temperature 60.0°C", UNIX_EPOCH);
+        let client = thread::spawn(move || poster.post(&body).is_ok());
+
+        let mut request = server.recv().unwrap();
+        assert_eq!(request.url(), "/hook");
+        let mut received = String::new();
+        request.as_reader().read_to_string(&mut received).unwrap();
+        let received: Value = serde_json::from_str(&received).unwrap();
+        assert_eq!(received["state"], "resolved");
+        request.respond(tiny_http::Response::empty(200)).unwrap();
+
+        assert!(client.join().unwrap());
+    }
+}
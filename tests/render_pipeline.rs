@@ -0,0 +1,96 @@
+// Every built-in screen through the whole render pipeline, as the display loop runs it,
+// with demo readings and the frame drawn into memory instead of onto a panel.
+
+use std::time::Duration;
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use info_display::display::{LINE_CHARS, TITLE_CHARS};
+use info_display::framebuffer::{HEIGHT, WIDTH};
+use info_display::screens::AmbientScreen;
+use info_display::system_info::dht22::Dht22Reading;
+use info_display::system_info::{DataSource, DemoDataSource, Dht22Sensor};
+use info_display::{AppConfig, ScreenErrorPolicy, ScreenManager, ScreenRegistry, TextLayout};
+use sysinfo::System;
+
+// The panel, noting the lit pixels drawn past its edges, which a real display drops
+#[derive(Default)]
+struct BoundedPanel {
+    lit: usize,
+    outside: Vec<Point>,
+}
+
+impl OriginDimensions for BoundedPanel {
+    fn size(&self) -> Size {
+        Size::new(WIDTH, HEIGHT)
+    }
+}
+
+impl DrawTarget for BoundedPanel {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, _) in pixels.into_iter().filter(|Pixel(_, color)| color.is_on()) {
+            if self.bounding_box().contains(point) {
+                self.lit += 1;
+            } else {
+                self.outside.push(point);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_every_screen_renders_within_the_display() {
+    let mut registry = ScreenRegistry::default();
+    // The built-in ambient screen would drive a GPIO pin, looking for a DHT22
+    registry.register("ambient", "", |_| {
+        let sensor = Dht22Sensor::with_reader(4, |_| Ok(Dht22Reading { temperature_celsius: -12.5, humidity_percent: 100.0 }), Duration::ZERO)?;
+        Ok(Box::new(AmbientScreen { sensor }))
+    });
+    let names = registry.names();
+    assert!(!names.is_empty());
+    let options = AppConfig::default().screen_options();
+    // A failing screen keeps its place in the rotation with a placeholder body
+    let mut manager = ScreenManager::with_registry(
+        &registry, names.clone(), None, Duration::from_secs(5), ScreenErrorPolicy::Placeholder, &options,
+    ).unwrap();
+    let snapshot = DemoDataSource::new().snapshot(&System::new(), manager.refresh_requirements());
+    let layout = TextLayout::default();
+
+    let mut problems = Vec::new();
+    for expected in &names {
+        let name = manager.current_screen().unwrap().name();
+        assert_eq!(name, *expected);
+        let rendered = manager.render_current_screen(&snapshot, &DemoDataSource::new()).unwrap();
+
+        let mut panel = BoundedPanel::default();
+        panel.draw_iter(rendered.canvas.pixels()).unwrap();
+        layout.draw(rendered.title.as_deref(), &rendered.content, &mut panel).unwrap();
+        if panel.lit == 0 {
+            problems.push(format!("{} drew nothing", name));
+        }
+        if let Some(point) = panel.outside.first() {
+            problems.push(format!("{} drew past the display at {}", name, point));
+        }
+
+        // Rows past the bottom are not drawn at all, so they are counted instead
+        let rows = rendered.content.lines().count();
+        if rows > layout.rows(rendered.title.is_some()) {
+            problems.push(format!("{} has {} rows: {:?}", name, rows, rendered.content));
+        }
+        if let Some(title) = rendered.title.as_ref().filter(|title| title.chars().count() > TITLE_CHARS) {
+            problems.push(format!("{} title {:?} is too long", name, title));
+        }
+        if let Some(line) = rendered.content.lines().find(|line| line.chars().count() > LINE_CHARS) {
+            problems.push(format!("{} line {:?} is too long", name, line));
+        }
+
+        manager.next_screen();
+    }
+    assert!(problems.is_empty(), "{}", problems.join("\n"));
+}